@@ -1,50 +1,46 @@
-use elysiumparser::{
-    add_search_with_expression, run_parser, BooleanExpression, ParserConfig,
-};
+use elysiumparser::{run_parser, ParserConfig, ProgressCallback, ProgressUpdate};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
 
 #[tokio::main]
 async fn main() -> std::io::Result<()> {
-    // Create search terms with complex boolean expressions
-    let mut search_terms = Vec::new();
+    // Setup the parser configuration; `build` checks it before anything runs
+    let config = ParserConfig::builder()
+        .log_folder("logs/application")
+        .output_log("logs/results.log")
+        .filename_filter("app") // Only include files with "app" in the name
+        // Match lines containing "error" AND ((database & connection) OR (timeout))
+        .add_search_expression("error", "(database & connection) | (timeout)")
+        // You can add multiple search terms
+        .add_search_expression("warning", "memory")
+        .workers(4) // Use 4 worker threads
+        .build()
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
     
-    // Add a search term with a primary keyword "error" and a complex boolean expression
-    // This will match if the line contains "error" AND ((database & connection) OR (timeout))
-    add_search_with_expression(
-        &mut search_terms,
-        "error",
-        "(database & connection) | (timeout)",
-    );
-    
-    // You can add multiple search terms
-    add_search_with_expression(&mut search_terms, "warning", "memory");
-    
-    // Setup the parser configuration
-    let config = ParserConfig {
-        log_folder: "logs/application".to_string(),
-        output_log: "logs/results.log".to_string(),
-        filename_filter: "app".to_string(), // Only include files with "app" in the name
-        line_filter: "".to_string(),        // No specific line filter
-        search_terms,
-        workers: Some(4),                   // Use 4 worker threads
-    };
-    
-    // Define a custom progress callback
-    let progress_callback = |processed: usize, total: usize| {
-        println!("Processed {}/{} files ({}%)", 
-            processed, 
-            total, 
-            (processed * 100) / total
+    // Define a custom progress callback; it can capture state, like this
+    // counter of the updates received
+    let updates = Arc::new(AtomicUsize::new(0));
+    let counter = Arc::clone(&updates);
+    let progress_callback: ProgressCallback = Arc::new(move |update: ProgressUpdate| {
+        counter.fetch_add(1, Ordering::Relaxed);
+        println!("Processed {}/{} files ({}%), {} matches so far: {}",
+            update.processed,
+            update.total,
+            (update.processed * 100) / update.total,
+            update.matches,
+            update.path.display()
         );
-    };
-    
+    });
+
     // Run the parser
     let result = run_parser(config, Some(progress_callback)).await?;
-    
+
     // Use the results
     println!("Found {} matches in {} files", 
         result.total_matches,
         result.processed_files
     );
+    println!("Received {} progress updates", updates.load(Ordering::Relaxed));
     
     Ok(())
 }
\ No newline at end of file
@@ -0,0 +1,35 @@
+use elysiumparser::{OutputTarget, ParserConfig, run_parser_stream};
+use futures::StreamExt;
+
+#[tokio::main]
+async fn main() -> std::io::Result<()> {
+    let mut config = ParserConfig::builder()
+        .log_folder("logs/application")
+        .add_search_expression("error", "database | timeout")
+        .build()
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+    // Only stream the matches, without writing an output log
+    config.output_target = OutputTarget::Discard;
+
+    // Handle each match as it is found; the parser waits for this consumer
+    // whenever it falls behind
+    let mut matches = run_parser_stream(config)?;
+    matches
+        .by_ref()
+        .for_each(|record| async move {
+            println!(
+                "{}:{}: {}",
+                record.file.display(),
+                record.line_number,
+                record.line
+            );
+        })
+        .await;
+
+    let result = matches.result().await?;
+    println!(
+        "Found {} matches in {} files",
+        result.total_matches, result.processed_files
+    );
+    Ok(())
+}
@@ -0,0 +1,88 @@
+//! Time readers writing thousands of matches through a shared `Mutex<File>`
+//! and through a `WriterSink`, the writer task `run_parser` uses, line by
+//! line and a file at a time
+//!
+//! Run with `cargo run --release --example writer_throughput`.
+
+use elysiumparser::{
+    DEFAULT_WRITER_CHANNEL_CAPACITY, MatchSink, ScanOptions, WriterSink, add_search, process_reader,
+};
+use std::fs::File;
+use std::io::Cursor;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const READERS: usize = 8;
+const LINES_PER_READER: usize = 200_000;
+
+/// Scan the same matching input from every reader at once into `sink`,
+/// within a batch of `batch_writer` when given
+fn time_readers(sink: Arc<dyn MatchSink>, batch_writer: Option<Arc<WriterSink>>) -> Duration {
+    let input: Arc<str> = (0..LINES_PER_READER)
+        .map(|line| format!("error: request {} failed with status 500\n", line))
+        .collect::<String>()
+        .into();
+
+    let started = Instant::now();
+    let readers: Vec<_> = (0..READERS)
+        .map(|reader| {
+            let sink = Arc::clone(&sink);
+            let input = Arc::clone(&input);
+            let batch_writer = batch_writer.clone();
+            thread::spawn(move || {
+                let mut terms = Vec::new();
+                add_search(&mut terms, "error", "");
+                let name = format!("{}.log", reader);
+                let scan = || {
+                    process_reader(
+                        Cursor::new(input.as_bytes()),
+                        Path::new(&name),
+                        &terms,
+                        &ScanOptions::default(),
+                        Some(sink.as_ref()),
+                    )
+                };
+                let scan = match &batch_writer {
+                    Some(writer) => writer.batch(scan),
+                    None => scan(),
+                };
+                assert!(scan.error.is_none());
+            })
+        })
+        .collect();
+    for reader in readers {
+        reader.join().unwrap();
+    }
+    sink.finish().unwrap();
+    started.elapsed()
+}
+
+#[tokio::main]
+async fn main() -> std::io::Result<()> {
+    let dir = std::env::temp_dir().join("elysiumparser-writer-throughput");
+    std::fs::create_dir_all(&dir)?;
+    let lines = READERS * LINES_PER_READER;
+
+    let locked = Arc::new(Mutex::new(File::create(dir.join("locked.log"))?));
+    let elapsed = time_readers(locked, None);
+    println!("{:<20} {} lines in {:.2?}", "Mutex<File>:", lines, elapsed);
+
+    for batched in [false, true] {
+        let writer = Arc::new(WriterSink::spawn(
+            File::create(dir.join("writer.log"))?,
+            DEFAULT_WRITER_CHANNEL_CAPACITY,
+        ));
+        let batch_writer = batched.then(|| Arc::clone(&writer));
+        let elapsed = time_readers(Arc::clone(&writer) as Arc<dyn MatchSink>, batch_writer);
+        writer.close()?;
+        let name = match batched {
+            true => "WriterSink, batched:",
+            false => "WriterSink:",
+        };
+        println!("{:<20} {} lines in {:.2?}", name, lines, elapsed);
+    }
+
+    std::fs::remove_dir_all(&dir)
+}
@@ -0,0 +1,25 @@
+//! Expressions never panic the parser, and every parsed expression renders
+//! back to text that parses to the same expression
+
+#![no_main]
+
+use elysiumparser::BooleanExpression;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|source: &str| {
+    for parse in [
+        BooleanExpression::parse,
+        BooleanExpression::parse_case_sensitive,
+    ] {
+        if let Ok(expression) = parse(source) {
+            let rendered = expression.to_string();
+            assert_eq!(
+                parse(&rendered).as_ref(),
+                Ok(&expression),
+                "{:?} rendered as {:?}",
+                source,
+                rendered
+            );
+        }
+    }
+});
@@ -0,0 +1,79 @@
+//! Arbitrary bytes scanned with arbitrary search terms never panic, and
+//! every match strategy counts the same matches
+
+#![no_main]
+
+use arbitrary::Arbitrary;
+use elysiumparser::{
+    InputFormat, MatchStrategy, ScanOptions, add_search_with_case, process_reader,
+};
+use libfuzzer_sys::fuzz_target;
+use std::io::Cursor;
+use std::path::Path;
+
+#[derive(Arbitrary, Debug)]
+struct Input {
+    /// Keyword and additional expression of each term
+    terms: Vec<(String, String)>,
+    line_filter: String,
+    case_sensitive: bool,
+    input_format: u8,
+    data: Vec<u8>,
+}
+
+fuzz_target!(|input: Input| {
+    // Long regexes and fuzzy patterns only make runs slow
+    if input.terms.len() > 8
+        || input
+            .terms
+            .iter()
+            .any(|(keyword, additional)| keyword.len() + additional.len() > 256)
+    {
+        return;
+    }
+
+    let mut search_terms = Vec::new();
+    for (keyword, additional) in &input.terms {
+        let _ = add_search_with_case(&mut search_terms, keyword, additional, input.case_sensitive);
+    }
+    let input_format = match input.input_format % 3 {
+        0 => InputFormat::Plain,
+        1 => InputFormat::DockerJson,
+        _ => InputFormat::Cri,
+    };
+    let line_filter = match input.case_sensitive {
+        true => input.line_filter.clone(),
+        false => input.line_filter.to_lowercase(),
+    };
+
+    let counts: Vec<usize> = [
+        MatchStrategy::Naive,
+        MatchStrategy::AhoCorasick,
+        MatchStrategy::Prefilter,
+    ]
+    .into_iter()
+    .map(|strategy| {
+        let options = ScanOptions {
+            line_filter: line_filter.clone(),
+            case_sensitive: input.case_sensitive,
+            strategy,
+            input_format,
+            ..Default::default()
+        };
+        process_reader(
+            Cursor::new(&input.data),
+            Path::new("fuzz.log"),
+            &search_terms,
+            &options,
+            None,
+        )
+        .matches
+    })
+    .collect();
+
+    assert!(
+        counts.windows(2).all(|pair| pair[0] == pair[1]),
+        "{:?}",
+        counts
+    );
+});
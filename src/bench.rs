@@ -0,0 +1,196 @@
+use crate::{CompressionKind, FileSystem, MatchStrategy, SearchSet, SearchTerm};
+use std::io::{self, BufRead};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+/// Lowercased lines loaded into memory so matching can be timed without I/O
+#[derive(Clone, Debug, Default)]
+pub struct Sample {
+    pub lines: Vec<String>,
+    /// Bytes read to build the sample, after decompression
+    pub bytes: u64,
+}
+
+/// Timing of a single strategy over a sample
+#[derive(Clone, Debug)]
+pub struct StrategyTiming {
+    pub strategy: MatchStrategy,
+    /// Matching lines found in one pass over the sample
+    pub matches: usize,
+    /// Lines matched across all iterations
+    pub lines: usize,
+    pub elapsed: Duration,
+}
+
+impl StrategyTiming {
+    pub fn lines_per_second(&self) -> f64 {
+        let seconds = self.elapsed.as_secs_f64();
+        if seconds > 0.0 {
+            self.lines as f64 / seconds
+        } else {
+            f64::INFINITY
+        }
+    }
+}
+
+/// Read the discovered files in order until about `max_bytes` have been loaded
+///
+/// Compressed files are decompressed and invalid UTF-8 is replaced, as
+/// during a normal run.
+pub fn load_sample(
+    file_system: &dyn FileSystem,
+    paths: &[PathBuf],
+    max_bytes: u64,
+) -> io::Result<Sample> {
+    let mut sample = Sample::default();
+
+    for path in paths {
+        if sample.bytes >= max_bytes {
+            break;
+        }
+
+        let compression = CompressionKind::from_path(path);
+        let mut reader = crate::compression::log_reader(file_system.open(path)?, compression)?;
+        let mut buffer = Vec::new();
+
+        while sample.bytes < max_bytes {
+            buffer.clear();
+            let bytes = reader.read_until(b'\n', &mut buffer)?;
+            if bytes == 0 {
+                break;
+            }
+            sample.bytes += bytes as u64;
+
+            let line = String::from_utf8_lossy(&buffer);
+            let line = line.strip_suffix('\n').unwrap_or(&line);
+            let line = line.strip_suffix('\r').unwrap_or(line);
+            sample.lines.push(line.to_lowercase());
+        }
+    }
+
+    Ok(sample)
+}
+
+/// Match every line of the sample `iterations` times with one strategy
+pub fn bench_strategy(
+    search_terms: &[SearchTerm],
+    line_filter: &str,
+    strategy: MatchStrategy,
+    sample: &Sample,
+    iterations: usize,
+) -> StrategyTiming {
+    let search_set = SearchSet::new(search_terms, &line_filter.to_lowercase(), strategy);
+    let iterations = iterations.max(1);
+    let mut matches = 0;
+
+    let start = Instant::now();
+    for _ in 0..iterations {
+        matches = sample
+            .lines
+            .iter()
+            .filter(|line| search_set.matching_term(line).is_some())
+            .count();
+    }
+
+    StrategyTiming {
+        strategy,
+        matches,
+        lines: sample.lines.len() * iterations,
+        elapsed: start.elapsed(),
+    }
+}
+
+/// Check that every strategy found the same number of matches
+pub fn counts_agree(timings: &[StrategyTiming]) -> bool {
+    timings
+        .windows(2)
+        .all(|pair| pair[0].matches == pair[1].matches)
+}
+
+/// Timing of one full scan of the files, reading them one way
+#[cfg(feature = "mmap")]
+#[derive(Clone, Debug)]
+pub struct ReadTiming {
+    /// Whether the files were memory-mapped rather than read into a buffer
+    pub mmap: bool,
+    /// Whether every file was evicted from the page cache before the scan
+    pub cold: bool,
+    pub bytes: u64,
+    pub matches: usize,
+    pub elapsed: Duration,
+}
+
+#[cfg(feature = "mmap")]
+impl ReadTiming {
+    pub fn bytes_per_second(&self) -> f64 {
+        let seconds = self.elapsed.as_secs_f64();
+        if seconds > 0.0 {
+            self.bytes as f64 / seconds
+        } else {
+            f64::INFINITY
+        }
+    }
+}
+
+/// Scan every file once, buffered or memory-mapped
+///
+/// With `cold`, the files are first evicted from the page cache where the
+/// platform allows it; `ReadTiming::cold` reports whether that succeeded.
+#[cfg(feature = "mmap")]
+pub fn bench_read(
+    file_system: &dyn FileSystem,
+    paths: &[PathBuf],
+    search_terms: &[SearchTerm],
+    line_filter: &str,
+    mmap: bool,
+    cold: bool,
+) -> io::Result<ReadTiming> {
+    let line_filter = line_filter.to_lowercase();
+    let search_set = SearchSet::new(search_terms, &line_filter, MatchStrategy::default());
+    let options = crate::ScanOptions {
+        line_filter,
+        mmap,
+        ..Default::default()
+    };
+    let evicted = cold && paths.iter().all(|path| evict_from_page_cache(path).is_ok());
+    let mut timing = ReadTiming {
+        mmap,
+        cold: evicted,
+        bytes: 0,
+        matches: 0,
+        elapsed: Duration::ZERO,
+    };
+
+    let start = Instant::now();
+    for path in paths {
+        let compression = CompressionKind::from_path(path);
+        let scan =
+            crate::scan_file(file_system, path, compression, &search_set, &options, None, None)?;
+        timing.bytes += scan.bytes_read;
+        timing.matches += scan.matches;
+    }
+    timing.elapsed = start.elapsed();
+
+    Ok(timing)
+}
+
+/// Drop the cached pages of a file so the next read goes to the disk
+#[cfg(all(feature = "mmap", target_os = "linux"))]
+fn evict_from_page_cache(path: &std::path::Path) -> io::Result<()> {
+    use std::os::fd::AsRawFd;
+
+    let file = std::fs::File::open(path)?;
+    let result = unsafe { libc::posix_fadvise(file.as_raw_fd(), 0, 0, libc::POSIX_FADV_DONTNEED) };
+    if result != 0 {
+        return Err(io::Error::from_raw_os_error(result));
+    }
+    Ok(())
+}
+
+#[cfg(all(feature = "mmap", not(target_os = "linux")))]
+fn evict_from_page_cache(_path: &std::path::Path) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "page cache eviction is not supported on this platform",
+    ))
+}
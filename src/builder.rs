@@ -0,0 +1,367 @@
+use crate::filename_filter::PathPatterns;
+use crate::{
+    BooleanExpression, FilenameFilter, OutputMode, OutputTarget, ParseError, ParserConfig, RunMode,
+    STDIN_LOG_FOLDER, STDOUT_OUTPUT_LOG, SearchTerm, Term, add_search_with_case,
+    has_allowed_extension, parse_keyword,
+};
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Why `ParserConfigBuilder::build` rejected a configuration
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ConfigError {
+    /// The log folder is empty; use `STDIN_LOG_FOLDER` to read standard input
+    EmptyLogFolder,
+    /// Neither a search term nor a line filter was given, so no line could match
+    NoSearchTerms,
+    /// `workers` was set to 0, so no file would ever be scanned
+    ZeroWorkers,
+    /// The folder the output log goes in does not exist; `run_parser` only
+    /// creates `log_folder` and its parents
+    MissingOutputFolder(PathBuf),
+    /// The output log is in the log folder but written as another path than
+    /// the one the folder is searched by, or is one of the input files, so
+    /// the run would scan its own output
+    OutputInsideLogFolder(PathBuf),
+    /// The expression of a search term does not parse
+    InvalidExpression { keyword: String, error: ParseError },
+    /// The filename filter is a malformed glob pattern
+    InvalidFilenameFilter { filter: String, message: String },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::EmptyLogFolder => write!(f, "log folder is empty"),
+            ConfigError::NoSearchTerms => write!(f, "no search term or line filter given"),
+            ConfigError::ZeroWorkers => write!(f, "workers must be at least 1"),
+            ConfigError::MissingOutputFolder(folder) => {
+                write!(f, "output folder '{}' does not exist", folder.display())
+            }
+            ConfigError::OutputInsideLogFolder(output_log) => write!(
+                f,
+                "output log '{}' would be searched as a log file",
+                output_log.display()
+            ),
+            ConfigError::InvalidExpression { keyword, error } => {
+                write!(f, "invalid expression for '{}': {}", keyword, error)
+            }
+            ConfigError::InvalidFilenameFilter { filter, message } => {
+                write!(f, "invalid filename filter '{}': {}", filter, message)
+            }
+        }
+    }
+}
+
+impl Error for ConfigError {}
+
+/// Search term added to a builder, compiled by `build` once the case
+/// sensitivity of the run is known
+enum PendingTerm {
+    /// Keyword with an optional literal, like `add_search`
+    Literal { keyword: String, additional: String },
+    /// Keyword with a boolean expression, like `add_search_with_expression`
+    Expression { keyword: String, expression: String },
+    /// Term compiled by the caller
+    Compiled(SearchTerm),
+}
+
+/// Validating builder of `ParserConfig`
+///
+/// Settings without a method here keep their default and can be changed on
+/// the built configuration, whose fields are all public.
+///
+/// ```no_run
+/// # use elysiumparser::ParserConfig;
+/// let config = ParserConfig::builder()
+///     .log_folder("logs/application")
+///     .output_log("logs/results.log")
+///     .add_search_expression("error", "database | timeout")
+///     .workers(4)
+///     .build()?;
+/// # Ok::<(), elysiumparser::ConfigError>(())
+/// ```
+pub struct ParserConfigBuilder {
+    config: ParserConfig,
+    terms: Vec<PendingTerm>,
+}
+
+impl ParserConfigBuilder {
+    pub fn new() -> Self {
+        Self {
+            config: ParserConfig::default(),
+            terms: Vec::new(),
+        }
+    }
+
+    pub fn log_folder(mut self, log_folder: impl Into<String>) -> Self {
+        self.config.log_folder = log_folder.into();
+        self
+    }
+
+    /// Search this folder too, besides `log_folder` and the folders added
+    /// before (see `ParserConfig::log_folders`)
+    pub fn add_log_folder(mut self, log_folder: impl Into<String>) -> Self {
+        self.config.log_folders.push(log_folder.into());
+        self
+    }
+
+    /// Read this file instead of searching the log folders, after the files
+    /// added before (see `ParserConfig::input_files`)
+    pub fn add_input_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.config.input_files.push(path.into());
+        self
+    }
+
+    pub fn output_log(mut self, output_log: impl Into<String>) -> Self {
+        self.config.output_log = output_log.into();
+        self
+    }
+
+    /// Write the matches of each log to its own file below `base_output_dir`,
+    /// instead of all of them to the output log
+    pub fn mirrored_output(mut self, base_output_dir: impl Into<PathBuf>) -> Self {
+        self.config.output_mode = OutputMode::MirroredTree {
+            base_output_dir: base_output_dir.into(),
+        };
+        self
+    }
+
+    pub fn filename_filter(mut self, filename_filter: impl Into<String>) -> Self {
+        self.config.filename_filter = filename_filter.into();
+        self
+    }
+
+    /// Only read the files matching `pattern`, or one of the patterns when
+    /// called several times (see `ParserConfig::include_patterns`)
+    pub fn include(mut self, pattern: impl Into<String>) -> Self {
+        self.config.include_patterns.push(pattern.into());
+        self
+    }
+
+    /// Skip the files matching `pattern`, even when included
+    pub fn exclude(mut self, pattern: impl Into<String>) -> Self {
+        self.config.exclude_patterns.push(pattern.into());
+        self
+    }
+
+    pub fn line_filter(mut self, line_filter: impl Into<String>) -> Self {
+        self.config.line_filter = line_filter.into();
+        self
+    }
+
+    /// Add a term matching `keyword`, and `additional` too unless it is empty
+    pub fn add_search(mut self, keyword: &str, additional: &str) -> Self {
+        self.terms.push(PendingTerm::Literal {
+            keyword: keyword.to_string(),
+            additional: additional.to_string(),
+        });
+        self
+    }
+
+    /// Add a term matching `keyword` and the boolean `expression`, which is
+    /// parsed by `build`
+    pub fn add_search_expression(mut self, keyword: &str, expression: &str) -> Self {
+        self.terms.push(PendingTerm::Expression {
+            keyword: keyword.to_string(),
+            expression: expression.to_string(),
+        });
+        self
+    }
+
+    /// Add a term compiled beforehand, which keeps the case it was compiled
+    /// with
+    pub fn search_term(mut self, term: SearchTerm) -> Self {
+        self.terms.push(PendingTerm::Compiled(term));
+        self
+    }
+
+    /// Match the terms and line filter with their case as written, whenever
+    /// they were added
+    pub fn case_sensitive(mut self, case_sensitive: bool) -> Self {
+        self.config.case_sensitive = case_sensitive;
+        self
+    }
+
+    pub fn workers(mut self, workers: usize) -> Self {
+        self.config.workers = Some(workers);
+        self
+    }
+
+    /// Split the plain logs of at least `threshold_bytes` among the workers
+    pub fn split_threshold_bytes(mut self, threshold_bytes: u64) -> Self {
+        self.config.split_threshold_bytes = Some(threshold_bytes);
+        self
+    }
+
+    pub fn recursive(mut self, recursive: bool) -> Self {
+        self.config.recursive = recursive;
+        self
+    }
+
+    pub fn invert_match(mut self, invert_match: bool) -> Self {
+        self.config.invert_match = invert_match;
+        self
+    }
+
+    pub fn mode(mut self, mode: RunMode) -> Self {
+        self.config.mode = mode;
+        self
+    }
+
+    pub fn append(mut self, append: bool) -> Self {
+        self.config.append = append;
+        self
+    }
+
+    pub fn ordered_output(mut self, ordered_output: bool) -> Self {
+        self.config.ordered_output = ordered_output;
+        self
+    }
+
+    pub fn max_matches(mut self, max_matches: usize) -> Self {
+        self.config.max_matches = Some(max_matches);
+        self
+    }
+
+    pub fn max_matches_per_file(mut self, max_matches: usize) -> Self {
+        self.config.max_matches_per_file = Some(max_matches);
+        self
+    }
+
+    /// Validate the settings and compile the search terms
+    ///
+    /// The terms are compiled with the final case sensitivity.
+    pub fn build(self) -> Result<ParserConfig, ConfigError> {
+        let mut config = self.config;
+        if config.log_folder.is_empty() {
+            return Err(ConfigError::EmptyLogFolder);
+        }
+
+        for term in self.terms {
+            push_term(&mut config.search_terms, term, config.case_sensitive)?;
+        }
+
+        if config.search_terms.is_empty() && config.line_filter.is_empty() {
+            return Err(ConfigError::NoSearchTerms);
+        }
+        if config.workers == Some(0) {
+            return Err(ConfigError::ZeroWorkers);
+        }
+        FilenameFilter::new(&config.filename_filter).map_err(|e| {
+            ConfigError::InvalidFilenameFilter {
+                filter: config.filename_filter.clone(),
+                message: e.to_string(),
+            }
+        })?;
+        PathPatterns::new(&config.include_patterns, &config.exclude_patterns).map_err(
+            |(filter, e)| ConfigError::InvalidFilenameFilter {
+                filter,
+                message: e.to_string(),
+            },
+        )?;
+
+        let writes_output_log = config.mode == RunMode::Normal
+            && config.output_target == OutputTarget::OutputLog
+            && config.output_mode == OutputMode::SingleFile
+            && config.output_log != STDOUT_OUTPUT_LOG;
+        if writes_output_log
+            && let Some(folder) = Path::new(&config.output_log).parent()
+            && !folder.as_os_str().is_empty()
+            && !Path::new(&config.log_folder).starts_with(folder)
+            && !folder.is_dir()
+        {
+            return Err(ConfigError::MissingOutputFolder(folder.to_path_buf()));
+        }
+        if writes_output_log && output_scanned_as_input(&config) {
+            return Err(ConfigError::OutputInsideLogFolder(PathBuf::from(
+                &config.output_log,
+            )));
+        }
+
+        Ok(config)
+    }
+}
+
+impl Default for ParserConfigBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Whether `run_parser` would scan the output log as input: it only skips
+/// the output log when the search of a log folder reaches it by the path it
+/// was written as, and never when it is one of the input files
+fn output_scanned_as_input(config: &ParserConfig) -> bool {
+    let output_log = Path::new(&config.output_log);
+    if config.log_folder != STDIN_LOG_FOLDER && !config.input_files.is_empty() {
+        let output = fs::canonicalize(output_log);
+        return config.input_files.iter().any(|path| {
+            path == output_log
+                || output
+                    .as_ref()
+                    .is_ok_and(|output| fs::canonicalize(path).is_ok_and(|path| path == *output))
+        });
+    }
+    if config.log_folder == STDIN_LOG_FOLDER
+        || !has_allowed_extension(output_log, &config.allowed_extensions)
+    {
+        return false;
+    }
+    // The folders exist by now, unless the run creates them and the output
+    // log with them
+    let output = output_log
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .map_or_else(|| fs::canonicalize("."), fs::canonicalize)
+        .map(|parent| parent.join(output_log.file_name().unwrap_or_default()));
+    let Ok(output) = output else {
+        return false;
+    };
+    config.log_roots().iter().any(|log_folder| {
+        let Ok(folder) = fs::canonicalize(log_folder) else {
+            return false;
+        };
+        let Ok(relative) = output.strip_prefix(&folder) else {
+            return false;
+        };
+        let searched = config.recursive || relative.components().count() == 1;
+        searched && log_folder.join(relative) != output_log
+    })
+}
+
+fn push_term(
+    search_terms: &mut Vec<SearchTerm>,
+    term: PendingTerm,
+    case_sensitive: bool,
+) -> Result<(), ConfigError> {
+    match term {
+        PendingTerm::Literal {
+            keyword,
+            additional,
+        } => {
+            let (keyword, keyword_pattern) = parse_keyword(&keyword, case_sensitive)
+                .unwrap_or_else(|_| (keyword.clone(), None));
+            search_terms.push(SearchTerm {
+                keyword,
+                keyword_pattern,
+                additional_expression: (!additional.is_empty())
+                    .then(|| BooleanExpression::Term(Term::Literal(additional))),
+                score: 0,
+                output: None,
+            });
+            Ok(())
+        }
+        PendingTerm::Expression {
+            keyword,
+            expression,
+        } => add_search_with_case(search_terms, &keyword, &expression, case_sensitive)
+            .map_err(|error| ConfigError::InvalidExpression { keyword, error }),
+        PendingTerm::Compiled(term) => {
+            search_terms.push(term);
+            Ok(())
+        }
+    }
+}
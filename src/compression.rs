@@ -0,0 +1,121 @@
+use flate2::read::GzDecoder;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::Path;
+
+/// Start of a bzip2 block, after the `BZh` header and block size digit
+const BZIP2_BLOCK_MAGIC: [u8; 6] = [0x31, 0x41, 0x59, 0x26, 0x53, 0x59];
+/// Start of the end of stream marker, which follows the header of an empty stream
+const BZIP2_END_MAGIC: [u8; 6] = [0x17, 0x72, 0x45, 0x38, 0x50, 0x90];
+
+/// Compression format of a log archive, recognized by its extension or,
+/// for files named like plain logs, by its first bytes
+///
+/// Gzip is always supported; bzip2, zstd and xz need the `bzip2`, `zstd`
+/// and `xz` features. Without its feature an extension is not recognized,
+/// so such archives are skipped like any other non-log file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CompressionKind {
+    /// `.gz`
+    Gzip,
+    /// `.bz2`
+    Bzip2,
+    /// `.zst`
+    Zstd,
+    /// `.xz`
+    Xz,
+}
+
+impl CompressionKind {
+    /// Kind of an archive named `path`, if its format is enabled
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?;
+        match extension.to_ascii_lowercase().as_str() {
+            "gz" => Some(CompressionKind::Gzip),
+            "bz2" if cfg!(feature = "bzip2") => Some(CompressionKind::Bzip2),
+            "zst" if cfg!(feature = "zstd") => Some(CompressionKind::Zstd),
+            "xz" if cfg!(feature = "xz") => Some(CompressionKind::Xz),
+            _ => None,
+        }
+    }
+
+    /// Kind of an archive starting with `bytes`, if its format is enabled
+    ///
+    /// Catches archives whose name does not tell, like a rotated `app.log`
+    /// that was compressed in place. Every magic is binary, so plain text
+    /// logs are never mistaken for archives.
+    pub fn from_magic(bytes: &[u8]) -> Option<Self> {
+        if bytes.starts_with(&[0x1f, 0x8b, 0x08]) {
+            return Some(CompressionKind::Gzip);
+        }
+        if cfg!(feature = "bzip2")
+            && let [b'B', b'Z', b'h', b'1'..=b'9', block @ ..] = bytes
+            && (block.starts_with(&BZIP2_BLOCK_MAGIC) || block.starts_with(&BZIP2_END_MAGIC))
+        {
+            return Some(CompressionKind::Bzip2);
+        }
+        if cfg!(feature = "zstd") && bytes.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+            return Some(CompressionKind::Zstd);
+        }
+        if cfg!(feature = "xz") && bytes.starts_with(&[0xfd, b'7', b'z', b'X', b'Z', 0x00]) {
+            return Some(CompressionKind::Xz);
+        }
+        None
+    }
+
+    /// Wrap `reader` in the decoder of this format
+    pub fn decoder<'a>(
+        self,
+        reader: Box<dyn Read + Send + 'a>,
+    ) -> io::Result<Box<dyn Read + Send + 'a>> {
+        match self {
+            CompressionKind::Gzip => Ok(Box::new(GzDecoder::new(reader))),
+            #[cfg(feature = "bzip2")]
+            CompressionKind::Bzip2 => Ok(Box::new(bzip2::read::BzDecoder::new(reader))),
+            #[cfg(feature = "zstd")]
+            CompressionKind::Zstd => Ok(Box::new(zstd::Decoder::new(reader)?)),
+            #[cfg(feature = "xz")]
+            CompressionKind::Xz => Ok(Box::new(xz2::read::XzDecoder::new(reader))),
+            #[allow(unreachable_patterns)]
+            kind => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("{} support is not enabled in this build", kind),
+            )),
+        }
+    }
+}
+
+impl fmt::Display for CompressionKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            CompressionKind::Gzip => "gzip",
+            CompressionKind::Bzip2 => "bzip2",
+            CompressionKind::Zstd => "zstd",
+            CompressionKind::Xz => "xz",
+        })
+    }
+}
+
+/// Buffer `reader`, decompressing it as `kind`, or as the format its first
+/// bytes show when `kind` is `None`
+pub(crate) fn log_reader<'a>(
+    reader: Box<dyn Read + Send + 'a>,
+    kind: Option<CompressionKind>,
+) -> io::Result<Box<dyn BufRead + Send + 'a>> {
+    let mut reader = BufReader::new(reader);
+    let kind = match kind {
+        Some(kind) => Some(kind),
+        None => CompressionKind::from_magic(reader.fill_buf()?),
+    };
+    match kind {
+        Some(kind) => Ok(Box::new(BufReader::new(kind.decoder(Box::new(reader))?))),
+        None => Ok(Box::new(reader)),
+    }
+}
+
+/// Open a log file, decompressing it when its extension or first bytes
+/// show an enabled archive format
+pub fn open_log_reader(path: &Path) -> io::Result<Box<dyn BufRead + Send>> {
+    log_reader(Box::new(File::open(path)?), CompressionKind::from_path(path))
+}
@@ -0,0 +1,138 @@
+use crate::labels::NO_LABELS;
+use crate::{SearchTerm, term_at};
+use crate::sink::{BlockLine, MatchRecord, MatchSink};
+use std::collections::VecDeque;
+use std::io;
+use std::path::Path;
+
+/// A line held until its block is written
+enum HeldLine {
+    Context {
+        line_number: usize,
+        line: String,
+    },
+    Match {
+        line_number: usize,
+        term_index: usize,
+        score: i32,
+        line: String,
+    },
+}
+
+/// Groups the matches of a reader with the lines around them, like grep's `-B`/`-A`
+///
+/// Matches whose windows overlap or touch share one block. Blocks are only
+/// written once no later line can join them, and all but the first block of
+/// a reader start with a separator.
+pub(crate) struct ContextWindow {
+    before: usize,
+    after: usize,
+    /// Up to `before` lines since the current block, candidates for the next one
+    recent: VecDeque<(usize, String)>,
+    block: Vec<HeldLine>,
+    /// Lines still owed to the current block after its last match
+    after_left: usize,
+    blocks_written: usize,
+}
+
+impl ContextWindow {
+    pub(crate) fn new(before: usize, after: usize) -> Self {
+        Self {
+            before,
+            after,
+            recent: VecDeque::with_capacity(before),
+            block: Vec::new(),
+            after_left: 0,
+            blocks_written: 0,
+        }
+    }
+
+    /// Add a matched line, pulling the lines before it into the current block
+    pub(crate) fn push_match(
+        &mut self,
+        line_number: usize,
+        term_index: usize,
+        score: i32,
+        line: &str,
+    ) {
+        self.block.extend(
+            self.recent
+                .drain(..)
+                .map(|(line_number, line)| HeldLine::Context { line_number, line }),
+        );
+        self.block.push(HeldLine::Match {
+            line_number,
+            term_index,
+            score,
+            line: line.to_string(),
+        });
+        self.after_left = self.after;
+    }
+
+    /// Add a line that did not match
+    ///
+    /// Returns true when the current block can no longer grow and should be
+    /// written before the next match.
+    pub(crate) fn push_line(&mut self, line_number: usize, line: &str) -> bool {
+        if self.after_left > 0 {
+            self.after_left -= 1;
+            self.block.push(HeldLine::Context {
+                line_number,
+                line: line.to_string(),
+            });
+            return false;
+        }
+
+        // Once a line falls out of `recent`, the next block cannot touch this one
+        let complete = !self.block.is_empty() && self.recent.len() == self.before;
+        if self.before > 0 {
+            if self.recent.len() == self.before {
+                self.recent.pop_front();
+            }
+            self.recent.push_back((line_number, line.to_string()));
+        }
+        complete
+    }
+
+    /// Write the current block, if any
+    pub(crate) fn flush(
+        &mut self,
+        output: &dyn MatchSink,
+        source: &Path,
+        search_terms: &[SearchTerm],
+    ) -> io::Result<()> {
+        if self.block.is_empty() {
+            return Ok(());
+        }
+
+        let mut lines = Vec::with_capacity(self.block.len() + 1);
+        if self.blocks_written > 0 {
+            lines.push(BlockLine::Separator);
+        }
+        lines.extend(self.block.iter().map(|held| match held {
+            HeldLine::Context { line_number, line } => BlockLine::Context {
+                line_number: *line_number,
+                line,
+            },
+            HeldLine::Match {
+                line_number,
+                term_index,
+                score,
+                line,
+            } => BlockLine::Match(MatchRecord {
+                source,
+                line_number: *line_number,
+                term: term_at(search_terms, *term_index),
+                term_index: *term_index,
+                score: *score,
+                line,
+                labels: &NO_LABELS,
+            }),
+        }));
+
+        let written = output.write_block(source, &lines);
+        self.blocks_written += 1;
+        self.block.clear();
+        written
+    }
+}
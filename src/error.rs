@@ -0,0 +1,55 @@
+use crate::CompressionKind;
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+/// What went wrong with a file or directory of a run
+#[derive(Debug)]
+pub enum ParserError {
+    /// The file could not be opened
+    Open(io::Error),
+    /// The archive could not be opened for decompression
+    Decompress {
+        kind: CompressionKind,
+        error: io::Error,
+    },
+    /// Reading failed midway, like on a corrupt archive; the matches found
+    /// until then are kept
+    Read(io::Error),
+    /// A directory of the log folder could not be listed
+    ReadDirectory(io::Error),
+    /// The matches could not be written
+    Write(io::Error),
+    /// The search terms or line filter do not compile
+    InvalidPattern(String),
+}
+
+impl fmt::Display for ParserError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParserError::Open(e) => write!(f, "error opening file: {}", e),
+            ParserError::Decompress { kind, error } => {
+                write!(f, "error processing {} file: {}", kind, error)
+            }
+            ParserError::Read(e) => write!(f, "error reading: {}", e),
+            ParserError::ReadDirectory(e) => write!(f, "error reading directory: {}", e),
+            ParserError::Write(e) => write!(f, "error writing to output file: {}", e),
+            ParserError::InvalidPattern(message) => {
+                write!(f, "invalid search pattern: {}", message)
+            }
+        }
+    }
+}
+
+impl Error for ParserError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ParserError::Open(e)
+            | ParserError::Decompress { error: e, .. }
+            | ParserError::Read(e)
+            | ParserError::ReadDirectory(e)
+            | ParserError::Write(e) => Some(e),
+            ParserError::InvalidPattern(_) => None,
+        }
+    }
+}
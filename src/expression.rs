@@ -0,0 +1,275 @@
+use crate::{BooleanExpression, Term};
+use std::error::Error;
+use std::fmt;
+
+/// What is wrong with an expression that failed to parse
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    /// The expression, or a parenthesized group, has no terms
+    Empty,
+    /// An operator is missing its operand, like the `&` of `a &`
+    DanglingOperator(char),
+    /// A `(` is never closed
+    UnclosedParen,
+    /// A `)` has no matching `(`
+    UnmatchedParen,
+    /// Two operands follow each other without an operator, like `(a) b`
+    MissingOperator,
+    /// A `"` is never closed
+    UnclosedQuote,
+    /// A `re:` atom is not a valid regular expression
+    InvalidRegex(String),
+}
+
+/// Error returned by `BooleanExpression::parse`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    /// Byte offset in the expression where the problem was found
+    pub position: usize,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            ParseErrorKind::Empty => write!(f, "empty expression at position {}", self.position),
+            ParseErrorKind::DanglingOperator(operator) => write!(
+                f,
+                "'{}' at position {} is missing an operand",
+                operator, self.position
+            ),
+            ParseErrorKind::UnclosedParen => {
+                write!(f, "'(' at position {} is never closed", self.position)
+            }
+            ParseErrorKind::UnmatchedParen => {
+                write!(f, "')' at position {} has no matching '('", self.position)
+            }
+            ParseErrorKind::MissingOperator => {
+                write!(f, "expected '&' or '|' at position {}", self.position)
+            }
+            ParseErrorKind::UnclosedQuote => {
+                write!(f, "'\"' at position {} is never closed", self.position)
+            }
+            ParseErrorKind::InvalidRegex(error) => {
+                write!(f, "invalid regex at position {}: {}", self.position, error)
+            }
+        }
+    }
+}
+
+impl Error for ParseError {}
+
+/// How the parser treats the case of atoms
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum AtomCase {
+    /// Literal and fuzzy atoms are lowercased; regexes ignore case
+    Lowercase,
+    /// Atoms are kept as written, for a `SearchSet` to lowercase in
+    /// case-insensitive runs; regexes ignore case
+    AsWritten,
+    /// Atoms are kept as written; regexes match text of the same case
+    Sensitive,
+}
+
+/// Recursive-descent parser; `&` binds tighter than `|` and `!` tighter than both
+///
+/// ```text
+/// or      := and ('|' and)*
+/// and     := unary ('&' unary)*
+/// unary   := '!' unary | primary
+/// primary := '(' or ')' | atom
+/// ```
+///
+/// An atom is any run of text without `&`, `|`, `(` or `)`, trimmed. Parts
+/// of an atom in double quotes may contain those characters, and `\"` inside
+/// quotes stands for a quote. Atoms are read as `case` says.
+pub(crate) fn parse(input: &str, case: AtomCase) -> Result<BooleanExpression, ParseError> {
+    let mut parser = Parser {
+        input,
+        position: 0,
+        case,
+    };
+    if parser.peek().is_none() {
+        return Err(parser.error(ParseErrorKind::Empty));
+    }
+
+    let expression = parser.parse_or()?;
+    match parser.peek() {
+        None => Ok(expression),
+        Some(')') => Err(parser.error(ParseErrorKind::UnmatchedParen)),
+        Some(_) => Err(parser.error(ParseErrorKind::MissingOperator)),
+    }
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    position: usize,
+    case: AtomCase,
+}
+
+impl Parser<'_> {
+    /// Next non-whitespace character, leaving the position on it
+    fn peek(&mut self) -> Option<char> {
+        let rest = &self.input[self.position..];
+        let trimmed = rest.trim_start();
+        self.position += rest.len() - trimmed.len();
+        trimmed.chars().next()
+    }
+
+    fn error(&self, kind: ParseErrorKind) -> ParseError {
+        ParseError {
+            kind,
+            position: self.position,
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<BooleanExpression, ParseError> {
+        let first = self.parse_and()?;
+        if self.peek() != Some('|') {
+            return Ok(first);
+        }
+
+        let mut expressions = vec![Box::new(first)];
+        while self.peek() == Some('|') {
+            let operator = self.position;
+            self.position += 1;
+            expressions.push(Box::new(self.parse_operand(
+                '|',
+                operator,
+                Self::parse_and,
+            )?));
+        }
+        Ok(BooleanExpression::Or(expressions))
+    }
+
+    fn parse_and(&mut self) -> Result<BooleanExpression, ParseError> {
+        let first = self.parse_unary()?;
+        if self.peek() != Some('&') {
+            return Ok(first);
+        }
+
+        let mut expressions = vec![first];
+        while self.peek() == Some('&') {
+            let operator = self.position;
+            self.position += 1;
+            expressions.push(self.parse_operand('&', operator, Self::parse_unary)?);
+        }
+        // Evaluate the cheap terms first
+        expressions.sort_by_key(BooleanExpression::cost);
+        Ok(BooleanExpression::And(expressions))
+    }
+
+    fn parse_unary(&mut self) -> Result<BooleanExpression, ParseError> {
+        if self.peek() == Some('!') {
+            let operator = self.position;
+            self.position += 1;
+            let inner = self.parse_operand('!', operator, Self::parse_unary)?;
+            return Ok(BooleanExpression::Not(Box::new(inner)));
+        }
+        self.parse_primary()
+    }
+
+    /// Parse the operand of the operator at `operator`, which must be present
+    fn parse_operand(
+        &mut self,
+        symbol: char,
+        operator: usize,
+        parse: fn(&mut Self) -> Result<BooleanExpression, ParseError>,
+    ) -> Result<BooleanExpression, ParseError> {
+        match self.peek() {
+            None | Some('&' | '|' | ')') => Err(ParseError {
+                kind: ParseErrorKind::DanglingOperator(symbol),
+                position: operator,
+            }),
+            Some(_) => parse(self),
+        }
+    }
+
+    fn parse_primary(&mut self) -> Result<BooleanExpression, ParseError> {
+        match self.peek() {
+            Some('(') => {
+                let open = self.position;
+                self.position += 1;
+                if self.peek() == Some(')') {
+                    return Err(ParseError {
+                        kind: ParseErrorKind::Empty,
+                        position: open,
+                    });
+                }
+
+                let inner = self.parse_or()?;
+                match self.peek() {
+                    Some(')') => {
+                        self.position += 1;
+                        Ok(inner)
+                    }
+                    None => Err(ParseError {
+                        kind: ParseErrorKind::UnclosedParen,
+                        position: open,
+                    }),
+                    Some(_) => Err(self.error(ParseErrorKind::MissingOperator)),
+                }
+            }
+            Some(')') => Err(self.error(ParseErrorKind::UnmatchedParen)),
+            Some(operator @ ('&' | '|')) => {
+                Err(self.error(ParseErrorKind::DanglingOperator(operator)))
+            }
+            None => Err(self.error(ParseErrorKind::Empty)),
+            Some(_) => {
+                let start = self.position;
+                let atom = self.take_atom()?;
+                Term::parse_atom(&atom, self.case)
+                    .map(BooleanExpression::Term)
+                    .map_err(|e| ParseError {
+                        kind: ParseErrorKind::InvalidRegex(e.to_string()),
+                        position: start,
+                    })
+            }
+        }
+    }
+
+    /// Read an atom up to the next operator or parenthesis outside quotes
+    fn take_atom(&mut self) -> Result<String, ParseError> {
+        let start = self.position;
+        let mut atom = String::new();
+        // Length of the atom without trailing unquoted whitespace
+        let mut kept = 0;
+        let mut quote = None;
+        let mut chars = self.input[start..].char_indices().peekable();
+
+        while let Some((offset, c)) = chars.next() {
+            match c {
+                '"' if quote.is_none() => quote = Some(start + offset),
+                '"' => {
+                    quote = None;
+                    kept = atom.len();
+                }
+                '\\' if quote.is_some() && chars.peek().is_some_and(|(_, next)| *next == '"') => {
+                    chars.next();
+                    atom.push('"');
+                }
+                '&' | '|' | '(' | ')' if quote.is_none() => {
+                    self.position = start + offset;
+                    atom.truncate(kept);
+                    return Ok(atom);
+                }
+                c => {
+                    atom.push(c);
+                    if quote.is_some() || !c.is_whitespace() {
+                        kept = atom.len();
+                    }
+                }
+            }
+        }
+
+        if let Some(position) = quote {
+            return Err(ParseError {
+                kind: ParseErrorKind::UnclosedQuote,
+                position,
+            });
+        }
+        self.position = self.input.len();
+        atom.truncate(kept);
+        Ok(atom)
+    }
+}
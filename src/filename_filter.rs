@@ -0,0 +1,158 @@
+use glob::{MatchOptions, Pattern, PatternError};
+use std::path::Path;
+
+const MATCH_OPTIONS: MatchOptions = MatchOptions {
+    case_sensitive: false,
+    require_literal_separator: false,
+    require_literal_leading_dot: false,
+};
+
+/// A compiled `ParserConfig::filename_filter`
+///
+/// A filter containing `*`, `?`, `[` or `{` is a glob pattern matched against
+/// the whole file name, like `app-*.log`, `[ab]*.log` or
+/// `app.{access,error}.log`. Any other filter is a substring that must appear
+/// in the file name. Both ignore case, and an empty filter matches every file.
+///
+/// Archives are matched by their whole name too, so `*.log` leaves out
+/// `app.log.gz`; `*.log*` takes both.
+#[derive(Clone, Debug)]
+pub enum FilenameFilter {
+    /// Lowercase text the file name must contain
+    Substring(String),
+    /// Patterns the whole file name must match one of, one for each
+    /// alternative of the braces of the filter
+    Glob(Vec<Pattern>),
+}
+
+impl FilenameFilter {
+    /// Compile `filter`, failing on a malformed glob pattern like `app[`
+    pub fn new(filter: &str) -> Result<Self, PatternError> {
+        if filter.contains(['*', '?', '[', '{']) {
+            Ok(FilenameFilter::Glob(compile(filter)?))
+        } else {
+            Ok(FilenameFilter::Substring(filter.to_lowercase()))
+        }
+    }
+
+    /// Whether the file name of `path` passes the filter
+    pub fn matches(&self, path: &Path) -> bool {
+        let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+            return false;
+        };
+        match self {
+            FilenameFilter::Substring(text) => name.to_lowercase().contains(text.as_str()),
+            FilenameFilter::Glob(patterns) => patterns
+                .iter()
+                .any(|pattern| pattern.matches_with(name, MATCH_OPTIONS)),
+        }
+    }
+
+    /// Like `matches`, but substrings may appear anywhere in the path
+    ///
+    /// Archives and rotated logs have always been filtered on their whole path.
+    pub(crate) fn matches_path(&self, path: &Path) -> bool {
+        match self {
+            FilenameFilter::Substring(text) => path
+                .to_string_lossy()
+                .to_lowercase()
+                .contains(text.as_str()),
+            FilenameFilter::Glob(_) => self.matches(path),
+        }
+    }
+}
+
+const PATH_MATCH_OPTIONS: MatchOptions = MatchOptions {
+    require_literal_separator: true,
+    ..MATCH_OPTIONS
+};
+
+/// Compiled `ParserConfig::include_patterns` and `exclude_patterns`
+///
+/// A pattern with a `/` is matched against the path relative to the log
+/// folder, like `2024/**/*.log`, where `*` stops at separators; any other
+/// pattern is matched against the file name. Both ignore case.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct PathPatterns {
+    include: Vec<Pattern>,
+    exclude: Vec<Pattern>,
+}
+
+impl PathPatterns {
+    /// Compile the patterns, failing with the first malformed one
+    pub fn new(include: &[String], exclude: &[String]) -> Result<Self, (String, PatternError)> {
+        let compile = |patterns: &[String]| {
+            patterns
+                .iter()
+                .map(|pattern| compile(pattern).map_err(|e| (pattern.clone(), e)))
+                .collect::<Result<Vec<_>, _>>()
+                .map(|patterns| patterns.concat())
+        };
+        Ok(Self {
+            include: compile(include)?,
+            exclude: compile(exclude)?,
+        })
+    }
+
+    /// Whether the file at `relative` in the log folder matches an include
+    /// pattern, if there are any, and no exclude pattern
+    pub fn matches(&self, relative: &Path) -> bool {
+        let matching = |pattern: &Pattern| match pattern.as_str().contains('/') {
+            true => pattern.matches_path_with(relative, PATH_MATCH_OPTIONS),
+            false => relative
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| pattern.matches_with(name, MATCH_OPTIONS)),
+        };
+        (self.include.is_empty() || self.include.iter().any(matching))
+            && !self.exclude.iter().any(matching)
+    }
+}
+
+/// Compile a glob pattern, expanding its braces into one pattern per
+/// alternative, since `glob` has no braces
+fn compile(pattern: &str) -> Result<Vec<Pattern>, PatternError> {
+    expand_braces(pattern)
+        .iter()
+        .map(|pattern| Pattern::new(pattern))
+        .collect()
+}
+
+/// The patterns `{a,b}` stands for, like `app.a.log` and `app.b.log` for
+/// `app.{a,b}.log`; braces nest, and an unclosed brace is kept as written
+fn expand_braces(pattern: &str) -> Vec<String> {
+    let Some(open) = pattern.find('{') else {
+        return vec![pattern.to_string()];
+    };
+    let mut depth = 0;
+    let mut alternatives = Vec::new();
+    let mut start = open + 1;
+    for (index, c) in pattern[open..].char_indices().map(|(i, c)| (open + i, c)) {
+        match c {
+            '{' => depth += 1,
+            ',' if depth == 1 => {
+                alternatives.push(&pattern[start..index]);
+                start = index + 1;
+            }
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    alternatives.push(&pattern[start..index]);
+                    let (prefix, suffix) = (&pattern[..open], &pattern[index + 1..]);
+                    return alternatives
+                        .into_iter()
+                        .flat_map(|alternative| {
+                            expand_braces(&format!("{}{}{}", prefix, alternative, suffix))
+                        })
+                        .collect();
+                }
+            }
+            _ => {}
+        }
+    }
+    // Unclosed, so literal: expand the braces after it only
+    expand_braces(&pattern[open + 1..])
+        .into_iter()
+        .map(|rest| format!("{}{}", &pattern[..=open], rest))
+        .collect()
+}
@@ -0,0 +1,133 @@
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// The subset of file metadata the parser relies on
+#[derive(Clone, Debug, Default)]
+pub struct FileMetadata {
+    pub is_file: bool,
+    pub is_dir: bool,
+    pub len: u64,
+    pub modified: Option<SystemTime>,
+    /// Identifies the file behind the path, like its inode, to tell when a
+    /// followed log was replaced; `None` where the source has no such id
+    pub file_id: Option<u64>,
+}
+
+/// File-system operations used by `run_parser` to discover and read logs
+///
+/// The default `StdFileSystem` goes to the local disk; other implementations
+/// can serve virtual sources or inject failures in tests.
+pub trait FileSystem: Send + Sync {
+    /// List the entries of a directory; each entry may fail on its own
+    fn list_dir(&self, path: &Path) -> io::Result<Vec<io::Result<PathBuf>>>;
+
+    /// Fetch the metadata of a path
+    fn metadata(&self, path: &Path) -> io::Result<FileMetadata>;
+
+    /// Open a file for reading
+    fn open(&self, path: &Path) -> io::Result<Box<dyn Read + Send>>;
+
+    /// Open a file for reading from `offset` on, to read what was appended
+    /// to a followed log
+    ///
+    /// The default opens the file and skips the first bytes; sources that
+    /// can seek should override it.
+    fn open_at(&self, path: &Path, offset: u64) -> io::Result<Box<dyn Read + Send>> {
+        let mut reader = self.open(path)?;
+        io::copy(&mut reader.by_ref().take(offset), &mut io::sink())?;
+        Ok(reader)
+    }
+
+    /// Resolve a path to a form identifying the entry it points to, used to
+    /// avoid visiting a directory twice through symlinks
+    ///
+    /// Sources without links can keep the default, which returns the path.
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        Ok(path.to_path_buf())
+    }
+
+    /// Create a directory and its missing parents, like a log folder that
+    /// does not exist yet
+    ///
+    /// Sources that cannot create directories keep the default, which does
+    /// nothing.
+    fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+        let _ = path;
+        Ok(())
+    }
+
+    /// Memory-map a file for reading
+    ///
+    /// Sources that cannot be mapped keep the default, which fails so the
+    /// file is read through `open` instead.
+    #[cfg(feature = "mmap")]
+    fn map(&self, path: &Path) -> io::Result<memmap2::Mmap> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("{} cannot be memory-mapped", path.display()),
+        ))
+    }
+}
+
+/// `FileSystem` backed by `std::fs`
+#[derive(Clone, Copy, Debug, Default)]
+pub struct StdFileSystem;
+
+impl FileSystem for StdFileSystem {
+    fn list_dir(&self, path: &Path) -> io::Result<Vec<io::Result<PathBuf>>> {
+        Ok(fs::read_dir(path)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect())
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<FileMetadata> {
+        let metadata = fs::metadata(path)?;
+        Ok(FileMetadata {
+            is_file: metadata.is_file(),
+            is_dir: metadata.is_dir(),
+            len: metadata.len(),
+            modified: metadata.modified().ok(),
+            file_id: file_id(&metadata),
+        })
+    }
+
+    fn open(&self, path: &Path) -> io::Result<Box<dyn Read + Send>> {
+        Ok(Box::new(File::open(path)?))
+    }
+
+    fn open_at(&self, path: &Path, offset: u64) -> io::Result<Box<dyn Read + Send>> {
+        let mut file = File::open(path)?;
+        file.seek(SeekFrom::Start(offset))?;
+        Ok(Box::new(file))
+    }
+
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        fs::canonicalize(path)
+    }
+
+    fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+        fs::create_dir_all(path)
+    }
+
+    #[cfg(feature = "mmap")]
+    fn map(&self, path: &Path) -> io::Result<memmap2::Mmap> {
+        let file = File::open(path)?;
+        // SAFETY: the mapping is only read. Logs are appended to or rotated by
+        // renaming, which leaves the mapped pages valid; a file truncated
+        // while it is scanned can still fault, as with any mapped file.
+        unsafe { memmap2::Mmap::map(&file) }
+    }
+}
+
+#[cfg(unix)]
+fn file_id(metadata: &fs::Metadata) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    Some(metadata.ino())
+}
+
+#[cfg(not(unix))]
+fn file_id(_metadata: &fs::Metadata) -> Option<u64> {
+    None
+}
@@ -0,0 +1,122 @@
+use crate::{CompressionKind, FileResult, FileSystem, ParserError};
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Time between two checks of the followed logs for appended lines
+pub const DEFAULT_FOLLOW_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Lines appended to a followed log since the previous poll
+pub(crate) struct Appended {
+    pub path: PathBuf,
+    /// Complete lines only; a line still being written waits for the next poll
+    pub lines: Vec<u8>,
+    /// Lines of the file before these, to number them
+    pub lines_before: usize,
+}
+
+/// How far a followed log was read
+struct FollowedFile {
+    offset: u64,
+    lines: usize,
+    file_id: Option<u64>,
+}
+
+/// Tracks the plain log files of a followed run, to read what is appended
+/// to them
+///
+/// Archives are never followed; rotated logs are compressed once complete.
+pub(crate) struct Follower {
+    files: HashMap<PathBuf, FollowedFile>,
+}
+
+impl Follower {
+    /// Follow the files of the initial pass from where it stopped reading
+    pub fn new(file_system: &dyn FileSystem, scanned: &[FileResult]) -> Self {
+        let files = scanned
+            .iter()
+            .filter(|file| file.compression.is_none())
+            .map(|file| {
+                let followed = FollowedFile {
+                    offset: file.bytes_read,
+                    lines: file.lines_scanned,
+                    file_id: file_id(file_system, &file.path),
+                };
+                (file.path.clone(), followed)
+            })
+            .collect();
+        Self { files }
+    }
+
+    /// Read the lines appended to `paths` since the previous poll
+    ///
+    /// Files seen for the first time are read from the start, and so are
+    /// files that were truncated or replaced, like by log rotation.
+    pub fn poll(
+        &mut self,
+        file_system: &dyn FileSystem,
+        paths: &[PathBuf],
+    ) -> (Vec<Appended>, Vec<(PathBuf, ParserError)>) {
+        let mut appended = Vec::new();
+        let mut errors = Vec::new();
+        for path in paths {
+            if CompressionKind::from_path(path).is_some() {
+                continue;
+            }
+            // Files removed since they were listed are picked up again if
+            // they come back
+            let Ok(metadata) = file_system.metadata(path) else {
+                continue;
+            };
+            let followed = self.files.entry(path.clone()).or_insert(FollowedFile {
+                offset: 0,
+                lines: 0,
+                file_id: metadata.file_id,
+            });
+            if metadata.len < followed.offset || metadata.file_id != followed.file_id {
+                *followed = FollowedFile {
+                    offset: 0,
+                    lines: 0,
+                    file_id: metadata.file_id,
+                };
+            }
+            if metadata.len == followed.offset {
+                continue;
+            }
+
+            let mut lines = Vec::new();
+            let read = file_system
+                .open_at(path, followed.offset)
+                .and_then(|reader| {
+                    reader
+                        .take(metadata.len - followed.offset)
+                        .read_to_end(&mut lines)
+                });
+            if let Err(e) = read {
+                // Skip what could not be read, so the failure is reported once
+                followed.offset = metadata.len;
+                errors.push((path.clone(), ParserError::Read(e)));
+                continue;
+            }
+            let Some(end) = lines.iter().rposition(|&byte| byte == b'\n') else {
+                continue;
+            };
+            lines.truncate(end + 1);
+
+            let lines_before = followed.lines;
+            followed.offset += lines.len() as u64;
+            followed.lines += lines.iter().filter(|&&byte| byte == b'\n').count();
+            appended.push(Appended {
+                path: path.clone(),
+                lines,
+                lines_before,
+            });
+        }
+        (appended, errors)
+    }
+}
+
+fn file_id(file_system: &dyn FileSystem, path: &Path) -> Option<u64> {
+    file_system.metadata(path).ok()?.file_id
+}
@@ -0,0 +1,143 @@
+use std::fmt;
+
+/// Largest edit distance a fuzzy atom may request
+pub const MAX_FUZZY_DISTANCE: u8 = 2;
+
+/// A pattern matched approximately against substrings of a line
+///
+/// Uses Myers' bit-parallel algorithm, so each line costs O(n) for patterns
+/// of up to 64 characters. Longer patterns fall back to the O(n * m)
+/// dynamic program.
+#[derive(Clone)]
+pub struct FuzzyPattern {
+    pattern: String,
+    chars: Vec<char>,
+    max_distance: u8,
+    /// Position masks for ASCII characters of the pattern
+    ascii_masks: Box<[u64; 128]>,
+    /// Position masks for the non-ASCII characters of the pattern
+    other_masks: Vec<(char, u64)>,
+}
+
+impl FuzzyPattern {
+    /// Build a pattern, capping the distance at `MAX_FUZZY_DISTANCE`
+    pub fn new(pattern: &str, max_distance: u8) -> Self {
+        let chars: Vec<char> = pattern.chars().collect();
+        let mut ascii_masks = Box::new([0u64; 128]);
+        let mut other_masks: Vec<(char, u64)> = Vec::new();
+
+        for (index, c) in chars.iter().take(64).enumerate() {
+            let bit = 1u64 << index;
+            if c.is_ascii() {
+                ascii_masks[*c as usize] |= bit;
+            } else if let Some((_, mask)) = other_masks.iter_mut().find(|(other, _)| other == c) {
+                *mask |= bit;
+            } else {
+                other_masks.push((*c, bit));
+            }
+        }
+
+        Self {
+            pattern: pattern.to_string(),
+            chars,
+            max_distance: max_distance.min(MAX_FUZZY_DISTANCE),
+            ascii_masks,
+            other_masks,
+        }
+    }
+
+    pub fn pattern(&self) -> &str {
+        &self.pattern
+    }
+
+    pub fn max_distance(&self) -> u8 {
+        self.max_distance
+    }
+
+    /// Check if some substring of `text` is within `max_distance` edits of the pattern
+    pub fn matches(&self, text: &str) -> bool {
+        let length = self.chars.len();
+        if length <= self.max_distance as usize {
+            return true;
+        }
+        if length > 64 {
+            return self.matches_slow(text);
+        }
+
+        let last_bit = 1u64 << (length - 1);
+        let mut positive = !0u64;
+        let mut negative = 0u64;
+        let mut score = length;
+
+        for c in text.chars() {
+            let eq = self.mask(c);
+            let xv = eq | negative;
+            let xh = ((eq & positive).wrapping_add(positive) ^ positive) | eq;
+            let mut horizontal_positive = negative | !(xh | positive);
+            let mut horizontal_negative = positive & xh;
+
+            if horizontal_positive & last_bit != 0 {
+                score += 1;
+            } else if horizontal_negative & last_bit != 0 {
+                score -= 1;
+            }
+
+            horizontal_positive <<= 1;
+            horizontal_negative <<= 1;
+            positive = horizontal_negative | !(xv | horizontal_positive);
+            negative = horizontal_positive & xv;
+
+            if score <= self.max_distance as usize {
+                return true;
+            }
+        }
+
+        false
+    }
+
+    fn mask(&self, c: char) -> u64 {
+        if c.is_ascii() {
+            self.ascii_masks[c as usize]
+        } else {
+            self.other_masks
+                .iter()
+                .find(|(other, _)| *other == c)
+                .map_or(0, |(_, mask)| *mask)
+        }
+    }
+
+    /// Column-by-column edit distance for patterns too long for a machine word
+    fn matches_slow(&self, text: &str) -> bool {
+        let mut column: Vec<usize> = (0..=self.chars.len()).collect();
+
+        for c in text.chars() {
+            let mut diagonal = column[0];
+            for (index, pattern_char) in self.chars.iter().enumerate() {
+                let above = column[index + 1];
+                let substitution = diagonal + usize::from(*pattern_char != c);
+                column[index + 1] = substitution.min(above + 1).min(column[index] + 1);
+                diagonal = above;
+            }
+            if column[self.chars.len()] <= self.max_distance as usize {
+                return true;
+            }
+        }
+
+        false
+    }
+}
+
+impl PartialEq for FuzzyPattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.pattern == other.pattern && self.max_distance == other.max_distance
+    }
+}
+
+impl fmt::Debug for FuzzyPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FuzzyPattern")
+            .field("pattern", &self.pattern)
+            .field("max_distance", &self.max_distance)
+            .finish()
+    }
+}
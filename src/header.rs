@@ -0,0 +1,86 @@
+use crate::SearchTerm;
+use serde_json::json;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Prefix marking the header line as a comment
+pub const HEADER_PREFIX: &str = "# ";
+
+/// Commented line describing a run, written at the top of plain-text output
+///
+/// The match count is deliberately left out: it is only known once the run
+/// completes, while the header is written before the first match.
+pub fn output_header(terms: &[SearchTerm], folder: &str, started: SystemTime) -> String {
+    let terms: Vec<_> = terms.iter().map(|term| term.to_string()).collect();
+    format!(
+        "{}elysiumparser v{} — {} — terms: {} — folder: {}",
+        HEADER_PREFIX,
+        env!("CARGO_PKG_VERSION"),
+        format_utc_minute(started),
+        terms.join(", "),
+        folder
+    )
+    .replace(['\r', '\n'], " ")
+}
+
+/// Commented line opening the matches a run appends to an existing output log
+pub fn run_separator(started: SystemTime) -> String {
+    format!("{}--- run at {} ---", HEADER_PREFIX, format_utc_minute(started))
+}
+
+/// JSON object standing for the header in JSONL output: a `header` object
+/// holding the `version`, the `started` time, the `terms` and the `folders`
+pub fn output_header_record(terms: &[SearchTerm], folders: &[&str], started: SystemTime) -> String {
+    let terms: Vec<_> = terms.iter().map(|term| term.to_string()).collect();
+    json!({
+        "header": {
+            "version": env!("CARGO_PKG_VERSION"),
+            "started": format_utc_minute(started),
+            "terms": terms,
+            "folders": folders,
+        }
+    })
+    .to_string()
+}
+
+/// JSON object standing for the run separator in JSONL output, like
+/// `{"run_separator":{"started":"2024-06-07T12:00Z"}}`
+pub fn run_separator_record(started: SystemTime) -> String {
+    json!({ "run_separator": { "started": format_utc_minute(started) } }).to_string()
+}
+
+/// Format a time as an ISO 8601 UTC timestamp to the minute, like `2024-06-07T12:00Z`
+pub fn format_utc_minute(time: SystemTime) -> String {
+    let seconds = time
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or(0);
+    let (year, month, day) = civil_from_days((seconds / 86_400) as i64);
+    let minutes = seconds % 86_400 / 60;
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        minutes / 60,
+        minutes % 60
+    )
+}
+
+/// Proleptic Gregorian date of a day count since 1970-01-01
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
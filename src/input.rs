@@ -0,0 +1,258 @@
+use crate::kubernetes::parse_cri_line;
+use serde::Deserialize;
+use std::fmt;
+use std::io::{self, BufRead};
+use std::str::FromStr;
+
+/// How the lines of a log file are encoded
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum InputFormat {
+    /// One log line per text line
+    #[default]
+    Plain,
+    /// Docker `json-file` records, `{"log":"line\n","stream":"stdout","time":"..."}`
+    ///
+    /// The `log` field is matched and written instead of the record. Lines
+    /// Docker split into several records are joined back together.
+    DockerJson,
+    /// CRI runtime lines, `2024-06-07T12:00:00.000Z stdout F message`, as
+    /// found under `/var/log/pods`
+    ///
+    /// The message is matched and written without its prefix. Partial (`P`)
+    /// lines are joined with the lines that follow, up to the final (`F`) one.
+    Cri,
+    /// `DockerJson` or `Cri` for files whose first line is in that format,
+    /// `Plain` otherwise
+    Auto,
+}
+
+impl fmt::Display for InputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            InputFormat::Plain => "plain",
+            InputFormat::DockerJson => "docker-json",
+            InputFormat::Cri => "cri",
+            InputFormat::Auto => "auto",
+        })
+    }
+}
+
+impl FromStr for InputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "plain" => Ok(InputFormat::Plain),
+            "docker" | "docker-json" => Ok(InputFormat::DockerJson),
+            "cri" | "kubernetes" => Ok(InputFormat::Cri),
+            "auto" => Ok(InputFormat::Auto),
+            other => Err(format!(
+                "unknown input format '{}' (expected plain, docker-json, cri or auto)",
+                other
+            )),
+        }
+    }
+}
+
+/// A single record of Docker's `json-file` logging driver
+#[derive(Deserialize)]
+struct DockerRecord {
+    log: String,
+    /// RFC 3339 time the record was written at
+    #[serde(default)]
+    time: String,
+}
+
+/// Reads the logical lines of a reader in a given `InputFormat`
+pub(crate) struct LineReader<R> {
+    reader: R,
+    /// Either `Plain`, `DockerJson` or `Cri`, never `Auto`
+    format: InputFormat,
+    /// Raw line read past the end of a split Docker or CRI line, returned next
+    pending: Option<Vec<u8>>,
+    /// Bytes of a line kept, the rest of it being read and dropped; Docker
+    /// and CRI lines are cut once joined back together
+    line_limit: Option<usize>,
+    /// RFC 3339 time of the first record of the last line read, empty for
+    /// plain lines
+    record_time: String,
+}
+
+impl<R: BufRead> LineReader<R> {
+    /// Read `reader` in `format`, keeping at most one byte more than
+    /// `max_line_length` of each line, so longer ones can still be told
+    pub(crate) fn new(mut reader: R, format: InputFormat, max_line_length: Option<usize>) -> Self {
+        let format = match format {
+            InputFormat::Auto => match reader.fill_buf() {
+                Ok(start) if looks_like_docker_record(start) => InputFormat::DockerJson,
+                Ok(start) if parse_cri_line(first_line(start)).is_some() => InputFormat::Cri,
+                _ => InputFormat::Plain,
+            },
+            format => format,
+        };
+
+        Self {
+            reader,
+            format,
+            pending: None,
+            line_limit: max_line_length.map(|max| max.saturating_add(1)),
+            record_time: String::new(),
+        }
+    }
+
+    /// Read the next line into `buffer`, returning the raw bytes consumed
+    ///
+    /// Returns 0 at the end of the input. The line keeps its terminator, if any.
+    pub(crate) fn read_line(&mut self, buffer: &mut Vec<u8>) -> io::Result<usize> {
+        self.record_time.clear();
+        if let Some(pending) = self.pending.take() {
+            buffer.extend_from_slice(&pending);
+            return Ok(pending.len());
+        }
+        match self.format {
+            InputFormat::DockerJson => self.read_docker_line(buffer),
+            InputFormat::Cri => self.read_cri_line(buffer),
+            _ => match self.line_limit {
+                Some(limit) => read_until_limited(&mut self.reader, buffer, limit),
+                None => self.reader.read_until(b'\n', buffer),
+            },
+        }
+    }
+
+    fn read_docker_line(&mut self, buffer: &mut Vec<u8>) -> io::Result<usize> {
+        let mut consumed = 0;
+        let mut raw = Vec::new();
+        let mut partial = false;
+        loop {
+            raw.clear();
+            let bytes = self.reader.read_until(b'\n', &mut raw)?;
+            if bytes == 0 {
+                return Ok(consumed);
+            }
+            consumed += bytes;
+
+            match serde_json::from_slice::<DockerRecord>(trim_terminator(&raw)) {
+                Ok(record) => {
+                    if !partial {
+                        self.record_time = record.time;
+                        partial = true;
+                    }
+                    self.push_limited(buffer, record.log.as_bytes());
+                    // Docker splits long lines into records without a newline
+                    if record.log.ends_with('\n') {
+                        return Ok(consumed);
+                    }
+                }
+                // Anything else is passed through as a line of its own
+                Err(_) if !partial => {
+                    self.push_limited(buffer, &raw);
+                    return Ok(consumed);
+                }
+                Err(_) => {
+                    self.pending = Some(raw);
+                    return Ok(consumed - bytes);
+                }
+            }
+        }
+    }
+
+    fn read_cri_line(&mut self, buffer: &mut Vec<u8>) -> io::Result<usize> {
+        let mut consumed = 0;
+        let mut raw = Vec::new();
+        let mut partial = false;
+        loop {
+            raw.clear();
+            let bytes = self.reader.read_until(b'\n', &mut raw)?;
+            if bytes == 0 {
+                return Ok(consumed);
+            }
+            consumed += bytes;
+
+            match parse_cri_line(&raw) {
+                Some(line) => {
+                    if !partial {
+                        line.timestamp.clone_into(&mut self.record_time);
+                    }
+                    self.push_limited(buffer, line.message);
+                    if !line.partial {
+                        self.push_limited(buffer, b"\n");
+                        return Ok(consumed);
+                    }
+                    partial = true;
+                }
+                // Anything else is passed through as a line of its own
+                None if !partial => {
+                    self.push_limited(buffer, &raw);
+                    return Ok(consumed);
+                }
+                None => {
+                    self.pending = Some(raw);
+                    return Ok(consumed - bytes);
+                }
+            }
+        }
+    }
+
+    /// Time of the Docker or CRI record the last line read started in
+    pub(crate) fn record_time(&self) -> Option<&str> {
+        Some(self.record_time.as_str()).filter(|time| !time.is_empty())
+    }
+
+    /// Add `bytes` to the line in `buffer`, dropping what goes past the limit
+    fn push_limited(&self, buffer: &mut Vec<u8>, bytes: &[u8]) {
+        let kept = match self.line_limit {
+            Some(limit) => bytes.len().min(limit.saturating_sub(buffer.len())),
+            None => bytes.len(),
+        };
+        buffer.extend_from_slice(&bytes[..kept]);
+    }
+}
+
+/// `read_until(b'\n')` keeping no more than `limit` bytes of the line in
+/// `buffer`; the rest of it is consumed without being kept
+fn read_until_limited<R: BufRead>(
+    reader: &mut R,
+    buffer: &mut Vec<u8>,
+    limit: usize,
+) -> io::Result<usize> {
+    let mut consumed = 0;
+    loop {
+        let available = match reader.fill_buf() {
+            Ok(available) => available,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        if available.is_empty() {
+            return Ok(consumed);
+        }
+        let (used, complete) = match available.iter().position(|&byte| byte == b'\n') {
+            Some(end) => (end + 1, true),
+            None => (available.len(), false),
+        };
+        let kept = used.min(limit.saturating_sub(buffer.len()));
+        buffer.extend_from_slice(&available[..kept]);
+        reader.consume(used);
+        consumed += used;
+        if complete {
+            return Ok(consumed);
+        }
+    }
+}
+
+fn trim_terminator(line: &[u8]) -> &[u8] {
+    let line = line.strip_suffix(b"\n").unwrap_or(line);
+    line.strip_suffix(b"\r").unwrap_or(line)
+}
+
+fn first_line(start: &[u8]) -> &[u8] {
+    start.split(|byte| *byte == b'\n').next().unwrap_or(start)
+}
+
+/// Whether a file starting with `start` holds Docker `json-file` records
+fn looks_like_docker_record(start: &[u8]) -> bool {
+    let first_line = first_line(start);
+    first_line.trim_ascii_start().starts_with(b"{")
+        && first_line
+            .windows(b"\"log\":".len())
+            .any(|window| window == b"\"log\":")
+}
@@ -0,0 +1,119 @@
+use std::error::Error;
+use std::fmt;
+
+/// How undefined variables without a default are handled
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum InterpolationMode {
+    /// Replace them with an empty string, like `envsubst`
+    #[default]
+    Lenient,
+    /// Fail with `InterpolationError::UndefinedVariable`
+    Strict,
+}
+
+/// Error raised while interpolating a configuration value
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum InterpolationError {
+    /// `${NAME}` referenced a variable that is not set, in strict mode
+    UndefinedVariable(String),
+    /// A `${` at this byte offset was never closed
+    Unterminated(usize),
+    /// The text between `${` and `}` is not a valid variable name
+    InvalidName(String),
+}
+
+impl fmt::Display for InterpolationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InterpolationError::UndefinedVariable(name) => {
+                write!(f, "undefined variable ${{{}}}", name)
+            }
+            InterpolationError::Unterminated(offset) => {
+                write!(f, "unterminated ${{ at byte {}", offset)
+            }
+            InterpolationError::InvalidName(name) => {
+                write!(f, "invalid variable name '{}'", name)
+            }
+        }
+    }
+}
+
+impl Error for InterpolationError {}
+
+/// Expand `${VAR}` and `${VAR:-default}` from the process environment
+///
+/// `$${` produces a literal `${`. A default is used when the variable is
+/// unset or empty. Meant for values read from configuration files only;
+/// values set programmatically are used as given.
+pub fn interpolate_env(value: &str, mode: InterpolationMode) -> Result<String, InterpolationError> {
+    interpolate_with(value, mode, |name| std::env::var(name).ok())
+}
+
+/// Expand variables like `interpolate_env`, resolving them with `lookup`
+pub fn interpolate_with<F>(
+    value: &str,
+    mode: InterpolationMode,
+    lookup: F,
+) -> Result<String, InterpolationError>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let mut result = String::with_capacity(value.len());
+    let mut rest = value;
+    let mut offset = 0;
+
+    while let Some(dollar) = rest.find('$') {
+        result.push_str(&rest[..dollar]);
+        let after = &rest[dollar..];
+
+        if let Some(escaped) = after.strip_prefix("$${") {
+            result.push_str("${");
+            offset += dollar + 3;
+            rest = escaped;
+            continue;
+        }
+
+        let Some(body) = after.strip_prefix("${") else {
+            result.push('$');
+            offset += dollar + 1;
+            rest = &after[1..];
+            continue;
+        };
+
+        let close = body
+            .find('}')
+            .ok_or(InterpolationError::Unterminated(offset + dollar))?;
+        let reference = &body[..close];
+        let (name, default) = match reference.split_once(":-") {
+            Some((name, default)) => (name, Some(default)),
+            None => (reference, None),
+        };
+        if !is_variable_name(name) {
+            return Err(InterpolationError::InvalidName(name.to_string()));
+        }
+
+        match (lookup(name), default) {
+            (Some(value), Some(default)) if value.is_empty() => result.push_str(default),
+            (Some(value), _) => result.push_str(&value),
+            (None, Some(default)) => result.push_str(default),
+            (None, None) if mode == InterpolationMode::Strict => {
+                return Err(InterpolationError::UndefinedVariable(name.to_string()));
+            }
+            (None, None) => {}
+        }
+
+        offset += dollar + 2 + close + 1;
+        rest = &body[close + 1..];
+    }
+
+    result.push_str(rest);
+    Ok(result)
+}
+
+fn is_variable_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
@@ -0,0 +1,118 @@
+use crate::labels::PathLabelRule;
+use regex::Regex;
+use std::path::{Path, PathBuf};
+
+/// Folder where the kubelet keeps the logs of the pods of a node
+pub const POD_LOG_FOLDER: &str = "/var/log/pods";
+
+/// A line written by a CRI container runtime, `<time> <stream> <tag> <message>`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CriLine<'a> {
+    /// RFC 3339 timestamp, as written by the runtime
+    pub timestamp: &'a str,
+    /// `stdout` or `stderr`
+    pub stream: &'a str,
+    /// Whether the line continues in the next record (tag `P` rather than `F`)
+    pub partial: bool,
+    /// The logged text, without the line terminator
+    pub message: &'a [u8],
+}
+
+/// Split a CRI log line into its prefix fields and message
+///
+/// Returns `None` for anything that does not start with a timestamp, a
+/// `stdout`/`stderr` stream and a `P`/`F` tag. Extra tags after the first,
+/// like the `F:x` of newer runtimes, are ignored.
+pub fn parse_cri_line(line: &[u8]) -> Option<CriLine<'_>> {
+    let line = line.strip_suffix(b"\n").unwrap_or(line);
+    let line = line.strip_suffix(b"\r").unwrap_or(line);
+
+    let mut fields = line.splitn(4, |byte| *byte == b' ');
+    let timestamp = std::str::from_utf8(fields.next()?).ok()?;
+    let stream = std::str::from_utf8(fields.next()?).ok()?;
+    let tag = fields.next()?;
+    let message = fields.next().unwrap_or_default();
+
+    if !looks_like_timestamp(timestamp) || !matches!(stream, "stdout" | "stderr") {
+        return None;
+    }
+    let partial = match tag.split(|byte| *byte == b':').next()? {
+        b"P" => true,
+        b"F" => false,
+        _ => return None,
+    };
+
+    Some(CriLine {
+        timestamp,
+        stream,
+        partial,
+        message,
+    })
+}
+
+/// Cheap check for the `YYYY-MM-DDTHH:MM:SS` start of an RFC 3339 timestamp
+fn looks_like_timestamp(text: &str) -> bool {
+    let bytes = text.as_bytes();
+    bytes.len() >= 19
+        && bytes[..19].iter().enumerate().all(|(i, byte)| match i {
+            4 | 7 => *byte == b'-',
+            10 => *byte == b'T',
+            13 | 16 => *byte == b':',
+            _ => byte.is_ascii_digit(),
+        })
+}
+
+/// Label rule reading `namespace`, `pod`, `uid` and `container` from
+/// `<namespace>_<pod>_<uid>/<container>/<n>.log` paths
+///
+/// The path may be relative to the log folder or to any of its parents, so
+/// the rule also works when `log_folder` is a single namespace's pod folder.
+pub fn kubernetes_label_rule() -> PathLabelRule {
+    PathLabelRule::Regex(
+        Regex::new(
+            r"(?:^|/)(?P<namespace>[^/_]+)_(?P<pod>[^/_]+)_(?P<uid>[^/_]+)/(?P<container>[^/]+)/[^/]+$",
+        )
+        .expect("kubernetes label regex is valid"),
+    )
+}
+
+/// Whether `path` is a log the kubelet rotated, `<n>.log.<YYYYMMDD-HHMMSS>`
+///
+/// Rotated logs compressed by the kubelet keep a `.gz` suffix and are picked
+/// up as compressed files instead.
+pub(crate) fn is_rotated_log_name(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .and_then(|name| name.split_once(".log."))
+        .is_some_and(|(_, suffix)| is_rotation_suffix(suffix))
+}
+
+fn is_rotation_suffix(suffix: &str) -> bool {
+    let bytes = suffix.as_bytes();
+    bytes.len() == 15
+        && bytes.iter().enumerate().all(|(i, byte)| match i {
+            8 => *byte == b'-',
+            _ => byte.is_ascii_digit(),
+        })
+}
+
+/// Sort key putting the rotated copies of a log before the live file, oldest first
+///
+/// `0.log.20240607-120000.gz` and `0.log.20240607-120000` sort right before
+/// `0.log`; other files sort by path.
+pub(crate) fn rotation_key(path: &Path) -> (PathBuf, bool, String) {
+    let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+        return (path.to_path_buf(), true, String::new());
+    };
+
+    if let Some(index) = name.find(".log.") {
+        let suffix = &name[index + ".log.".len()..];
+        let stamp = suffix.split('.').next().unwrap_or(suffix);
+        if is_rotation_suffix(stamp) {
+            let live = path.with_file_name(&name[..index + ".log".len()]);
+            return (live, false, stamp.to_string());
+        }
+    }
+
+    (path.to_path_buf(), true, String::new())
+}
@@ -0,0 +1,63 @@
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Labels attached to a file, keyed by label name
+pub type PathLabels = BTreeMap<String, String>;
+
+/// Labels of the records of readers not labelled by a run
+pub(crate) static NO_LABELS: PathLabels = PathLabels::new();
+
+/// Rule deriving labels for a file from its path relative to the log folder
+#[derive(Clone, Debug)]
+pub enum PathLabelRule {
+    /// Use the directory component at `index` (0 is the top-level directory) as `key`
+    Component { index: usize, key: String },
+    /// Use the named captures of a regex matched against the relative path,
+    /// written with `/` separators
+    Regex(Regex),
+}
+
+impl PathLabelRule {
+    /// Apply the rule to a normalized relative path, adding any labels it yields
+    fn apply(&self, relative_path: &str, labels: &mut PathLabels) {
+        match self {
+            PathLabelRule::Component { index, key } => {
+                let mut components: Vec<&str> = relative_path.split('/').collect();
+                // The last component is the file name itself
+                components.pop();
+                if let Some(value) = components.get(*index) {
+                    labels.insert(key.clone(), value.to_string());
+                }
+            }
+            PathLabelRule::Regex(regex) => {
+                let Some(captures) = regex.captures(relative_path) else {
+                    return;
+                };
+                for name in regex.capture_names().flatten() {
+                    if let Some(value) = captures.name(name) {
+                        labels.insert(name.to_string(), value.as_str().to_string());
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Compute the labels of a file from its path relative to the log folder
+///
+/// Both `/` and `\` are treated as separators. Rules that do not match the
+/// path contribute no labels.
+pub fn path_labels(rules: &[PathLabelRule], relative_path: &Path) -> PathLabels {
+    let mut labels = PathLabels::new();
+    if rules.is_empty() {
+        return labels;
+    }
+
+    let normalized = relative_path.to_string_lossy().replace('\\', "/");
+    for rule in rules {
+        rule.apply(&normalized, &mut labels);
+    }
+
+    labels
+}
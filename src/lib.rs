@@ -1,353 +1,2980 @@
-use flate2::read::GzDecoder;
+use chrono::NaiveDateTime;
+use futures::future;
 use futures::stream::{self, StreamExt};
+use context::ContextWindow;
+use expression::AtomCase;
+use filename_filter::PathPatterns;
+use follow::{Appended, Follower};
+use input::LineReader;
+use labels::NO_LABELS;
+use matcher::lowercase_into;
+use match_stream::BoundedChannelSink;
+use sink::{BufferSink, LabelSink};
+use split::Split;
+use trace::Sampler;
+use std::collections::{HashMap, HashSet};
+use std::collections::hash_map::DefaultHasher;
+use std::fmt;
+use std::hash::{Hash, Hasher};
 use std::fs::{self, File, OpenOptions};
-use std::io::{self, BufRead, BufReader, Write};
+use std::io::{self, BufRead, Read, Write};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+use tokio::sync::Notify;
+use tokio::sync::mpsc::UnboundedSender;
 use tokio::task;
 
+pub mod bench;
+mod builder;
+mod compression;
+mod context;
+mod error;
+mod expression;
+mod filename_filter;
+mod filesystem;
+mod follow;
+mod fuzzy;
+mod header;
+mod input;
+mod interpolate;
+mod kubernetes;
+mod labels;
+mod match_stream;
+mod matcher;
+mod mirror;
+#[cfg(feature = "mmap")]
+mod mmap;
+#[cfg(feature = "arrow")]
+mod parquet_sink;
+mod priority;
+mod profile;
+mod progress;
+mod regex_pattern;
+mod routing;
+mod search;
+pub mod selftest;
+mod sink;
+mod split;
+mod time_window;
+mod trace;
+mod triage;
+pub mod units;
+#[cfg(feature = "watch")]
+mod watch;
+mod writer;
+
+pub use builder::{ConfigError, ParserConfigBuilder};
+pub use compression::{CompressionKind, open_log_reader};
+pub use error::ParserError;
+pub use expression::{ParseError, ParseErrorKind};
+pub use filename_filter::FilenameFilter;
+pub use filesystem::{FileMetadata, FileSystem, StdFileSystem};
+pub use follow::DEFAULT_FOLLOW_INTERVAL;
+pub use fuzzy::{FuzzyPattern, MAX_FUZZY_DISTANCE};
+pub use header::{
+    HEADER_PREFIX, format_utc_minute, output_header, output_header_record, run_separator,
+    run_separator_record,
+};
+pub use input::InputFormat;
+pub use interpolate::{
+    InterpolationError, InterpolationMode, interpolate_env, interpolate_with,
+};
+pub use kubernetes::{CriLine, POD_LOG_FOLDER, kubernetes_label_rule, parse_cri_line};
+pub use labels::{PathLabelRule, PathLabels, path_labels};
+pub use match_stream::{
+    MATCH_STREAM_CAPACITY, MatchStream, MatchStreamSender, run_parser_stream,
+};
+pub use matcher::{MatchInfo, Matcher};
+pub use mirror::MirroredTreeSink;
+#[cfg(feature = "arrow")]
+pub use parquet_sink::{ParquetSink, parquet_schema};
+pub use priority::{enter_background_mode, system_load};
+pub use profile::{Profile, ProfileScoreRule, ProfileTerm};
+pub use progress::{DEFAULT_PROGRESS_INTERVAL_BYTES, FileProgress, ReadProgress};
+pub use regex_pattern::RegexPattern;
+pub use routing::TermRoutingSink;
+pub use search::{MatchMode, MatchStrategy, SearchSet};
+pub use sink::{
+    BlockLine, ChannelSink, CollectSink, DedupSink, FanOutSink, JsonlSink, LocationSink,
+    MatchRecord, MatchRecordBuf, MatchSink, OutputFormat,
+};
+pub use time_window::{
+    DEFAULT_TIMESTAMP_FORMAT, TimeWindow, filename_date, parse_time_bound,
+};
+pub use trace::{AtomTiming, LatencyHistogram};
+pub use triage::{
+    DEFAULT_TRIAGE_TOP, ScoreRule, ScoredMatch, TriageSink, score_match, write_triage,
+};
+#[cfg(feature = "watch")]
+pub use watch::{MatchEvent, WatchHandle, run_parser_watch};
+pub use writer::{DEFAULT_WRITER_CHANNEL_CAPACITY, WriterSink};
+
+/// A keyword and an optional boolean expression a line must both satisfy
+///
+/// Terms may be built directly as well as with the `add_search` helpers:
+/// unless a run is case-sensitive, their text is lowercased when compiled
+/// into a `SearchSet`, so `keyword: "Error".into()` matches like `"error"`.
 #[derive(Clone, Debug)]
 pub struct SearchTerm {
     pub keyword: String,
+    /// Compiled `keyword` when it is a regular expression, either written
+    /// `/…/` or added by `add_search_regex`
+    pub keyword_pattern: Option<RegexPattern>,
     pub additional_expression: Option<BooleanExpression>,
+    /// Base severity of the lines matched by this term
+    pub score: i32,
+    /// File the lines matched by this term are written to instead of the
+    /// output of the run (see `TermRoutingSink`), when it writes the output
+    /// log or standard output; created with its folders, or appended to with
+    /// `ParserConfig::append`
+    pub output: Option<PathBuf>,
 }
 
-#[derive(Clone, Debug)]
+/// `term_index` of the lines kept by `ParserConfig::invert_match`, which
+/// match no term
+pub const INVERTED_MATCH: usize = usize::MAX;
+
+/// Term reported for `INVERTED_MATCH` lines: no keyword and no expression
+static INVERTED_TERM: SearchTerm = SearchTerm {
+    keyword: String::new(),
+    keyword_pattern: None,
+    additional_expression: None,
+    score: 0,
+    output: None,
+};
+
+/// Term `index` of `terms`, or the blank term of `INVERTED_MATCH` lines
+pub(crate) fn term_at(terms: &[SearchTerm], index: usize) -> &SearchTerm {
+    terms.get(index).unwrap_or(&INVERTED_TERM)
+}
+
+/// Renders the term as `keyword + expression`, omitting either when absent
+impl fmt::Display for SearchTerm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.additional_expression {
+            Some(expression) if self.keyword.is_empty() => write!(f, "{}", expression),
+            Some(expression) => write!(f, "{} + {}", self.keyword, expression),
+            None => write!(f, "{}", self.keyword),
+        }
+    }
+}
+
+impl SearchTerm {
+    /// Check if a line matches the keyword and the additional expression,
+    /// with their case as written
+    pub fn matches(&self, line: &str) -> bool {
+        // Check if line contains the main keyword (if not empty)
+        let keyword_matches = match &self.keyword_pattern {
+            Some(pattern) => pattern.is_match(line),
+            None => self.keyword.is_empty() || line.contains(&self.keyword),
+        };
+        if !keyword_matches {
+            return false;
+        }
+
+        // Check if line satisfies the additional expression (if any)
+        match &self.additional_expression {
+            Some(expr) => expr.matches(line),
+            None => true,
+        }
+    }
+}
+
+/// A single atom of a boolean expression
+#[derive(Clone, Debug, PartialEq)]
+pub enum Term {
+    /// Matches lines containing the text
+    Literal(String),
+    /// Matches lines containing the pattern within a bounded edit distance
+    ///
+    /// Written `fuzzy:pattern:distance`; this is much more expensive than a
+    /// literal and is always evaluated after the cheap atoms of an `And`.
+    Fuzzy(FuzzyPattern),
+    /// Matches lines the regular expression finds a match in
+    ///
+    /// Written `re:pattern`; the pattern keeps its case and matches case-insensitively.
+    Regex(RegexPattern),
+}
+
+impl Term {
+    /// Parse an atom, lowercasing it unless it is a regular expression
+    ///
+    /// Regular expressions are written `re:pattern`, or `/pattern/` with the
+    /// `regex` feature.
+    pub fn parse(atom: &str) -> Result<Self, regex::Error> {
+        Self::parse_with_case(atom, false)
+    }
+
+    /// Parse an atom; when `case_sensitive`, it is kept as written and
+    /// regular expressions only match text of the same case
+    pub fn parse_with_case(atom: &str, case_sensitive: bool) -> Result<Self, regex::Error> {
+        match case_sensitive {
+            true => Self::parse_atom(atom, AtomCase::Sensitive),
+            false => Self::parse_atom(atom, AtomCase::Lowercase),
+        }
+    }
+
+    pub(crate) fn parse_atom(atom: &str, case: AtomCase) -> Result<Self, regex::Error> {
+        let ignore_case = case != AtomCase::Sensitive;
+        if atom
+            .get(..3)
+            .is_some_and(|prefix| prefix.eq_ignore_ascii_case("re:"))
+        {
+            return Ok(Term::Regex(RegexPattern::with_case(&atom[3..], ignore_case)?));
+        }
+        if let Some(pattern) = regex_pattern::delimited_pattern(atom) {
+            return Ok(Term::Regex(RegexPattern::with_case(pattern, ignore_case)?));
+        }
+
+        let atom = match case {
+            AtomCase::Lowercase => atom.to_lowercase(),
+            _ => atom.to_string(),
+        };
+
+        if let Some(fuzzy) = atom.strip_prefix("fuzzy:") {
+            let (pattern, max_distance) = match fuzzy.rsplit_once(':') {
+                Some((pattern, distance)) => match distance.parse() {
+                    Ok(distance) => (pattern, distance),
+                    Err(_) => (fuzzy, 1),
+                },
+                None => (fuzzy, 1),
+            };
+            return Ok(Term::Fuzzy(FuzzyPattern::new(pattern, max_distance)));
+        }
+
+        Ok(Term::Literal(atom))
+    }
+
+    pub fn matches(&self, text: &str) -> bool {
+        match self {
+            Term::Literal(literal) => text.contains(literal.as_str()),
+            Term::Fuzzy(pattern) => pattern.matches(text),
+            Term::Regex(pattern) => pattern.is_match(text),
+        }
+    }
+
+    /// Rough relative cost of evaluating the term against a line
+    pub fn cost(&self) -> usize {
+        match self {
+            Term::Literal(_) => 1,
+            Term::Regex(_) => 10,
+            Term::Fuzzy(_) => 100,
+        }
+    }
+}
+
+impl fmt::Display for Term {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Term::Literal(literal) => write!(f, "{}", quote_atom(literal)),
+            Term::Fuzzy(pattern) => {
+                write!(
+                    f,
+                    "fuzzy:{}:{}",
+                    quote_atom(pattern.pattern()),
+                    pattern.max_distance()
+                )
+            }
+            Term::Regex(pattern) => write!(f, "re:{}", quote_atom(pattern.as_str())),
+        }
+    }
+}
+
+/// Quote atom text that would otherwise parse differently
+fn quote_atom(text: &str) -> std::borrow::Cow<'_, str> {
+    if text.is_empty()
+        || text.starts_with('!')
+        || text.trim() != text
+        || text.contains(['&', '|', '(', ')', '"'])
+    {
+        // A backslash right before the closing quote would escape it, so
+        // trailing backslashes are written after it
+        let quoted = text.trim_end_matches('\\');
+        format!(
+            "\"{}\"{}",
+            quoted.replace('"', "\\\""),
+            &text[quoted.len()..]
+        )
+        .into()
+    } else {
+        text.into()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
 pub enum BooleanExpression {
-    And(Vec<String>),
+    /// A single atom
+    Term(Term),
+    And(Vec<BooleanExpression>),
     Or(Vec<Box<BooleanExpression>>),
+    /// Matches lines the inner expression does not match
+    Not(Box<BooleanExpression>),
 }
 
 impl BooleanExpression {
-    pub fn parse(expr: &str) -> Option<Self> {
-        if expr.is_empty() {
-            return None;
+    /// Parse an expression of terms combined with `&`, `|`, `!` and parentheses
+    pub fn parse(expr: &str) -> Result<Self, ParseError> {
+        expression::parse(expr, AtomCase::Lowercase)
+    }
+
+    /// Parse an expression whose atoms keep their case (see `Term::parse_with_case`)
+    pub fn parse_case_sensitive(expr: &str) -> Result<Self, ParseError> {
+        expression::parse(expr, AtomCase::Sensitive)
+    }
+
+    pub fn matches(&self, text: &str) -> bool {
+        match self {
+            BooleanExpression::Term(term) => term.matches(text),
+            BooleanExpression::And(expressions) => expressions.iter().all(|expr| expr.matches(text)),
+            BooleanExpression::Or(expressions) => expressions.iter().any(|expr| expr.matches(text)),
+            BooleanExpression::Not(expression) => !expression.matches(text),
         }
+    }
 
-        // Check if the expression has OR operators at the top level
-        if expr.contains("|") {
-            let or_parts: Vec<&str> = expr.split("|").map(|s| s.trim()).collect();
-            let or_expressions: Vec<Box<BooleanExpression>> = or_parts
-                .iter()
-                .filter_map(|part| {
-                    // Remove surrounding parentheses if present
-                    let clean_part = part.trim_start_matches('(').trim_end_matches(')').trim();
-                    BooleanExpression::parse(clean_part).map(Box::new)
-                })
-                .collect();
+    /// Rough relative cost of evaluating the expression against a line
+    pub fn cost(&self) -> usize {
+        match self {
+            BooleanExpression::Term(term) => term.cost(),
+            BooleanExpression::And(expressions) => expressions.iter().map(|expr| expr.cost()).sum(),
+            BooleanExpression::Or(expressions) => expressions.iter().map(|expr| expr.cost()).sum(),
+            BooleanExpression::Not(expression) => expression.cost(),
+        }
+    }
+
+    /// Number of atoms in the expression, a rough estimate of the work of
+    /// matching it
+    pub fn complexity(&self) -> usize {
+        match self {
+            BooleanExpression::Term(_) => 1,
+            BooleanExpression::And(expressions) => {
+                expressions.iter().map(|expr| expr.complexity()).sum()
+            }
+            BooleanExpression::Or(expressions) => {
+                expressions.iter().map(|expr| expr.complexity()).sum()
+            }
+            BooleanExpression::Not(expression) => expression.complexity(),
+        }
+    }
+
+    /// An equivalent expression without redundant nodes
+    ///
+    /// `And` and `Or` nodes nested in one of the same kind are merged into
+    /// it, repeated operands are dropped and nodes left with one operand are
+    /// replaced by it. An empty `And`, which matches every line, and an empty
+    /// `Or`, which matches none, are the only empty nodes left: they absorb
+    /// or vanish from the nodes holding them, so either the whole expression
+    /// is one of them or it holds none.
+    pub fn normalize(self) -> Self {
+        match self {
+            BooleanExpression::Term(term) => BooleanExpression::Term(term),
+            BooleanExpression::And(expressions) => {
+                let mut operands = Vec::new();
+                for expression in expressions {
+                    match expression.normalize() {
+                        BooleanExpression::And(inner) => {
+                            inner.into_iter().for_each(|expr| push_new(&mut operands, expr))
+                        }
+                        BooleanExpression::Or(inner) if inner.is_empty() => {
+                            return BooleanExpression::Or(Vec::new());
+                        }
+                        expression => push_new(&mut operands, expression),
+                    }
+                }
+                // Merged operands are evaluated cheapest first, like parsed ones
+                operands.sort_by_key(Self::cost);
+                match operands.len() {
+                    1 => operands.pop().unwrap(),
+                    _ => BooleanExpression::And(operands),
+                }
+            }
+            BooleanExpression::Or(expressions) => {
+                let mut operands = Vec::new();
+                for expression in expressions {
+                    match expression.normalize() {
+                        BooleanExpression::Or(inner) => {
+                            inner.into_iter().for_each(|expr| push_new(&mut operands, expr))
+                        }
+                        BooleanExpression::And(inner) if inner.is_empty() => {
+                            return BooleanExpression::And(Vec::new());
+                        }
+                        expression => push_new(&mut operands, Box::new(expression)),
+                    }
+                }
+                match operands.len() {
+                    1 => *operands.pop().unwrap(),
+                    _ => BooleanExpression::Or(operands),
+                }
+            }
+            BooleanExpression::Not(expression) => match expression.normalize() {
+                BooleanExpression::And(inner) if inner.is_empty() => {
+                    BooleanExpression::Or(Vec::new())
+                }
+                BooleanExpression::Or(inner) if inner.is_empty() => {
+                    BooleanExpression::And(Vec::new())
+                }
+                expression => BooleanExpression::Not(Box::new(expression)),
+            },
+        }
+    }
+}
+
+/// Push `operand` unless an equal one is already there
+fn push_new<T: PartialEq>(operands: &mut Vec<T>, operand: T) {
+    if !operands.contains(&operand) {
+        operands.push(operand);
+    }
+}
 
-            if !or_expressions.is_empty() {
-                return Some(BooleanExpression::Or(or_expressions));
+/// Renders the expression in the syntax accepted by `parse`
+impl fmt::Display for BooleanExpression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BooleanExpression::Term(term) => write!(f, "{}", term),
+            BooleanExpression::And(expressions) => {
+                let parts: Vec<String> = expressions
+                    .iter()
+                    .map(|expr| match expr {
+                        BooleanExpression::Or(_) => format!("({})", expr),
+                        expr => expr.to_string(),
+                    })
+                    .collect();
+                write!(f, "({})", parts.join(" & "))
+            }
+            BooleanExpression::Or(expressions) => {
+                // A nested `Or` keeps its parentheses, or it would parse flattened
+                let parts: Vec<String> = expressions
+                    .iter()
+                    .map(|expr| match &**expr {
+                        BooleanExpression::Or(_) => format!("({})", expr),
+                        expr => expr.to_string(),
+                    })
+                    .collect();
+                write!(f, "{}", parts.join(" | "))
             }
+            BooleanExpression::Not(expression) => match &**expression {
+                BooleanExpression::Or(_) => write!(f, "!({})", expression),
+                expression => write!(f, "!{}", expression),
+            },
+        }
+    }
+}
+
+/// A file-level rule flagging files that contain `must_contain` somewhere
+/// but never contain `must_not_contain`
+#[derive(Clone, Debug)]
+pub struct FileAssertion {
+    pub must_contain: BooleanExpression,
+    pub must_not_contain: BooleanExpression,
+}
+
+impl FileAssertion {
+    /// What a failing file holds: `must_contain & !must_not_contain`
+    pub fn failing_clause(&self) -> BooleanExpression {
+        BooleanExpression::And(vec![
+            self.must_contain.clone(),
+            BooleanExpression::Not(Box::new(self.must_not_contain.clone())),
+        ])
+    }
+}
+
+/// A file that failed one of the configured file assertions
+#[derive(Clone, Debug)]
+pub struct AssertionFailure {
+    pub path: PathBuf,
+    /// Index of the failed rule in `ParserConfig::assertions`
+    pub assertion: usize,
+    /// The clause the file failed on, its `must_contain` expression being
+    /// seen and its `must_not_contain` one never (see
+    /// `FileAssertion::failing_clause`)
+    pub clause: String,
+}
+
+/// Options applied to every reader of a run
+#[derive(Clone, Debug, Default)]
+pub struct ScanOptions {
+    /// Text every matching line must contain, ignoring case unless
+    /// `case_sensitive`
+    pub line_filter: String,
+    /// Match terms and the line filter against lines as read rather than
+    /// lowercased; file assertions and score rules still ignore case
+    pub case_sensitive: bool,
+    /// Merge runs of identical matched lines into one line with a `(xN)` suffix;
+    /// ignored when context lines are written
+    pub collapse_consecutive: bool,
+    /// Keep the lines that do not match instead (see `ParserConfig::invert_match`)
+    pub invert_match: bool,
+    /// Lines written before each match, like grep's `-B`
+    pub before_context: usize,
+    /// Lines written after each match, like grep's `-A`
+    pub after_context: usize,
+    /// File-level assertions evaluated on every line
+    pub assertions: Vec<FileAssertion>,
+    /// How the search terms are evaluated against each line
+    pub strategy: MatchStrategy,
+    /// Whether keywords, the line filter and plain atoms are substrings, whole
+    /// words or regexes
+    pub match_mode: MatchMode,
+    /// How lines are encoded in each reader
+    pub input_format: InputFormat,
+    /// Memory-map plain, uncompressed files instead of reading them
+    #[cfg(feature = "mmap")]
+    pub mmap: bool,
+    /// Bonus points added to the score of each match
+    pub score_rules: Vec<ScoreRule>,
+    /// Fraction of lines, between 0 and 1, whose atom evaluations are timed
+    pub trace_sampling: Option<f64>,
+    /// Stop reading once this limit is reached, counting the matches of
+    /// every reader sharing it
+    pub match_limit: Option<Arc<MatchLimit>>,
+    /// Stop reading a reader once it had this many matches; with assertions,
+    /// the rest is still read for them without matching
+    pub max_matches_per_file: Option<usize>,
+    /// Stop reading once this is cancelled
+    pub cancel: Option<CancelToken>,
+    /// Cancelled by the first reader whose matches could not be written,
+    /// stopping every reader sharing it, like `cancel`
+    pub write_failed: Option<CancelToken>,
+    /// Bytes of input between two ticks of a reader's `FileProgress`; 0
+    /// disables the ticks
+    pub progress_interval_bytes: u64,
+    /// Only lines stamped in this window can match, inverted or not
+    pub time_window: Option<TimeWindow>,
+    /// Date Docker and CRI lines by the timestamp their message starts with
+    /// rather than by the time of their record
+    pub message_timestamps: bool,
+    /// Bytes of a line matched and written; longer lines are cut to this
+    /// length, or skipped with `skip_long_lines`
+    pub max_line_length: Option<usize>,
+    /// Skip the lines longer than `max_line_length` instead of cutting them
+    pub skip_long_lines: bool,
+    /// Read nothing from readers with a NUL byte in their first
+    /// `BINARY_CHECK_BYTES`, which hold binary data rather than text
+    pub skip_binary_files: bool,
+}
+
+/// Match count shared by the readers of a run, which stop once it reaches `max`
+#[derive(Debug, Default)]
+pub struct MatchLimit {
+    max: usize,
+    found: AtomicUsize,
+}
+
+impl MatchLimit {
+    pub fn new(max: usize) -> Self {
+        Self {
+            max,
+            found: AtomicUsize::new(0),
+        }
+    }
+
+    /// Count one match, returning whether the limit is now reached
+    pub fn record(&self) -> bool {
+        self.found.fetch_add(1, Ordering::Relaxed) + 1 >= self.max
+    }
+
+    /// Count one match unless the limit was already reached: `None` when
+    /// the match is over the limit, otherwise whether it reached it
+    pub fn admit(&self) -> Option<bool> {
+        let found = self.found.fetch_add(1, Ordering::Relaxed);
+        (found < self.max).then_some(found + 1 >= self.max)
+    }
+
+    pub fn reached(&self) -> bool {
+        self.found.load(Ordering::Relaxed) >= self.max
+    }
+}
+
+/// Cancels a run from another task or thread; clones share the same flag
+#[derive(Clone, Debug, Default)]
+pub struct CancelToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ask every reader of the run to stop
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+/// Outcome of scanning a single reader
+#[derive(Debug, Default)]
+pub struct FileScan {
+    pub matches: usize,
+    pub lines_scanned: usize,
+    pub bytes_read: u64,
+    /// Matches of each search term, by index; a line counts for the first
+    /// term it matches only
+    pub term_matches: Vec<usize>,
+    /// Indices of the assertions this reader failed
+    pub failed_assertions: Vec<usize>,
+    /// Sampled atom evaluation times by atom id, empty unless
+    /// `ScanOptions::trace_sampling` is set
+    pub atom_timings: Vec<AtomTiming>,
+    /// First error met, like a file that could not be opened, a corrupt
+    /// archive or a failed write; reading stops at read and write errors
+    pub error: Option<ParserError>,
+    /// Lines longer than `ScanOptions::max_line_length`, cut or skipped
+    pub long_lines: usize,
+    /// Whether the reader was left unread as binary, with
+    /// `ScanOptions::skip_binary_files`
+    pub binary: bool,
+}
+
+/// Statistics for a single processed file
+#[derive(Clone, Debug)]
+pub struct FileResult {
+    pub path: PathBuf,
+    pub matches: usize,
+    pub lines_scanned: usize,
+    pub bytes_read: u64,
+    /// Format the file was decompressed from, `None` for plain logs
+    pub compression: Option<CompressionKind>,
+    /// Labels derived from the path by `ParserConfig::path_labels`
+    pub labels: PathLabels,
+    /// Lines longer than `ParserConfig::max_line_length`, cut or skipped
+    pub long_lines: usize,
+    /// Whether the file was skipped as binary
+    pub binary: bool,
+    /// File its matches were written to in `OutputMode::MirroredTree`,
+    /// `None` in other modes or when it had none
+    pub output_path: Option<PathBuf>,
+}
+
+/// Where the matched lines of a run are written
+#[derive(Clone, Default)]
+pub enum OutputTarget {
+    /// Plain text lines in `ParserConfig::output_log`
+    #[default]
+    OutputLog,
+    /// The lines of `OutputLog`, in the same format, on standard output
+    Stdout,
+    /// Apache Parquet file with one row per match
+    #[cfg(feature = "arrow")]
+    Parquet(PathBuf),
+    /// A sink of the caller, given every match as it is found and finished
+    /// with the run; targets are equal when they share the sink
+    Custom(Arc<dyn MatchSink>),
+    /// No output file; matches only reach `collect_matches` and `match_sender`
+    Discard,
+}
+
+impl fmt::Debug for OutputTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OutputTarget::OutputLog => write!(f, "OutputLog"),
+            OutputTarget::Stdout => write!(f, "Stdout"),
+            #[cfg(feature = "arrow")]
+            OutputTarget::Parquet(path) => f.debug_tuple("Parquet").field(path).finish(),
+            OutputTarget::Custom(_) => write!(f, "Custom(..)"),
+            OutputTarget::Discard => write!(f, "Discard"),
+        }
+    }
+}
+
+impl PartialEq for OutputTarget {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            #[cfg(feature = "arrow")]
+            (OutputTarget::Parquet(path), OutputTarget::Parquet(other)) => path == other,
+            (OutputTarget::Custom(sink), OutputTarget::Custom(other)) => Arc::ptr_eq(sink, other),
+            _ => std::mem::discriminant(self) == std::mem::discriminant(other),
+        }
+    }
+}
+
+impl Eq for OutputTarget {}
+
+impl Hash for OutputTarget {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+        match self {
+            #[cfg(feature = "arrow")]
+            OutputTarget::Parquet(path) => path.hash(state),
+            OutputTarget::Custom(sink) => Arc::as_ptr(sink).cast::<()>().hash(state),
+            _ => {}
+        }
+    }
+}
+
+/// What a run does with the files it selects
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum RunMode {
+    /// Search the files and write their matches to the output target
+    #[default]
+    Normal,
+    /// Search the files and count their matches; the output file is neither
+    /// created nor truncated
+    CountOnly,
+    /// Only list the files the run would read in `ParserResult::file_results`,
+    /// without opening them; nothing is created, written or deleted
+    DryRun,
+}
+
+/// How the output log is laid out, when matches go to `OutputTarget::OutputLog`
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum OutputMode {
+    /// Every match in `ParserConfig::output_log`
+    #[default]
+    SingleFile,
+    /// The matches of each log in a file of its own, at the same path below
+    /// `base_output_dir` as the log below its log folder, written by a
+    /// `MirroredTreeSink`; `ParserConfig::output_log` is left alone
+    ///
+    /// The files are written in place, and only for the logs with matches.
+    /// The folder is never searched for logs, even inside a log folder.
+    MirroredTree { base_output_dir: PathBuf },
+}
+
+impl OutputMode {
+    /// Folder of the output tree, in `MirroredTree`
+    pub fn base_output_dir(&self) -> Option<&Path> {
+        match self {
+            OutputMode::SingleFile => None,
+            OutputMode::MirroredTree { base_output_dir } => Some(base_output_dir),
+        }
+    }
+}
+
+/// `ParserConfig::log_folder` reading the logs from standard input
+pub const STDIN_LOG_FOLDER: &str = "-";
+
+/// `ParserConfig::output_log` writing the matches to standard output, like
+/// `OutputTarget::Stdout`
+pub const STDOUT_OUTPUT_LOG: &str = "-";
+
+/// Path naming `ParserConfig::input` in the results and the output log
+pub const STDIN_SOURCE: &str = "(standard input)";
+
+/// Configuration for the log parser
+pub struct ParserConfig {
+    /// Directory of the logs, or `STDIN_LOG_FOLDER` to read standard input
+    pub log_folder: String,
+    /// More directories searched like `log_folder` within the same run
+    ///
+    /// Unlike `log_folder`, they are not created when missing: a folder that
+    /// cannot be listed is reported in `ParserResult::errors` and the others
+    /// are searched all the same. Ignored when reading standard input.
+    pub log_folders: Vec<String>,
+    /// Files read, in this order, instead of searching the log folders
+    ///
+    /// They are read whatever their name, extension or modification time,
+    /// archives told apart by their extension like the files of a folder.
+    /// A file that cannot be opened, like a missing one, is reported in
+    /// `ParserResult::errors`. Ignored when reading standard input.
+    pub input_files: Vec<PathBuf>,
+    /// File the matches are written to, or `STDOUT_OUTPUT_LOG` for
+    /// standard output
+    pub output_log: String,
+    /// Text the names of the files to read must contain, or a glob pattern
+    /// like `app-*.log` when it has `*`, `?` or `[` (see `FilenameFilter`)
+    pub filename_filter: String,
+    /// Skip the files, plain or compressed, whose name starts with one of
+    /// these, ignoring case; `DEFAULT_EXCLUDED_PREFIX` unless changed
+    pub excluded_prefixes: Vec<String>,
+    /// Extensions of the files to read, ignoring case; `DEFAULT_LOG_EXTENSION`
+    /// unless changed
+    ///
+    /// An empty extension stands for files without one, and an empty list or
+    /// `*` for any file. Archives are read when the name inside them has one
+    /// of these, like `app.txt` for `app.txt.gz`.
+    pub allowed_extensions: Vec<String>,
+    /// Glob patterns of the files to read, matched against the file name,
+    /// or against the path relative to `log_folder` when they have a `/`;
+    /// empty to read every file (see `filename_filter`)
+    pub include_patterns: Vec<String>,
+    /// Glob patterns, like `include_patterns`, of the files to skip even when
+    /// included
+    pub exclude_patterns: Vec<String>,
+    pub line_filter: String,
+    pub search_terms: Vec<SearchTerm>,
+    /// Match the search terms and line filter with their case as written
+    ///
+    /// The helpers keep the case of the terms they add, so any of them will
+    /// do; `/…/` keywords and `re:` atoms only match text of their case when
+    /// added with `add_search_with_case`. File assertions and score rules
+    /// still ignore case.
+    pub case_sensitive: bool,
+    pub workers: Option<usize>,
+    /// Scan the plain logs of at least this many bytes as several ranges at
+    /// once, one per this many bytes up to one per worker, instead of with a
+    /// single worker
+    ///
+    /// Ranges are split at newlines and their matches written in file order,
+    /// so the results are those of a whole scan. Archives, which cannot be
+    /// read from the middle, and runs where lines depend on the ones before,
+    /// with context lines, `collapse_consecutive`, assertions or another
+    /// `input_format`, scan every file whole.
+    pub split_threshold_bytes: Option<u64>,
+    /// Keep every line that does not match, like `grep -v`
+    ///
+    /// The whole predicate is inverted: a line matches when it fails the
+    /// line filter or matches none of the search terms. Lines failing the
+    /// line filter are therefore kept too, even if they contain a term. Kept
+    /// lines are counted in `ParserResult::total_matches` but not per term,
+    /// and are reported with the `INVERTED_MATCH` term index and an empty
+    /// keyword. File assertions are not affected.
+    pub invert_match: bool,
+    /// Merge runs of identical matched lines into one line with a `(xN)` suffix
+    pub collapse_consecutive: bool,
+    /// Write each matched line to the output once, whichever files it is
+    /// found in (see `DedupSink`); in-memory consumers still get every match
+    pub deduplicate: bool,
+    /// With `deduplicate`, forget the lines seen once this many are held, so
+    /// months of logs do not exhaust memory
+    pub dedup_max_capacity: Option<usize>,
+    /// Write this many lines before each match, like grep's `-B`
+    ///
+    /// Overlapping windows are merged and non-contiguous blocks are separated
+    /// by a `--` line. In the output log, match lines are then marked with
+    /// `> ` and context lines with `- `. Context lines are not counted as
+    /// matches.
+    pub before_context: usize,
+    /// Write this many lines after each match, like grep's `-A`
+    pub after_context: usize,
+    /// File-level assertions evaluated against every scanned file
+    pub assertions: Vec<FileAssertion>,
+    /// Append a synthetic record for each assertion failure to the output file
+    pub write_assertion_failures: bool,
+    /// Whether the run writes its matches, only counts them or only lists
+    /// the files it would read
+    pub mode: RunMode,
+    /// Add the matches to the end of an existing output log instead of
+    /// replacing it
+    ///
+    /// In plain-text output, each run's matches are preceded by a commented
+    /// `# --- run at ... ---` line (see `run_separator`), in JSONL output by
+    /// a `{"run_separator":...}` object (see `run_separator_record`).
+    pub append: bool,
+    /// Also look for logs in the subdirectories of `log_folder`
+    pub recursive: bool,
+    /// With `recursive`, the deepest level searched, where 1 is `log_folder`
+    /// itself; unlimited when `None`
+    pub max_depth: Option<usize>,
+    /// Skip files, plain or compressed, last modified more than this many
+    /// seconds before the run started
+    ///
+    /// Where the platform does not report modification times, the run fails
+    /// rather than reading every file.
+    pub modified_within_secs: Option<u64>,
+    /// Only match lines stamped at or after this time, taken as UTC
+    ///
+    /// Lines are dated by the timestamp they start with (see
+    /// `timestamp_format`), Docker and CRI lines by the time of their record
+    /// unless `message_timestamps`. Files last modified earlier are skipped before
+    /// they are opened.
+    pub since: Option<NaiveDateTime>,
+    /// Only match lines stamped before this time, taken as UTC
+    pub until: Option<NaiveDateTime>,
+    /// strftime-like format of the timestamp starting each line, used with
+    /// `since` and `until`
+    pub timestamp_format: String,
+    /// With `since` or `until`, still match the lines that do not start with
+    /// a timestamp, like the continuation lines of a stack trace
+    pub include_untimestamped: bool,
+    /// With `since` or `until`, date Docker and CRI lines by the timestamp
+    /// their message starts with instead of the time of their record
+    pub message_timestamps: bool,
+    /// With `since` or `until`, also skip the files whose name holds a
+    /// `YYYY-MM-DD` date outside the range, like `app-2024-03-15.log.gz`
+    pub filename_dates: bool,
+    /// Prefix each line of the output log with `path:line_number:`, like
+    /// grep, counting every line read from the file
+    ///
+    /// Paths are relative to `log_folder`, so the files of a non-recursive
+    /// run are named by their file name alone.
+    pub show_location: bool,
+    /// How matches are written to the output log; with `OutputFormat::Jsonl`
+    /// and `OutputFormat::Tsv` each record already carries its location, so
+    /// `show_location` is ignored
+    pub output_format: OutputFormat,
+    /// Start the output log with a commented `# elysiumparser ...` line
+    /// describing the run (see `output_header`), or a `{"header":...}` object
+    /// in JSONL output (see `output_header_record`); TSV output and other
+    /// targets are unaffected
+    pub output_header: bool,
+    /// Write the matches of each file together, in the order of
+    /// `collect_log_files`, instead of as they are found
+    ///
+    /// Each file's matches are held in memory until the files before it are
+    /// written, so two runs over the same logs produce identical output.
+    pub ordered_output: bool,
+    /// With `ordered_output`, precede the matches of each file with a
+    /// `==> path <==` line, like `tail`; files without matches get none
+    pub file_headers: bool,
+    /// Rules deriving labels from each file's path relative to `log_folder`
+    pub path_labels: Vec<PathLabelRule>,
+    /// File system used to discover and read the logs
+    pub file_system: Arc<dyn FileSystem>,
+    /// Read the logs from this stream instead of the files of `log_folder`
+    ///
+    /// The stream is scanned as a single file named `STDIN_SOURCE` and is
+    /// decompressed when its first bytes show an archive. It has no known
+    /// length, so `ProgressUpdate::total` is 0. Set by `run_parser` when
+    /// `log_folder` is `STDIN_LOG_FOLDER`.
+    pub input: Option<Box<dyn Read + Send>>,
+    /// Where matched lines are written
+    pub output_target: OutputTarget,
+    /// Whether `OutputTarget::OutputLog` is one file or a tree of them
+    pub output_mode: OutputMode,
+    /// Lines of output waiting for the task writing the output log or
+    /// standard output; once that many wait, the readers wait too
+    pub writer_channel_capacity: usize,
+    /// Keep every match in `ParserResult::matches`
+    pub collect_matches: bool,
+    /// Stream every match to this channel as it is found
+    pub match_sender: Option<UnboundedSender<MatchRecordBuf>>,
+    /// Send every match to this bounded channel, blocking the readers while
+    /// it is full; only set by `run_parser_stream`, which runs the parser on
+    /// a runtime of its own so that blocking cannot stall a tokio worker
+    /// the consumer or other tasks need
+    pub match_stream_sender: Option<MatchStreamSender>,
+    /// How the search terms are evaluated against each line
+    pub match_strategy: MatchStrategy,
+    /// Whether keywords, the line filter and plain atoms are substrings, whole
+    /// words or regexes; regexes are compiled once before any file is read
+    pub match_mode: MatchMode,
+    /// How lines are encoded in the log files
+    pub input_format: InputFormat,
+    /// Memory-map plain, uncompressed files instead of reading them; files
+    /// that cannot be mapped are read as usual
+    #[cfg(feature = "mmap")]
+    pub mmap: bool,
+    /// Bytes of a line matched and written; longer lines are cut to this
+    /// length, or skipped with `skip_long_lines`. Plain logs are never held
+    /// in memory beyond it, however long their lines.
+    pub max_line_length: Option<usize>,
+    /// Skip the lines longer than `max_line_length` instead of cutting them
+    pub skip_long_lines: bool,
+    /// Leave out the files with a NUL byte in their first
+    /// `BINARY_CHECK_BYTES`, like a core dump named `app.log`
+    pub skip_binary_files: bool,
+    /// Bonus points added to the score of each match
+    pub score_rules: Vec<ScoreRule>,
+    /// Write the highest scoring matches here, best first
+    pub triage_output: Option<PathBuf>,
+    /// Number of highest scoring matches kept for triage
+    ///
+    /// 0, the default, keeps none and leaves the matches alone, unless
+    /// `triage_output` is set: `DEFAULT_TRIAGE_TOP` are then kept for it.
+    pub triage_top: usize,
+    /// Lower the process CPU and I/O priority before scanning
+    pub background: bool,
+    /// Hold back new files while the system load average is above this
+    pub pause_when_load_above: Option<f32>,
+    /// Publish the position of every file being scanned here, for sampling
+    /// the progress within large files on a timer
+    pub read_progress: Option<Arc<ReadProgress>>,
+    /// Bytes of input between two updates of a file's position in
+    /// `read_progress`
+    pub progress_interval_bytes: u64,
+    /// Stop once this many matches were found
+    ///
+    /// Files not started yet are skipped and files being read stop within a
+    /// few hundred lines. Matches found by concurrent files after the limit
+    /// was reached are dropped, so `ParserResult::total_matches` and the
+    /// output never exceed it. With `assertions`, every file is still read
+    /// to the end for them, without matching more lines.
+    pub max_matches: Option<usize>,
+    /// Stop reading each file once it had this many matches, like grep's `-m`
+    ///
+    /// The other files are still read. Followed logs count the matches of
+    /// each read of their appended lines apart. With `assertions`, files are
+    /// read to the end for them all the same, without matching more lines.
+    pub max_matches_per_file: Option<usize>,
+    /// Stop the run once this is cancelled
+    ///
+    /// Like `max_matches`, files not started yet are skipped and files being
+    /// read stop within a few hundred lines; the result holds what was found
+    /// until then, with `ParserResult::cancelled` set.
+    pub cancel: Option<CancelToken>,
+    /// Once the files were scanned, keep scanning the lines appended to them
+    /// and the new files of `log_folder` until `cancel` is cancelled
+    ///
+    /// Files truncated or replaced, like by log rotation, are read again from
+    /// the start. The output log is written in place instead of under a
+    /// temporary name, so it can be watched. Needs `cancel`; ignored when
+    /// reading `input`.
+    pub follow: bool,
+    /// Time between two checks of the followed files for appended lines
+    pub follow_interval: Duration,
+    /// Check the followed files as soon as this is notified instead of
+    /// waiting for `follow_interval`, like on the file events of
+    /// `run_parser_watch`
+    pub follow_wakeup: Option<Arc<Notify>>,
+    /// Time the atom evaluations of this fraction of lines, between 0 and 1,
+    /// and report them in `ParserResult::atom_timings`
+    ///
+    /// Lines are picked at random. Unsampled lines only pay for a counter
+    /// decrement, so small fractions like 0.001 cost nothing measurable.
+    pub trace_sampling: Option<f64>,
+    /// Rows buffered before each Parquet record batch is written
+    #[cfg(feature = "arrow")]
+    pub parquet_batch_rows: usize,
+}
+
+impl Default for ParserConfig {
+    fn default() -> Self {
+        Self {
+            log_folder: "logs/parser".to_string(),
+            log_folders: vec![],
+            input_files: vec![],
+            output_log: "logs/parser/output.log".to_string(),
+            filename_filter: String::new(),
+            excluded_prefixes: vec![DEFAULT_EXCLUDED_PREFIX.to_string()],
+            allowed_extensions: vec![DEFAULT_LOG_EXTENSION.to_string()],
+            include_patterns: vec![],
+            exclude_patterns: vec![],
+            line_filter: String::new(),
+            search_terms: vec![],
+            case_sensitive: false,
+            workers: None,
+            split_threshold_bytes: None,
+            collapse_consecutive: false,
+            deduplicate: false,
+            dedup_max_capacity: None,
+            invert_match: false,
+            before_context: 0,
+            after_context: 0,
+            assertions: vec![],
+            write_assertion_failures: false,
+            mode: RunMode::Normal,
+            append: false,
+            recursive: false,
+            max_depth: None,
+            modified_within_secs: None,
+            since: None,
+            until: None,
+            timestamp_format: DEFAULT_TIMESTAMP_FORMAT.to_string(),
+            include_untimestamped: true,
+            message_timestamps: false,
+            filename_dates: false,
+            show_location: false,
+            output_format: OutputFormat::default(),
+            output_header: false,
+            ordered_output: false,
+            file_headers: false,
+            path_labels: vec![],
+            file_system: Arc::new(StdFileSystem),
+            input: None,
+            output_target: OutputTarget::default(),
+            output_mode: OutputMode::default(),
+            writer_channel_capacity: DEFAULT_WRITER_CHANNEL_CAPACITY,
+            collect_matches: false,
+            match_sender: None,
+            match_stream_sender: None,
+            match_strategy: MatchStrategy::default(),
+            match_mode: MatchMode::default(),
+            input_format: InputFormat::default(),
+            #[cfg(feature = "mmap")]
+            mmap: false,
+            max_line_length: None,
+            skip_long_lines: false,
+            skip_binary_files: false,
+            score_rules: vec![],
+            triage_output: None,
+            triage_top: 0,
+            background: false,
+            pause_when_load_above: None,
+            read_progress: None,
+            progress_interval_bytes: DEFAULT_PROGRESS_INTERVAL_BYTES,
+            max_matches: None,
+            max_matches_per_file: None,
+            cancel: None,
+            follow: false,
+            follow_interval: DEFAULT_FOLLOW_INTERVAL,
+            follow_wakeup: None,
+            trace_sampling: None,
+            #[cfg(feature = "arrow")]
+            parquet_batch_rows: 8192,
+        }
+    }
+}
+
+impl ParserConfig {
+    /// Configuration for the pod logs of a Kubernetes node
+    ///
+    /// Searches `POD_LOG_FOLDER` recursively, decodes CRI lines and labels
+    /// every file with the `namespace`, `pod`, `uid` and `container` it
+    /// belongs to (see `kubernetes_label_rule`).
+    pub fn kubernetes() -> Self {
+        Self {
+            log_folder: POD_LOG_FOLDER.to_string(),
+            recursive: true,
+            input_format: InputFormat::Cri,
+            path_labels: vec![kubernetes_label_rule()],
+            ..Self::default()
+        }
+    }
+
+    /// Builder validating the configuration before a run (see `ParserConfigBuilder`)
+    pub fn builder() -> ParserConfigBuilder {
+        ParserConfigBuilder::new()
+    }
+
+    /// Window of `since` and `until`, when either is set
+    fn time_window(&self) -> io::Result<Option<TimeWindow>> {
+        if self.since.is_none() && self.until.is_none() {
+            return Ok(None);
+        }
+        TimeWindow::new(
+            self.since,
+            self.until,
+            &self.timestamp_format,
+            self.include_untimestamped,
+        )
+        .map(Some)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))
+    }
+
+    /// `log_folder` followed by `log_folders`
+    pub(crate) fn log_roots(&self) -> Vec<PathBuf> {
+        std::iter::once(&self.log_folder)
+            .chain(&self.log_folders)
+            .map(PathBuf::from)
+            .collect()
+    }
+
+    /// The settings `find_log_files` relies on, to list the followed files
+    /// once the run took the others
+    fn listing(&self) -> ParserConfig {
+        ParserConfig {
+            log_folder: self.log_folder.clone(),
+            log_folders: self.log_folders.clone(),
+            input_files: self.input_files.clone(),
+            output_log: self.output_log.clone(),
+            output_mode: self.output_mode.clone(),
+            search_terms: self.search_terms.clone(),
+            filename_filter: self.filename_filter.clone(),
+            excluded_prefixes: self.excluded_prefixes.clone(),
+            allowed_extensions: self.allowed_extensions.clone(),
+            include_patterns: self.include_patterns.clone(),
+            exclude_patterns: self.exclude_patterns.clone(),
+            recursive: self.recursive,
+            max_depth: self.max_depth,
+            modified_within_secs: self.modified_within_secs,
+            since: self.since,
+            until: self.until,
+            timestamp_format: self.timestamp_format.clone(),
+            include_untimestamped: self.include_untimestamped,
+            filename_dates: self.filename_dates,
+            file_system: Arc::clone(&self.file_system),
+            ..Default::default()
+        }
+    }
+
+    /// Hash of what a run reads and writes: folders, output, filters and terms
+    ///
+    /// Runs with different settings get different fingerprints, which
+    /// namespace the temporary files of each run. The value is not stable
+    /// across builds and must not be persisted.
+    pub fn fingerprint(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.log_folder.hash(&mut hasher);
+        self.log_folders.hash(&mut hasher);
+        self.input_files.hash(&mut hasher);
+        self.output_log.hash(&mut hasher);
+        self.output_target.hash(&mut hasher);
+        self.output_mode.hash(&mut hasher);
+        self.filename_filter.hash(&mut hasher);
+        self.excluded_prefixes.hash(&mut hasher);
+        self.allowed_extensions.hash(&mut hasher);
+        self.include_patterns.hash(&mut hasher);
+        self.exclude_patterns.hash(&mut hasher);
+        self.line_filter.hash(&mut hasher);
+        self.case_sensitive.hash(&mut hasher);
+        self.recursive.hash(&mut hasher);
+        self.max_depth.hash(&mut hasher);
+        self.since.hash(&mut hasher);
+        self.until.hash(&mut hasher);
+        for term in &self.search_terms {
+            term.to_string().hash(&mut hasher);
+        }
+        hasher.finish()
+    }
+}
+
+/// Matches of one search term of a run
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TermStat {
+    pub keyword: String,
+    /// The additional expression as rendered by its `Display`, empty without one
+    pub expression: String,
+    pub match_count: usize,
+    /// File of `SearchTerm::output`, `None` when the matches go to the
+    /// output of the run
+    pub output: Option<PathBuf>,
+    /// Lines written to `output`, counting those first matched by another
+    /// term, which `match_count` leaves out
+    pub lines_written: usize,
+}
+
+/// Progress of a run, reported after each file
+#[derive(Clone, Copy, Debug)]
+pub struct ProgressUpdate<'a> {
+    /// Files processed so far, including `path`
+    pub processed: usize,
+    /// Files the run will process, 0 when reading `ParserConfig::input`
+    pub total: usize,
+    /// File that was just processed
+    pub path: &'a Path,
+    /// Matches found so far in all processed files
+    pub matches: usize,
+}
+
+/// Callback receiving the progress of a run
+///
+/// Updates are delivered one at a time, in the order files complete.
+pub type ProgressCallback = Arc<dyn Fn(ProgressUpdate<'_>) + Send + Sync>;
+
+/// Result of parsing logs
+pub struct ParserResult {
+    pub total_matches: usize,
+    pub processed_files: usize,
+    /// Lines read across all files, including non-matching ones
+    pub lines_scanned: usize,
+    /// Bytes read across all files, after decompression
+    pub bytes_read: u64,
+    /// Per-file statistics, sorted by path
+    pub file_results: Vec<FileResult>,
+    /// Matches of each search term, aligned with `ParserConfig::search_terms`
+    pub per_term: Vec<usize>,
+    /// `per_term` together with the terms counted
+    ///
+    /// The counts add up to `total_matches`, except that lines kept by
+    /// `ParserConfig::invert_match` count for no term.
+    pub term_stats: Vec<TermStat>,
+    /// Files that failed a file assertion, sorted by path
+    pub assertion_failures: Vec<AssertionFailure>,
+    /// Up to `ParserConfig::triage_top` highest scoring matches, best first
+    pub top_matches: Vec<ScoredMatch>,
+    /// Every match, ordered by file and line, when `ParserConfig::collect_matches` is set
+    pub matches: Vec<MatchRecordBuf>,
+    /// Whether `ParserConfig::background` lowered the process priority
+    pub background_applied: bool,
+    /// Sampled evaluation times of the atoms that were evaluated, slowest
+    /// total first, when `ParserConfig::trace_sampling` is set
+    pub atom_timings: Vec<AtomTiming>,
+    /// Files and directories that could not be read or written, sorted by
+    /// path, with what went wrong
+    ///
+    /// A file that failed to open counts as processed without matches; one
+    /// that failed midway keeps the matches found until then. A failed write
+    /// of the output stops every reader and fails the run instead.
+    pub errors: Vec<(PathBuf, ParserError)>,
+    /// Whether `ParserConfig::cancel` was cancelled during the run; the
+    /// counts and output then only cover the lines read until then
+    pub cancelled: bool,
+    /// Whether `ParserConfig::max_matches` was reached, so the run may have
+    /// stopped before reading every line
+    pub limit_reached: bool,
+    /// Matches left out of the output as duplicates, with
+    /// `ParserConfig::deduplicate`; they still count in `total_matches`
+    pub skipped_duplicates: usize,
+    /// Times the duplicate filter forgot the lines it had seen on reaching
+    /// `ParserConfig::dedup_max_capacity`, after which lines already written
+    /// may be written again
+    pub dedup_resets: usize,
+    /// Lines longer than `ParserConfig::max_line_length` across all files
+    pub long_lines: usize,
+    /// Files skipped as binary with `ParserConfig::skip_binary_files`
+    pub binary_files: usize,
+}
+
+impl ParserResult {
+    /// Order `file_results` by match count, noisiest first; ties keep path order
+    pub fn sort_by_matches(&mut self) {
+        self.file_results.sort_by(|a, b| {
+            b.matches
+                .cmp(&a.matches)
+                .then_with(|| a.path.cmp(&b.path))
+        });
+    }
+}
+
+/// Keep a keyword as written, compiling it when it is a `/…/` regular
+/// expression, which ignores case unless `case_sensitive`
+fn parse_keyword(
+    keyword: &str,
+    case_sensitive: bool,
+) -> Result<(String, Option<RegexPattern>), regex::Error> {
+    match regex_pattern::delimited_pattern(keyword) {
+        Some(pattern) => Ok((
+            keyword.to_string(),
+            Some(RegexPattern::with_case(pattern, !case_sensitive)?),
+        )),
+        None => Ok((keyword.to_string(), None)),
+    }
+}
+
+/// Add a simple search term
+///
+/// The keywords keep their case as written: runs ignore it unless
+/// `ParserConfig::case_sensitive`. A `/…/` keyword that is not a valid
+/// regular expression is matched as plain text; `add_search_with_expression`
+/// reports it instead.
+pub fn add_search(search_terms: &mut Vec<SearchTerm>, keyword: &str, additional_keyword: &str) {
+    let (keyword, keyword_pattern) =
+        parse_keyword(keyword, false).unwrap_or_else(|_| (keyword.to_string(), None));
+    search_terms.push(SearchTerm {
+        keyword,
+        keyword_pattern,
+        additional_expression: if additional_keyword.is_empty() {
+            None
+        } else {
+            Some(BooleanExpression::Term(Term::Literal(
+                additional_keyword.to_string(),
+            )))
+        },
+        score: 0,
+        output: None,
+    });
+}
+
+/// Add a search term whose keyword is a regular expression
+///
+/// The pattern is compiled once, here, and matched against each line as
+/// read; with `case_insensitive` unset its case must agree with the line.
+pub fn add_search_regex(
+    search_terms: &mut Vec<SearchTerm>,
+    pattern: &str,
+    case_insensitive: bool,
+) -> Result<(), regex::Error> {
+    search_terms.push(SearchTerm {
+        keyword: pattern.to_string(),
+        keyword_pattern: Some(RegexPattern::with_case(pattern, case_insensitive)?),
+        additional_expression: None,
+        score: 0,
+        output: None,
+    });
+    Ok(())
+}
+
+/// Atoms past which an additional expression is reported as slow to match,
+/// by default (see `BooleanExpression::complexity`)
+pub const EXPRESSION_COMPLEXITY_WARNING: usize = 32;
+
+static COMPLEXITY_WARNING: AtomicUsize = AtomicUsize::new(EXPRESSION_COMPLEXITY_WARNING);
+
+/// Report the additional expressions added from now on with more than
+/// `atoms` atoms, `EXPRESSION_COMPLEXITY_WARNING` until set
+pub fn set_expression_complexity_warning(atoms: usize) {
+    COMPLEXITY_WARNING.store(atoms, Ordering::Relaxed);
+}
+
+/// Add a search term with a complex boolean expression
+///
+/// The keyword and atoms keep their case as written, like those of
+/// `add_search`; `/…/` keywords and `re:` atoms ignore case. A blank
+/// expression adds a term matching on the keyword alone. An invalid `/…/`
+/// keyword is reported as `ParseErrorKind::InvalidRegex` at position 0. The
+/// expression is kept normalized (see `BooleanExpression::normalize`).
+pub fn add_search_with_expression(
+    search_terms: &mut Vec<SearchTerm>,
+    keyword: &str,
+    additional_expr: &str,
+) -> Result<(), ParseError> {
+    add_search_with_case(search_terms, keyword, additional_expr, false)
+}
+
+/// Add a search term with a boolean expression whose `/…/` keyword and
+/// `re:` atoms match text of their case when `case_sensitive`
+///
+/// Keywords and atoms are kept as written either way. Expressions with
+/// more atoms than set by `set_expression_complexity_warning` are reported
+/// with a `tracing` warning.
+pub fn add_search_with_case(
+    search_terms: &mut Vec<SearchTerm>,
+    keyword: &str,
+    additional_expr: &str,
+    case_sensitive: bool,
+) -> Result<(), ParseError> {
+    let (keyword, keyword_pattern) =
+        parse_keyword(keyword, case_sensitive).map_err(|e| ParseError {
+            kind: ParseErrorKind::InvalidRegex(e.to_string()),
+            position: 0,
+        })?;
+    let additional_expression = if additional_expr.trim().is_empty() {
+        None
+    } else {
+        let case = match case_sensitive {
+            true => AtomCase::Sensitive,
+            false => AtomCase::AsWritten,
+        };
+        Some(expression::parse(additional_expr, case)?.normalize())
+    };
+    let complexity = additional_expression
+        .as_ref()
+        .map_or(0, BooleanExpression::complexity);
+    let threshold = COMPLEXITY_WARNING.load(Ordering::Relaxed);
+    if complexity > threshold {
+        tracing::warn!(
+            "'{}' has {} atoms, more than {}; matching it may be slow",
+            additional_expr,
+            complexity,
+            threshold
+        );
+    }
+
+    search_terms.push(SearchTerm {
+        keyword,
+        keyword_pattern,
+        additional_expression,
+        score: 0,
+        output: None,
+    });
+    Ok(())
+}
+
+/// Add a search term whose matches start with the given severity score
+pub fn add_scored_search(
+    search_terms: &mut Vec<SearchTerm>,
+    keyword: &str,
+    additional_expr: &str,
+    score: i32,
+) -> Result<(), ParseError> {
+    add_search_with_expression(search_terms, keyword, additional_expr)?;
+    if let Some(term) = search_terms.last_mut() {
+        term.score = score;
+    }
+    Ok(())
+}
+
+/// Add a file assertion from two boolean expressions
+pub fn add_file_assertion(
+    assertions: &mut Vec<FileAssertion>,
+    must_contain: &str,
+    must_not_contain: &str,
+) -> Result<(), ParseError> {
+    assertions.push(FileAssertion {
+        must_contain: BooleanExpression::parse(must_contain)?,
+        must_not_contain: BooleanExpression::parse(must_not_contain)?,
+    });
+    Ok(())
+}
+
+/// Prefix of the file names `ParserConfig::excluded_prefixes` skips by default
+pub const DEFAULT_EXCLUDED_PREFIX: &str = "debug";
+
+/// Extension of the files `ParserConfig::allowed_extensions` reads by default
+pub const DEFAULT_LOG_EXTENSION: &str = "log";
+
+/// Check if a file is a valid log file for processing
+///
+/// A malformed glob `filename_filter` is used as a plain substring.
+pub fn is_valid_log_file(
+    path: &Path,
+    filename_filter: &str,
+    output_log: &str,
+    excluded_prefixes: &[String],
+    allowed_extensions: &[String],
+) -> bool {
+    let filename_filter = FilenameFilter::new(filename_filter)
+        .unwrap_or_else(|_| FilenameFilter::Substring(filename_filter.to_lowercase()));
+    path.is_file()
+        && is_log_file_name(path, &filename_filter, output_log, allowed_extensions)
+        && !has_excluded_prefix(path, excluded_prefixes)
+}
+
+/// Check if the extension of a file name is one of `allowed_extensions`,
+/// ignoring case and a leading dot
+///
+/// An empty entry allows names without an extension; `*` or an empty list
+/// allows every name.
+pub(crate) fn has_allowed_extension(path: &Path, allowed_extensions: &[String]) -> bool {
+    let extension = path.extension().map(|extension| extension.to_string_lossy());
+    let extension = extension.as_deref().unwrap_or_default();
+    allowed_extensions.is_empty()
+        || allowed_extensions.iter().any(|allowed| {
+            let allowed = allowed.strip_prefix('.').unwrap_or(allowed);
+            allowed == "*" || allowed.eq_ignore_ascii_case(extension)
+        })
+}
+
+/// Check if a path is an archive of a log file with one of
+/// `allowed_extensions`, like `app.log.gz`, or of a log rotated by the kubelet
+fn is_archive_name(path: &Path, allowed_extensions: &[String]) -> bool {
+    CompressionKind::from_path(path).is_some() && {
+        let inner = path.with_extension("");
+        has_allowed_extension(&inner, allowed_extensions)
+            || kubernetes::is_rotated_log_name(&inner)
+    }
+}
+
+/// Check if a file name starts with one of `excluded_prefixes`, ignoring case
+///
+/// Empty prefixes exclude nothing.
+fn has_excluded_prefix(path: &Path, excluded_prefixes: &[String]) -> bool {
+    let Some(filename) = path.file_name().and_then(|filename| filename.to_str()) else {
+        return false;
+    };
+    let filename = filename.to_lowercase();
+    excluded_prefixes
+        .iter()
+        .any(|prefix| !prefix.is_empty() && filename.starts_with(&prefix.to_lowercase()))
+}
+
+/// Check if a path is named like a log file, without touching the disk
+fn is_log_file_name(
+    path: &Path,
+    filename_filter: &FilenameFilter,
+    output_log: &str,
+    allowed_extensions: &[String],
+) -> bool {
+    if !has_allowed_extension(path, allowed_extensions) || is_partial_output(path) {
+        return false;
+    }
+
+    let output_path = Path::new(output_log);
+    if path == output_path {
+        return false;
+    }
+
+    path.file_name().and_then(|filename| filename.to_str()).is_some()
+        && filename_filter.matches(path)
+}
+
+/// Check if a file is a gzipped file whose name has none of `excluded_prefixes`,
+/// holding a log with one of `allowed_extensions`
+pub fn is_gz_file(
+    path: &Path,
+    excluded_prefixes: &[String],
+    allowed_extensions: &[String],
+) -> bool {
+    is_compressed_file(path) == Some(CompressionKind::Gzip)
+        && is_archive_name(path, allowed_extensions)
+        && !has_excluded_prefix(path, excluded_prefixes)
+}
+
+/// Check if a file is a compressed log archive of an enabled format
+pub fn is_compressed_file(path: &Path) -> Option<CompressionKind> {
+    CompressionKind::from_path(path).filter(|_| path.is_file())
+}
+
+/// Process a log file without progress output, decompressing archives
+/// like `open_log_reader`
+pub fn process_file_silent(
+    path: &Path,
+    search_terms: &[SearchTerm],
+    options: &ScanOptions,
+    output: Option<&dyn MatchSink>,
+) -> FileScan {
+    match open_log_reader(path) {
+        Ok(reader) => process_reader(reader, path, search_terms, options, output),
+        Err(e) => FileScan {
+            error: Some(ParserError::Open(e)),
+            ..Default::default()
+        },
+    }
+}
+
+/// Process a gzipped log file without progress output
+pub fn process_gz_file_silent(
+    gz_path: &PathBuf,
+    search_terms: &[SearchTerm],
+    options: &ScanOptions,
+    output: Option<&dyn MatchSink>,
+) -> Result<FileScan, io::Error> {
+    process_compressed_file_silent(gz_path, CompressionKind::Gzip, search_terms, options, output)
+}
+
+/// Process a compressed log file of the given kind without progress output
+pub fn process_compressed_file_silent(
+    path: &PathBuf,
+    kind: CompressionKind,
+    search_terms: &[SearchTerm],
+    options: &ScanOptions,
+    output: Option<&dyn MatchSink>,
+) -> Result<FileScan, io::Error> {
+    let reader = compression::log_reader(Box::new(File::open(path)?), Some(kind))?;
+    Ok(process_reader(reader, path, search_terms, options, output))
+}
+
+/// Open a file through the configured file system and scan it
+fn scan_file(
+    file_system: &dyn FileSystem,
+    path: &Path,
+    compression: Option<CompressionKind>,
+    search_set: &SearchSet,
+    options: &ScanOptions,
+    output: Option<&dyn MatchSink>,
+    progress: Option<&FileProgress>,
+) -> io::Result<FileScan> {
+    #[cfg(feature = "mmap")]
+    if options.mmap
+        && compression.is_none()
+        && options.input_format == InputFormat::Plain
+        && let Ok(data) = file_system.map(path)
+        && CompressionKind::from_magic(&data).is_none()
+    {
+        return Ok(mmap::scan_mapped(
+            &data, path, search_set, options, output, progress,
+        ));
+    }
+
+    let reader = compression::log_reader(file_system.open(path)?, compression)?;
+    Ok(scan_reader(
+        reader,
+        path,
+        search_set,
+        options,
+        output,
+        progress,
+    ))
+}
+
+/// Process a reader (regular or decompressed file)
+///
+/// `source` identifies the reader in the records handed to `output`.
+///
+/// When `collapse_consecutive` is set, runs of identical matched lines are
+/// written once with a ` (xN)` repeat suffix, like `uniq -c` over the
+/// matches of a single reader. Every match is still counted.
+///
+/// With `before_context` or `after_context`, matches are written through
+/// `MatchSink::write_block` together with the lines around them instead;
+/// collapsing is then skipped.
+///
+/// File assertions are evaluated on every line, regardless of the line
+/// filter, and the ones the reader failed are reported in the result.
+///
+/// With `InputFormat::DockerJson` or `InputFormat::Cri`, line numbers count
+/// the decoded lines rather than the records.
+///
+/// Without an output only the statistics are collected. In
+/// `MatchMode::Regex` an invalid pattern is reported in `FileScan::error`
+/// and nothing is scanned.
+pub fn process_reader<R: BufRead>(
+    reader: R,
+    source: &Path,
+    search_terms: &[SearchTerm],
+    options: &ScanOptions,
+    output: Option<&dyn MatchSink>,
+) -> FileScan {
+    compile_and_scan(reader, source, search_terms, options, output, None)
+}
+
+/// `process_reader`, publishing the bytes read to `progress` every
+/// `options.progress_interval_bytes` and once more at the end
+pub fn process_reader_with_progress<R: BufRead>(
+    reader: R,
+    source: &Path,
+    search_terms: &[SearchTerm],
+    options: &ScanOptions,
+    output: Option<&dyn MatchSink>,
+    progress: &FileProgress,
+) -> FileScan {
+    compile_and_scan(reader, source, search_terms, options, output, Some(progress))
+}
+
+/// Lines of a reader matching the search terms, in order
+///
+/// A synchronous shortcut over `process_reader` for callers that want the
+/// lines themselves, with no runtime, log folder or output file involved.
+/// Lines come back as a sink receives them, so with `collapse_consecutive`
+/// runs of identical lines are returned once with their ` (xN)` suffix;
+/// context lines are left out.
+///
+/// An invalid `MatchMode::Regex` pattern or a read error fails the search.
+pub fn search_reader<R: BufRead>(
+    reader: R,
+    search_terms: &[SearchTerm],
+    options: &ScanOptions,
+) -> io::Result<Vec<String>> {
+    let collector = CollectSink::default();
+    let scan = process_reader(reader, Path::new(""), search_terms, options, Some(&collector));
+    if let Some(error) = scan.error {
+        return Err(io::Error::other(error));
+    }
+    Ok(collector
+        .take_matches()
+        .into_iter()
+        .map(|record| record.line)
+        .collect())
+}
+
+fn compile_and_scan<R: BufRead>(
+    reader: R,
+    source: &Path,
+    search_terms: &[SearchTerm],
+    options: &ScanOptions,
+    output: Option<&dyn MatchSink>,
+    progress: Option<&FileProgress>,
+) -> FileScan {
+    match Matcher::with_options(search_terms, options) {
+        Ok(matcher) => scan_reader(
+            reader,
+            source,
+            matcher.search_set(),
+            options,
+            output,
+            progress,
+        ),
+        Err(e) => FileScan {
+            error: Some(ParserError::InvalidPattern(e.to_string())),
+            ..Default::default()
+        },
+    }
+}
+
+/// Scan a reader with already compiled search terms
+fn scan_reader<R: BufRead>(
+    reader: R,
+    source: &Path,
+    search_set: &SearchSet,
+    options: &ScanOptions,
+    output: Option<&dyn MatchSink>,
+    progress: Option<&FileProgress>,
+) -> FileScan {
+    let scanner = LineScanner::new(source, search_set, options, output, progress);
+    scan_lines(reader, scanner)
+}
+
+/// Scan the lines appended to a followed log, numbered after those read before
+fn scan_appended(
+    appended: &Appended,
+    search_set: &SearchSet,
+    options: &ScanOptions,
+    output: Option<&dyn MatchSink>,
+) -> FileScan {
+    let mut scanner = LineScanner::new(&appended.path, search_set, options, output, None);
+    scanner.scan.lines_scanned = appended.lines_before;
+    let mut scan = scan_lines(appended.lines.as_slice(), scanner);
+    scan.lines_scanned -= appended.lines_before;
+    scan
+}
+
+fn scan_lines<R: BufRead>(mut reader: R, mut scanner: LineScanner<'_>) -> FileScan {
+    if scanner.options.skip_binary_files
+        && reader.fill_buf().is_ok_and(looks_binary)
+    {
+        scanner.scan.binary = true;
+        return scanner.finish();
+    }
+    let mut reader = LineReader::new(
+        reader,
+        scanner.options.input_format,
+        scanner.options.max_line_length,
+    );
+    let mut buffer = Vec::new();
+
+    loop {
+        buffer.clear();
+        match reader.read_line(&mut buffer) {
+            Ok(0) => break,
+            Ok(bytes) => {
+                scanner.scan_line(&buffer, bytes, reader.record_time());
+                if scanner.stopped {
+                    break;
+                }
+            }
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => {
+                keep_first_error(&mut scanner.scan.error, ParserError::Read, Err(e));
+                break;
+            }
+        }
+    }
+
+    scanner.finish()
+}
+
+/// Bytes at the start of a file searched for a NUL byte by
+/// `ScanOptions::skip_binary_files`
+pub const BINARY_CHECK_BYTES: usize = 8192;
+
+/// Whether a file starting with `start` holds binary data, judged by a NUL
+/// byte in its first `BINARY_CHECK_BYTES`, like grep does
+pub(crate) fn looks_binary(start: &[u8]) -> bool {
+    start[..start.len().min(BINARY_CHECK_BYTES)].contains(&0)
+}
+
+/// Lines between two looks at a `MatchLimit` reached by other readers, or
+/// at a `CancelToken`
+const LIMIT_CHECK_INTERVAL: usize = 256;
+
+/// Scanning state of a single reader, fed one line at a time
+struct LineScanner<'a> {
+    source: &'a Path,
+    search_set: &'a SearchSet,
+    options: &'a ScanOptions,
+    output: Option<&'a dyn MatchSink>,
+    scan: FileScan,
+    /// First matched line of the current run, its line number, term, score and repeat count
+    pending: Option<(String, usize, usize, i32, usize)>,
+    /// Block of matches and surrounding lines, when context lines are written
+    context: Option<ContextWindow>,
+    /// Whether each assertion's (must_contain, must_not_contain) clause was seen
+    assertion_seen: Vec<(bool, bool)>,
+    /// Picks the lines whose atom evaluations are timed
+    sampler: Option<Sampler>,
+    /// Set once a match limit is reached or the scan is cancelled; no more
+    /// lines are read
+    stopped: bool,
+    /// Set instead of `stopped` when a limit is reached in a file taking
+    /// part in assertions: the rest of it is read for them, matching nothing
+    matching_done: bool,
+    /// Where the bytes read are published, with the position of the next tick
+    progress: Option<(&'a FileProgress, u64)>,
+    /// Lowercase copy of the current line, reused from line to line
+    lowercase: String,
+}
+
+impl<'a> LineScanner<'a> {
+    fn new(
+        source: &'a Path,
+        search_set: &'a SearchSet,
+        options: &'a ScanOptions,
+        output: Option<&'a dyn MatchSink>,
+        progress: Option<&'a FileProgress>,
+    ) -> Self {
+        Self {
+            source,
+            search_set,
+            options,
+            output,
+            scan: FileScan {
+                term_matches: vec![0; search_set.terms().len()],
+                atom_timings: match options.trace_sampling {
+                    Some(_) => search_set.atom_timings(),
+                    None => Vec::new(),
+                },
+                ..Default::default()
+            },
+            pending: None,
+            context: (output.is_some() && (options.before_context > 0 || options.after_context > 0))
+                .then(|| ContextWindow::new(options.before_context, options.after_context)),
+            assertion_seen: vec![(false, false); options.assertions.len()],
+            sampler: Sampler::new(options.trace_sampling),
+            stopped: false,
+            matching_done: false,
+            progress: progress
+                .filter(|_| options.progress_interval_bytes > 0)
+                .map(|progress| (progress, options.progress_interval_bytes)),
+            lowercase: String::new(),
+        }
+    }
+
+    /// Scan one line, with its terminator, that took `bytes` bytes to read
+    /// from the Docker or CRI record written at `record_time`, if any
+    fn scan_line(&mut self, raw: &[u8], bytes: usize, record_time: Option<&str>) {
+        let options = self.options;
+        self.scan.bytes_read += bytes as u64;
+        self.scan.lines_scanned += 1;
+        if let Some((progress, next_tick)) = &mut self.progress
+            && self.scan.bytes_read >= *next_tick
+        {
+            progress.tick(self.scan.bytes_read);
+            *next_tick = self.scan.bytes_read + options.progress_interval_bytes;
+        }
+        let line_number = self.scan.lines_scanned;
+
+        // Other readers may have reached the limit; looking now and then is enough
+        if line_number.is_multiple_of(LIMIT_CHECK_INTERVAL) {
+            if options.cancel.as_ref().is_some_and(CancelToken::is_cancelled)
+                || options.write_failed.as_ref().is_some_and(CancelToken::is_cancelled)
+            {
+                self.stopped = true;
+            } else if options.match_limit.as_ref().is_some_and(|limit| limit.reached()) {
+                self.stop_matching();
+            }
+        }
+
+        let raw = raw.strip_suffix(b"\n").unwrap_or(raw);
+        let mut raw = raw.strip_suffix(b"\r").unwrap_or(raw);
+        if let Some(max_line_length) = options.max_line_length
+            && raw.len() > max_line_length
+        {
+            self.scan.long_lines += 1;
+            if options.skip_long_lines {
+                return;
+            }
+            raw = &raw[..max_line_length];
+        }
+        // Invalid UTF-8, like binary payloads, becomes replacement characters
+        let line = String::from_utf8_lossy(raw);
+        let line = line.as_ref();
+        // Assertions and score rules stay case-insensitive in case-sensitive runs
+        if options.case_sensitive && options.assertions.is_empty() && options.score_rules.is_empty()
+        {
+            self.match_line(line, line, line_number, record_time);
+            return;
+        }
+        // ASCII lines, most of them, are lowercased in place without allocating
+        let mut lowercase = std::mem::take(&mut self.lowercase);
+        lowercase_into(line, &mut lowercase);
+        self.match_line(line, &lowercase, line_number, record_time);
+        self.lowercase = lowercase;
+    }
+
+    /// Match a decoded line, given its lowercase form unless only its case
+    /// as written is needed
+    fn match_line(
+        &mut self,
+        line: &str,
+        lowercase_line: &str,
+        line_number: usize,
+        record_time: Option<&str>,
+    ) {
+        let search_terms = self.search_set.terms();
+        let options = self.options;
+        let search_line = if options.case_sensitive {
+            line
+        } else {
+            lowercase_line
+        };
+
+        for (assertion, seen) in options.assertions.iter().zip(self.assertion_seen.iter_mut()) {
+            if !seen.0 && assertion.must_contain.matches(lowercase_line) {
+                seen.0 = true;
+            }
+            if !seen.1 && assertion.must_not_contain.matches(lowercase_line) {
+                seen.1 = true;
+            }
+        }
+        if self.matching_done {
+            return;
+        }
+
+        // Lines outside the time window match nothing, inverted or not
+        let in_window = options
+            .time_window
+            .as_ref()
+            .is_none_or(|window| match record_time {
+                Some(time) if !options.message_timestamps => window.contains_record(time, line),
+                _ => window.contains_line(line),
+            });
+        let traced = in_window && self.sampler.as_mut().is_some_and(Sampler::sample);
+        let term = if !in_window {
+            None
+        } else if traced {
+            self.search_set
+                .matching_line_traced(line, search_line, &mut self.scan.atom_timings)
+        } else {
+            self.search_set.matching_line(line, search_line)
+        };
+        // Inverted, a line matches when the line filter and terms together reject it
+        let term = match (term, options.invert_match) {
+            (Some(term), false) => Some(term),
+            (None, true) if in_window => Some(INVERTED_MATCH),
+            _ => None,
+        };
+        let Some(term) = term else {
+            if let (Some(context), Some(output)) = (&mut self.context, self.output)
+                && context.push_line(line_number, line)
+            {
+                let written = context.flush(output, self.source, search_terms);
+                self.keep_write_error(written);
+            }
+            return;
+        };
+        // Readers that had not noticed the limit yet drop the matches over it
+        if let Some(limit) = &options.match_limit {
+            match limit.admit() {
+                Some(true) => self.stop_matching(),
+                Some(false) => {}
+                None => {
+                    self.stop_matching();
+                    return;
+                }
+            }
+        }
+        self.scan.matches += 1;
+        if let Some(matches) = self.scan.term_matches.get_mut(term) {
+            *matches += 1;
+        }
+        if options
+            .max_matches_per_file
+            .is_some_and(|max| self.scan.matches >= max)
+        {
+            self.stop_matching();
+        }
+
+        let Some(output) = self.output else {
+            return;
+        };
+        let score = score_match(
+            term_at(search_terms, term),
+            &options.score_rules,
+            line,
+            lowercase_line,
+        );
+
+        if let Some(context) = &mut self.context {
+            context.push_match(line_number, term, score, line);
+            return;
+        }
+        if !options.collapse_consecutive {
+            let written =
+                write_match(output, self.source, line_number, search_terms, term, score, line);
+            self.keep_write_error(written);
+            return;
+        }
+
+        match &mut self.pending {
+            Some((previous, _, _, _, repeats)) if previous == line => *repeats += 1,
+            _ => {
+                if let Some(run) = self.pending.take() {
+                    let written = write_collapsed_run(output, self.source, search_terms, run);
+                    self.keep_write_error(written);
+                }
+                self.pending = Some((line.to_string(), line_number, term, score, 1));
+            }
+        }
+    }
+
+    /// Keep the first failed write and stop, along with every other reader
+    /// of the run: its output would miss matches anyway
+    fn keep_write_error(&mut self, written: io::Result<()>) {
+        if written.is_err() {
+            keep_first_error(&mut self.scan.error, ParserError::Write, written);
+            self.stopped = true;
+            if let Some(write_failed) = &self.options.write_failed {
+                write_failed.cancel();
+            }
+        }
+    }
+
+    /// Stop at a match limit, reading on for the assertions if there are any
+    fn stop_matching(&mut self) {
+        if self.options.assertions.is_empty() {
+            self.stopped = true;
+        } else {
+            self.matching_done = true;
         }
+    }
 
-        // If no OR operator or only one part, treat as AND expression
-        let clean_expr = expr.trim_start_matches('(').trim_end_matches(')').trim();
+    fn finish(mut self) -> FileScan {
+        if let Some((progress, _)) = self.progress {
+            progress.tick(self.scan.bytes_read);
+        }
+        if let (Some(output), Some(run)) = (self.output, self.pending.take()) {
+            let written = write_collapsed_run(output, self.source, self.search_set.terms(), run);
+            self.keep_write_error(written);
+        }
+        if let (Some(output), Some(context)) = (self.output, &mut self.context) {
+            let written = context.flush(output, self.source, self.search_set.terms());
+            self.keep_write_error(written);
+        }
 
-        // Check if it has explicit AND operators
-        if clean_expr.contains(" & ") {
-            let and_parts: Vec<String> = clean_expr
-                .split(" & ")
-                .map(|s| s.trim().to_lowercase())
-                .collect();
-            return Some(BooleanExpression::And(and_parts));
+        // A cancelled file was not read far enough to fail or pass
+        if self.stopped && !self.options.assertions.is_empty() {
+            return self.scan;
         }
+        self.scan.failed_assertions = self
+            .assertion_seen
+            .iter()
+            .enumerate()
+            .filter(|(_, (contains, not_contains))| *contains && !*not_contains)
+            .map(|(index, _)| index)
+            .collect();
 
-        // Single term
-        Some(BooleanExpression::And(vec![clean_expr.to_lowercase()]))
+        self.scan
     }
+}
 
-    pub fn matches(&self, text: &str) -> bool {
-        match self {
-            BooleanExpression::And(terms) => terms.iter().all(|term| text.contains(term)),
-            BooleanExpression::Or(expressions) => expressions.iter().any(|expr| expr.matches(text)),
-        }
+/// Keep the first error of a reader, prefixed with what was being done
+fn keep_first_error(
+    error: &mut Option<ParserError>,
+    doing: fn(io::Error) -> ParserError,
+    result: io::Result<()>,
+) {
+    if let Err(e) = result
+        && error.is_none()
+    {
+        *error = Some(doing(e));
     }
 }
 
-/// Configuration for the log parser
-pub struct ParserConfig {
-    pub log_folder: String,
-    pub output_log: String,
-    pub filename_filter: String,
-    pub line_filter: String,
-    pub search_terms: Vec<SearchTerm>,
-    pub workers: Option<usize>,
+/// Fail a run whose output misses matches, as writing it failed
+fn output_write_failed(e: io::Error) -> io::Error {
+    io::Error::new(e.kind(), ParserError::Write(e))
 }
 
-impl Default for ParserConfig {
-    fn default() -> Self {
-        Self {
-            log_folder: "logs/parser".to_string(),
-            output_log: "logs/parser/output.log".to_string(),
-            filename_filter: String::new(),
-            line_filter: String::new(),
-            search_terms: vec![],
-            workers: None,
-        }
-    }
+/// Write a collapsed run of identical lines, adding the repeat count when needed
+fn write_collapsed_run(
+    output: &dyn MatchSink,
+    source: &Path,
+    search_terms: &[SearchTerm],
+    (line, line_number, term, score, repeats): (String, usize, usize, i32, usize),
+) -> io::Result<()> {
+    let line = if repeats > 1 {
+        format!("{} (x{})", line, repeats)
+    } else {
+        line
+    };
+    write_match(output, source, line_number, search_terms, term, score, &line)
 }
 
-/// Result of parsing logs
-pub struct ParserResult {
-    pub total_matches: usize,
-    pub processed_files: usize,
+/// Write a single matched line to the output
+fn write_match(
+    output: &dyn MatchSink,
+    source: &Path,
+    line_number: usize,
+    search_terms: &[SearchTerm],
+    term_index: usize,
+    score: i32,
+    line: &str,
+) -> io::Result<()> {
+    let record = MatchRecord {
+        source,
+        line_number,
+        term: term_at(search_terms, term_index),
+        term_index,
+        score,
+        line,
+        labels: &NO_LABELS,
+    };
+    output.write_match(&record)
 }
 
-/// Add a simple search term
-pub fn add_search(search_terms: &mut Vec<SearchTerm>, keyword: &str, additional_keyword: &str) {
-    search_terms.push(SearchTerm {
-        keyword: keyword.to_lowercase(),
-        additional_expression: if additional_keyword.is_empty() {
-            None
-        } else {
-            Some(BooleanExpression::And(vec![
-                additional_keyword.to_lowercase(),
-            ]))
-        },
-    });
+/// Run `scan` within a batch of the writer task of the run, when it has one
+fn batched<T>(writer: Option<&WriterSink>, scan: impl FnOnce() -> T) -> T {
+    match writer {
+        Some(writer) => writer.batch(scan),
+        None => scan(),
+    }
 }
 
-/// Add a search term with a complex boolean expression
-pub fn add_search_with_expression(
-    search_terms: &mut Vec<SearchTerm>,
-    keyword: &str,
-    additional_expr: &str,
-) {
-    search_terms.push(SearchTerm {
-        keyword: keyword.to_lowercase(),
-        additional_expression: BooleanExpression::parse(additional_expr),
-    });
+/// Output log of a run, written next to its destination under a temporary name
+///
+/// The temporary name is namespaced by the run's `ParserConfig::fingerprint`
+/// and is never taken for a log (see `is_partial_output`), so concurrent runs
+/// over the same folder do not pick it up. It is removed if the run fails
+/// before `commit`.
+struct PartialOutput {
+    temporary: PathBuf,
+    destination: PathBuf,
+    committed: bool,
 }
 
-/// Check if a file is a valid log file for processing
-pub fn is_valid_log_file(path: &PathBuf, filename_filter: &str, output_log: &str) -> bool {
-    if !path.is_file() {
-        return false;
-    }
-
-    if let Some(extension) = path.extension() {
-        if extension != "log" {
-            return false;
+impl PartialOutput {
+    fn new(destination: &Path, fingerprint: u64) -> Self {
+        let name = destination
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        Self {
+            temporary: destination.with_file_name(format!(".{}.{:016x}.tmp", name, fingerprint)),
+            destination: destination.to_path_buf(),
+            committed: false,
         }
-    } else {
-        return false;
     }
 
-    let output_path = Path::new(output_log);
-    if path == output_path {
-        return false;
+    /// Open the temporary file, starting from a copy of the destination
+    /// when appending to it
+    fn create(&self, append: bool) -> io::Result<File> {
+        let appending = append
+            && match fs::copy(&self.destination, &self.temporary) {
+                Ok(_) => true,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => false,
+                Err(e) => return Err(e),
+            };
+        OpenOptions::new()
+            .write(true)
+            .create(true)
+            .append(appending)
+            .truncate(!appending)
+            .open(&self.temporary)
     }
 
-    if let Some(filename) = path.file_name() {
-        if let Some(filename_str) = filename.to_str() {
-            // Skip files starting with "debug"
-            if filename_str.to_lowercase().starts_with("debug") {
-                return false;
-            }
+    /// Replace the destination with the finished output
+    fn commit(mut self) -> io::Result<()> {
+        fs::rename(&self.temporary, &self.destination)?;
+        self.committed = true;
+        Ok(())
+    }
+}
 
-            return filename_str.to_lowercase().contains(filename_filter);
+impl Drop for PartialOutput {
+    fn drop(&mut self) {
+        if !self.committed {
+            let _ = fs::remove_file(&self.temporary);
         }
     }
+}
 
-    false
+/// Check if a file is named like the temporary output of a `PartialOutput`,
+/// `.name.<fingerprint>.tmp`
+fn is_partial_output(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .and_then(|name| name.strip_prefix('.')?.strip_suffix(".tmp"))
+        .and_then(|name| name.rsplit_once('.'))
+        .is_some_and(|(_, fingerprint)| {
+            fingerprint.len() == 16 && fingerprint.bytes().all(|byte| byte.is_ascii_hexdigit())
+        })
 }
 
-/// Check if a file is a gzipped file
-pub fn is_gz_file(path: &PathBuf) -> bool {
-    if !path.is_file() {
-        return false;
-    }
+/// Find the log files of `config.log_folder` and `config.log_folders` that a
+/// run would process
+///
+/// With `config.recursive`, subdirectories are searched too, down to
+/// `config.max_depth`. Each directory is listed once, so symlink loops end
+/// the walk and folders nested in one another are not searched twice;
+/// unreadable subdirectories are skipped. So are the `log_folders` that
+/// cannot be listed, while an unreadable `log_folder` fails.
+///
+/// With `config.modified_within_secs`, files modified earlier are left out.
+/// With `config.since`, so are the files modified before it, and with
+/// `config.filename_dates` the files named after a day outside the range.
+///
+/// Files are returned by path, except that logs rotated by the kubelet,
+/// `0.log.20240607-120000[.gz]`, come right before their live file, oldest
+/// first. `config.input_files`, when given, are returned as they are instead.
+pub fn collect_log_files(config: &ParserConfig) -> io::Result<Vec<PathBuf>> {
+    find_log_files(config, &mut Vec::new())
+}
 
-    if let Some(extension) = path.extension() {
-        if extension != "gz" {
-            return false;
-        }
-    } else {
-        return false;
+/// `collect_log_files`, adding the subdirectories that could not be read to `errors`
+fn find_log_files(
+    config: &ParserConfig,
+    errors: &mut Vec<(PathBuf, ParserError)>,
+) -> io::Result<Vec<PathBuf>> {
+    // Files named one by one are taken as they are, unfiltered
+    if !config.input_files.is_empty() {
+        return Ok(config.input_files.clone());
     }
+    let filename_filter = FilenameFilter::new(&config.filename_filter).map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("invalid filename filter '{}': {}", config.filename_filter, e),
+        )
+    })?;
+    let path_patterns = PathPatterns::new(&config.include_patterns, &config.exclude_patterns)
+        .map_err(|(pattern, e)| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid file pattern '{}': {}", pattern, e),
+            )
+        })?;
+    let file_system = config.file_system.as_ref();
+    let max_depth = match config.recursive {
+        true => config.max_depth.unwrap_or(usize::MAX),
+        false => 1,
+    };
+
+    let time_window = config.time_window()?;
+    let modified_after = config
+        .modified_within_secs
+        .map(|secs| SystemTime::now().checked_sub(Duration::from_secs(secs)));
+
+    // Each folder is walked from its own root, in the order they were given.
+    // An output tree counts as visited, so its files are never taken for logs.
+    let roots = config.log_roots();
+    let mut visited: HashSet<_> = config
+        .output_mode
+        .base_output_dir()
+        .map(|folder| identity(file_system, folder))
+        .into_iter()
+        .collect();
+    let mut directories: Vec<_> = roots
+        .iter()
+        .filter(|root| visited.insert(identity(file_system, root)))
+        .map(|root| (root.clone(), 1, root.as_path()))
+        .collect();
+    directories.reverse();
+    let mut file_paths = Vec::new();
+
+    while let Some((directory, depth, root)) = directories.pop() {
+        let entries = match file_system.list_dir(&directory) {
+            Ok(entries) => entries,
+            Err(e) if depth == 1 && root == roots[0] => {
+                return Err(io::Error::other(format!("Error reading log directory: {}", e)));
+            }
+            Err(e) => {
+                errors.push((directory, ParserError::ReadDirectory(e)));
+                continue;
+            }
+        };
+
+        for path in entries.into_iter().flatten() {
+            let Ok(metadata) = file_system.metadata(&path) else {
+                continue;
+            };
+            if metadata.is_dir {
+                if depth < max_depth && visited.insert(identity(file_system, &path)) {
+                    directories.push((path, depth + 1, root));
+                }
+                continue;
+            }
+            if !metadata.is_file {
+                continue;
+            }
+
+            let is_log = is_log_file_name(
+                &path,
+                &filename_filter,
+                &config.output_log,
+                &config.allowed_extensions,
+            ) || (kubernetes::is_rotated_log_name(&path) && filename_filter.matches_path(&path));
+            let is_compressed = is_archive_name(&path, &config.allowed_extensions)
+                && filename_filter.matches_path(&path);
+
+            if !is_log && !is_compressed {
+                continue;
+            }
+            // Nor are the output files of the terms
+            if config
+                .search_terms
+                .iter()
+                .any(|term| term.output.as_deref() == Some(path.as_path()))
+            {
+                continue;
+            }
+            if has_excluded_prefix(&path, &config.excluded_prefixes)
+                || !path_patterns.matches(path.strip_prefix(root).unwrap_or(&path))
+            {
+                continue;
+            }
 
-    // Skip files starting with "debug"
-    if let Some(filename) = path.file_name() {
-        if let Some(filename_str) = filename.to_str() {
-            if filename_str.to_lowercase().starts_with("debug") {
-                return false;
+            // Checked before anything is read or decompressed
+            if let Some(modified_after) = modified_after {
+                let modified = metadata.modified.ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::Unsupported,
+                        format!("no modification time reported for {}", path.display()),
+                    )
+                })?;
+                if modified_after.is_some_and(|after| modified < after) {
+                    continue;
+                }
+            }
+            if let Some(time_window) = &time_window {
+                if metadata
+                    .modified
+                    .is_some_and(|modified| !time_window.modified_in_range(modified))
+                {
+                    continue;
+                }
+                if config.filename_dates
+                    && filename_date(&path).is_some_and(|day| !time_window.overlaps_day(day))
+                {
+                    continue;
+                }
             }
-            return true;
+            file_paths.push(path);
         }
     }
 
-    false
+    file_paths.sort_by_cached_key(|path| kubernetes::rotation_key(path));
+    Ok(file_paths)
 }
 
-/// Process a regular log file without progress output
-pub fn process_file_silent(
-    path: &PathBuf,
-    search_terms: &[SearchTerm],
-    line_filter: &str,
-    output_file: &Arc<Mutex<File>>,
-) -> usize {
-    let file = match File::open(path) {
-        Ok(file) => file,
-        Err(e) => {
-            eprintln!("Error opening file {}: {}", path.display(), e);
-            return 0;
-        }
-    };
-
-    let reader = BufReader::new(file);
-    process_reader(reader, search_terms, line_filter, output_file)
+/// Path of a file below the closest of `roots`, if it is below one
+pub(crate) fn relative_to_roots<'a>(path: &'a Path, roots: &[PathBuf]) -> Option<&'a Path> {
+    roots
+        .iter()
+        .filter_map(|root| path.strip_prefix(root).ok())
+        .min_by_key(|relative| relative.components().count())
 }
 
-/// Process a gzipped log file without progress output
-pub fn process_gz_file_silent(
-    gz_path: &PathBuf,
-    search_terms: &[SearchTerm],
-    line_filter: &str,
-    output_file: &Arc<Mutex<File>>,
-) -> Result<usize, io::Error> {
-    let file = File::open(gz_path)?;
-    let gz = GzDecoder::new(file);
-    let reader = BufReader::new(gz);
-    Ok(process_reader(
-        reader,
-        search_terms,
-        line_filter,
-        output_file,
-    ))
+/// Canonical form of a directory, or the path itself when it cannot be resolved
+fn identity(file_system: &dyn FileSystem, path: &Path) -> PathBuf {
+    file_system
+        .canonicalize(path)
+        .unwrap_or_else(|_| path.to_path_buf())
 }
 
-/// Process a reader (regular or gzipped file)
-pub fn process_reader<R: BufRead>(
-    reader: R,
+/// Match count of each term, with its keyword, expression and output;
+/// `written` holds the lines written to each output, when routed
+fn term_stats(
     search_terms: &[SearchTerm],
-    line_filter: &str,
-    output_file: &Arc<Mutex<File>>,
-) -> usize {
-    let mut file_match_count = 0;
-
-    for line in reader.lines() {
-        if let Ok(line) = line {
-            let lowercase_line = line.to_lowercase();
-
-            let is_match = search_terms.iter().any(|term| {
-                // Check if line contains the primary filter
-                if !lowercase_line.contains(line_filter) {
-                    return false;
-                }
-
-                // Check if line contains the main keyword (if not empty)
-                if !term.keyword.is_empty() && !lowercase_line.contains(&term.keyword) {
-                    return false;
-                }
+    per_term: &[usize],
+    written: Option<&[usize]>,
+) -> Vec<TermStat> {
+    search_terms
+        .iter()
+        .zip(per_term)
+        .enumerate()
+        .map(|(index, (term, matches))| TermStat {
+            keyword: term.keyword.clone(),
+            expression: term
+                .additional_expression
+                .as_ref()
+                .map(ToString::to_string)
+                .unwrap_or_default(),
+            match_count: *matches,
+            output: term.output.clone(),
+            lines_written: written.map_or(0, |written| written[index]),
+        })
+        .collect()
+}
 
-                // Check if line satisfies the additional expression (if any)
-                match &term.additional_expression {
-                    Some(expr) => expr.matches(&lowercase_line),
-                    None => true,
-                }
-            });
+/// `written` with the location prefix of the output format
+fn formatted(config: &ParserConfig, written: Arc<dyn MatchSink>) -> Arc<dyn MatchSink> {
+    match config.output_format {
+        OutputFormat::Tsv => {
+            let sink = LocationSink::new(written)
+                .relative_to_roots(config.log_roots())
+                .tab_separated();
+            Arc::new(sink)
+        }
+        OutputFormat::Plain if config.show_location => {
+            Arc::new(LocationSink::new(written).relative_to_roots(config.log_roots()))
+        }
+        OutputFormat::Plain | OutputFormat::Jsonl => written,
+    }
+}
 
-            if is_match {
-                file_match_count += 1;
+/// Output files of the search terms that have one
+#[derive(Default)]
+struct TermOutputs {
+    /// Sink of each term, `None` for the terms writing to the output log
+    routes: Vec<Option<Arc<dyn MatchSink>>>,
+    /// Writer of each file, with the temporary file it writes until the run
+    /// ends unless following the logs
+    files: Vec<(Arc<WriterSink>, Option<PartialOutput>)>,
+}
 
-                // Write to the output file with mutex lock
-                if let Ok(mut file) = output_file.lock() {
-                    if let Err(e) = writeln!(file, "{}", line) {
-                        eprintln!("Error writing to output file: {}", e);
-                    }
-                }
-            }
+/// Open the output file of each search term that has one, once for the
+/// terms sharing a file; the terms writing to the output log get `None`
+///
+/// Like the output log, each file is written by a task of its own and, out
+/// of follow mode, only replaced once its writer is closed and its partial
+/// output committed.
+fn open_term_outputs(config: &ParserConfig, following: bool) -> io::Result<TermOutputs> {
+    let fingerprint = config.fingerprint();
+    let mut opened: HashMap<&Path, Arc<dyn MatchSink>> = HashMap::new();
+    let mut outputs = Vec::new();
+    let mut files = Vec::new();
+    for term in &config.search_terms {
+        let Some(path) = term
+            .output
+            .as_deref()
+            .filter(|path| *path != Path::new(&config.output_log))
+        else {
+            outputs.push(None);
+            continue;
+        };
+        if let Some(output) = opened.get(path) {
+            outputs.push(Some(Arc::clone(output)));
+            continue;
+        }
+        if let Some(folder) = path.parent().filter(|folder| !folder.as_os_str().is_empty()) {
+            fs::create_dir_all(folder)?;
         }
+        let partial_output = (!following).then(|| PartialOutput::new(path, fingerprint));
+        let file: Box<dyn Write + Send> = match &partial_output {
+            Some(partial_output) => Box::new(partial_output.create(config.append)?),
+            None => Box::new(
+                OpenOptions::new()
+                    .append(config.append)
+                    .write(true)
+                    .create(true)
+                    .truncate(!config.append)
+                    .open(path)?,
+            ),
+        };
+        let writer = WriterSink::spawn(file, config.writer_channel_capacity);
+        let writer = Arc::new(match config.output_format {
+            OutputFormat::Jsonl => writer.jsonl(),
+            _ => writer,
+        });
+        files.push((Arc::clone(&writer), partial_output));
+        // Formatted once, so the terms sharing the file share the sink too
+        let output = formatted(config, writer);
+        opened.insert(path, Arc::clone(&output));
+        outputs.push(Some(output));
     }
+    Ok(TermOutputs {
+        routes: outputs,
+        files,
+    })
+}
+
+/// Result of a dry run: the files a run would read, none of them opened,
+/// with no match counted
+fn dry_run(config: &ParserConfig, background_applied: bool) -> io::Result<ParserResult> {
+    let mut errors = Vec::new();
+    let file_paths = match &config.input {
+        Some(_) => vec![PathBuf::from(STDIN_SOURCE)],
+        None => find_log_files(config, &mut errors)?,
+    };
+    let log_roots = config.log_roots();
+    let file_results = file_paths
+        .into_iter()
+        .map(|path| FileResult {
+            labels: path_labels(
+                &config.path_labels,
+                relative_to_roots(&path, &log_roots).unwrap_or(&path),
+            ),
+            compression: CompressionKind::from_path(&path),
+            path,
+            matches: 0,
+            lines_scanned: 0,
+            bytes_read: 0,
+            long_lines: 0,
+            binary: false,
+            output_path: None,
+        })
+        .collect();
+    let per_term = vec![0; config.search_terms.len()];
 
-    file_match_count
+    Ok(ParserResult {
+        total_matches: 0,
+        processed_files: 0,
+        lines_scanned: 0,
+        bytes_read: 0,
+        file_results,
+        term_stats: term_stats(&config.search_terms, &per_term, None),
+        per_term,
+        assertion_failures: Vec::new(),
+        top_matches: Vec::new(),
+        matches: Vec::new(),
+        background_applied,
+        atom_timings: Vec::new(),
+        errors,
+        cancelled: false,
+        limit_reached: false,
+        skipped_duplicates: 0,
+        dedup_resets: 0,
+        long_lines: 0,
+        binary_files: 0,
+    })
 }
 
 /// Main parser function that processes all files
-pub async fn run_parser(config: ParserConfig, progress_callback: Option<fn(usize, usize)>) -> io::Result<ParserResult> {
-    // Convert filters to lowercase
-    let filename_filter = config.filename_filter.to_lowercase();
-    let line_filter = config.line_filter.to_lowercase();
+pub async fn run_parser(mut config: ParserConfig, progress_callback: Option<ProgressCallback>) -> io::Result<ParserResult> {
+    // Lowering the priority is best effort
+    let background_applied = config.background && enter_background_mode().is_ok();
+
+    // The search set lowercases the filter itself, unless it is a regex
+    let line_filter = config.line_filter.clone();
 
-    // Initialize output file
-    if Path::new(&config.output_log).exists() {
-        fs::remove_file(&config.output_log)?;
+    // Compile the terms once, failing before the output is touched
+    let search_set = Arc::new(
+        SearchSet::with_case(
+            &config.search_terms,
+            &line_filter,
+            config.match_strategy,
+            config.match_mode,
+            config.case_sensitive,
+        )
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?,
+    );
+
+    let time_window = config.time_window()?;
+
+    // Initialize output file, unless only counting or writing another target.
+    // It is written under a temporary name, so a previous output stays in
+    // place until this run completes and other runs over the same folder
+    // never scan it half written. Followed runs never complete, so they
+    // write it in place.
+    if config.output_target == OutputTarget::OutputLog && config.output_log == STDOUT_OUTPUT_LOG {
+        config.output_target = OutputTarget::Stdout;
+    }
+    let counts_only = config.mode == RunMode::CountOnly;
+    let writes_output_log = !counts_only && config.output_target == OutputTarget::OutputLog;
+    let writes_stdout = !counts_only && config.output_target == OutputTarget::Stdout;
+
+    if config.input.is_none() && config.log_folder == STDIN_LOG_FOLDER {
+        config.input = Some(Box::new(io::stdin()));
+    }
+    let following = config.follow && config.input.is_none();
+    if following && config.cancel.is_none() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "following the logs needs a cancel token to end the run",
+        ));
+    }
+
+    if config.mode == RunMode::DryRun {
+        return dry_run(&config, background_applied);
     }
 
     let log_dir = Path::new(&config.log_folder);
-    if !log_dir.exists() {
-        fs::create_dir_all(log_dir)?;
+    if config.input.is_none()
+        && config.input_files.is_empty()
+        && config.file_system.metadata(log_dir).is_err()
+    {
+        config.file_system.create_dir_all(log_dir)?;
     }
 
-    let output_file = Arc::new(Mutex::new(
-        OpenOptions::new()
-            .write(true)
-            .create(true)
-            .open(&config.output_log)?,
-    ));
+    // A tree of output files replaces the output log
+    let mirrored_output = match &config.output_mode {
+        OutputMode::MirroredTree { base_output_dir } if writes_output_log => {
+            let sink = MirroredTreeSink::new(base_output_dir.clone(), config.log_roots())
+                .append(config.append);
+            Some(Arc::new(match config.output_format {
+                OutputFormat::Jsonl => sink.jsonl(),
+                _ => sink,
+            }))
+        }
+        _ => None,
+    };
+    let writes_output_log = writes_output_log && mirrored_output.is_none();
 
-    // Collect paths to process
-    let mut file_paths = Vec::new();
-    match fs::read_dir(&config.log_folder) {
-        Ok(entries) => {
-            for entry in entries {
-                if let Ok(entry) = entry {
-                    let path = entry.path();
-                    let is_log = is_valid_log_file(&path, &filename_filter, &config.output_log);
-                    let is_gz = is_gz_file(&path)
-                        && path
-                            .to_string_lossy()
-                            .to_lowercase()
-                            .contains(&filename_filter);
-
-                    if is_log || is_gz {
-                        file_paths.push(path);
-                    }
-                }
-            }
+    let partial_output = (writes_output_log && !following)
+        .then(|| PartialOutput::new(Path::new(&config.output_log), config.fingerprint()));
+    let output_file: Option<Box<dyn Write + Send>> = match &partial_output {
+        Some(partial_output) => Some(Box::new(partial_output.create(config.append)?)),
+        None if writes_output_log => Some(Box::new(
+            OpenOptions::new()
+                .append(config.append)
+                .write(true)
+                .create(true)
+                .truncate(!config.append)
+                .open(&config.output_log)?,
+        )),
+        None if writes_stdout => Some(Box::new(io::stdout())),
+        None => None,
+    };
+    // Written by a task of its own, which the sinks hand their lines to
+    let output_file = output_file.map(|file| {
+        let writer = WriterSink::spawn(file, config.writer_channel_capacity);
+        Arc::new(match config.output_format {
+            OutputFormat::Jsonl => writer.jsonl(),
+            _ => writer,
+        })
+    });
+
+    // The separator and header bypass the sinks, so they are never counted
+    // or collapsed. JSONL output gets them as JSON objects, TSV none.
+    let started = SystemTime::now();
+    let jsonl = config.output_format == OutputFormat::Jsonl;
+    if (jsonl || config.output_format == OutputFormat::Plain)
+        && let Some(output_file) = &output_file
+    {
+        if config.append {
+            output_file.write_line(&match jsonl {
+                true => run_separator_record(started),
+                false => run_separator(started),
+            })?;
+        }
+        if config.output_header {
+            let folders: Vec<_> = std::iter::once(&config.log_folder)
+                .chain(&config.log_folders)
+                .map(String::as_str)
+                .collect();
+            output_file.write_line(&match jsonl {
+                true => output_header_record(&config.search_terms, &folders, started),
+                false => output_header(&config.search_terms, &folders.join(", "), started),
+            })?;
+        }
+    }
+
+    let output: Option<Arc<dyn MatchSink>> = match &config.output_target {
+        _ if counts_only => None,
+        OutputTarget::OutputLog | OutputTarget::Stdout => {
+            let written = match &mirrored_output {
+                Some(mirrored_output) => Some(Arc::clone(mirrored_output) as Arc<dyn MatchSink>),
+                None => output_file.clone().map(|output_file| output_file as Arc<dyn MatchSink>),
+            };
+            written.map(|written| formatted(&config, written))
+        }
+        #[cfg(feature = "arrow")]
+        OutputTarget::Parquet(path) => Some(Arc::new(ParquetSink::create(
+            path,
+            config.parquet_batch_rows,
+        )?)),
+        OutputTarget::Custom(sink) => Some(Arc::clone(sink)),
+        OutputTarget::Discard => None,
+    };
+
+    // Send the matches of the terms with an output file of their own there
+    let term_outputs = match &config.output_target {
+        OutputTarget::OutputLog | OutputTarget::Stdout if !counts_only => {
+            open_term_outputs(&config, following)?
         }
-        Err(e) => return Err(io::Error::new(io::ErrorKind::Other, format!("Error reading log directory: {}", e))),
+        _ => TermOutputs::default(),
+    };
+    let routing = match term_outputs.routes.iter().any(Option::is_some) {
+        true => {
+            let routes = term_outputs.routes;
+            let options = ScanOptions {
+                case_sensitive: config.case_sensitive,
+                strategy: config.match_strategy,
+                match_mode: config.match_mode,
+                ..Default::default()
+            };
+            let routing =
+                TermRoutingSink::new(&config.search_terms, &options, routes, output.clone())
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+            Some(Arc::new(routing))
+        }
+        false => None,
+    };
+    let output = match &routing {
+        Some(routing) => Some(Arc::clone(routing) as Arc<dyn MatchSink>),
+        None => output,
+    };
+
+    // Drop the lines already written before they reach the output
+    let dedup = output
+        .clone()
+        .filter(|_| config.deduplicate)
+        .map(|output| Arc::new(DedupSink::new(output, config.dedup_max_capacity)));
+    let output = match &dedup {
+        Some(dedup) => Some(Arc::clone(dedup) as Arc<dyn MatchSink>),
+        None => output,
+    };
+
+    // Hand the matches to the in-memory consumers as well
+    let collector = config
+        .collect_matches
+        .then(|| Arc::new(CollectSink::default()));
+    let mut sinks: Vec<Arc<dyn MatchSink>> = output.into_iter().collect();
+    if let Some(collector) = &collector {
+        sinks.push(Arc::clone(collector) as Arc<dyn MatchSink>);
+    }
+    if let Some(sender) = config.match_sender.take() {
+        sinks.push(Arc::new(ChannelSink::new(sender)));
     }
+    if let Some(sender) = config.match_stream_sender.take() {
+        sinks.push(Arc::new(BoundedChannelSink::new(sender)));
+    }
+    let output = match sinks.len() {
+        0 | 1 => sinks.pop(),
+        _ => Some(Arc::new(FanOutSink::new(sinks)) as Arc<dyn MatchSink>),
+    };
+
+    // Keep the highest scoring matches while forwarding all of them, only when
+    // asked: every match would take the heap lock otherwise
+    let triage_top = match (config.triage_top, &config.triage_output) {
+        (0, Some(_)) => DEFAULT_TRIAGE_TOP,
+        (triage_top, _) => triage_top,
+    };
+    let triage =
+        (triage_top > 0).then(|| Arc::new(TriageSink::new(output.clone(), triage_top)));
+    let output = match &triage {
+        Some(triage) => Some(Arc::clone(triage) as Arc<dyn MatchSink>),
+        None => output,
+    };
+
+    // Collect paths to process, or stand in for the stream with its name
+    let mut errors = Vec::new();
+    let file_paths = match &config.input {
+        Some(_) => vec![PathBuf::from(STDIN_SOURCE)],
+        None => find_log_files(&config, &mut errors)?,
+    };
+    let listing = following.then(|| config.listing());
+    let log_roots = Arc::new(config.log_roots());
+    let input = Arc::new(Mutex::new(config.input.take()));
 
     // Create shared state
-    let search_terms = Arc::new(config.search_terms);
-    let line_filter = Arc::new(line_filter);
+    let match_limit = config.max_matches.map(|max| Arc::new(MatchLimit::new(max)));
+    let write_failed = CancelToken::new();
+    let file_system = config.file_system;
+    let options = Arc::new(ScanOptions {
+        line_filter,
+        case_sensitive: config.case_sensitive,
+        collapse_consecutive: config.collapse_consecutive,
+        invert_match: config.invert_match,
+        before_context: config.before_context,
+        after_context: config.after_context,
+        assertions: config.assertions,
+        strategy: config.match_strategy,
+        match_mode: config.match_mode,
+        input_format: config.input_format,
+        #[cfg(feature = "mmap")]
+        mmap: config.mmap,
+        score_rules: config.score_rules,
+        trace_sampling: config.trace_sampling,
+        match_limit: match_limit.clone(),
+        max_matches_per_file: config.max_matches_per_file,
+        cancel: config.cancel.clone(),
+        write_failed: Some(write_failed.clone()),
+        progress_interval_bytes: config.progress_interval_bytes,
+        time_window,
+        message_timestamps: config.message_timestamps,
+        max_line_length: config.max_line_length,
+        skip_long_lines: config.skip_long_lines,
+        skip_binary_files: config.skip_binary_files,
+    });
     let total_match_count = Arc::new(Mutex::new(0));
+    let term_match_counts = Arc::new(Mutex::new(vec![0; search_set.terms().len()]));
+    let atom_timings = Arc::new(Mutex::new(match options.trace_sampling {
+        Some(_) => search_set.atom_timings(),
+        None => Vec::new(),
+    }));
+    let file_results = Arc::new(Mutex::new(Vec::new()));
+    let label_rules = Arc::new(config.path_labels);
+    let assertion_failures = Arc::new(Mutex::new(Vec::new()));
+    let file_errors = Arc::new(Mutex::new(Vec::new()));
 
     // Process files in parallel
     let concurrency = config.workers.unwrap_or_else(num_cpus::get);
-    let total_files = file_paths.len();
+    let split = config.split_threshold_bytes.map(|threshold_bytes| Split {
+        threshold_bytes,
+        max_ranges: concurrency,
+    });
+    let total_files = match input.lock().unwrap().is_some() {
+        true => 0,
+        false => file_paths.len(),
+    };
     let processed_files = Arc::new(Mutex::new(0));
     let progress_mutex = Arc::new(Mutex::new(()));
+    let pause_when_load_above = config.pause_when_load_above;
+    let read_progress = config.read_progress;
+    let ordered_output = config.ordered_output && output.is_some();
 
-    stream::iter(file_paths)
+    // Once the limit is reached, the run cancelled or a write failed, files
+    // not started yet are dropped; assertions still need every file past the limit
+    let cancel = config.cancel.clone();
+    let asserting = !options.assertions.is_empty();
+    let stop = write_failed.clone();
+    let scans = stream::iter(file_paths)
+        .take_while(move |_| {
+            future::ready(
+                (asserting || match_limit.as_ref().is_none_or(|limit| !limit.reached()))
+                    && cancel.as_ref().is_none_or(|cancel| !cancel.is_cancelled())
+                    && !stop.is_cancelled(),
+            )
+        })
         .map(|path| {
-            let search_terms = Arc::clone(&search_terms);
-            let line_filter = Arc::clone(&line_filter);
-            let output_file = Arc::clone(&output_file);
+            let file_system = Arc::clone(&file_system);
+            let input = Arc::clone(&input);
+            let search_set = Arc::clone(&search_set);
+            let options = Arc::clone(&options);
+            let output = output.clone();
             let total_match_count = Arc::clone(&total_match_count);
+            let term_match_counts = Arc::clone(&term_match_counts);
+            let atom_timings = Arc::clone(&atom_timings);
+            let file_results = Arc::clone(&file_results);
+            let log_roots = Arc::clone(&log_roots);
+            let label_rules = Arc::clone(&label_rules);
+            let assertion_failures = Arc::clone(&assertion_failures);
+            let file_errors = Arc::clone(&file_errors);
             let processed_files = Arc::clone(&processed_files);
             let progress_mutex = Arc::clone(&progress_mutex);
+            let progress_callback = progress_callback.clone();
+            let read_progress = read_progress.clone();
+            let writer = output_file.clone();
 
             task::spawn(async move {
-                let is_gz = is_gz_file(&path);
-                let file_match_count = if is_gz {
-                    match process_gz_file_silent(&path, &search_terms, &line_filter, &output_file) {
-                        Ok(count) => count,
-                        Err(e) => {
-                            eprintln!("Error processing gzip file {}: {}", path.display(), e);
-                            0
-                        }
-                    }
-                } else {
-                    process_file_silent(&path, &search_terms, &line_filter, &output_file)
+                // Hold this worker slot until the machine is idle enough
+                priority::wait_for_idle(pause_when_load_above, options.cancel.as_ref()).await;
+
+                // Hold the matches back when files must be written in order
+                let buffer = ordered_output.then(BufferSink::default);
+                let sink = match &buffer {
+                    Some(buffer) => Some(buffer as &dyn MatchSink),
+                    None => output.as_deref(),
+                };
+                // Matches carry the labels of their file
+                let relative_path = relative_to_roots(&path, &log_roots).unwrap_or(&path);
+                let labels = path_labels(&label_rules, relative_path);
+                let labelled = sink
+                    .filter(|_| !labels.is_empty())
+                    .map(|inner| LabelSink { inner, labels: &labels });
+                let sink = match &labelled {
+                    Some(labelled) => Some(labelled as &dyn MatchSink),
+                    None => sink,
                 };
 
+                let stream = input.lock().unwrap().take();
+                let compression = CompressionKind::from_path(&path);
+                // The size on disk only tells how far along uncompressed files
+                // are, and streams have none
+                let file_progress = read_progress.as_ref().map(|read_progress| {
+                    let size = match compression {
+                        Some(_) => None,
+                        None if stream.is_some() => None,
+                        None => file_system.metadata(&path).ok().map(|metadata| metadata.len),
+                    };
+                    read_progress.start(&path, size)
+                });
+                let progress = file_progress.as_deref();
+                // The lines of a file reach the writer task together; a stream
+                // may never end, so its lines go as they are found
+                let writer = writer.as_deref().filter(|_| stream.is_none());
+                let scan = batched(writer, || match stream {
+                    Some(stream) => compression::log_reader(stream, None).map(|reader| {
+                        scan_reader(reader, &path, &search_set, &options, sink, progress)
+                    }),
+                    None => match split {
+                        Some(split) if compression.is_none() => split.scan_file(
+                            file_system.as_ref(),
+                            &path,
+                            &search_set,
+                            &options,
+                            sink,
+                            progress,
+                        ),
+                        _ => scan_file(
+                            file_system.as_ref(),
+                            &path,
+                            compression,
+                            &search_set,
+                            &options,
+                            sink,
+                            progress,
+                        ),
+                    },
+                });
+                let scan = match scan {
+                    Ok(scan) => scan,
+                    Err(e) => FileScan {
+                        error: Some(match compression {
+                            Some(kind) => ParserError::Decompress { kind, error: e },
+                            None => ParserError::Open(e),
+                        }),
+                        ..Default::default()
+                    },
+                };
+                if let Some(error) = scan.error {
+                    file_errors.lock().unwrap().push((path.clone(), error));
+                }
+                if let (Some(read_progress), Some(file_progress)) = (&read_progress, &file_progress) {
+                    read_progress.finish(file_progress);
+                }
+
                 // Update total count
                 {
                     let mut count = total_match_count.lock().unwrap();
-                    *count += file_match_count;
+                    *count += scan.matches;
+                }
+                for (total, matches) in term_match_counts
+                    .lock()
+                    .unwrap()
+                    .iter_mut()
+                    .zip(&scan.term_matches)
+                {
+                    *total += matches;
+                }
+                for (total, timing) in atom_timings
+                    .lock()
+                    .unwrap()
+                    .iter_mut()
+                    .zip(&scan.atom_timings)
+                {
+                    total.merge(timing);
+                }
+
+                // Record per-file statistics
+                file_results.lock().unwrap().push(FileResult {
+                    path: path.clone(),
+                    matches: scan.matches,
+                    lines_scanned: scan.lines_scanned,
+                    bytes_read: scan.bytes_read,
+                    compression,
+                    labels,
+                    long_lines: scan.long_lines,
+                    binary: scan.binary,
+                    output_path: None,
+                });
+
+                // Record failed file assertions
+                if !scan.failed_assertions.is_empty() {
+                    let mut failures = assertion_failures.lock().unwrap();
+                    for assertion in scan.failed_assertions {
+                        failures.push(AssertionFailure {
+                            path: path.clone(),
+                            assertion,
+                            clause: options.assertions[assertion].failing_clause().to_string(),
+                        });
+                    }
                 }
 
                 // Update progress
@@ -355,26 +2982,246 @@ pub async fn run_parser(config: ParserConfig, progress_callback: Option<fn(usize
                     let _lock = progress_mutex.lock().unwrap();
                     let mut processed = processed_files.lock().unwrap();
                     *processed += 1;
-                    // Calculate percentage for the callback
-                    let percentage = (*processed * 100) / total_files;
-                    let _ = percentage; // Suppress unused variable warning when no callback is provided
-                    
+
                     // Call the progress callback if provided
-                    if let Some(callback) = progress_callback {
-                        callback(*processed, total_files);
+                    if let Some(callback) = &progress_callback {
+                        callback(ProgressUpdate {
+                            processed: *processed,
+                            total: total_files,
+                            path: &path,
+                            matches: *total_match_count.lock().unwrap(),
+                        });
                     }
                 }
+
+                buffer.map(|buffer| (path, buffer))
             })
-        })
-        .buffer_unordered(concurrency)
-        .collect::<Vec<_>>()
-        .await;
+        });
+
+    if ordered_output {
+        // `buffered` yields the files in order, whichever finishes first
+        let mut scans = scans.buffered(concurrency);
+        while let Some(scan) = scans.next().await {
+            let Ok(Some((path, buffer))) = scan else {
+                continue;
+            };
+            if buffer.is_empty() {
+                continue;
+            }
+            let mut error = None;
+            if config.file_headers
+                && config.output_format == OutputFormat::Plain
+                && let Some(output_file) = &output_file
+            {
+                let header = format!("==> {} <==", path.display());
+                let written = output_file.write_line(&header);
+                keep_first_error(&mut error, ParserError::Write, written);
+            }
+            if let Some(output) = &output {
+                let replayed = batched(output_file.as_deref(), || {
+                    buffer.replay(output.as_ref(), search_set.terms())
+                });
+                keep_first_error(&mut error, ParserError::Write, replayed);
+            }
+            if let Some(error) = error {
+                write_failed.cancel();
+                errors.push((path, error));
+            }
+        }
+    } else {
+        scans.buffer_unordered(concurrency).collect::<Vec<_>>().await;
+    }
+
+    // Keep scanning what is appended to the logs until the run is cancelled
+    if let Some(listing) = listing {
+        let mut follower = Follower::new(file_system.as_ref(), &file_results.lock().unwrap());
+        let stopped = || {
+            options.match_limit.as_ref().is_some_and(|limit| limit.reached())
+                || options.cancel.as_ref().is_none_or(|cancel| cancel.is_cancelled())
+                || write_failed.is_cancelled()
+        };
+        while !stopped() {
+            match &config.follow_wakeup {
+                Some(wakeup) => {
+                    let _ = tokio::time::timeout(config.follow_interval, wakeup.notified()).await;
+                }
+                None => tokio::time::sleep(config.follow_interval).await,
+            }
+            // A log folder missing for a moment, like while rotated, is listed next time
+            let paths = find_log_files(&listing, &mut Vec::new()).unwrap_or_default();
+            let (appended, failures) = follower.poll(file_system.as_ref(), &paths);
+            errors.extend(failures);
+
+            for appended in appended {
+                let relative_path =
+                    relative_to_roots(&appended.path, &log_roots).unwrap_or(&appended.path);
+                let labels = path_labels(&label_rules, relative_path);
+                let labelled = output
+                    .as_deref()
+                    .filter(|_| !labels.is_empty())
+                    .map(|inner| LabelSink { inner, labels: &labels });
+                let sink = match &labelled {
+                    Some(labelled) => Some(labelled as &dyn MatchSink),
+                    None => output.as_deref(),
+                };
+                let scan = scan_appended(&appended, &search_set, &options, sink);
+                *total_match_count.lock().unwrap() += scan.matches;
+                for (total, matches) in term_match_counts
+                    .lock()
+                    .unwrap()
+                    .iter_mut()
+                    .zip(&scan.term_matches)
+                {
+                    *total += matches;
+                }
+                for (total, timing) in atom_timings
+                    .lock()
+                    .unwrap()
+                    .iter_mut()
+                    .zip(&scan.atom_timings)
+                {
+                    total.merge(timing);
+                }
+                match scan.error {
+                    Some(ParserError::Write(e)) => return Err(output_write_failed(e)),
+                    Some(error) => errors.push((appended.path.clone(), error)),
+                    None => {}
+                }
+
+                let mut file_results = file_results.lock().unwrap();
+                match file_results.iter_mut().find(|file| file.path == appended.path) {
+                    Some(file) => {
+                        file.matches += scan.matches;
+                        file.lines_scanned += scan.lines_scanned;
+                        file.bytes_read += scan.bytes_read;
+                        file.long_lines += scan.long_lines;
+                    }
+                    None => {
+                        *processed_files.lock().unwrap() += 1;
+                        file_results.push(FileResult {
+                            labels,
+                            path: appended.path,
+                            matches: scan.matches,
+                            lines_scanned: scan.lines_scanned,
+                            bytes_read: scan.bytes_read,
+                            compression: None,
+                            long_lines: scan.long_lines,
+                            binary: scan.binary,
+                            output_path: None,
+                        });
+                    }
+                }
+            }
+        }
+    }
 
     let total_matches = *total_match_count.lock().unwrap();
+    let per_term = std::mem::take(&mut *term_match_counts.lock().unwrap());
+    let written = routing.map(|routing| routing.written());
+    let term_stats = term_stats(&config.search_terms, &per_term, written.as_deref());
+    let mut atom_timings = std::mem::take(&mut *atom_timings.lock().unwrap());
+    atom_timings.retain(|timing| timing.samples() > 0);
+    atom_timings.sort_by(|a, b| b.total.cmp(&a.total).then(a.id.cmp(&b.id)));
     let processed = *processed_files.lock().unwrap();
 
+    let mut file_results = std::mem::take(&mut *file_results.lock().unwrap());
+    file_results.sort_by(|a, b| a.path.cmp(&b.path));
+    if let Some(mirrored_output) = &mirrored_output {
+        for file in &mut file_results {
+            file.output_path = mirrored_output.written(&file.path);
+        }
+    }
+    let lines_scanned = file_results.iter().map(|file| file.lines_scanned).sum();
+    let bytes_read = file_results.iter().map(|file| file.bytes_read).sum();
+    let long_lines = file_results.iter().map(|file| file.long_lines).sum();
+    let binary_files = file_results.iter().filter(|file| file.binary).count();
+
+    errors.append(&mut file_errors.lock().unwrap());
+    errors.sort_by(|a, b| a.0.cmp(&b.0));
+    // Matches are missing from the output once a write failed
+    if let Some(index) = errors
+        .iter()
+        .position(|(_, error)| matches!(error, ParserError::Write(_)))
+        && let (_, ParserError::Write(e)) = errors.swap_remove(index)
+    {
+        return Err(output_write_failed(e));
+    }
+
+    let mut assertion_failures = std::mem::take(&mut *assertion_failures.lock().unwrap());
+    assertion_failures.sort_by(|a, b| a.path.cmp(&b.path).then(a.assertion.cmp(&b.assertion)));
+
+    // Append synthetic records for the failed assertions
+    if config.write_assertion_failures
+        && let Some(output_file) = &output_file
+    {
+        for failure in &assertion_failures {
+            let line = match config.output_format {
+                OutputFormat::Plain => format!(
+                    "ASSERTION FAILED [{}] {}: {}",
+                    failure.assertion,
+                    failure.clause,
+                    failure.path.display()
+                ),
+                OutputFormat::Tsv => format!(
+                    "{}\t0\tASSERTION FAILED [{}] {}",
+                    failure.path.display(),
+                    failure.assertion,
+                    failure.clause
+                ),
+                OutputFormat::Jsonl => serde_json::json!({
+                    "assertion_failed": failure.assertion,
+                    "clause": failure.clause,
+                    "source_file": failure.path.to_string_lossy(),
+                })
+                .to_string(),
+            };
+            output_file.write_line(&line).map_err(output_write_failed)?;
+        }
+    }
+
+    if let Some(output) = &output {
+        output.finish()?;
+    }
+
+    let top_matches = triage.map(|triage| triage.top_matches()).unwrap_or_default();
+    if let Some(triage_output) = &config.triage_output {
+        write_triage(triage_output, &top_matches)?;
+    }
+
+    // Close every handle on the output log before moving it into place
+    drop(output);
+    if let Some(output_file) = output_file {
+        output_file.close()?;
+    }
+    if let Some(partial_output) = partial_output {
+        partial_output.commit()?;
+    }
+    for (writer, partial_output) in term_outputs.files {
+        writer.close()?;
+        if let Some(partial_output) = partial_output {
+            partial_output.commit()?;
+        }
+    }
+
     Ok(ParserResult {
         total_matches,
         processed_files: processed,
+        lines_scanned,
+        bytes_read,
+        file_results,
+        per_term,
+        term_stats,
+        assertion_failures,
+        top_matches,
+        matches: collector.map(|collector| collector.take_matches()).unwrap_or_default(),
+        background_applied,
+        atom_timings,
+        errors,
+        cancelled: config.cancel.is_some_and(|cancel| cancel.is_cancelled()),
+        limit_reached: options.match_limit.as_ref().is_some_and(|limit| limit.reached()),
+        skipped_duplicates: dedup.as_ref().map_or(0, |dedup| dedup.skipped()),
+        dedup_resets: dedup.as_ref().map_or(0, |dedup| dedup.resets()),
+        long_lines,
+        binary_files,
     })
-}
\ No newline at end of file
+}
@@ -1,25 +1,99 @@
-use clap::Parser;
+use chrono::NaiveDateTime;
+use clap::parser::ValueSource;
+use clap::{ArgMatches, CommandFactory, FromArgMatches, Parser, Subcommand};
+use elysiumparser::bench::{bench_strategy, counts_agree, load_sample};
+use elysiumparser::selftest::run_self_test;
+use elysiumparser::units::{duration_help, parse_duration, parse_size, size_help};
 use elysiumparser::{
-    add_search_with_expression, run_parser, BooleanExpression, ParserConfig,
+    add_file_assertion, add_search_with_case, add_search_with_expression, collect_log_files,
+    kubernetes_label_rule, parse_time_bound, run_parser, set_expression_complexity_warning,
+    AtomTiming, BooleanExpression, CancelToken, InputFormat, InterpolationMode, MatchMode,
+    MatchStrategy, OutputFormat, OutputMode, OutputTarget, ParseError, ParserConfig, ParserResult,
+    Profile, ProgressCallback, ProgressUpdate, ReadProgress, RunMode, ScoreRule,
+    DEFAULT_EXCLUDED_PREFIX,
+    DEFAULT_LOG_EXTENSION, DEFAULT_PROGRESS_INTERVAL_BYTES, DEFAULT_TIMESTAMP_FORMAT,
+    EXPRESSION_COMPLEXITY_WARNING, STDIN_LOG_FOLDER, STDOUT_OUTPUT_LOG,
 };
-use std::io::{stdout, Write};
+use std::io::{self, stdout, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
 
 #[derive(Parser)]
 #[command(author, version, about = "Log file parser")]
 struct Cli {
-    /// Directory containing log files to parse
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Read settings from this JSON or TOML profile; options given on the
+    /// command line take precedence over it
+    #[arg(long, value_name = "PATH")]
+    config: Option<PathBuf>,
+
+    /// Fail on ${VAR} references in the profile to unset variables without a
+    /// default, instead of expanding them to nothing
+    #[arg(long, requires = "config")]
+    strict_env: bool,
+
+    /// Directory containing log files to parse, or '-' to read standard input;
+    /// repeat to search several directories in one run
     #[arg(short, long, default_value = "logs/parser")]
-    log_folder: String,
+    log_folder: Vec<String>,
 
-    /// Output log file path
+    /// Read the log from standard input instead of --log-folder
+    #[arg(long)]
+    stdin: bool,
+
+    /// Log files to read instead of searching --log-folder, whatever their name;
+    /// archives are told apart by their extension
+    #[arg(value_name = "FILE", conflicts_with = "stdin")]
+    files: Vec<PathBuf>,
+
+    /// Read this log file instead of searching --log-folder, like a FILE argument;
+    /// repeat for several
+    #[arg(long = "file", value_name = "FILE", conflicts_with = "stdin")]
+    file: Vec<PathBuf>,
+
+    /// Output log file path, or '-' to write the matches to standard output without
+    /// the report of the run
     #[arg(short, long, default_value = "logs/parser/output.log")]
     output_log: String,
 
-    /// Filter for filenames (case insensitive)
+    /// Instead of the output log, write the matches of each log to a file at the
+    /// same path below this folder; logs without matches get no file
+    #[arg(long, value_name = "DIR", conflicts_with = "stdin")]
+    output_tree: Option<std::path::PathBuf>,
+
+    /// Filter for filenames (case insensitive); with `*`, `?`, `[...]` or `{a,b}` it
+    /// is a glob matched against the whole file name, like 'app-*.log', which must
+    /// be quoted so the shell does not expand it
     #[arg(short, long, default_value = "")]
     filename_filter: String,
 
-    /// Filter for line content (case insensitive)
+    /// Skip files whose name starts with this prefix (case insensitive); repeat for
+    /// several, or pass '' to skip none
+    #[arg(long, value_name = "PREFIX", default_value = DEFAULT_EXCLUDED_PREFIX)]
+    exclude_prefix: Vec<String>,
+
+    /// Read files with this extension (case insensitive); repeat for several, pass ''
+    /// for files without one or '*' for any file. Archives are read when the file
+    /// inside has one, like app.txt.gz for 'txt'
+    #[arg(long, value_name = "EXT", default_value = DEFAULT_LOG_EXTENSION)]
+    extension: Vec<String>,
+
+    /// Only read files matching this glob (case insensitive); repeat for several.
+    /// Patterns with a '/' match the path relative to the log folder, like
+    /// '2024/*/app-*.log', others the file name
+    #[arg(long, value_name = "PATTERN")]
+    include: Vec<String>,
+
+    /// Skip files matching this glob, like 'app-staging-*', even when included;
+    /// repeat for several
+    #[arg(long, value_name = "PATTERN")]
+    exclude: Vec<String>,
+
+    /// Filter for line content (case insensitive unless --case-sensitive)
     #[arg(short = 'L', long, default_value = "")]
     line_filter: String,
 
@@ -27,51 +101,848 @@ struct Cli {
     #[arg(short, long)]
     search: Vec<String>,
 
+    /// Match search terms and the line filter with their case as written
+    #[arg(long)]
+    case_sensitive: bool,
+
+    /// Treat search terms, the line filter and plain expression atoms as regular expressions
+    #[arg(long)]
+    regex: bool,
+
+    /// Only match search terms, the line filter and plain expression atoms as whole words,
+    /// so 'err' no longer matches 'terror'
+    #[arg(long, visible_alias = "word-regexp", conflicts_with = "regex")]
+    whole_word: bool,
+
     /// Additional search terms (supports boolean expressions: (term1 & term2) | (term3 & term4))
     #[arg(short, long)]
     additional: Vec<String>,
 
+    /// Warn about additional expressions with more atoms than this, which slow matching down
+    #[arg(long, value_name = "ATOMS", default_value_t = EXPRESSION_COMPLEXITY_WARNING)]
+    complexity_warning: usize,
+
+    /// How log lines are encoded: plain, docker-json, cri or auto
+    #[arg(long, default_value_t = InputFormat::Plain)]
+    input_format: InputFormat,
+
+    /// Read Kubernetes pod logs (point --log-folder at /var/log/pods): search
+    /// recursively, decode CRI lines and label files by namespace, pod and container
+    #[arg(long)]
+    kubernetes: bool,
+
     /// Number of worker threads to use (defaults to number of CPU cores)
     #[arg(short, long)]
     workers: Option<usize>,
+
+    #[arg(
+        long,
+        value_name = "SIZE",
+        value_parser = parse_size,
+        help = size_help("Search plain logs of at least this size with several workers at once")
+    )]
+    split_threshold: Option<u64>,
+
+    /// Keep the lines that do not match, like grep -v; lines failing the
+    /// line filter are kept too
+    #[arg(short = 'v', long)]
+    invert_match: bool,
+
+    /// Merge consecutive identical matches from the same file into one line with an (xN) suffix
+    #[arg(long)]
+    collapse: bool,
+
+    /// Write each matched line once, however many files it appears in
+    #[arg(long, visible_alias = "deduplicate")]
+    dedup: bool,
+
+    /// With --dedup, forget the lines seen once this many are remembered
+    #[arg(long, value_name = "LINES", requires = "dedup")]
+    dedup_max_capacity: Option<usize>,
+
+    #[arg(
+        long,
+        value_name = "SIZE",
+        value_parser = parse_size,
+        help = size_help("Match and write only this much of each line, cutting longer lines")
+    )]
+    max_line_length: Option<u64>,
+
+    /// With --max-line-length, skip longer lines instead of cutting them
+    #[arg(long, requires = "max_line_length")]
+    skip_long_lines: bool,
+
+    /// Skip files with a NUL byte in their first 8 KiB, which hold binary data
+    #[arg(long)]
+    skip_binary: bool,
+
+    /// Print this many lines after each match
+    #[arg(short = 'A', long, visible_alias = "after")]
+    after_context: Option<usize>,
+
+    /// Print this many lines before each match
+    #[arg(short = 'B', long, visible_alias = "before")]
+    before_context: Option<usize>,
+
+    /// Print this many lines before and after each match
+    #[arg(short = 'C', long)]
+    context: Option<usize>,
+
+    /// Flag files containing this expression (paired with --assert-absent)
+    #[arg(long)]
+    assert_contains: Vec<String>,
+
+    /// ...but never containing this expression (paired with --assert-contains)
+    #[arg(long)]
+    assert_absent: Vec<String>,
+
+    /// Also search the subdirectories of the log folder
+    #[arg(short, long)]
+    recursive: bool,
+
+    /// With --recursive, the deepest directory level searched (1 is the log folder itself)
+    #[arg(long, requires = "recursive")]
+    max_depth: Option<usize>,
+
+    #[arg(
+        long,
+        value_name = "DURATION",
+        value_parser = parse_duration,
+        help = duration_help("Skip log files and archives last modified longer ago than this")
+    )]
+    modified_within: Option<Duration>,
+
+    /// Only match lines stamped at or after this UTC time, like 2024-03-15 or
+    /// 2024-03-15T12:00; files last modified earlier are skipped
+    #[arg(long, value_name = "TIME", value_parser = parse_time_bound)]
+    since: Option<NaiveDateTime>,
+
+    /// Only match lines stamped before this UTC time
+    #[arg(long, value_name = "TIME", value_parser = parse_time_bound)]
+    until: Option<NaiveDateTime>,
+
+    /// strftime-like format of the timestamp starting each line, for --since and --until
+    #[arg(long, default_value = DEFAULT_TIMESTAMP_FORMAT)]
+    timestamp_format: String,
+
+    /// With --since or --until, drop the lines that do not start with a timestamp
+    #[arg(long)]
+    exclude_untimestamped: bool,
+
+    /// With --since or --until, date Docker and CRI lines by the timestamp their message
+    /// starts with instead of the time of their record
+    #[arg(long)]
+    message_timestamps: bool,
+
+    /// With --since or --until, also skip files named after a day (YYYY-MM-DD) outside the range
+    #[arg(long)]
+    filename_dates: bool,
+
+    /// Once the files were searched, keep searching the lines appended to them
+    /// and new files until interrupted, like tail -F
+    #[arg(short = 'F', long, conflicts_with = "stdin")]
+    follow: bool,
+
+    /// Only count matches without writing the output file, listing the
+    /// matches of every file and what would have been written
+    #[arg(short = 'n', long, visible_alias = "count")]
+    count_only: bool,
+
+    /// Only list the files that would be searched, without opening them or
+    /// touching the output file
+    #[arg(long, conflicts_with_all = ["follow", "count_only"])]
+    dry_run: bool,
+
+    /// Add the matches to the end of the output file instead of replacing it
+    #[arg(long, conflicts_with = "count_only")]
+    append: bool,
+
+    /// Prefix each output line with the path, relative to the log folder, and
+    /// line number it came from
+    #[arg(long, visible_alias = "annotate")]
+    show_location: bool,
+
+    /// Memory-map plain log files instead of reading them
+    #[cfg(feature = "mmap")]
+    #[arg(long)]
+    mmap: bool,
+
+    /// How matches are written to the output file: plain, tsv for the path, line
+    /// number and line separated by tabs, or jsonl (also json) for one JSON object
+    /// per match
+    #[arg(long, visible_alias = "output-format", default_value_t = OutputFormat::Plain)]
+    format: OutputFormat,
+
+    /// Start the output file with a commented line describing the run, or a
+    /// header object in JSONL output
+    #[arg(long)]
+    output_header: bool,
+
+    /// Write the matches grouped by file, in path order, so runs can be diffed
+    #[arg(long)]
+    ordered: bool,
+
+    /// With --ordered, precede the matches of each file with a `==> path <==` line
+    #[arg(long, requires = "ordered")]
+    file_headers: bool,
+
+    /// Severity score of the matches of each search term (paired with --search)
+    #[arg(long, allow_hyphen_values = true)]
+    score: Vec<i32>,
+
+    /// File the matches of each search term are written to instead of the output log
+    /// (paired with --search); '' keeps them in the output log. A line matching several
+    /// terms is written once to each of their files
+    #[arg(long, value_name = "PATH")]
+    search_output: Vec<String>,
+
+    /// Add points to matches satisfying an expression, written EXPR=POINTS
+    #[arg(long, value_parser = parse_bonus)]
+    bonus: Vec<(String, i32)>,
+
+    /// Add points to matches longer than a number of bytes, written LENGTH=POINTS
+    #[arg(long, value_parser = parse_bonus)]
+    long_line_bonus: Vec<(String, i32)>,
+
+    /// Write the highest scoring matches to this file, best first
+    #[arg(long)]
+    triage_output: Option<std::path::PathBuf>,
+
+    /// Number of files listed in the closing summary, most matches first
+    #[arg(long, default_value_t = 10)]
+    top_files: usize,
+
+    /// Number of highest scoring matches kept for triage and listed in the
+    /// summary [default: 100 with --triage-output, else none]
+    #[arg(long)]
+    triage_top: Option<usize>,
+
+    /// Run with lowered CPU and I/O priority
+    #[arg(long)]
+    background: bool,
+
+    /// Pause starting new files while the load average is above this value
+    #[arg(long)]
+    pause_when_load_above: Option<f32>,
+
+    /// Stop once this many matches were found
+    #[arg(long)]
+    max_matches: Option<usize>,
+
+    /// Stop reading each file after this many matches, like grep -m
+    #[arg(short = 'm', long, value_name = "MATCHES")]
+    max_matches_per_file: Option<usize>,
+
+    /// Time the atoms of this fraction of lines (like 0.001) and list the slowest
+    #[arg(long, value_name = "FRACTION", value_parser = parse_fraction)]
+    trace_sample: Option<f64>,
+
+    #[arg(
+        long,
+        default_value_t = DEFAULT_PROGRESS_INTERVAL_BYTES,
+        value_parser = parse_size,
+        help = size_help("Input read between two progress updates of a large file")
+    )]
+    progress_interval: u64,
+
+    /// Write matches to this Parquet file instead of the output log
+    #[cfg(feature = "arrow")]
+    #[arg(long)]
+    parquet: Option<std::path::PathBuf>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Verify decompression and matching against embedded fixtures
+    SelfTest,
+    /// Time the match strategies against an in-memory sample of the logs
+    Bench(BenchArgs),
+}
+
+#[derive(clap::Args)]
+struct BenchArgs {
+    /// Directory containing log files to sample
+    #[arg(short, long, default_value = "logs/parser")]
+    log_folder: String,
+
+    /// Filter for filenames (case insensitive); with `*`, `?`, `[...]` or `{a,b}` it
+    /// is a glob matched against the whole file name, like 'app-*.log', which must
+    /// be quoted so the shell does not expand it
+    #[arg(short, long, default_value = "")]
+    filename_filter: String,
+
+    /// Filter for line content (case insensitive)
+    #[arg(short = 'L', long, default_value = "")]
+    line_filter: String,
+
+    /// Search terms
+    #[arg(short, long)]
+    term: Vec<String>,
+
+    /// Additional search terms, paired with --term like --additional is with --search
+    #[arg(short, long)]
+    additional: Vec<String>,
+
+    /// Comma-separated strategies to compare (naive, aho, prefilter)
+    #[arg(long, value_delimiter = ',', default_value = "naive,aho,prefilter")]
+    strategies: Vec<MatchStrategy>,
+
+    #[arg(
+        long,
+        default_value = "64MiB",
+        value_parser = parse_size,
+        help = size_help("Maximum log data loaded into the sample")
+    )]
+    sample_bytes: u64,
+
+    /// Passes over the sample per strategy
+    #[arg(long, default_value_t = 5)]
+    iterations: usize,
+}
+
+/// Run the embedded self-test and return the process exit code
+async fn self_test() -> i32 {
+    let outcomes = run_self_test().await;
+    let mut failed = 0;
+
+    for outcome in &outcomes {
+        if outcome.passed() {
+            println!("ok   {}", outcome.name);
+            continue;
+        }
+
+        failed += 1;
+        match &outcome.error {
+            Some(error) => println!("FAIL {}: {}", outcome.name, error),
+            None => println!(
+                "FAIL {}: {} matches (expected {}), output hash {:016x} (expected {:016x})",
+                outcome.name,
+                outcome.actual_matches,
+                outcome.expected_matches,
+                outcome.actual_output_hash,
+                outcome.expected_output_hash
+            ),
+        }
+    }
+
+    println!("{}/{} self-test cases passed", outcomes.len() - failed, outcomes.len());
+    if failed == 0 { 0 } else { 1 }
+}
+
+/// Run the match strategy benchmark and return the process exit code
+fn bench(mut args: BenchArgs) -> i32 {
+    let mut search_terms = Vec::new();
+    let max_len = args.term.len().max(args.additional.len());
+    args.term.resize(max_len, String::new());
+    args.additional.resize(max_len, String::new());
+    for i in 0..max_len {
+        if let Err(e) =
+            add_search_with_expression(&mut search_terms, &args.term[i], &args.additional[i])
+        {
+            eprintln!("Invalid expression '{}': {}", args.additional[i], e);
+            return 2;
+        }
+    }
+
+    // Discovery runs once, outside of the timed section
+    let config = ParserConfig {
+        log_folder: args.log_folder,
+        filename_filter: args.filename_filter,
+        ..Default::default()
+    };
+    let paths = match collect_log_files(&config) {
+        Ok(paths) => paths,
+        Err(e) => {
+            eprintln!("Error loading sample: {}", e);
+            return 1;
+        }
+    };
+    let sample = match load_sample(config.file_system.as_ref(), &paths, args.sample_bytes) {
+        Ok(sample) => sample,
+        Err(e) => {
+            eprintln!("Error loading sample: {}", e);
+            return 1;
+        }
+    };
+    println!(
+        "Sample: {} lines ({} bytes), {} iterations",
+        sample.lines.len(),
+        sample.bytes,
+        args.iterations
+    );
+
+    let timings: Vec<_> = args
+        .strategies
+        .iter()
+        .map(|strategy| {
+            let timing = bench_strategy(
+                &search_terms,
+                &args.line_filter,
+                *strategy,
+                &sample,
+                args.iterations,
+            );
+            println!(
+                "{:<10} {:>14.0} lines/sec  {} matches",
+                timing.strategy,
+                timing.lines_per_second(),
+                timing.matches
+            );
+            timing
+        })
+        .collect();
+
+    #[cfg(feature = "mmap")]
+    if !bench_reads(&config, &paths, &search_terms, &args.line_filter) {
+        eprintln!("Buffered and memory-mapped reads disagree on the number of matches");
+        return 1;
+    }
+
+    if counts_agree(&timings) {
+        0
+    } else {
+        eprintln!("Strategies disagree on the number of matches");
+        1
+    }
+}
+
+/// Time full scans of the files, buffered and memory-mapped with a cold and
+/// a warm page cache, returning whether all of them found the same matches
+#[cfg(feature = "mmap")]
+fn bench_reads(
+    config: &ParserConfig,
+    paths: &[std::path::PathBuf],
+    search_terms: &[elysiumparser::SearchTerm],
+    line_filter: &str,
+) -> bool {
+    let mut matches = Vec::new();
+    for (mmap, cold) in [(false, true), (false, false), (true, true), (true, false)] {
+        let timing = match elysiumparser::bench::bench_read(
+            config.file_system.as_ref(),
+            paths,
+            search_terms,
+            line_filter,
+            mmap,
+            cold,
+        ) {
+            Ok(timing) => timing,
+            Err(e) => {
+                eprintln!("Error reading files: {}", e);
+                return false;
+            }
+        };
+        println!(
+            "{:<10} {:<12} {:>10.1} MB/s  {} matches",
+            if timing.mmap { "mmap" } else { "buffered" },
+            match (cold, timing.cold) {
+                (false, _) => "warm cache",
+                (true, true) => "cold cache",
+                (true, false) => "cold (n/a)",
+            },
+            timing.bytes_per_second() / 1_000_000.0,
+            timing.matches
+        );
+        matches.push(timing.matches);
+    }
+    matches.windows(2).all(|pair| pair[0] == pair[1])
+}
+
+/// Unwrap a parsed expression, exiting with a usage error when it is invalid
+fn expect_expression<T>(result: Result<T, ParseError>, expression: &str) -> T {
+    result.unwrap_or_else(|e| {
+        eprintln!("Invalid expression '{}': {}", expression, e);
+        std::process::exit(2);
+    })
+}
+
+/// Load the profile at `path`, dropping the settings given on the command line
+fn load_profile(path: &Path, mode: InterpolationMode, matches: &ArgMatches) -> Profile {
+    let mut profile = Profile::load_with(path, mode).unwrap_or_else(|e| {
+        eprintln!("Cannot load profile {}", e);
+        std::process::exit(2);
+    });
+    let given = |ids: &[&str]| {
+        ids.iter()
+            .any(|id| matches.value_source(id) == Some(ValueSource::CommandLine))
+    };
+    if given(&["log_folder", "stdin"]) {
+        profile.log_folder = None;
+        profile.log_folders = None;
+    }
+    if given(&["output_log"]) {
+        profile.output_log = None;
+    }
+    if given(&["filename_filter"]) {
+        profile.filename_filter = None;
+    }
+    if given(&["exclude_prefix"]) {
+        profile.excluded_prefixes = None;
+    }
+    if given(&["extension"]) {
+        profile.allowed_extensions = None;
+    }
+    if given(&["include"]) {
+        profile.include_patterns = None;
+    }
+    if given(&["exclude"]) {
+        profile.exclude_patterns = None;
+    }
+    if given(&["line_filter"]) {
+        profile.line_filter = None;
+    }
+    if given(&["search", "additional"]) {
+        profile.search = None;
+    }
+    if given(&["case_sensitive"]) {
+        profile.case_sensitive = None;
+    }
+    if given(&["regex", "whole_word"]) {
+        profile.match_mode = None;
+    }
+    if given(&["input_format", "kubernetes"]) {
+        profile.input_format = None;
+    }
+    if given(&["workers"]) {
+        profile.workers = None;
+    }
+    if given(&["recursive", "kubernetes"]) {
+        profile.recursive = None;
+    }
+    if given(&["max_depth"]) {
+        profile.max_depth = None;
+    }
+    if given(&["modified_within"]) {
+        profile.modified_within = None;
+    }
+    if given(&["since"]) {
+        profile.since = None;
+    }
+    if given(&["until"]) {
+        profile.until = None;
+    }
+    if given(&["timestamp_format"]) {
+        profile.timestamp_format = None;
+    }
+    if given(&["exclude_untimestamped"]) {
+        profile.include_untimestamped = None;
+    }
+    if given(&["message_timestamps"]) {
+        profile.message_timestamps = None;
+    }
+    if given(&["invert_match"]) {
+        profile.invert_match = None;
+    }
+    if given(&["before_context", "context"]) {
+        profile.before_context = None;
+    }
+    if given(&["after_context", "context"]) {
+        profile.after_context = None;
+    }
+    if given(&["collapse"]) {
+        profile.collapse_consecutive = None;
+    }
+    if given(&["count_only"]) {
+        profile.count_only = None;
+    }
+    if given(&["append"]) {
+        profile.append = None;
+    }
+    if given(&["show_location"]) {
+        profile.show_location = None;
+    }
+    if given(&["format"]) {
+        profile.output_format = None;
+    }
+    if given(&["ordered"]) {
+        profile.ordered_output = None;
+    }
+    if given(&["max_matches"]) {
+        profile.max_matches = None;
+    }
+    if given(&["max_matches_per_file"]) {
+        profile.max_matches_per_file = None;
+    }
+    if given(&["max_line_length"]) {
+        profile.max_line_length = None;
+    }
+    if given(&["skip_long_lines"]) {
+        profile.skip_long_lines = None;
+    }
+    if given(&["skip_binary"]) {
+        profile.skip_binary_files = None;
+    }
+    if given(&["bonus", "long_line_bonus"]) {
+        profile.score_rules = None;
+    }
+    if given(&["triage_output"]) {
+        profile.triage_output = None;
+    }
+    if given(&["triage_top"]) {
+        profile.triage_top = None;
+    }
+    profile
+}
+
+/// Parse a `KEY=POINTS` bonus
+fn parse_bonus(value: &str) -> Result<(String, i32), String> {
+    let (key, points) = value
+        .rsplit_once('=')
+        .ok_or_else(|| format!("expected KEY=POINTS, got '{}'", value))?;
+    let points = points
+        .trim()
+        .parse()
+        .map_err(|e| format!("invalid points '{}': {}", points, e))?;
+    Ok((key.to_string(), points))
+}
+
+/// Print the overall progress, and how far the oldest file being read got
+fn print_progress(percentage: usize, read_progress: &ReadProgress) {
+    let in_flight = read_progress.in_flight();
+    let current = in_flight.first().and_then(|file| {
+        let name = file.path().file_name()?.to_string_lossy().into_owned();
+        Some(match file.fraction() {
+            Some(fraction) => format!(" [{} {:.0}%]", name, fraction * 100.0),
+            None => format!(" [{} {} bytes]", name, file.position()),
+        })
+    });
+    // Pad over the end of a longer previous line
+    print!("\rProgress: {}%{:<60}", percentage, current.unwrap_or_default());
+    stdout().flush().unwrap();
+}
+
+/// Parse a sampling fraction, greater than 0 and at most 1
+fn parse_fraction(value: &str) -> Result<f64, String> {
+    let fraction: f64 = value
+        .trim()
+        .parse()
+        .map_err(|e| format!("invalid fraction '{}': {}", value, e))?;
+    if fraction > 0.0 && fraction <= 1.0 {
+        Ok(fraction)
+    } else {
+        Err(format!("fraction '{}' must be greater than 0 and at most 1", value))
+    }
 }
 
 #[tokio::main]
 async fn main() {
-    let mut cli = Cli::parse();
+    let matches = Cli::command().get_matches();
+    let mut cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+
+    match cli.command.take() {
+        Some(Command::SelfTest) => std::process::exit(self_test().await),
+        Some(Command::Bench(args)) => std::process::exit(bench(args)),
+        None => {}
+    }
+    tracing_subscriber::fmt()
+        .with_writer(io::stderr)
+        .with_max_level(tracing::Level::WARN)
+        .with_target(false)
+        .without_time()
+        .init();
+    set_expression_complexity_warning(cli.complexity_warning);
+    let interpolation = match cli.strict_env {
+        true => InterpolationMode::Strict,
+        false => InterpolationMode::Lenient,
+    };
+    let mut profile = cli
+        .config
+        .as_deref()
+        .map(|path| load_profile(path, interpolation, &matches));
+    // The terms of the command line are compiled with the case sensitivity of the profile
+    if let Some(case_sensitive) = profile.as_mut().and_then(|p| p.case_sensitive.take()) {
+        cli.case_sensitive = case_sensitive;
+    }
     let mut search_terms = Vec::new();
 
     // Process search terms
     if cli.search.is_empty() && cli.additional.is_empty() {
         // Default search term if none provided
-        add_search_with_expression(&mut search_terms, "", "Master");
+        expect_expression(
+            add_search_with_case(&mut search_terms, "", "Master", cli.case_sensitive),
+            "Master",
+        );
     } else {
         // Pad the shorter vector with empty strings
         let max_len = cli.search.len().max(cli.additional.len());
         cli.search.resize(max_len, String::new());
         cli.additional.resize(max_len, String::new());
+        cli.score.resize(max_len, 0);
+        cli.search_output.resize(max_len, String::new());
 
         // Create search terms from command line arguments
         for i in 0..max_len {
-            add_search_with_expression(&mut search_terms, &cli.search[i], &cli.additional[i]);
+            expect_expression(
+                add_search_with_case(
+                    &mut search_terms,
+                    &cli.search[i],
+                    &cli.additional[i],
+                    cli.case_sensitive,
+                ),
+                &cli.additional[i],
+            );
+            if let Some(term) = search_terms.last_mut() {
+                term.score = cli.score[i];
+                let output = &cli.search_output[i];
+                term.output = (!output.is_empty()).then(|| PathBuf::from(output));
+            }
         }
     }
 
+    // Pair up file assertions
+    if cli.assert_contains.len() != cli.assert_absent.len() {
+        eprintln!("Each --assert-contains needs a matching --assert-absent");
+        std::process::exit(2);
+    }
+    let mut assertions = Vec::new();
+    for (must_contain, must_not_contain) in cli.assert_contains.iter().zip(&cli.assert_absent) {
+        expect_expression(
+            add_file_assertion(&mut assertions, must_contain, must_not_contain),
+            &format!("{} / {}", must_contain, must_not_contain),
+        );
+    }
+
+    // Collect score bonuses
+    let mut score_rules = Vec::new();
+    for (expression, points) in cli.bonus {
+        let expression = expect_expression(BooleanExpression::parse(&expression), &expression);
+        score_rules.push(ScoreRule::Matches { expression, points });
+    }
+    for (length, points) in cli.long_line_bonus {
+        match length.trim().parse() {
+            Ok(length) => score_rules.push(ScoreRule::LineLongerThan { length, points }),
+            Err(_) => {
+                eprintln!("Invalid --long-line-bonus length: {}", length);
+                std::process::exit(2);
+            }
+        }
+    }
+
+    #[cfg(feature = "arrow")]
+    let output_target = cli
+        .parquet
+        .map_or_else(OutputTarget::default, OutputTarget::Parquet);
+    #[cfg(not(feature = "arrow"))]
+    let output_target = OutputTarget::default();
+
     // Setup the parser configuration
-    let config = ParserConfig {
-        log_folder: cli.log_folder,
+    let read_progress = Arc::new(ReadProgress::default());
+    let cancel = CancelToken::new();
+    let mut config = ParserConfig {
+        log_folder: match cli.stdin {
+            true => STDIN_LOG_FOLDER.to_string(),
+            false => cli.log_folder[0].clone(),
+        },
+        log_folders: match cli.stdin {
+            true => vec![],
+            false => cli.log_folder[1..].to_vec(),
+        },
+        input_files: cli.files.into_iter().chain(cli.file).collect(),
         output_log: cli.output_log,
         filename_filter: cli.filename_filter,
+        excluded_prefixes: cli.exclude_prefix,
+        allowed_extensions: cli.extension,
+        include_patterns: cli.include,
+        exclude_patterns: cli.exclude,
         line_filter: cli.line_filter,
         search_terms,
+        case_sensitive: cli.case_sensitive,
         workers: cli.workers,
+        split_threshold_bytes: cli.split_threshold,
+        collapse_consecutive: cli.collapse,
+        deduplicate: cli.dedup,
+        dedup_max_capacity: cli.dedup_max_capacity,
+        max_line_length: cli.max_line_length.map(|max| max as usize),
+        skip_long_lines: cli.skip_long_lines,
+        skip_binary_files: cli.skip_binary,
+        invert_match: cli.invert_match,
+        before_context: cli.before_context.or(cli.context).unwrap_or(0),
+        after_context: cli.after_context.or(cli.context).unwrap_or(0),
+        assertions,
+        write_assertion_failures: true,
+        mode: if cli.dry_run {
+            RunMode::DryRun
+        } else if cli.count_only {
+            RunMode::CountOnly
+        } else {
+            RunMode::Normal
+        },
+        append: cli.append,
+        show_location: cli.show_location,
+        output_mode: cli.output_tree.map_or_else(OutputMode::default, |base_output_dir| {
+            OutputMode::MirroredTree { base_output_dir }
+        }),
+        output_format: cli.format,
+        output_header: cli.output_header,
+        ordered_output: cli.ordered,
+        file_headers: cli.file_headers,
+        recursive: cli.recursive,
+        max_depth: cli.max_depth,
+        modified_within_secs: cli.modified_within.map(|age| age.as_secs()),
+        since: cli.since,
+        until: cli.until,
+        timestamp_format: cli.timestamp_format,
+        include_untimestamped: !cli.exclude_untimestamped,
+        message_timestamps: cli.message_timestamps,
+        filename_dates: cli.filename_dates,
+        input_format: cli.input_format,
+        output_target,
+        score_rules,
+        triage_output: cli.triage_output,
+        triage_top: cli.triage_top.unwrap_or(0),
+        background: cli.background,
+        pause_when_load_above: cli.pause_when_load_above,
+        max_matches: cli.max_matches,
+        max_matches_per_file: cli.max_matches_per_file,
+        cancel: Some(cancel.clone()),
+        follow: cli.follow,
+        trace_sampling: cli.trace_sample,
+        read_progress: Some(Arc::clone(&read_progress)),
+        progress_interval_bytes: cli.progress_interval,
+        match_mode: if cli.regex {
+            MatchMode::Regex
+        } else if cli.whole_word {
+            MatchMode::WholeWord
+        } else {
+            MatchMode::Substring
+        },
+        #[cfg(feature = "mmap")]
+        mmap: cli.mmap,
+        ..Default::default()
     };
+    if cli.kubernetes {
+        config.recursive = true;
+        config.input_format = InputFormat::Cri;
+        config.path_labels.push(kubernetes_label_rule());
+    }
+    if let Some(profile) = profile
+        && let Err(e) = profile.apply(&mut config)
+    {
+        eprintln!("Invalid profile: {}", e);
+        std::process::exit(2);
+    }
+    if cli.dry_run {
+        run_dry(config).await;
+        return;
+    }
+    // Matches written to standard output are kept apart from the report
+    let quiet = config.output_target == OutputTarget::OutputLog
+        && config.output_log == STDOUT_OUTPUT_LOG;
+    if quiet {
+        run_quietly(config).await;
+        return;
+    }
 
     // Print header information
     println!("LOG Parser 1.0");
     println!("--------------");
     println!("Filters:");
     println!(" Filename: [{}]", config.filename_filter);
+    if !config.include_patterns.is_empty() {
+        println!(" Include: [{}]", config.include_patterns.join(", "));
+    }
+    if !config.exclude_patterns.is_empty() {
+        println!(" Exclude: [{}]", config.exclude_patterns.join(", "));
+    }
     println!(" Line: [{}]", config.line_filter);
     println!();
 
@@ -79,27 +950,7 @@ async fn main() {
     for term in &config.search_terms {
         print!("[{}", term.keyword);
         if let Some(ref expr) = term.additional_expression {
-            print!(" + ");
-            match expr {
-                BooleanExpression::And(terms) => {
-                    print!("({})", terms.join(" & "));
-                }
-                BooleanExpression::Or(sub_exprs) => {
-                    let mut first = true;
-                    for sub_expr in sub_exprs {
-                        if !first {
-                            print!(" | ");
-                        }
-                        first = false;
-                        match &**sub_expr {
-                            BooleanExpression::And(terms) => {
-                                print!("({})", terms.join(" & "));
-                            }
-                            _ => print!("{:?}", sub_expr), // Simplified for complex expressions
-                        }
-                    }
-                }
-            }
+            print!(" + {}", expr);
         }
         print!("] ");
     }
@@ -107,19 +958,203 @@ async fn main() {
     println!();
 
     // Configure progress callback
-    let progress_callback = |processed: usize, total: usize| {
-        let percentage = (processed * 100) / total;
-        print!("\rProgress: {}%", percentage);
-        stdout().flush().unwrap();
+    let percentage = Arc::new(AtomicUsize::new(0));
+    let progress_callback: ProgressCallback = {
+        let percentage = Arc::clone(&percentage);
+        let read_progress = Arc::clone(&read_progress);
+        Arc::new(move |update: ProgressUpdate| {
+            // Streams have no known total and are done once reported
+            let processed = (update.processed * 100).checked_div(update.total).unwrap_or(100);
+            percentage.store(processed, Ordering::Relaxed);
+            print_progress(processed, &read_progress);
+        })
     };
+    // Large files tick in between, so sample them on a timer too
+    let reporter = tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_millis(500));
+        loop {
+            interval.tick().await;
+            print_progress(percentage.load(Ordering::Relaxed), &read_progress);
+        }
+    });
+
+    // The first Ctrl-C stops the run and keeps its partial results, a second one exits
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            cancel.cancel();
+            eprintln!("\nCancelling, press Ctrl-C again to exit immediately");
+        }
+        if tokio::signal::ctrl_c().await.is_ok() {
+            std::process::exit(130);
+        }
+    });
 
     // Run the parser
-    match run_parser(config, Some(progress_callback)).await {
-        Ok(result) => {
+    let term_labels: Vec<String> = config
+        .search_terms
+        .iter()
+        .map(|term| match &term.output {
+            Some(output) => format!("{} => {}", term, output.display()),
+            None => term.to_string(),
+        })
+        .collect();
+    let result = run_parser(config, Some(progress_callback)).await;
+    reporter.abort();
+    match result {
+        Ok(mut result) => {
+            if result.cancelled {
+                println!("\nCancelled, results cover the lines read until then");
+            } else if result.limit_reached {
+                println!("\nReached --max-matches, later lines may not have been read");
+            }
             println!("\nTotal occurrencies: {}", result.total_matches);
+            if cli.count_only {
+                println!("Would have written {} matches", result.total_matches);
+            }
+            if cli.dedup {
+                println!("Skipped {} duplicate matches", result.skipped_duplicates);
+            }
+            if result.dedup_resets > 0 {
+                eprintln!(
+                    "Warning: forgot the lines seen {} times at --dedup-max-capacity, \
+                     some duplicates were written",
+                    result.dedup_resets
+                );
+            }
+            if result.long_lines > 0 {
+                let action = if cli.skip_long_lines { "Skipped" } else { "Cut" };
+                println!(
+                    "{} {} lines longer than --max-line-length",
+                    action, result.long_lines
+                );
+            }
+            if result.binary_files > 0 {
+                println!("Skipped {} binary files", result.binary_files);
+            }
+            if result.background_applied {
+                println!("Ran in background mode");
+            } else if cli.background {
+                eprintln!("Warning: could not enter background mode");
+            }
+            for (path, error) in &result.errors {
+                eprintln!("{}: {}", path.display(), error);
+            }
+            println!(
+                "Scanned {} lines ({} bytes) in {} files",
+                result.lines_scanned, result.bytes_read, result.processed_files
+            );
+            for failure in &result.assertion_failures {
+                println!(
+                    "Assertion [{}] failed, {}: {}",
+                    failure.assertion,
+                    failure.clause,
+                    failure.path.display()
+                );
+            }
+            if !result.top_matches.is_empty() {
+                println!("Top scored matches:");
+                for scored in result.top_matches.iter().take(5) {
+                    println!(
+                        " [{}] {}:{} {}",
+                        scored.score,
+                        scored.path.display(),
+                        scored.line_number,
+                        scored.line
+                    );
+                }
+            }
+            // Counting is about the breakdown, so every file is listed
+            let top_files = if cli.count_only { usize::MAX } else { cli.top_files };
+            print_summary(&mut result, &term_labels, top_files);
+            if cli.trace_sample.is_some() {
+                print_atom_timings(&result.atom_timings, 10);
+            }
+        }
+        Err(e) => {
+            eprintln!("Error running parser: {}", e);
+        }
+    }
+}
+
+/// Run with the matches going to standard output, reporting only errors
+async fn run_quietly(config: ParserConfig) {
+    match run_parser(config, None).await {
+        Ok(result) => {
+            for (path, error) in &result.errors {
+                eprintln!("{}: {}", path.display(), error);
+            }
+        }
+        // The reader of the pipe is done, like `head`
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => {}
+        Err(e) => {
+            eprintln!("Error running parser: {}", e);
+        }
+    }
+}
+
+/// List the files a run would search, one per line, without opening them
+async fn run_dry(config: ParserConfig) {
+    match run_parser(config, None).await {
+        Ok(result) => {
+            for file in &result.file_results {
+                println!("{}", file.path.display());
+            }
+            for (path, error) in &result.errors {
+                eprintln!("{}: {}", path.display(), error);
+            }
+            eprintln!("Would search {} files", result.file_results.len());
+            eprintln!("(--dry-run no longer counts matches, -n/--count-only does)");
         }
         Err(e) => {
             eprintln!("Error running parser: {}", e);
         }
     }
-}
\ No newline at end of file
+}
+
+/// Print the matches of each search term and the files with the most matches
+fn print_summary(result: &mut ParserResult, term_labels: &[String], top_files: usize) {
+    let width = term_labels
+        .iter()
+        .map(|label| label.chars().count())
+        .max()
+        .unwrap_or(0);
+    println!("Matches per search term:");
+    for (label, matches) in term_labels.iter().zip(&result.per_term) {
+        println!(" {:<width$}  {:>8}", label, matches, width = width);
+    }
+
+    result.sort_by_matches();
+    let noisiest: Vec<_> = result
+        .file_results
+        .iter()
+        .filter(|file| file.matches > 0)
+        .take(top_files)
+        .collect();
+    if !noisiest.is_empty() {
+        println!("Files with the most matches:");
+        for file in noisiest {
+            println!(" {:>8}  {}", file.matches, file.path.display());
+        }
+    }
+}
+
+/// Print the atoms that took the most sampled matching time
+fn print_atom_timings(timings: &[AtomTiming], top: usize) {
+    let total: Duration = timings.iter().map(|timing| timing.total).sum();
+    if total.is_zero() {
+        println!("No atom evaluations were sampled");
+        return;
+    }
+
+    println!("Most expensive atoms (sampled):");
+    for timing in timings.iter().take(top) {
+        println!(
+            " {:>5.1}%  {:>10?}  {:>8} evals  p99 {:>6}ns  {}",
+            timing.total.as_secs_f64() * 100.0 / total.as_secs_f64(),
+            timing.total,
+            timing.samples(),
+            timing.histogram.quantile(0.99),
+            timing.atom
+        );
+    }
+}
@@ -0,0 +1,120 @@
+//! Matches of a run as an async stream, for consumers like a database
+//! writer or a websocket that take them as they are found
+
+use crate::{MatchRecord, MatchRecordBuf, MatchSink, ParserConfig, ParserResult, run_parser};
+use futures::Stream;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::thread;
+use tokio::runtime;
+use tokio::sync::mpsc::{self, Receiver, Sender};
+use tokio::sync::oneshot;
+use tokio::task;
+
+/// Matches buffered between the readers of `run_parser_stream` and its
+/// consumer; once that many wait, the readers wait too
+pub const MATCH_STREAM_CAPACITY: usize = 1024;
+
+/// Sending half of the channel of `run_parser_stream`, which alone creates
+/// one (see `ParserConfig::match_stream_sender`)
+pub struct MatchStreamSender(Sender<MatchRecordBuf>);
+
+/// Sends every match to a bounded channel, blocking while it is full
+///
+/// Blocking would starve a consumer polled on the same runtime threads, so
+/// the run must have a runtime of its own, as `run_parser_stream` gives it.
+pub(crate) struct BoundedChannelSink {
+    sender: Sender<MatchRecordBuf>,
+}
+
+impl BoundedChannelSink {
+    pub fn new(MatchStreamSender(sender): MatchStreamSender) -> Self {
+        Self { sender }
+    }
+}
+
+impl MatchSink for BoundedChannelSink {
+    fn write_match(&self, record: &MatchRecord<'_>) -> io::Result<()> {
+        // A closed channel means the consumer stopped listening. The send
+        // opts out of the task budget of the runtime: once spent, tokio
+        // defers the wakeup to the next yield, which never comes here.
+        let send = task::unconstrained(self.sender.send(record.to_buf()));
+        let _ = futures::executor::block_on(send);
+        Ok(())
+    }
+}
+
+/// Matches of a run started by `run_parser_stream`, in the order they are
+/// found
+///
+/// The stream ends once every file was read; `result` then gives the
+/// counts and errors of the run.
+pub struct MatchStream {
+    receiver: Receiver<MatchRecordBuf>,
+    result: oneshot::Receiver<io::Result<ParserResult>>,
+}
+
+impl MatchStream {
+    /// Wait for the run to end and return its result
+    ///
+    /// Matches not received yet are discarded, so the run finishes without
+    /// waiting for them to be consumed.
+    pub async fn result(self) -> io::Result<ParserResult> {
+        drop(self.receiver);
+        self.result
+            .await
+            .unwrap_or_else(|_| Err(io::Error::other("the parser thread panicked")))
+    }
+}
+
+impl Stream for MatchStream {
+    type Item = MatchRecordBuf;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.receiver.poll_recv(cx)
+    }
+}
+
+/// Run the parser on a thread of its own and stream its matches
+///
+/// The matches also reach the output of `config` as with `run_parser`; set
+/// `output_target` to `OutputTarget::Discard` to only stream them. A slow
+/// consumer holds the readers back once `MATCH_STREAM_CAPACITY` matches
+/// wait, so they are never buffered without bound.
+///
+/// ```no_run
+/// # use elysiumparser::{OutputTarget, ParserConfig, run_parser_stream};
+/// # use futures::StreamExt;
+/// # async fn example() -> std::io::Result<()> {
+/// let mut config = ParserConfig::builder()
+///     .log_folder("logs/application")
+///     .add_search("error", "")
+///     .build()
+///     .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+/// config.output_target = OutputTarget::Discard;
+///
+/// let mut matches = run_parser_stream(config)?;
+/// while let Some(record) = matches.next().await {
+///     println!("{}:{}: {}", record.file.display(), record.line_number, record.line);
+/// }
+/// let result = matches.result().await?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn run_parser_stream(mut config: ParserConfig) -> io::Result<MatchStream> {
+    let (sender, receiver) = mpsc::channel(MATCH_STREAM_CAPACITY);
+    let (result_sender, result) = oneshot::channel();
+    config.match_stream_sender = Some(MatchStreamSender(sender));
+
+    thread::Builder::new()
+        .name("elysiumparser-stream".to_string())
+        .spawn(move || {
+            let result = runtime::Builder::new_multi_thread()
+                .enable_all()
+                .build()
+                .and_then(|runtime| runtime.block_on(run_parser(config, None)));
+            let _ = result_sender.send(result);
+        })?;
+    Ok(MatchStream { receiver, result })
+}
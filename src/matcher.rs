@@ -0,0 +1,123 @@
+//! Classifying single lines against search terms, with no reader, output or
+//! runtime involved
+
+use crate::{MatchMode, MatchStrategy, ScanOptions, SearchSet, SearchTerm};
+
+/// The term a line matched, as found by `Matcher::matches_line`
+#[derive(Clone, Copy, Debug)]
+pub struct MatchInfo<'a> {
+    /// Index of `term` among the terms the matcher was built from
+    pub term_index: usize,
+    /// First term matching the line
+    pub term: &'a SearchTerm,
+    /// Base severity of the match, the score of `term`
+    pub score: i32,
+}
+
+/// Search terms and line filter compiled to classify lines one at a time
+///
+/// A line matches when it contains the line filter and a term does: its
+/// keyword and, when it has one, its additional expression. The first
+/// matching term wins, like in a run. Lines are taken as read and lowercased
+/// here unless the matcher is case-sensitive, so callers need no
+/// preparation; this is what `process_reader` does for each line.
+///
+/// ```
+/// # use elysiumparser::{Matcher, add_search_with_expression};
+/// let mut terms = Vec::new();
+/// add_search_with_expression(&mut terms, "error", "disk | network").unwrap();
+/// let matcher = Matcher::new(&terms, "db");
+///
+/// assert_eq!(matcher.matches_line("ERROR: db disk full").map(|m| m.term_index), Some(0));
+/// assert!(matcher.matches_line("ERROR: db timeout").is_none());
+/// assert!(matcher.matches_line("ERROR: cache disk full").is_none());
+/// ```
+pub struct Matcher {
+    search_set: SearchSet,
+    case_sensitive: bool,
+}
+
+impl Matcher {
+    /// Match `terms` and `line_filter` as substrings, ignoring case
+    pub fn new(terms: &[SearchTerm], line_filter: &str) -> Self {
+        Self {
+            search_set: SearchSet::new(terms, line_filter, MatchStrategy::default()),
+            case_sensitive: false,
+        }
+    }
+
+    /// Match `terms` with the line filter, case, strategy and mode of
+    /// `options`, failing on the first invalid pattern in `MatchMode::Regex`
+    ///
+    /// The other options, like `invert_match` or the time window, apply to
+    /// whole scans and are left to `process_reader`.
+    pub fn with_options(terms: &[SearchTerm], options: &ScanOptions) -> Result<Self, regex::Error> {
+        Ok(Self {
+            search_set: SearchSet::with_case(
+                terms,
+                &options.line_filter,
+                options.strategy,
+                options.match_mode,
+                options.case_sensitive,
+            )?,
+            case_sensitive: options.case_sensitive,
+        })
+    }
+
+    /// Match in `mode` instead, failing on the first invalid pattern in
+    /// `MatchMode::Regex`
+    pub fn with_mode(
+        terms: &[SearchTerm],
+        line_filter: &str,
+        mode: MatchMode,
+    ) -> Result<Self, regex::Error> {
+        Ok(Self {
+            search_set: SearchSet::with_mode(terms, line_filter, MatchStrategy::default(), mode)?,
+            case_sensitive: false,
+        })
+    }
+
+    /// The first term matching `line`, as read and without its line
+    /// terminator, or `None` when no term matches or the line filter rejects it
+    pub fn matches_line(&self, line: &str) -> Option<MatchInfo<'_>> {
+        let term_index = match self.case_sensitive {
+            true => self.search_set.matching_line(line, line),
+            false => {
+                let mut lowercase = String::new();
+                lowercase_into(line, &mut lowercase);
+                self.search_set.matching_line(line, &lowercase)
+            }
+        }?;
+        let term = &self.search_set.terms()[term_index];
+        Some(MatchInfo {
+            term_index,
+            term,
+            score: term.score,
+        })
+    }
+
+    pub fn terms(&self) -> &[SearchTerm] {
+        self.search_set.terms()
+    }
+
+    pub fn case_sensitive(&self) -> bool {
+        self.case_sensitive
+    }
+
+    /// The compiled terms, for scanning whole readers
+    pub fn search_set(&self) -> &SearchSet {
+        &self.search_set
+    }
+}
+
+/// Replace the contents of `lowercase` with `line` lowercased; ASCII lines,
+/// most of them, without allocating once `lowercase` grew large enough
+pub(crate) fn lowercase_into(line: &str, lowercase: &mut String) {
+    lowercase.clear();
+    if line.is_ascii() {
+        lowercase.push_str(line);
+        lowercase.make_ascii_lowercase();
+    } else {
+        lowercase.push_str(&line.to_lowercase());
+    }
+}
@@ -0,0 +1,173 @@
+//! Output spread over a tree of files mirroring the log folders: the matches
+//! of `<log_folder>/a/b/c.log` go to `<base_output_dir>/a/b/c.log`
+
+use crate::sink::jsonl_line;
+use crate::{BlockLine, MatchRecord, MatchSink, relative_to_roots};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Output files kept open at once; the least recently written is closed to
+/// open another, and opened again to append if more matches come
+const MAX_OPEN_FILES: usize = 64;
+
+#[derive(Default)]
+struct OutputFiles {
+    /// Output file of each source written to so far
+    written: HashMap<PathBuf, PathBuf>,
+    /// Output files created by this sink, appended to when opened again
+    created: HashSet<PathBuf>,
+    /// Writers still open with their source, least recently written first
+    open: VecDeque<(PathBuf, BufWriter<File>)>,
+}
+
+/// Sink writing the matches of each log file to a file of its own, at the
+/// same path below `base_output_dir` as the log below its log folder
+///
+/// An output file and its folders are only created once its log has a
+/// match, so logs without any leave no file behind. Files from an earlier
+/// run are replaced when written again, or appended to with `append`, and
+/// left alone otherwise. Logs found below none of the roots, like the
+/// standard input, are written by file name at the top of the tree; logs
+/// at the same path below different roots share their output file.
+///
+/// Lines are written like by a `Mutex` writer, or as by `JsonlSink` with
+/// `jsonl`.
+pub struct MirroredTreeSink {
+    base_output_dir: PathBuf,
+    roots: Vec<PathBuf>,
+    append: bool,
+    jsonl: bool,
+    files: Mutex<OutputFiles>,
+}
+
+impl MirroredTreeSink {
+    /// Mirror the logs found below `roots`, the log folders of the run
+    pub fn new(base_output_dir: PathBuf, roots: Vec<PathBuf>) -> Self {
+        Self {
+            base_output_dir,
+            roots,
+            append: false,
+            jsonl: false,
+            files: Mutex::new(OutputFiles::default()),
+        }
+    }
+
+    /// Add the matches to the end of existing output files instead of
+    /// replacing them
+    pub fn append(mut self, append: bool) -> Self {
+        self.append = append;
+        self
+    }
+
+    /// Write each match as a JSON object, like `JsonlSink`
+    pub fn jsonl(mut self) -> Self {
+        self.jsonl = true;
+        self
+    }
+
+    /// Path the matches of `source` are written to, whether or not it had any
+    pub fn output_path(&self, source: &Path) -> PathBuf {
+        let relative = relative_to_roots(source, &self.roots)
+            .filter(|relative| !relative.as_os_str().is_empty())
+            .or_else(|| source.file_name().map(Path::new))
+            .unwrap_or(source);
+        self.base_output_dir.join(relative)
+    }
+
+    /// Output file created for the matches of `source`, `None` while it had
+    /// none
+    pub fn written(&self, source: &Path) -> Option<PathBuf> {
+        let files = self.files.lock().ok()?;
+        files.written.get(source).cloned()
+    }
+
+    /// Run `write` on the output file of `source`, opening it first if needed
+    fn with_file(
+        &self,
+        source: &Path,
+        write: impl FnOnce(&mut BufWriter<File>) -> io::Result<()>,
+    ) -> io::Result<()> {
+        let mut files = self
+            .files
+            .lock()
+            .map_err(|_| io::Error::other("output writer poisoned"))?;
+        let files = &mut *files;
+        match files.open.iter().rposition(|(open, _)| open == source) {
+            Some(index) if index + 1 == files.open.len() => {}
+            Some(index) => {
+                let opened = files.open.remove(index).expect("index is in bounds");
+                files.open.push_back(opened);
+            }
+            None => {
+                if files.open.len() >= MAX_OPEN_FILES
+                    && let Some((_, mut oldest)) = files.open.pop_front()
+                {
+                    oldest.flush()?;
+                }
+                let output = self.output_path(source);
+                if let Some(folder) = output.parent() {
+                    fs::create_dir_all(folder)?;
+                }
+                let append = self.append || files.created.contains(&output);
+                let file = OpenOptions::new()
+                    .write(true)
+                    .create(true)
+                    .append(append)
+                    .truncate(!append)
+                    .open(&output)?;
+                files.created.insert(output.clone());
+                files.written.insert(source.to_path_buf(), output);
+                files
+                    .open
+                    .push_back((source.to_path_buf(), BufWriter::new(file)));
+            }
+        }
+        let (_, writer) = files.open.back_mut().expect("the file was just opened");
+        write(writer)
+    }
+}
+
+impl MatchSink for MirroredTreeSink {
+    fn write_match(&self, record: &MatchRecord<'_>) -> io::Result<()> {
+        let line = match self.jsonl {
+            true => jsonl_line(record)?,
+            false => record.line.to_string(),
+        };
+        self.with_file(record.source, |writer| writeln!(writer, "{}", line))
+    }
+
+    /// Plain blocks are written like by a `Mutex` writer; JSONL blocks only
+    /// keep their matches
+    fn write_block(&self, source: &Path, lines: &[BlockLine<'_>]) -> io::Result<()> {
+        if self.jsonl {
+            return lines.iter().try_for_each(|line| match line {
+                BlockLine::Match(record) => self.write_match(record),
+                _ => Ok(()),
+            });
+        }
+        self.with_file(source, |writer| {
+            for line in lines {
+                match line {
+                    BlockLine::Separator => writeln!(writer, "--")?,
+                    BlockLine::Context { line, .. } => writeln!(writer, "- {}", line)?,
+                    BlockLine::Match(record) => writeln!(writer, "> {}", record.line)?,
+                }
+            }
+            Ok(())
+        })
+    }
+
+    fn finish(&self) -> io::Result<()> {
+        let mut files = self
+            .files
+            .lock()
+            .map_err(|_| io::Error::other("output writer poisoned"))?;
+        files
+            .open
+            .iter_mut()
+            .try_for_each(|(_, writer)| writer.flush())
+    }
+}
@@ -0,0 +1,38 @@
+use crate::{FileProgress, FileScan, LineScanner, MatchSink, ScanOptions, SearchSet, looks_binary};
+use std::path::Path;
+
+/// Scan a memory-mapped file without copying its lines
+///
+/// Lines are split with `memchr` and scanned in place rather than copied
+/// into a read buffer. Only the `data.len()` bytes captured when the file was
+/// mapped are read, so data appended meanwhile is left for the next run.
+pub(crate) fn scan_mapped(
+    data: &[u8],
+    source: &Path,
+    search_set: &SearchSet,
+    options: &ScanOptions,
+    output: Option<&dyn MatchSink>,
+    progress: Option<&FileProgress>,
+) -> FileScan {
+    let mut scanner = LineScanner::new(source, search_set, options, output, progress);
+    if options.skip_binary_files && looks_binary(data) {
+        scanner.scan.binary = true;
+        return scanner.finish();
+    }
+    let mut start = 0;
+
+    for end in memchr::memchr_iter(b'\n', data) {
+        let line = &data[start..=end];
+        scanner.scan_line(line, line.len(), None);
+        if scanner.stopped {
+            return scanner.finish();
+        }
+        start = end + 1;
+    }
+    if start < data.len() {
+        let line = &data[start..];
+        scanner.scan_line(line, line.len(), None);
+    }
+
+    scanner.finish()
+}
@@ -0,0 +1,169 @@
+use crate::sink::{MatchRecord, MatchSink};
+use arrow_array::builder::{MapBuilder, StringBuilder, TimestampMillisecondBuilder, UInt64Builder};
+use arrow_array::{Array, ArrayRef, RecordBatch};
+use arrow_schema::{DataType, Field, Schema, SchemaRef};
+use parquet::arrow::ArrowWriter;
+use std::fs::File;
+use std::io;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+/// Schema of the Parquet output
+///
+/// Columns are never removed or reordered so downstream tables keep working:
+/// `file`, `line_number`, `label` (the matched keyword), `timestamp`, `line`,
+/// `captures` and `path_labels` (the labels of the file, see
+/// `ParserConfig::path_labels`). Timestamps and captures are left empty until
+/// the parser extracts them from lines.
+pub fn parquet_schema() -> SchemaRef {
+    let timestamp = TimestampMillisecondBuilder::new()
+        .with_timezone("UTC")
+        .finish()
+        .data_type()
+        .clone();
+    let captures = captures_builder().finish().data_type().clone();
+
+    Arc::new(Schema::new(vec![
+        Field::new("file", DataType::Utf8, false),
+        Field::new("line_number", DataType::UInt64, false),
+        Field::new("label", DataType::Utf8, true),
+        Field::new("timestamp", timestamp, true),
+        Field::new("line", DataType::Utf8, false),
+        Field::new("captures", captures.clone(), false),
+        Field::new("path_labels", captures, false),
+    ]))
+}
+
+fn captures_builder() -> MapBuilder<StringBuilder, StringBuilder> {
+    MapBuilder::new(None, StringBuilder::new(), StringBuilder::new())
+}
+
+/// Writes matches to a Parquet file, buffering them into record batches
+///
+/// The file is finalized by `finish`, or when the sink is dropped so that an
+/// aborted run still leaves a readable file behind.
+pub struct ParquetSink {
+    state: Mutex<ParquetState>,
+}
+
+struct ParquetState {
+    writer: Option<ArrowWriter<File>>,
+    schema: SchemaRef,
+    batch_rows: usize,
+    rows: usize,
+    files: StringBuilder,
+    line_numbers: UInt64Builder,
+    labels: StringBuilder,
+    timestamps: TimestampMillisecondBuilder,
+    lines: StringBuilder,
+    captures: MapBuilder<StringBuilder, StringBuilder>,
+    path_labels: MapBuilder<StringBuilder, StringBuilder>,
+}
+
+impl ParquetSink {
+    /// Create (or truncate) the Parquet file at `path`
+    pub fn create(path: &Path, batch_rows: usize) -> io::Result<Self> {
+        let schema = parquet_schema();
+        let file = File::create(path)?;
+        let writer =
+            ArrowWriter::try_new(file, Arc::clone(&schema), None).map_err(io::Error::other)?;
+
+        Ok(Self {
+            state: Mutex::new(ParquetState {
+                writer: Some(writer),
+                schema,
+                batch_rows: batch_rows.max(1),
+                rows: 0,
+                files: StringBuilder::new(),
+                line_numbers: UInt64Builder::new(),
+                labels: StringBuilder::new(),
+                timestamps: TimestampMillisecondBuilder::new().with_timezone("UTC"),
+                lines: StringBuilder::new(),
+                captures: captures_builder(),
+                path_labels: captures_builder(),
+            }),
+        })
+    }
+
+    fn lock(&self) -> io::Result<std::sync::MutexGuard<'_, ParquetState>> {
+        self.state
+            .lock()
+            .map_err(|_| io::Error::other("parquet writer poisoned"))
+    }
+}
+
+impl ParquetState {
+    /// Write the buffered rows as one record batch
+    fn flush(&mut self) -> io::Result<()> {
+        if self.rows == 0 {
+            return Ok(());
+        }
+
+        let columns: Vec<ArrayRef> = vec![
+            Arc::new(self.files.finish()),
+            Arc::new(self.line_numbers.finish()),
+            Arc::new(self.labels.finish()),
+            Arc::new(self.timestamps.finish()),
+            Arc::new(self.lines.finish()),
+            Arc::new(self.captures.finish()),
+            Arc::new(self.path_labels.finish()),
+        ];
+        self.rows = 0;
+
+        let batch =
+            RecordBatch::try_new(Arc::clone(&self.schema), columns).map_err(io::Error::other)?;
+        match self.writer.as_mut() {
+            Some(writer) => writer.write(&batch).map_err(io::Error::other),
+            None => Err(io::Error::other("parquet writer already closed")),
+        }
+    }
+
+    /// Flush the remaining rows and write the file footer
+    fn close(&mut self) -> io::Result<()> {
+        self.flush()?;
+        if let Some(writer) = self.writer.take() {
+            writer.close().map_err(io::Error::other)?;
+        }
+        Ok(())
+    }
+}
+
+impl MatchSink for ParquetSink {
+    fn write_match(&self, record: &MatchRecord<'_>) -> io::Result<()> {
+        let mut state = self.lock()?;
+
+        state.files.append_value(record.source.to_string_lossy());
+        state.line_numbers.append_value(record.line_number as u64);
+        if record.term.keyword.is_empty() {
+            state.labels.append_null();
+        } else {
+            state.labels.append_value(&record.term.keyword);
+        }
+        state.timestamps.append_null();
+        state.lines.append_value(record.line);
+        state.captures.append(true).map_err(io::Error::other)?;
+        for (key, value) in record.labels {
+            state.path_labels.keys().append_value(key);
+            state.path_labels.values().append_value(value);
+        }
+        state.path_labels.append(true).map_err(io::Error::other)?;
+        state.rows += 1;
+
+        if state.rows >= state.batch_rows {
+            state.flush()?;
+        }
+        Ok(())
+    }
+
+    fn finish(&self) -> io::Result<()> {
+        self.lock()?.close()
+    }
+}
+
+impl Drop for ParquetSink {
+    fn drop(&mut self) {
+        if let Ok(state) = self.state.get_mut() {
+            let _ = state.close();
+        }
+    }
+}
@@ -0,0 +1,120 @@
+use crate::CancelToken;
+use std::io;
+use std::time::Duration;
+
+/// How often the system load is sampled while dispatching is paused
+pub const LOAD_SAMPLE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Lower the CPU and I/O priority of the whole process
+///
+/// On Unix this raises the nice value and, on Linux, moves the process to
+/// the idle I/O class. On Windows it enters background processing mode. The
+/// change lasts for the rest of the process.
+pub fn enter_background_mode() -> io::Result<()> {
+    platform::enter_background_mode()
+}
+
+/// One-minute system load average, where the platform reports one
+pub fn system_load() -> Option<f32> {
+    platform::system_load()
+}
+
+/// Wait until the system load drops to `threshold` or below, or `cancel` is cancelled
+///
+/// Returns immediately without a threshold or when the load is unknown.
+pub async fn wait_for_idle(threshold: Option<f32>, cancel: Option<&CancelToken>) {
+    let Some(threshold) = threshold else {
+        return;
+    };
+
+    while system_load().is_some_and(|load| load > threshold)
+        && cancel.is_none_or(|cancel| !cancel.is_cancelled())
+    {
+        tokio::time::sleep(LOAD_SAMPLE_INTERVAL).await;
+    }
+}
+
+#[cfg(unix)]
+mod platform {
+    use std::io;
+
+    /// Nice value of background scans, the lowest priority
+    const BACKGROUND_NICE: libc::c_int = 19;
+
+    pub fn enter_background_mode() -> io::Result<()> {
+        // The `which` parameter is typed differently across libc targets
+        let result = unsafe { libc::setpriority(libc::PRIO_PROCESS as _, 0, BACKGROUND_NICE) };
+        if result != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        set_idle_io_class()
+    }
+
+    #[cfg(target_os = "linux")]
+    fn set_idle_io_class() -> io::Result<()> {
+        const IOPRIO_WHO_PROCESS: libc::c_long = 1;
+        const IOPRIO_CLASS_IDLE: libc::c_long = 3;
+        const IOPRIO_CLASS_SHIFT: libc::c_long = 13;
+
+        let result = unsafe {
+            libc::syscall(
+                libc::SYS_ioprio_set,
+                IOPRIO_WHO_PROCESS,
+                0,
+                IOPRIO_CLASS_IDLE << IOPRIO_CLASS_SHIFT,
+            )
+        };
+        if result != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn set_idle_io_class() -> io::Result<()> {
+        Ok(())
+    }
+
+    pub fn system_load() -> Option<f32> {
+        let mut load = [0f64; 1];
+        let samples = unsafe { libc::getloadavg(load.as_mut_ptr(), 1) };
+        (samples == 1).then_some(load[0] as f32)
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use std::io;
+    use windows_sys::Win32::System::Threading::{
+        GetCurrentProcess, PROCESS_MODE_BACKGROUND_BEGIN, SetPriorityClass,
+    };
+
+    pub fn enter_background_mode() -> io::Result<()> {
+        let result =
+            unsafe { SetPriorityClass(GetCurrentProcess(), PROCESS_MODE_BACKGROUND_BEGIN) };
+        if result == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    pub fn system_load() -> Option<f32> {
+        None
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+mod platform {
+    use std::io;
+
+    pub fn enter_background_mode() -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "background mode is not supported on this platform",
+        ))
+    }
+
+    pub fn system_load() -> Option<f32> {
+        None
+    }
+}
@@ -0,0 +1,363 @@
+//! Search profiles: the settings of a run kept in a JSON or TOML file
+//!
+//! A profile names the settings it changes and leaves the others to the
+//! command line or their defaults:
+//!
+//! ```json
+//! {
+//!     "log_folder": "/var/log/app",
+//!     "search": [
+//!         { "keyword": "error", "expression": "database | timeout", "score": 5 },
+//!         { "keyword": "warning" },
+//!         { "keyword": "out of memory", "output": "matches/oom.log" }
+//!     ],
+//!     "score_rules": [
+//!         { "expression": "level=error", "points": 3 },
+//!         { "longer_than": 200, "points": 1 }
+//!     ],
+//!     "triage_output": "triage.tsv",
+//!     "modified_within": "2d"
+//! }
+//! ```
+//!
+//! Keys are named after the `ParserConfig` fields they set, except `search`
+//! for the terms and `modified_within`, a duration like `2d` or `90m` read
+//! by `units::parse_duration`, as `--modified-within` is. Unknown keys are
+//! rejected with their name. Files ending in `.toml`
+//! hold the same keys in TOML, the terms as `[[search]]` tables.
+//!
+//! Paths, filters, keywords and expressions may refer to environment
+//! variables as `${VAR}` or `${VAR:-default}`, expanded by `interpolate_env`
+//! once the file is read; `$${` stands for a literal `${`.
+
+use crate::units::deserialize_duration;
+use crate::{
+    BooleanExpression, InputFormat, InterpolationError, InterpolationMode, MatchMode,
+    MatchStrategy, OutputFormat, ParserConfig, RunMode, ScoreRule, add_search_with_case,
+    interpolate_env, parse_time_bound,
+};
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Deserializer};
+use std::fmt::Display;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Settings read from a profile file, `None` where the profile is silent
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Profile {
+    pub log_folder: Option<String>,
+    pub log_folders: Option<Vec<String>>,
+    pub output_log: Option<String>,
+    pub filename_filter: Option<String>,
+    pub excluded_prefixes: Option<Vec<String>>,
+    pub allowed_extensions: Option<Vec<String>>,
+    pub include_patterns: Option<Vec<String>>,
+    pub exclude_patterns: Option<Vec<String>>,
+    pub line_filter: Option<String>,
+    /// Search terms, replacing those of the configuration
+    pub search: Option<Vec<ProfileTerm>>,
+    pub case_sensitive: Option<bool>,
+    #[serde(deserialize_with = "parsed")]
+    pub match_mode: Option<MatchMode>,
+    #[serde(deserialize_with = "parsed")]
+    pub match_strategy: Option<MatchStrategy>,
+    #[serde(deserialize_with = "parsed")]
+    pub input_format: Option<InputFormat>,
+    pub workers: Option<usize>,
+    pub recursive: Option<bool>,
+    pub max_depth: Option<usize>,
+    /// Skip files last modified longer ago than this
+    #[serde(deserialize_with = "duration")]
+    pub modified_within: Option<Duration>,
+    #[serde(deserialize_with = "time")]
+    pub since: Option<NaiveDateTime>,
+    #[serde(deserialize_with = "time")]
+    pub until: Option<NaiveDateTime>,
+    pub timestamp_format: Option<String>,
+    pub include_untimestamped: Option<bool>,
+    pub message_timestamps: Option<bool>,
+    pub invert_match: Option<bool>,
+    pub before_context: Option<usize>,
+    pub after_context: Option<usize>,
+    pub collapse_consecutive: Option<bool>,
+    pub count_only: Option<bool>,
+    pub append: Option<bool>,
+    pub show_location: Option<bool>,
+    #[serde(deserialize_with = "parsed")]
+    pub output_format: Option<OutputFormat>,
+    pub ordered_output: Option<bool>,
+    pub max_matches: Option<usize>,
+    pub max_matches_per_file: Option<usize>,
+    pub max_line_length: Option<usize>,
+    pub skip_long_lines: Option<bool>,
+    pub skip_binary_files: Option<bool>,
+    /// Bonus points, replacing the rules of the configuration
+    pub score_rules: Option<Vec<ProfileScoreRule>>,
+    pub triage_output: Option<PathBuf>,
+    pub triage_top: Option<usize>,
+}
+
+/// Search term of a profile: a keyword and an optional boolean expression,
+/// as taken by `add_search_with_expression`, and the file its matches go to
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProfileTerm {
+    #[serde(default)]
+    pub keyword: String,
+    #[serde(default)]
+    pub expression: String,
+    /// See `SearchTerm::output`
+    #[serde(default)]
+    pub output: Option<PathBuf>,
+    /// See `SearchTerm::score`
+    #[serde(default)]
+    pub score: i32,
+}
+
+/// Score rule of a profile: the points of lines matching `expression` or
+/// longer than `longer_than` bytes, one of the two (see `ScoreRule`)
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProfileScoreRule {
+    #[serde(default)]
+    pub expression: Option<String>,
+    #[serde(default)]
+    pub longer_than: Option<usize>,
+    pub points: i32,
+}
+
+impl ProfileScoreRule {
+    fn to_rule(&self) -> io::Result<ScoreRule> {
+        let points = self.points;
+        match (&self.expression, self.longer_than) {
+            (Some(expression), None) => match BooleanExpression::parse(expression) {
+                Ok(expression) => Ok(ScoreRule::Matches { expression, points }),
+                Err(e) => Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("invalid score rule expression '{}': {}", expression, e),
+                )),
+            },
+            (None, Some(length)) => Ok(ScoreRule::LineLongerThan { length, points }),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "score rules need either an expression or longer_than",
+            )),
+        }
+    }
+}
+
+impl Profile {
+    /// Read a profile, failing on unknown keys and malformed values
+    ///
+    /// Files ending in `.toml` are read as TOML and those ending in `.json`
+    /// as JSON; other extensions are rejected. Undefined variables without a
+    /// default expand to nothing.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        Self::load_with(path, InterpolationMode::Lenient)
+    }
+
+    /// Read a profile like `load`, handling undefined variables by `mode`
+    pub fn load_with(path: &Path, mode: InterpolationMode) -> io::Result<Self> {
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(str::to_ascii_lowercase);
+        let toml = match extension.as_deref() {
+            Some("json") => false,
+            Some("toml") => true,
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    format!(
+                        "{}: profiles must be JSON or TOML files ending in .json or .toml",
+                        path.display()
+                    ),
+                ));
+            }
+        };
+        let text = fs::read_to_string(path)
+            .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path.display(), e)))?;
+        let invalid = |e: &dyn Display| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: {}", path.display(), e),
+            )
+        };
+        let mut profile: Self = match toml {
+            true => toml::from_str(&text).map_err(|e| invalid(&e))?,
+            false => serde_json::from_str(&text).map_err(|e| invalid(&e))?,
+        };
+        profile.interpolate(mode).map_err(|e| invalid(&e))?;
+        Ok(profile)
+    }
+
+    /// Expand the environment variables of every path, filter, keyword and
+    /// expression of the profile
+    pub fn interpolate(&mut self, mode: InterpolationMode) -> Result<(), InterpolationError> {
+        let expand = |value: &mut String| -> Result<(), InterpolationError> {
+            *value = interpolate_env(value, mode)?;
+            Ok(())
+        };
+        let strings = [
+            &mut self.log_folder,
+            &mut self.output_log,
+            &mut self.filename_filter,
+            &mut self.line_filter,
+            &mut self.timestamp_format,
+        ];
+        for value in strings.into_iter().flatten() {
+            expand(value)?;
+        }
+        let lists = [
+            &mut self.log_folders,
+            &mut self.excluded_prefixes,
+            &mut self.allowed_extensions,
+            &mut self.include_patterns,
+            &mut self.exclude_patterns,
+        ];
+        for value in lists.into_iter().flatten().flatten() {
+            expand(value)?;
+        }
+        for term in self.search.iter_mut().flatten() {
+            expand(&mut term.keyword)?;
+            expand(&mut term.expression)?;
+        }
+        let outputs = self.search.iter_mut().flatten().map(|term| &mut term.output);
+        for output in outputs.chain([&mut self.triage_output]).flatten() {
+            *output = PathBuf::from(interpolate_env(&output.to_string_lossy(), mode)?);
+        }
+        for rule in self.score_rules.iter_mut().flatten() {
+            if let Some(expression) = &mut rule.expression {
+                expand(expression)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Set the settings of the profile on `config`, leaving the others
+    ///
+    /// The terms are compiled with the case sensitivity of `config` once the
+    /// profile is applied; an invalid expression fails.
+    pub fn apply(self, config: &mut ParserConfig) -> io::Result<()> {
+        set(&mut config.log_folder, self.log_folder);
+        set(&mut config.log_folders, self.log_folders);
+        set(&mut config.output_log, self.output_log);
+        set(&mut config.filename_filter, self.filename_filter);
+        set(&mut config.excluded_prefixes, self.excluded_prefixes);
+        set(&mut config.allowed_extensions, self.allowed_extensions);
+        set(&mut config.include_patterns, self.include_patterns);
+        set(&mut config.exclude_patterns, self.exclude_patterns);
+        set(&mut config.line_filter, self.line_filter);
+        set(&mut config.case_sensitive, self.case_sensitive);
+        set(&mut config.match_mode, self.match_mode);
+        set(&mut config.match_strategy, self.match_strategy);
+        set(&mut config.input_format, self.input_format);
+        config.workers = self.workers.or(config.workers);
+        set(&mut config.recursive, self.recursive);
+        config.max_depth = self.max_depth.or(config.max_depth);
+        config.modified_within_secs = self
+            .modified_within
+            .map(|age| age.as_secs())
+            .or(config.modified_within_secs);
+        config.since = self.since.or(config.since);
+        config.until = self.until.or(config.until);
+        set(&mut config.timestamp_format, self.timestamp_format);
+        set(
+            &mut config.include_untimestamped,
+            self.include_untimestamped,
+        );
+        set(&mut config.message_timestamps, self.message_timestamps);
+        set(&mut config.invert_match, self.invert_match);
+        set(&mut config.before_context, self.before_context);
+        set(&mut config.after_context, self.after_context);
+        set(&mut config.collapse_consecutive, self.collapse_consecutive);
+        match self.count_only {
+            Some(true) if config.mode == RunMode::Normal => config.mode = RunMode::CountOnly,
+            Some(false) if config.mode == RunMode::CountOnly => config.mode = RunMode::Normal,
+            _ => {}
+        }
+        set(&mut config.append, self.append);
+        set(&mut config.show_location, self.show_location);
+        set(&mut config.output_format, self.output_format);
+        set(&mut config.ordered_output, self.ordered_output);
+        config.max_matches = self.max_matches.or(config.max_matches);
+        config.max_matches_per_file = self.max_matches_per_file.or(config.max_matches_per_file);
+        config.max_line_length = self.max_line_length.or(config.max_line_length);
+        set(&mut config.skip_long_lines, self.skip_long_lines);
+        set(&mut config.skip_binary_files, self.skip_binary_files);
+        if let Some(rules) = self.score_rules {
+            config.score_rules = rules
+                .iter()
+                .map(ProfileScoreRule::to_rule)
+                .collect::<io::Result<_>>()?;
+        }
+        config.triage_output = self.triage_output.or(config.triage_output.take());
+        set(&mut config.triage_top, self.triage_top);
+
+        if let Some(search) = self.search {
+            let mut search_terms = Vec::new();
+            for term in search {
+                add_search_with_case(
+                    &mut search_terms,
+                    &term.keyword,
+                    &term.expression,
+                    config.case_sensitive,
+                )
+                .map_err(|e| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!("invalid expression for '{}': {}", term.keyword, e),
+                    )
+                })?;
+                if let Some(added) = search_terms.last_mut() {
+                    added.output = term.output;
+                    added.score = term.score;
+                }
+            }
+            config.search_terms = search_terms;
+        }
+        Ok(())
+    }
+}
+
+impl ParserConfig {
+    /// Default configuration with the settings of the profile at `path`
+    /// (see `Profile`)
+    pub fn from_file(path: &Path) -> io::Result<Self> {
+        let mut config = Self::default();
+        Profile::load(path)?.apply(&mut config)?;
+        Ok(config)
+    }
+}
+
+fn set<T>(field: &mut T, value: Option<T>) {
+    if let Some(value) = value {
+        *field = value;
+    }
+}
+
+/// `deserialize_with` helper reading a value through its `FromStr`
+fn parsed<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: Display,
+{
+    let text = String::deserialize(deserializer)?;
+    text.parse().map(Some).map_err(serde::de::Error::custom)
+}
+
+fn duration<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Duration>, D::Error> {
+    deserialize_duration(deserializer).map(Some)
+}
+
+fn time<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<NaiveDateTime>, D::Error> {
+    let text = String::deserialize(deserializer)?;
+    parse_time_bound(&text)
+        .map(Some)
+        .map_err(serde::de::Error::custom)
+}
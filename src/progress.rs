@@ -0,0 +1,94 @@
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Default `ParserConfig::progress_interval_bytes`, 64 MiB
+pub const DEFAULT_PROGRESS_INTERVAL_BYTES: u64 = 64 << 20;
+
+/// How far the scan of one file got, updated without taking a lock
+///
+/// The reader ticks every `ScanOptions::progress_interval_bytes` of input;
+/// a reporter samples `position` whenever it likes, typically on a timer.
+#[derive(Debug)]
+pub struct FileProgress {
+    path: PathBuf,
+    size: Option<u64>,
+    position: AtomicU64,
+    ticks: AtomicU64,
+}
+
+impl FileProgress {
+    /// Progress of `path`, whose input is `size` bytes long when known
+    pub fn new(path: &Path, size: Option<u64>) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            size,
+            position: AtomicU64::new(0),
+            ticks: AtomicU64::new(0),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Bytes of input, unknown for compressed files
+    pub fn size(&self) -> Option<u64> {
+        self.size
+    }
+
+    /// Bytes read as of the last tick
+    pub fn position(&self) -> u64 {
+        self.position.load(Ordering::Relaxed)
+    }
+
+    /// Number of ticks so far
+    pub fn ticks(&self) -> u64 {
+        self.ticks.load(Ordering::Relaxed)
+    }
+
+    /// Share of the file read, between 0 and 1, when its size is known
+    pub fn fraction(&self) -> Option<f64> {
+        self.size
+            .filter(|size| *size > 0)
+            .map(|size| (self.position() as f64 / size as f64).min(1.0))
+    }
+
+    pub(crate) fn tick(&self, position: u64) {
+        self.position.store(position, Ordering::Relaxed);
+        self.ticks.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// The files a run is scanning right now, for a reporter to sample
+///
+/// Files are added when their scan starts and removed when it ends; only
+/// those two steps lock, never the ticks in between.
+#[derive(Debug, Default)]
+pub struct ReadProgress {
+    in_flight: Mutex<Vec<Arc<FileProgress>>>,
+}
+
+impl ReadProgress {
+    /// The files being scanned, oldest first
+    pub fn in_flight(&self) -> Vec<Arc<FileProgress>> {
+        self.in_flight
+            .lock()
+            .map(|files| files.clone())
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn start(&self, path: &Path, size: Option<u64>) -> Arc<FileProgress> {
+        let progress = Arc::new(FileProgress::new(path, size));
+        if let Ok(mut files) = self.in_flight.lock() {
+            files.push(Arc::clone(&progress));
+        }
+        progress
+    }
+
+    pub(crate) fn finish(&self, progress: &Arc<FileProgress>) {
+        if let Ok(mut files) = self.in_flight.lock() {
+            files.retain(|file| !Arc::ptr_eq(file, progress));
+        }
+    }
+}
@@ -0,0 +1,72 @@
+use regex::{Regex, RegexBuilder};
+use std::fmt;
+
+/// A regular expression atom
+///
+/// Compiled case-insensitively by default, since lines are lowercased before
+/// matching, and without lowercasing the pattern so classes like `\D` keep
+/// their meaning.
+#[derive(Clone)]
+pub struct RegexPattern {
+    regex: Regex,
+    case_insensitive: bool,
+}
+
+impl RegexPattern {
+    pub fn new(pattern: &str) -> Result<Self, regex::Error> {
+        Self::with_case(pattern, true)
+    }
+
+    /// Compile a pattern that only matches when `case_insensitive` is set or
+    /// the case of the line agrees
+    ///
+    /// Case-sensitive patterns are only meaningful against the original line,
+    /// which search sets provide to keyword patterns.
+    pub fn with_case(pattern: &str, case_insensitive: bool) -> Result<Self, regex::Error> {
+        Ok(Self {
+            regex: RegexBuilder::new(pattern)
+                .case_insensitive(case_insensitive)
+                .build()?,
+            case_insensitive,
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        self.regex.as_str()
+    }
+
+    pub fn is_case_insensitive(&self) -> bool {
+        self.case_insensitive
+    }
+
+    pub fn is_match(&self, text: &str) -> bool {
+        self.regex.is_match(text)
+    }
+}
+
+impl PartialEq for RegexPattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str() && self.case_insensitive == other.case_insensitive
+    }
+}
+
+impl fmt::Debug for RegexPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RegexPattern")
+            .field("pattern", &self.as_str())
+            .field("case_insensitive", &self.case_insensitive)
+            .finish()
+    }
+}
+
+/// Pattern of a `/…/` delimited keyword or atom, with the `regex` feature
+#[cfg(feature = "regex")]
+pub(crate) fn delimited_pattern(text: &str) -> Option<&str> {
+    text.strip_prefix('/')?.strip_suffix('/')
+}
+
+/// Pattern of a `/…/` delimited keyword or atom, with the `regex` feature
+#[cfg(not(feature = "regex"))]
+pub(crate) fn delimited_pattern(_text: &str) -> Option<&str> {
+    None
+}
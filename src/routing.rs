@@ -0,0 +1,133 @@
+//! Writing the matches of each search term to an output of its own
+
+use crate::{BlockLine, INVERTED_MATCH, MatchRecord, MatchSink, Matcher, ScanOptions, SearchTerm};
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Sends each match to the outputs of every term matching its line
+///
+/// Terms without an output of their own send their matches to the fallback
+/// sink instead. The scan only names the first term matching a line, so the
+/// line is matched again against the terms after it: it is written once to
+/// each of the outputs they lead to, however many of them share one.
+pub struct TermRoutingSink {
+    /// Each term alone, to tell which of them match a line
+    matchers: Vec<Matcher>,
+    /// Output of each term, `None` for the fallback
+    routes: Vec<Option<Arc<dyn MatchSink>>>,
+    fallback: Option<Arc<dyn MatchSink>>,
+    /// Every distinct output, for `finish`
+    sinks: Vec<Arc<dyn MatchSink>>,
+    /// Matches written to the output of each term
+    written: Vec<AtomicUsize>,
+}
+
+impl TermRoutingSink {
+    /// Route the matches of `terms`, compiled with the case, strategy and
+    /// mode of `options`, to the output at the same index of `routes`
+    ///
+    /// The line filter of `options` is ignored: lines reaching a sink
+    /// passed it already.
+    pub fn new(
+        terms: &[SearchTerm],
+        options: &ScanOptions,
+        routes: Vec<Option<Arc<dyn MatchSink>>>,
+        fallback: Option<Arc<dyn MatchSink>>,
+    ) -> Result<Self, regex::Error> {
+        let options = ScanOptions {
+            case_sensitive: options.case_sensitive,
+            strategy: options.strategy,
+            match_mode: options.match_mode,
+            ..Default::default()
+        };
+        let matchers = terms
+            .iter()
+            .map(|term| Matcher::with_options(std::slice::from_ref(term), &options))
+            .collect::<Result<_, _>>()?;
+        let mut sinks = Vec::new();
+        for sink in routes.iter().flatten().chain(&fallback) {
+            push_new(&mut sinks, sink);
+        }
+        let sinks = sinks.into_iter().cloned().collect();
+        Ok(Self {
+            matchers,
+            written: terms.iter().map(|_| AtomicUsize::new(0)).collect(),
+            routes,
+            fallback,
+            sinks,
+        })
+    }
+
+    /// Matches written to the output of each term, counting the lines first
+    /// matched by another term; 0 for the terms without an output
+    pub fn written(&self) -> Vec<usize> {
+        self.written
+            .iter()
+            .map(|written| written.load(Ordering::Relaxed))
+            .collect()
+    }
+
+    /// Add the outputs `record` goes to to `destinations`
+    fn destinations<'a>(
+        &'a self,
+        record: &MatchRecord<'_>,
+        destinations: &mut Vec<&'a Arc<dyn MatchSink>>,
+    ) {
+        if record.term_index == INVERTED_MATCH {
+            destinations.extend(&self.fallback);
+            return;
+        }
+        // Terms before the first matching one do not match the line
+        let later = (record.term_index + 1..self.matchers.len())
+            .filter(|&index| self.matchers[index].matches_line(record.line).is_some());
+        for index in std::iter::once(record.term_index).chain(later) {
+            match self.routes.get(index).and_then(Option::as_ref) {
+                Some(route) => {
+                    self.written[index].fetch_add(1, Ordering::Relaxed);
+                    push_new(destinations, route);
+                }
+                None => {
+                    if let Some(fallback) = &self.fallback {
+                        push_new(destinations, fallback);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl MatchSink for TermRoutingSink {
+    fn write_match(&self, record: &MatchRecord<'_>) -> io::Result<()> {
+        let mut destinations = Vec::new();
+        self.destinations(record, &mut destinations);
+        destinations
+            .into_iter()
+            .try_for_each(|sink| sink.write_match(record))
+    }
+
+    /// Blocks go whole to the outputs of each of their matches
+    fn write_block(&self, source: &Path, lines: &[BlockLine<'_>]) -> io::Result<()> {
+        let mut destinations = Vec::new();
+        for line in lines {
+            if let BlockLine::Match(record) = line {
+                self.destinations(record, &mut destinations);
+            }
+        }
+        destinations
+            .into_iter()
+            .try_for_each(|sink| sink.write_block(source, lines))
+    }
+
+    fn finish(&self) -> io::Result<()> {
+        self.sinks.iter().try_for_each(|sink| sink.finish())
+    }
+}
+
+/// Push `sink` unless it is already there
+fn push_new<'a>(sinks: &mut Vec<&'a Arc<dyn MatchSink>>, sink: &'a Arc<dyn MatchSink>) {
+    if !sinks.iter().any(|known| Arc::ptr_eq(known, sink)) {
+        sinks.push(sink);
+    }
+}
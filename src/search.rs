@@ -0,0 +1,567 @@
+use crate::trace::AtomTiming;
+use crate::{BooleanExpression, FuzzyPattern, RegexPattern, SearchTerm, Term};
+use aho_corasick::AhoCorasick;
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::time::Instant;
+
+/// How a `SearchSet` evaluates its terms against a line
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MatchStrategy {
+    /// One substring search per atom of every term
+    #[default]
+    Naive,
+    /// Find every literal atom in a single Aho-Corasick pass, then evaluate
+    /// the terms from the set of literals present
+    AhoCorasick,
+    /// Reject lines containing none of the keywords with one Aho-Corasick
+    /// pass, evaluating the remaining lines naively
+    Prefilter,
+}
+
+impl MatchStrategy {
+    pub const ALL: [MatchStrategy; 3] = [
+        MatchStrategy::Naive,
+        MatchStrategy::AhoCorasick,
+        MatchStrategy::Prefilter,
+    ];
+}
+
+impl fmt::Display for MatchStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            MatchStrategy::Naive => "naive",
+            MatchStrategy::AhoCorasick => "aho",
+            MatchStrategy::Prefilter => "prefilter",
+        })
+    }
+}
+
+impl FromStr for MatchStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "naive" => Ok(MatchStrategy::Naive),
+            "aho" | "aho-corasick" => Ok(MatchStrategy::AhoCorasick),
+            "prefilter" => Ok(MatchStrategy::Prefilter),
+            other => Err(format!(
+                "unknown match strategy '{}' (expected naive, aho or prefilter)",
+                other
+            )),
+        }
+    }
+}
+
+/// How keywords, the line filter and plain expression atoms are matched
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MatchMode {
+    /// The line must contain the text
+    #[default]
+    Substring,
+    /// The text is a regular expression the line must match
+    ///
+    /// Patterns keep their text as written and are matched
+    /// case-insensitively unless the run is case-sensitive.
+    Regex,
+    /// The line must contain the text as a whole word, between characters
+    /// that are not alphanumeric or at the start or end of the line
+    ///
+    /// `err` then matches `an err occurred` and `err: retry`, but not
+    /// `terror` or `errors`. Fuzzy and `re:` atoms and `/…/` keywords are
+    /// matched as usual.
+    WholeWord,
+}
+
+impl fmt::Display for MatchMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            MatchMode::Substring => "substring",
+            MatchMode::Regex => "regex",
+            MatchMode::WholeWord => "whole-word",
+        })
+    }
+}
+
+impl FromStr for MatchMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "substring" => Ok(MatchMode::Substring),
+            "regex" => Ok(MatchMode::Regex),
+            "whole-word" | "whole_word" | "word" => Ok(MatchMode::WholeWord),
+            other => Err(format!(
+                "unknown match mode '{}' (expected substring, regex or whole-word)",
+                other
+            )),
+        }
+    }
+}
+
+/// Search terms and line filter compiled for a specific `MatchStrategy`
+///
+/// Identical atoms across all terms are interned into one pool, so each
+/// unique literal, fuzzy pattern or regex is compiled once and evaluated at
+/// most once per line; expressions read the cached per-line results.
+///
+/// Lines handed to a search set must already be lowercased, unless it was
+/// built with `with_case` for case-sensitive matching. Otherwise, keywords
+/// and atoms are lowercased as they are compiled, so terms built directly
+/// match like those added by the `add_search` helpers.
+pub struct SearchSet {
+    terms: Vec<SearchTerm>,
+    strategy: MatchStrategy,
+    atoms: Vec<Term>,
+    /// `None` when the line filter is empty
+    line_filter: Option<usize>,
+    compiled: Vec<CompiledTerm>,
+    engine: Engine,
+    /// Literal atoms only match whole words (`MatchMode::WholeWord`)
+    whole_word: bool,
+}
+
+enum Engine {
+    Naive,
+    Automaton(Automaton),
+    /// `None` when some term has no keyword, so no line can be rejected early
+    Prefilter(Option<AhoCorasick>),
+}
+
+/// Aho-Corasick automaton over the non-empty literal atoms
+struct Automaton {
+    automaton: AhoCorasick,
+    /// Atom id of each automaton pattern
+    atom_ids: Vec<usize>,
+    /// Bitset of the atoms the automaton decides
+    decided: Vec<u64>,
+}
+
+struct CompiledTerm {
+    keyword: Option<usize>,
+    expression: Option<CompiledExpression>,
+}
+
+enum CompiledExpression {
+    Atom(usize),
+    And(Vec<CompiledExpression>),
+    Or(Vec<CompiledExpression>),
+    Not(Box<CompiledExpression>),
+}
+
+impl SearchSet {
+    /// Compile the terms in `MatchMode::Substring`
+    pub fn new(terms: &[SearchTerm], line_filter: &str, strategy: MatchStrategy) -> Self {
+        Self::with_mode(terms, line_filter, strategy, MatchMode::Substring)
+            .expect("substring atoms always compile")
+    }
+
+    /// Compile the terms, failing on the first invalid pattern in `MatchMode::Regex`
+    pub fn with_mode(
+        terms: &[SearchTerm],
+        line_filter: &str,
+        strategy: MatchStrategy,
+        mode: MatchMode,
+    ) -> Result<Self, regex::Error> {
+        Self::with_case(terms, line_filter, strategy, mode, false)
+    }
+
+    /// Compile the terms for lines that are lowercased or, when
+    /// `case_sensitive`, matched as read
+    ///
+    /// Case-sensitive sets expect terms added with `add_search_with_case`,
+    /// and compile `MatchMode::Regex` keywords and filters case-sensitively.
+    pub fn with_case(
+        terms: &[SearchTerm],
+        line_filter: &str,
+        strategy: MatchStrategy,
+        mode: MatchMode,
+        case_sensitive: bool,
+    ) -> Result<Self, regex::Error> {
+        let mut pool = AtomPool {
+            mode,
+            case_sensitive,
+            ..Default::default()
+        };
+        let line_filter = pool.intern_text(line_filter)?;
+        let compiled = terms
+            .iter()
+            .map(|term| {
+                Ok(CompiledTerm {
+                    keyword: match &term.keyword_pattern {
+                        Some(pattern) => Some(pool.intern(&Term::Regex(pattern.clone()))?),
+                        None => pool.intern_text(&term.keyword)?,
+                    },
+                    expression: term
+                        .additional_expression
+                        .as_ref()
+                        .map(|expression| pool.compile(expression))
+                        .transpose()?,
+                })
+            })
+            .collect::<Result<_, regex::Error>>()?;
+
+        let engine = match (strategy, mode) {
+            (MatchStrategy::Naive, _) => Engine::Naive,
+            (MatchStrategy::AhoCorasick, _) => Engine::Automaton(Automaton::new(&pool.atoms)),
+            // Whole words contain their keyword, so the substring search still rejects lines
+            (MatchStrategy::Prefilter, MatchMode::Substring | MatchMode::WholeWord) => {
+                Engine::Prefilter(keyword_prefilter(terms, case_sensitive))
+            }
+            // Keywords are patterns, so they cannot be searched for literally
+            (MatchStrategy::Prefilter, MatchMode::Regex) => Engine::Prefilter(None),
+        };
+
+        Ok(Self {
+            terms: terms.to_vec(),
+            strategy,
+            atoms: pool.atoms,
+            line_filter,
+            compiled,
+            engine,
+            whole_word: mode == MatchMode::WholeWord,
+        })
+    }
+
+    pub fn strategy(&self) -> MatchStrategy {
+        self.strategy
+    }
+
+    pub fn terms(&self) -> &[SearchTerm] {
+        &self.terms
+    }
+
+    /// Number of unique atoms across the line filter, keywords and expressions
+    pub fn unique_atoms(&self) -> usize {
+        self.atoms.len()
+    }
+
+    /// Index of the first term matching the (lowercase) line, if any
+    ///
+    /// Case-sensitive keyword patterns see the lowercase line too; use
+    /// `matching_line` when the original line is at hand.
+    pub fn matching_term(&self, line: &str) -> Option<usize> {
+        self.matching_line(line, line)
+    }
+
+    /// Index of the first term matching a line, given as read and lowercased
+    pub fn matching_line(&self, original: &str, line: &str) -> Option<usize> {
+        self.evaluate(original, line, None)
+    }
+
+    /// `matching_line`, adding the time each atom evaluation took to `timings`
+    ///
+    /// `timings` is indexed by atom id, as built by `atom_timings`. Literal
+    /// atoms decided by the Aho-Corasick automaton in one pass over the line
+    /// are not timed.
+    pub fn matching_line_traced(
+        &self,
+        original: &str,
+        line: &str,
+        timings: &mut [AtomTiming],
+    ) -> Option<usize> {
+        self.evaluate(original, line, Some(timings))
+    }
+
+    /// Empty timings for every atom, to be filled by `matching_line_traced`
+    pub fn atom_timings(&self) -> Vec<AtomTiming> {
+        self.atoms
+            .iter()
+            .enumerate()
+            .map(|(id, atom)| AtomTiming::new(id, atom.to_string()))
+            .collect()
+    }
+
+    fn evaluate(
+        &self,
+        original: &str,
+        line: &str,
+        timings: Option<&mut [AtomTiming]>,
+    ) -> Option<usize> {
+        if let Engine::Prefilter(Some(prefilter)) = &self.engine
+            && !prefilter.is_match(line)
+        {
+            return None;
+        }
+
+        let words = self.atoms.len().div_ceil(64);
+        let mut inline = [0u64; 8];
+        let mut heap = Vec::new();
+        let bits: &mut [u64] = if words * 2 <= inline.len() {
+            &mut inline[..words * 2]
+        } else {
+            heap.resize(words * 2, 0);
+            &mut heap
+        };
+        let (known, values) = bits.split_at_mut(words);
+        let mut cache = AtomCache {
+            line,
+            original,
+            atoms: &self.atoms,
+            known,
+            values,
+            timings,
+            whole_word: self.whole_word,
+        };
+
+        if let Engine::Automaton(automaton) = &self.engine {
+            automaton.decide(&mut cache);
+        }
+
+        // Check if line contains the primary filter
+        if let Some(filter) = self.line_filter
+            && !cache.get(filter)
+        {
+            return None;
+        }
+
+        self.compiled.iter().position(|term| {
+            term.keyword.is_none_or(|keyword| cache.get(keyword))
+                && term
+                    .expression
+                    .as_ref()
+                    .is_none_or(|expression| expression.matches(&mut cache))
+        })
+    }
+}
+
+/// Per-line results of the atoms evaluated so far
+struct AtomCache<'a> {
+    line: &'a str,
+    /// The line before lowercasing, for case-sensitive patterns
+    original: &'a str,
+    atoms: &'a [Term],
+    known: &'a mut [u64],
+    values: &'a mut [u64],
+    /// Evaluation times by atom id, when the line is traced
+    timings: Option<&'a mut [AtomTiming]>,
+    /// Literal atoms only match whole words
+    whole_word: bool,
+}
+
+impl AtomCache<'_> {
+    fn get(&mut self, id: usize) -> bool {
+        let (word, bit) = (id / 64, 1u64 << (id % 64));
+        if self.known[word] & bit == 0 {
+            self.known[word] |= bit;
+            let atom = &self.atoms[id];
+            let matched = match &mut self.timings {
+                None => atom_matches(atom, self.original, self.line, self.whole_word),
+                Some(timings) => {
+                    let start = Instant::now();
+                    let matched = atom_matches(atom, self.original, self.line, self.whole_word);
+                    timings[id].record(start.elapsed());
+                    matched
+                }
+            };
+            if matched {
+                self.values[word] |= bit;
+            }
+        }
+        self.values[word] & bit != 0
+    }
+}
+
+fn atom_matches(atom: &Term, original: &str, line: &str, whole_word: bool) -> bool {
+    match atom {
+        Term::Regex(pattern) if !pattern.is_case_insensitive() => pattern.is_match(original),
+        Term::Literal(literal) if whole_word => contains_word(line, literal),
+        atom => atom.matches(line),
+    }
+}
+
+/// Check if `word` occurs in `line` between non-alphanumeric characters
+fn contains_word(line: &str, word: &str) -> bool {
+    if word.is_empty() {
+        return true;
+    }
+    // Occurrences may overlap, so resume right after the start of each one
+    let mut from = 0;
+    while let Some(found) = line[from..].find(word) {
+        let start = from + found;
+        if is_word_at(line, start, start + word.len()) {
+            return true;
+        }
+        from = start + line[start..].chars().next().map_or(1, char::len_utf8);
+    }
+    false
+}
+
+/// Check if `line[start..end]` is bounded by non-alphanumeric characters
+fn is_word_at(line: &str, start: usize, end: usize) -> bool {
+    !line[..start].chars().next_back().is_some_and(char::is_alphanumeric)
+        && !line[end..].chars().next().is_some_and(char::is_alphanumeric)
+}
+
+fn has_uppercase(text: &str) -> bool {
+    text.chars().any(char::is_uppercase)
+}
+
+/// Automaton over the keywords, usable only when every term has a literal one
+fn keyword_prefilter(terms: &[SearchTerm], case_sensitive: bool) -> Option<AhoCorasick> {
+    if terms.is_empty()
+        || terms
+            .iter()
+            .any(|term| term.keyword.is_empty() || term.keyword_pattern.is_some())
+    {
+        return None;
+    }
+
+    AhoCorasick::new(terms.iter().map(|term| match case_sensitive {
+        true => term.keyword.clone(),
+        false => term.keyword.to_lowercase(),
+    }))
+    .ok()
+}
+
+impl Automaton {
+    fn new(atoms: &[Term]) -> Self {
+        let mut patterns = Vec::new();
+        let mut atom_ids = Vec::new();
+        let mut decided = vec![0u64; atoms.len().div_ceil(64)];
+        for (id, atom) in atoms.iter().enumerate() {
+            if let Term::Literal(literal) = atom
+                && !literal.is_empty()
+            {
+                patterns.push(literal.as_str());
+                atom_ids.push(id);
+                decided[id / 64] |= 1 << (id % 64);
+            }
+        }
+
+        Self {
+            automaton: AhoCorasick::new(patterns)
+                .expect("literal patterns always build an automaton"),
+            atom_ids,
+            decided,
+        }
+    }
+
+    /// Resolve every literal atom with a single pass over the line
+    fn decide(&self, cache: &mut AtomCache<'_>) {
+        cache.known.copy_from_slice(&self.decided);
+        for found in self.automaton.find_overlapping_iter(cache.line) {
+            if cache.whole_word && !is_word_at(cache.line, found.start(), found.end()) {
+                continue;
+            }
+            let id = self.atom_ids[found.pattern().as_usize()];
+            cache.values[id / 64] |= 1 << (id % 64);
+        }
+    }
+}
+
+impl CompiledExpression {
+    fn matches(&self, cache: &mut AtomCache<'_>) -> bool {
+        match self {
+            CompiledExpression::Atom(id) => cache.get(*id),
+            CompiledExpression::And(expressions) => expressions
+                .iter()
+                .all(|expression| expression.matches(cache)),
+            CompiledExpression::Or(expressions) => expressions
+                .iter()
+                .any(|expression| expression.matches(cache)),
+            CompiledExpression::Not(expression) => !expression.matches(cache),
+        }
+    }
+}
+
+/// Identity of an atom, used to intern identical atoms
+#[derive(PartialEq, Eq, Hash)]
+enum AtomKey {
+    Literal(String),
+    Fuzzy(String, u8),
+    Regex(String, bool),
+}
+
+/// Unique atoms, each identified by its index
+#[derive(Default)]
+struct AtomPool {
+    mode: MatchMode,
+    case_sensitive: bool,
+    atoms: Vec<Term>,
+    ids: HashMap<AtomKey, usize>,
+}
+
+impl AtomPool {
+    fn intern(&mut self, term: &Term) -> Result<usize, regex::Error> {
+        // Literals carry their case as written; regexes keep it, since
+        // lowercasing `\D` would turn it into `\d`
+        let normalized = match term {
+            Term::Literal(literal)
+                if !self.case_sensitive
+                    && self.mode != MatchMode::Regex
+                    && has_uppercase(literal) =>
+            {
+                Some(Term::Literal(literal.to_lowercase()))
+            }
+            Term::Fuzzy(pattern) if !self.case_sensitive && has_uppercase(pattern.pattern()) => {
+                Some(Term::Fuzzy(FuzzyPattern::new(
+                    &pattern.pattern().to_lowercase(),
+                    pattern.max_distance(),
+                )))
+            }
+            _ => None,
+        };
+        let term = normalized.as_ref().unwrap_or(term);
+
+        let key = match term {
+            Term::Literal(literal) if self.mode == MatchMode::Regex => {
+                AtomKey::Regex(literal.clone(), !self.case_sensitive)
+            }
+            Term::Literal(literal) => AtomKey::Literal(literal.clone()),
+            Term::Fuzzy(pattern) => {
+                AtomKey::Fuzzy(pattern.pattern().to_string(), pattern.max_distance())
+            }
+            Term::Regex(pattern) => {
+                AtomKey::Regex(pattern.as_str().to_string(), pattern.is_case_insensitive())
+            }
+        };
+        if let Some(id) = self.ids.get(&key) {
+            return Ok(*id);
+        }
+
+        // Each unique pattern is compiled once
+        let atom = match term {
+            Term::Literal(literal) if self.mode == MatchMode::Regex => {
+                Term::Regex(RegexPattern::with_case(literal, !self.case_sensitive)?)
+            }
+            term => term.clone(),
+        };
+        self.atoms.push(atom);
+        self.ids.insert(key, self.atoms.len() - 1);
+        Ok(self.atoms.len() - 1)
+    }
+
+    /// Intern a keyword or filter; empty ones match every line and are skipped
+    fn intern_text(&mut self, text: &str) -> Result<Option<usize>, regex::Error> {
+        if text.is_empty() {
+            return Ok(None);
+        }
+        self.intern(&Term::Literal(text.to_string())).map(Some)
+    }
+
+    fn compile(
+        &mut self,
+        expression: &BooleanExpression,
+    ) -> Result<CompiledExpression, regex::Error> {
+        Ok(match expression {
+            BooleanExpression::Term(term) => CompiledExpression::Atom(self.intern(term)?),
+            BooleanExpression::And(expressions) => CompiledExpression::And(
+                expressions
+                    .iter()
+                    .map(|expression| self.compile(expression))
+                    .collect::<Result<_, _>>()?,
+            ),
+            BooleanExpression::Or(expressions) => CompiledExpression::Or(
+                expressions
+                    .iter()
+                    .map(|expression| self.compile(expression))
+                    .collect::<Result<_, _>>()?,
+            ),
+            BooleanExpression::Not(expression) => {
+                CompiledExpression::Not(Box::new(self.compile(expression)?))
+            }
+        })
+    }
+}
@@ -0,0 +1,150 @@
+use crate::{ParserConfig, add_search_with_expression, run_parser};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Keyword searched by every self-test case
+pub const SELF_TEST_KEYWORD: &str = "error";
+/// Additional expression searched by every self-test case
+pub const SELF_TEST_EXPRESSION: &str = "disk | timeout";
+
+/// An embedded fixture together with the results it must produce
+pub struct SelfTestCase {
+    pub name: &'static str,
+    /// File name the fixture is written under; the extension selects the decoder
+    pub file_name: &'static str,
+    pub contents: &'static [u8],
+    pub expected_matches: usize,
+    /// FNV-1a hash of the expected output file
+    pub expected_output_hash: u64,
+}
+
+/// Fixtures exercised by `elysiumparser self-test` and the integration tests
+pub const SELF_TEST_CASES: &[SelfTestCase] = &[
+    SelfTestCase {
+        name: "plain",
+        file_name: "plain.log",
+        contents: include_bytes!("../tests/fixtures/plain.log"),
+        expected_matches: 2,
+        expected_output_hash: 0x90f1298fe271ca12,
+    },
+    SelfTestCase {
+        name: "gzip",
+        file_name: "archive.log.gz",
+        contents: include_bytes!("../tests/fixtures/archive.log.gz"),
+        expected_matches: 3,
+        expected_output_hash: 0xe8bcd18729d1c633,
+    },
+    SelfTestCase {
+        name: "crlf",
+        file_name: "crlf.log",
+        contents: include_bytes!("../tests/fixtures/crlf.log"),
+        expected_matches: 1,
+        expected_output_hash: 0x95205c840f071c4b,
+    },
+    SelfTestCase {
+        name: "non-utf8",
+        file_name: "non_utf8.log",
+        contents: include_bytes!("../tests/fixtures/non_utf8.log"),
+        // The line with the binary payload matches, with replacement characters
+        expected_matches: 2,
+        expected_output_hash: 0x9eeaeff07dd0f90d,
+    },
+];
+
+/// Result of running a single self-test case
+#[derive(Clone, Debug)]
+pub struct SelfTestOutcome {
+    pub name: &'static str,
+    pub expected_matches: usize,
+    pub actual_matches: usize,
+    pub expected_output_hash: u64,
+    pub actual_output_hash: u64,
+    /// Set when the case could not run at all
+    pub error: Option<String>,
+}
+
+impl SelfTestOutcome {
+    pub fn passed(&self) -> bool {
+        self.error.is_none()
+            && self.actual_matches == self.expected_matches
+            && self.actual_output_hash == self.expected_output_hash
+    }
+}
+
+/// FNV-1a hash, stable across platforms and compiler versions
+pub fn fnv1a_hash(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// Run every self-test case through the full parser pipeline
+pub async fn run_self_test() -> Vec<SelfTestOutcome> {
+    let mut outcomes = Vec::with_capacity(SELF_TEST_CASES.len());
+    for case in SELF_TEST_CASES {
+        outcomes.push(run_case(case).await);
+    }
+    outcomes
+}
+
+/// Run one case in its own scratch directory
+async fn run_case(case: &SelfTestCase) -> SelfTestOutcome {
+    let mut outcome = SelfTestOutcome {
+        name: case.name,
+        expected_matches: case.expected_matches,
+        actual_matches: 0,
+        expected_output_hash: case.expected_output_hash,
+        actual_output_hash: 0,
+        error: None,
+    };
+
+    let dir = scratch_dir(case.name);
+    match run_case_in(case, &dir).await {
+        Ok((matches, output_hash)) => {
+            outcome.actual_matches = matches;
+            outcome.actual_output_hash = output_hash;
+        }
+        Err(e) => outcome.error = Some(e.to_string()),
+    }
+    let _ = fs::remove_dir_all(&dir);
+
+    outcome
+}
+
+async fn run_case_in(case: &SelfTestCase, dir: &Path) -> std::io::Result<(usize, u64)> {
+    let input_dir = dir.join("input");
+    fs::create_dir_all(&input_dir)?;
+    fs::write(input_dir.join(case.file_name), case.contents)?;
+
+    let output_log = dir.join("output.log");
+    let mut config = ParserConfig {
+        log_folder: input_dir.to_string_lossy().into_owned(),
+        output_log: output_log.to_string_lossy().into_owned(),
+        workers: Some(1),
+        ..Default::default()
+    };
+    add_search_with_expression(
+        &mut config.search_terms,
+        SELF_TEST_KEYWORD,
+        SELF_TEST_EXPRESSION,
+    )
+    .map_err(std::io::Error::other)?;
+
+    let result = run_parser(config, None).await?;
+    let output = fs::read(&output_log)?;
+
+    Ok((result.total_matches, fnv1a_hash(&output)))
+}
+
+/// A fresh directory under the system temp dir, unique to this process and run
+fn scratch_dir(name: &str) -> PathBuf {
+    static NEXT_RUN: AtomicUsize = AtomicUsize::new(0);
+
+    std::env::temp_dir().join(format!(
+        "elysiumparser-self-test-{}-{}-{}",
+        std::process::id(),
+        NEXT_RUN.fetch_add(1, Ordering::Relaxed),
+        name
+    ))
+}
@@ -0,0 +1,641 @@
+use crate::labels::PathLabels;
+use crate::{SearchTerm, relative_to_roots, term_at};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::collections::hash_map::DefaultHasher;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc::UnboundedSender;
+
+/// How matches are written to the output log
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// The matched line as it was read
+    #[default]
+    Plain,
+    /// One JSON object per match (see `JsonlSink`)
+    Jsonl,
+    /// The path, line number and line separated by tabs, for `cut` and `awk`
+    /// (see `LocationSink::tab_separated`)
+    Tsv,
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            OutputFormat::Plain => "plain",
+            OutputFormat::Jsonl => "jsonl",
+            OutputFormat::Tsv => "tsv",
+        })
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "plain" | "text" => Ok(OutputFormat::Plain),
+            "jsonl" | "json" => Ok(OutputFormat::Jsonl),
+            "tsv" | "tab" => Ok(OutputFormat::Tsv),
+            other => Err(format!(
+                "unknown output format '{}' (expected plain, tsv or jsonl)",
+                other
+            )),
+        }
+    }
+}
+
+/// A matched line together with where it came from
+#[derive(Clone, Copy, Debug)]
+pub struct MatchRecord<'a> {
+    /// File the line was read from
+    pub source: &'a Path,
+    /// 1-based line number within the source
+    pub line_number: usize,
+    /// First search term that matched the line; blank for inverted matches
+    pub term: &'a SearchTerm,
+    /// Index of `term` among the search terms of the run, or `INVERTED_MATCH`
+    pub term_index: usize,
+    /// Severity of the match: the term's score plus any rule bonuses
+    pub score: i32,
+    /// The line as written, without its line terminator
+    pub line: &'a str,
+    /// Labels of the source, from `ParserConfig::path_labels`
+    pub labels: &'a PathLabels,
+}
+
+impl MatchRecord<'_> {
+    /// Copy the record into an owned `MatchRecordBuf`
+    pub fn to_buf(&self) -> MatchRecordBuf {
+        MatchRecordBuf {
+            file: self.source.to_path_buf(),
+            line_number: self.line_number,
+            term_index: self.term_index,
+            score: self.score,
+            line: self.line.to_string(),
+            labels: self.labels.clone(),
+        }
+    }
+}
+
+/// Owned copy of a `MatchRecord`, for keeping or sending matches
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MatchRecordBuf {
+    pub file: PathBuf,
+    pub line_number: usize,
+    /// Index of the matching term in `ParserConfig::search_terms`, or
+    /// `INVERTED_MATCH`
+    pub term_index: usize,
+    pub score: i32,
+    pub line: String,
+    pub labels: PathLabels,
+}
+
+impl MatchRecordBuf {
+    /// Borrow the record back as a `MatchRecord` of a run with `terms`
+    pub(crate) fn as_record<'a>(&'a self, terms: &'a [SearchTerm]) -> MatchRecord<'a> {
+        MatchRecord {
+            source: &self.file,
+            line_number: self.line_number,
+            term: term_at(terms, self.term_index),
+            term_index: self.term_index,
+            score: self.score,
+            line: &self.line,
+            labels: &self.labels,
+        }
+    }
+}
+
+/// A line of a block of matches written with their surrounding context
+#[derive(Clone, Copy, Debug)]
+pub enum BlockLine<'a> {
+    /// Gap between this block and the previous one of the same file, `--`
+    Separator,
+    /// A line before or after a match that did not match itself
+    Context { line_number: usize, line: &'a str },
+    /// A matched line
+    Match(MatchRecord<'a>),
+}
+
+/// Destination for the matched lines of a run
+pub trait MatchSink: Send + Sync {
+    /// Write a single matched line
+    fn write_match(&self, record: &MatchRecord<'_>) -> io::Result<()>;
+
+    /// Write a block of contiguous lines of `source` as one unit
+    ///
+    /// The default writes the matches and drops the context, for sinks that
+    /// only keep matches.
+    fn write_block(&self, source: &Path, lines: &[BlockLine<'_>]) -> io::Result<()> {
+        let _ = source;
+        lines.iter().try_for_each(|line| match line {
+            BlockLine::Match(record) => self.write_match(record),
+            _ => Ok(()),
+        })
+    }
+
+    /// Flush and finalize the output once the run is complete
+    fn finish(&self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Plain-text output: one matched line per output line
+///
+/// In blocks, match lines are marked with `> ` and context lines with `- `.
+impl<W: Write + Send> MatchSink for Mutex<W> {
+    fn write_match(&self, record: &MatchRecord<'_>) -> io::Result<()> {
+        let mut writer = self
+            .lock()
+            .map_err(|_| io::Error::other("output writer poisoned"))?;
+        writeln!(writer, "{}", record.line)
+    }
+
+    fn write_block(&self, _source: &Path, lines: &[BlockLine<'_>]) -> io::Result<()> {
+        // Hold the lock for the whole block so other files cannot interleave
+        let mut writer = self
+            .lock()
+            .map_err(|_| io::Error::other("output writer poisoned"))?;
+        for line in lines {
+            match line {
+                BlockLine::Separator => writeln!(writer, "--")?,
+                BlockLine::Context { line, .. } => writeln!(writer, "- {}", line)?,
+                BlockLine::Match(record) => writeln!(writer, "> {}", record.line)?,
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(&self) -> io::Result<()> {
+        self.lock()
+            .map_err(|_| io::Error::other("output writer poisoned"))?
+            .flush()
+    }
+}
+
+/// Prefixes each line with `file_name:line_number:` before forwarding it
+///
+/// Context lines get `file_name-line_number-` instead, like grep. With
+/// `relative_to`, files are named by their path below that folder, so files
+/// of the same name in different subdirectories can be told apart.
+pub struct LocationSink {
+    inner: Arc<dyn MatchSink>,
+    roots: Vec<PathBuf>,
+    tab_separated: bool,
+}
+
+impl LocationSink {
+    pub fn new(inner: Arc<dyn MatchSink>) -> Self {
+        Self {
+            inner,
+            roots: Vec::new(),
+            tab_separated: false,
+        }
+    }
+
+    /// Write `path<TAB>line_number<TAB>line` instead, for matches and context
+    /// lines alike
+    pub fn tab_separated(mut self) -> Self {
+        self.tab_separated = true;
+        self
+    }
+
+    /// Name files by their path relative to `root`, when they are below it
+    pub fn relative_to(mut self, root: &Path) -> Self {
+        self.roots = vec![root.to_path_buf()];
+        self
+    }
+
+    /// Name files by their path relative to the closest of `roots` they are
+    /// below, for runs searching several folders
+    pub fn relative_to_roots(mut self, roots: Vec<PathBuf>) -> Self {
+        self.roots = roots;
+        self
+    }
+
+    fn name<'a>(&self, source: &'a Path) -> std::borrow::Cow<'a, str> {
+        match relative_to_roots(source, &self.roots) {
+            Some(relative) if !relative.as_os_str().is_empty() => relative.to_string_lossy(),
+            _ => display_name(source),
+        }
+    }
+
+    /// Prefix a line, with `separator` between the fields unless tab separated
+    fn prefix(&self, name: &str, line_number: usize, line: &str, separator: char) -> String {
+        let separator = if self.tab_separated { '\t' } else { separator };
+        format!("{}{}{}{}{}", name, separator, line_number, separator, line)
+    }
+}
+
+impl MatchSink for LocationSink {
+    fn write_match(&self, record: &MatchRecord<'_>) -> io::Result<()> {
+        let name = self.name(record.source);
+        let line = self.prefix(&name, record.line_number, record.line, ':');
+        self.inner.write_match(&MatchRecord {
+            line: &line,
+            ..*record
+        })
+    }
+
+    fn write_block(&self, source: &Path, lines: &[BlockLine<'_>]) -> io::Result<()> {
+        let name = self.name(source);
+        let prefixed: Vec<String> = lines
+            .iter()
+            .map(|line| match line {
+                BlockLine::Separator => String::new(),
+                BlockLine::Context { line_number, line } => {
+                    self.prefix(&name, *line_number, line, '-')
+                }
+                BlockLine::Match(record) => {
+                    self.prefix(&name, record.line_number, record.line, ':')
+                }
+            })
+            .collect();
+
+        let lines: Vec<BlockLine<'_>> = lines
+            .iter()
+            .zip(&prefixed)
+            .map(|(line, prefixed)| match line {
+                BlockLine::Separator => BlockLine::Separator,
+                BlockLine::Context { line_number, .. } => BlockLine::Context {
+                    line_number: *line_number,
+                    line: prefixed,
+                },
+                BlockLine::Match(record) => BlockLine::Match(MatchRecord {
+                    line: prefixed,
+                    ..*record
+                }),
+            })
+            .collect();
+        self.inner.write_block(source, &lines)
+    }
+
+    fn finish(&self) -> io::Result<()> {
+        self.inner.finish()
+    }
+}
+
+/// File name of `source`, or the whole path when it has none
+fn display_name(source: &Path) -> std::borrow::Cow<'_, str> {
+    match source.file_name() {
+        Some(name) => name.to_string_lossy(),
+        None => source.to_string_lossy(),
+    }
+}
+
+/// A match as written by `JsonlSink`
+#[derive(Serialize)]
+struct JsonlRecord<'a> {
+    source_file: &'a str,
+    line_number: usize,
+    matched_keyword: &'a str,
+    score: i32,
+    /// The raw line; `line` repeats it for readers written before `content`
+    content: &'a str,
+    line: &'a str,
+    /// Labels of the source file, left out when it has none
+    #[serde(skip_serializing_if = "PathLabels::is_empty")]
+    labels: &'a PathLabels,
+}
+
+/// Writes each match as a JSON object on its own line
+///
+/// ```text
+/// {"source_file":"logs/app.log","line_number":3,"matched_keyword":"error","score":0,"content":"ERROR: disk full","line":"ERROR: disk full"}
+/// ```
+///
+/// The line is written as read, not lowercased. Context lines are not written.
+/// Matches of files labelled by `ParserConfig::path_labels` carry a `labels`
+/// object as well.
+pub struct JsonlSink<W> {
+    writer: Arc<Mutex<W>>,
+}
+
+impl<W> JsonlSink<W> {
+    pub fn new(writer: Arc<Mutex<W>>) -> Self {
+        Self { writer }
+    }
+}
+
+/// A match as a JSON object, as written by `JsonlSink`, without the newline
+pub(crate) fn jsonl_line(record: &MatchRecord<'_>) -> io::Result<String> {
+    let source_file = record.source.to_string_lossy();
+    let json = serde_json::to_string(&JsonlRecord {
+        source_file: &source_file,
+        line_number: record.line_number,
+        matched_keyword: &record.term.keyword,
+        score: record.score,
+        content: record.line,
+        line: record.line,
+        labels: record.labels,
+    })?;
+    Ok(json)
+}
+
+impl<W: Write + Send> MatchSink for JsonlSink<W> {
+    fn write_match(&self, record: &MatchRecord<'_>) -> io::Result<()> {
+        let json = jsonl_line(record)?;
+        let mut writer = self
+            .writer
+            .lock()
+            .map_err(|_| io::Error::other("output writer poisoned"))?;
+        writeln!(writer, "{}", json)
+    }
+
+    fn finish(&self) -> io::Result<()> {
+        self.writer.finish()
+    }
+}
+
+/// Labels the records of one file before handing them to `inner`
+pub(crate) struct LabelSink<'a> {
+    pub(crate) inner: &'a dyn MatchSink,
+    pub(crate) labels: &'a PathLabels,
+}
+
+impl MatchSink for LabelSink<'_> {
+    fn write_match(&self, record: &MatchRecord<'_>) -> io::Result<()> {
+        self.inner.write_match(&MatchRecord {
+            labels: self.labels,
+            ..*record
+        })
+    }
+
+    fn write_block(&self, source: &Path, lines: &[BlockLine<'_>]) -> io::Result<()> {
+        let lines: Vec<BlockLine<'_>> = lines
+            .iter()
+            .map(|line| match line {
+                BlockLine::Match(record) => BlockLine::Match(MatchRecord {
+                    labels: self.labels,
+                    ..*record
+                }),
+                line => *line,
+            })
+            .collect();
+        self.inner.write_block(source, &lines)
+    }
+}
+
+/// Keeps every match in memory
+#[derive(Default)]
+pub struct CollectSink {
+    matches: Mutex<Vec<MatchRecordBuf>>,
+}
+
+impl CollectSink {
+    /// Take the collected matches, ordered by file and line number
+    pub fn take_matches(&self) -> Vec<MatchRecordBuf> {
+        let mut matches = match self.matches.lock() {
+            Ok(mut matches) => std::mem::take(&mut *matches),
+            Err(_) => Vec::new(),
+        };
+        matches.sort_by(|a, b| (&a.file, a.line_number).cmp(&(&b.file, b.line_number)));
+        matches
+    }
+}
+
+impl MatchSink for CollectSink {
+    fn write_match(&self, record: &MatchRecord<'_>) -> io::Result<()> {
+        self.matches
+            .lock()
+            .map_err(|_| io::Error::other("match collector poisoned"))?
+            .push(record.to_buf());
+        Ok(())
+    }
+}
+
+/// Streams every match to a channel as it is found
+///
+/// Once the receiver is dropped, further matches are discarded.
+pub struct ChannelSink {
+    sender: UnboundedSender<MatchRecordBuf>,
+}
+
+impl ChannelSink {
+    pub fn new(sender: UnboundedSender<MatchRecordBuf>) -> Self {
+        Self { sender }
+    }
+}
+
+impl MatchSink for ChannelSink {
+    fn write_match(&self, record: &MatchRecord<'_>) -> io::Result<()> {
+        // A closed channel means the consumer stopped listening
+        let _ = self.sender.send(record.to_buf());
+        Ok(())
+    }
+}
+
+/// Forwards every match to several sinks
+pub struct FanOutSink {
+    sinks: Vec<Arc<dyn MatchSink>>,
+}
+
+impl FanOutSink {
+    pub fn new(sinks: Vec<Arc<dyn MatchSink>>) -> Self {
+        Self { sinks }
+    }
+}
+
+impl MatchSink for FanOutSink {
+    fn write_match(&self, record: &MatchRecord<'_>) -> io::Result<()> {
+        self.sinks
+            .iter()
+            .try_for_each(|sink| sink.write_match(record))
+    }
+
+    fn write_block(&self, source: &Path, lines: &[BlockLine<'_>]) -> io::Result<()> {
+        self.sinks
+            .iter()
+            .try_for_each(|sink| sink.write_block(source, lines))
+    }
+
+    fn finish(&self) -> io::Result<()> {
+        self.sinks.iter().try_for_each(|sink| sink.finish())
+    }
+}
+
+/// Drops the matches whose line was already written, whichever file it
+/// came from
+///
+/// Lines are remembered by a 64-bit hash of their content, before any
+/// location prefix. With a capacity, the hashes are forgotten whenever that
+/// many are held, bounding the memory of long runs at the cost of writing
+/// some lines again. Blocks of context lines are written as they come.
+pub struct DedupSink {
+    inner: Arc<dyn MatchSink>,
+    seen: Mutex<HashSet<u64>>,
+    capacity: Option<usize>,
+    skipped: AtomicUsize,
+    resets: AtomicUsize,
+}
+
+impl DedupSink {
+    pub fn new(inner: Arc<dyn MatchSink>, capacity: Option<usize>) -> Self {
+        Self {
+            inner,
+            seen: Mutex::new(HashSet::new()),
+            capacity,
+            skipped: AtomicUsize::new(0),
+            resets: AtomicUsize::new(0),
+        }
+    }
+
+    /// Matches dropped as duplicates so far
+    pub fn skipped(&self) -> usize {
+        self.skipped.load(Ordering::Relaxed)
+    }
+
+    /// Times the remembered hashes were forgotten on reaching the capacity
+    pub fn resets(&self) -> usize {
+        self.resets.load(Ordering::Relaxed)
+    }
+}
+
+impl MatchSink for DedupSink {
+    fn write_match(&self, record: &MatchRecord<'_>) -> io::Result<()> {
+        let mut hasher = DefaultHasher::new();
+        record.line.hash(&mut hasher);
+        let hash = hasher.finish();
+        {
+            let mut seen = self
+                .seen
+                .lock()
+                .map_err(|_| io::Error::other("duplicate filter poisoned"))?;
+            if seen.contains(&hash) {
+                self.skipped.fetch_add(1, Ordering::Relaxed);
+                return Ok(());
+            }
+            if self.capacity.is_some_and(|capacity| seen.len() >= capacity) {
+                seen.clear();
+                self.resets.fetch_add(1, Ordering::Relaxed);
+            }
+            seen.insert(hash);
+        }
+        self.inner.write_match(record)
+    }
+
+    fn write_block(&self, source: &Path, lines: &[BlockLine<'_>]) -> io::Result<()> {
+        self.inner.write_block(source, lines)
+    }
+
+    fn finish(&self) -> io::Result<()> {
+        self.inner.finish()
+    }
+}
+
+/// A write held by `BufferSink`
+enum BufferedWrite {
+    Match(MatchRecordBuf),
+    Block(PathBuf, Vec<BufferedBlockLine>),
+}
+
+/// Owned copy of a `BlockLine`
+enum BufferedBlockLine {
+    Separator,
+    Context { line_number: usize, line: String },
+    Match(MatchRecordBuf),
+}
+
+/// `buffered` as a record of a run with `terms`, `lines_before` lines further
+fn shifted<'a>(
+    buffered: &'a MatchRecordBuf,
+    terms: &'a [SearchTerm],
+    lines_before: usize,
+) -> MatchRecord<'a> {
+    let record = buffered.as_record(terms);
+    MatchRecord {
+        line_number: record.line_number + lines_before,
+        ..record
+    }
+}
+
+/// Holds the writes for one file until they are replayed into the real output
+#[derive(Default)]
+pub(crate) struct BufferSink {
+    writes: Mutex<Vec<BufferedWrite>>,
+}
+
+impl BufferSink {
+    /// Write the held matches and blocks to `output`, in the order they came
+    pub(crate) fn replay(self, output: &dyn MatchSink, terms: &[SearchTerm]) -> io::Result<()> {
+        self.replay_after(output, terms, 0)
+    }
+
+    /// `replay`, numbering the lines after the `lines_before` lines of the
+    /// file that came before the ones held, like for a range of a split file
+    pub(crate) fn replay_after(
+        self,
+        output: &dyn MatchSink,
+        terms: &[SearchTerm],
+        lines_before: usize,
+    ) -> io::Result<()> {
+        let record = |buffered| shifted(buffered, terms, lines_before);
+        let writes = self
+            .writes
+            .into_inner()
+            .map_err(|_| io::Error::other("output buffer poisoned"))?;
+        for write in &writes {
+            match write {
+                BufferedWrite::Match(buffered) => output.write_match(&record(buffered))?,
+                BufferedWrite::Block(source, lines) => {
+                    let lines: Vec<BlockLine<'_>> = lines
+                        .iter()
+                        .map(|line| match line {
+                            BufferedBlockLine::Separator => BlockLine::Separator,
+                            BufferedBlockLine::Context { line_number, line } => {
+                                BlockLine::Context {
+                                    line_number: line_number + lines_before,
+                                    line,
+                                }
+                            }
+                            BufferedBlockLine::Match(buffered) => {
+                                BlockLine::Match(record(buffered))
+                            }
+                        })
+                        .collect();
+                    output.write_block(source, &lines)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.writes.lock().map_or(true, |writes| writes.is_empty())
+    }
+
+    fn push(&self, write: BufferedWrite) -> io::Result<()> {
+        self.writes
+            .lock()
+            .map_err(|_| io::Error::other("output buffer poisoned"))?
+            .push(write);
+        Ok(())
+    }
+}
+
+impl MatchSink for BufferSink {
+    fn write_match(&self, record: &MatchRecord<'_>) -> io::Result<()> {
+        self.push(BufferedWrite::Match(record.to_buf()))
+    }
+
+    fn write_block(&self, source: &Path, lines: &[BlockLine<'_>]) -> io::Result<()> {
+        let lines = lines
+            .iter()
+            .map(|line| match line {
+                BlockLine::Separator => BufferedBlockLine::Separator,
+                BlockLine::Context { line_number, line } => BufferedBlockLine::Context {
+                    line_number: *line_number,
+                    line: line.to_string(),
+                },
+                BlockLine::Match(record) => BufferedBlockLine::Match(record.to_buf()),
+            })
+            .collect();
+        self.push(BufferedWrite::Block(source.to_path_buf(), lines))
+    }
+}
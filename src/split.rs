@@ -0,0 +1,254 @@
+//! Scanning one large plain log as several byte ranges at once, so a single
+//! big file does not keep one worker busy while the others idle
+
+use crate::sink::BufferSink;
+use crate::{
+    CompressionKind, FileProgress, FileScan, FileSystem, InputFormat, LineScanner, MatchSink,
+    ParserError, ScanOptions, SearchSet, keep_first_error, scan_file, scan_lines,
+};
+use std::io::{self, BufRead, BufReader, Read};
+use std::ops::Range;
+use std::path::Path;
+use std::thread;
+
+/// Bytes read to tell an archive named like a plain log by its first bytes
+const MAGIC_BYTES: u64 = 8;
+
+/// When and how far files are split, from `ParserConfig::split_threshold_bytes`
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Split {
+    /// Files of at least this many bytes are split
+    pub(crate) threshold_bytes: u64,
+    /// Most ranges a file is split into, the workers of the run
+    pub(crate) max_ranges: usize,
+}
+
+impl Split {
+    /// Scan a plain log like `scan_file`, in one range per `threshold_bytes`
+    /// of it, up to `max_ranges`, each on a thread of its own
+    ///
+    /// Ranges end right after a newline, so each line is scanned by exactly
+    /// one of them. The matches of each range are held until all of them
+    /// are scanned, then written in file order with their line numbers in
+    /// the whole file. Files whose lines depend on one another, through
+    /// context lines, collapsed repeats, assertions, a per-file match limit
+    /// or an input format other than plain, and archives are scanned whole.
+    pub(crate) fn scan_file(
+        self,
+        file_system: &dyn FileSystem,
+        path: &Path,
+        search_set: &SearchSet,
+        options: &ScanOptions,
+        output: Option<&dyn MatchSink>,
+        progress: Option<&FileProgress>,
+    ) -> io::Result<FileScan> {
+        let whole = || {
+            scan_file(
+                file_system,
+                path,
+                None,
+                search_set,
+                options,
+                output,
+                progress,
+            )
+        };
+        let Ok(metadata) = file_system.metadata(path) else {
+            return whole();
+        };
+        let ranges = metadata
+            .len
+            .div_ceil(self.threshold_bytes.max(1))
+            .min(self.max_ranges as u64);
+        if metadata.len < self.threshold_bytes || ranges < 2 || !splittable(options) {
+            return whole();
+        }
+        let mut magic = Vec::new();
+        file_system
+            .open(path)?
+            .take(MAGIC_BYTES)
+            .read_to_end(&mut magic)?;
+        if CompressionKind::from_magic(&magic).is_some() {
+            return whole();
+        }
+
+        #[cfg(feature = "mmap")]
+        if options.mmap
+            && let Ok(data) = file_system.map(path)
+        {
+            let ranges = line_ranges(data.len() as u64, ranges, |offset| {
+                let start = offset as usize;
+                Ok(memchr::memchr(b'\n', &data[start..]).map(|end| (start + end + 1) as u64))
+            })?;
+            let scan = scan_ranges(
+                &ranges,
+                search_set,
+                options,
+                output,
+                |range, options, sink| {
+                    let range = range.start as usize..range.end as usize;
+                    let scanned = crate::mmap::scan_mapped(
+                        &data[range],
+                        path,
+                        search_set,
+                        options,
+                        sink,
+                        None,
+                    );
+                    Ok(scanned)
+                },
+            );
+            return Ok(finished(scan, progress));
+        }
+
+        let ranges = line_ranges(metadata.len, ranges, |offset| {
+            let mut reader = BufReader::new(file_system.open_at(path, offset)?);
+            let mut line = Vec::new();
+            let read = reader.read_until(b'\n', &mut line)?;
+            Ok(line.ends_with(b"\n").then(|| offset + read as u64))
+        })?;
+        let scan = scan_ranges(
+            &ranges,
+            search_set,
+            options,
+            output,
+            |range, options, sink| {
+                let reader = file_system
+                    .open_at(path, range.start)?
+                    .take(range.end - range.start);
+                let scanner = LineScanner::new(path, search_set, options, sink, None);
+                Ok(scan_lines(BufReader::new(reader), scanner))
+            },
+        );
+        Ok(finished(scan, progress))
+    }
+}
+
+/// Whether each line can be scanned apart from those before it
+fn splittable(options: &ScanOptions) -> bool {
+    options.input_format == InputFormat::Plain
+        && options.before_context == 0
+        && options.after_context == 0
+        && !options.collapse_consecutive
+        && options.assertions.is_empty()
+        && options.max_matches_per_file.is_none()
+}
+
+/// Up to `count` ranges covering `len` bytes, each ending after a newline
+/// but the last; `line_end` gives the end of the line holding an offset, or
+/// `None` when no newline follows it
+fn line_ranges(
+    len: u64,
+    count: u64,
+    mut line_end: impl FnMut(u64) -> io::Result<Option<u64>>,
+) -> io::Result<Vec<Range<u64>>> {
+    let mut ranges = Vec::new();
+    let mut start = 0;
+    for index in 1..count {
+        // The line holding the byte before the even split ends the range,
+        // unless an earlier range already took it
+        let split = len * index / count;
+        if split <= start {
+            continue;
+        }
+        let Some(end) = line_end(split - 1)?.filter(|end| *end < len) else {
+            break;
+        };
+        if end > start {
+            ranges.push(start..end);
+            start = end;
+        }
+    }
+    ranges.push(start..len);
+    Ok(ranges)
+}
+
+/// Scan each of `ranges` on a thread of its own with `scan_range`, then
+/// write their matches to `output` in order and add their scans up
+fn scan_ranges<F>(
+    ranges: &[Range<u64>],
+    search_set: &SearchSet,
+    options: &ScanOptions,
+    output: Option<&dyn MatchSink>,
+    scan_range: F,
+) -> FileScan
+where
+    F: Fn(Range<u64>, &ScanOptions, Option<&dyn MatchSink>) -> io::Result<FileScan> + Sync,
+{
+    // Only the start of the file tells whether it is binary
+    let rest_options = ScanOptions {
+        skip_binary_files: false,
+        ..options.clone()
+    };
+    let buffers: Vec<_> = ranges.iter().map(|_| BufferSink::default()).collect();
+    let scans: Vec<_> = thread::scope(|scope| {
+        let threads: Vec<_> = ranges
+            .iter()
+            .zip(&buffers)
+            .enumerate()
+            .map(|(index, (range, buffer))| {
+                let options = if index == 0 { options } else { &rest_options };
+                let sink = output.map(|_| buffer as &dyn MatchSink);
+                let scan_range = &scan_range;
+                scope.spawn(move || scan_range(range.clone(), options, sink))
+            })
+            .collect();
+        threads
+            .into_iter()
+            .map(|thread| {
+                thread
+                    .join()
+                    .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+            })
+            .collect()
+    });
+
+    let mut total = FileScan {
+        term_matches: vec![0; search_set.terms().len()],
+        ..Default::default()
+    };
+    for (index, (scan, buffer)) in scans.into_iter().zip(buffers).enumerate() {
+        let scan = scan.unwrap_or_else(|e| FileScan {
+            error: Some(ParserError::Open(e)),
+            ..Default::default()
+        });
+        if index == 0 && scan.binary {
+            return scan;
+        }
+        if let Some(output) = output {
+            let replayed = buffer.replay_after(output, search_set.terms(), total.lines_scanned);
+            keep_first_error(&mut total.error, ParserError::Write, replayed);
+        }
+        total.matches += scan.matches;
+        total.lines_scanned += scan.lines_scanned;
+        total.bytes_read += scan.bytes_read;
+        total.long_lines += scan.long_lines;
+        for (total, matches) in total.term_matches.iter_mut().zip(&scan.term_matches) {
+            *total += matches;
+        }
+        if total.atom_timings.is_empty() {
+            total.atom_timings = scan.atom_timings;
+        } else {
+            for (total, timing) in total.atom_timings.iter_mut().zip(&scan.atom_timings) {
+                total.merge(timing);
+            }
+        }
+        // Reading a whole file stops at its first error, and so do the
+        // ranges after it
+        if total.error.is_none() && scan.error.is_some() {
+            total.error = scan.error;
+        }
+        if total.error.is_some() {
+            break;
+        }
+    }
+    total
+}
+
+/// Publish the bytes read by a split scan, which its ranges do not
+fn finished(scan: FileScan, progress: Option<&FileProgress>) -> FileScan {
+    if let Some(progress) = progress {
+        progress.tick(scan.bytes_read);
+    }
+    scan
+}
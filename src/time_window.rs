@@ -0,0 +1,128 @@
+use chrono::format::{Item, Parsed, StrftimeItems};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, TimeDelta, Utc};
+use std::path::Path;
+use std::time::SystemTime;
+
+/// ISO 8601 timestamp, with optional fractional seconds, like `2024-03-15T12:00:00.123`
+pub const DEFAULT_TIMESTAMP_FORMAT: &str = "%Y-%m-%dT%H:%M:%S%.f";
+
+/// Range of times whose log lines a run keeps, from `since` up to but
+/// excluding `until`
+///
+/// Lines are dated by the timestamp they start with, parsed with a
+/// strftime-like format. Timestamps with an offset, parsed by `%z` or `%:z`,
+/// are converted to UTC; the others, like `since` and `until`, are taken as
+/// UTC already.
+#[derive(Clone, Debug)]
+pub struct TimeWindow {
+    since: Option<NaiveDateTime>,
+    until: Option<NaiveDateTime>,
+    format: Vec<Item<'static>>,
+    include_untimestamped: bool,
+}
+
+impl TimeWindow {
+    /// Window keeping the lines stamped in `timestamp_format` between `since`
+    /// and `until`, and the lines without a timestamp when `include_untimestamped`
+    pub fn new(
+        since: Option<NaiveDateTime>,
+        until: Option<NaiveDateTime>,
+        timestamp_format: &str,
+        include_untimestamped: bool,
+    ) -> Result<Self, String> {
+        let format = StrftimeItems::new(timestamp_format)
+            .parse_to_owned()
+            .map_err(|e| format!("invalid timestamp format '{}': {}", timestamp_format, e))?;
+        Ok(Self {
+            since,
+            until,
+            format,
+            include_untimestamped,
+        })
+    }
+
+    pub fn contains(&self, time: NaiveDateTime) -> bool {
+        self.since.is_none_or(|since| time >= since) && self.until.is_none_or(|until| time < until)
+    }
+
+    /// Whether a line is kept, judging by the timestamp it starts with
+    pub fn contains_line(&self, line: &str) -> bool {
+        match self.line_time(line) {
+            Some(time) => self.contains(time),
+            None => self.include_untimestamped,
+        }
+    }
+
+    /// Whether a line from a Docker or CRI record written at `record_time`
+    /// is kept, judging by the line itself when the time is not RFC 3339
+    pub fn contains_record(&self, record_time: &str, line: &str) -> bool {
+        match DateTime::parse_from_rfc3339(record_time) {
+            Ok(time) => self.contains(time.naive_utc()),
+            Err(_) => self.contains_line(line),
+        }
+    }
+
+    /// Time of the timestamp `line` starts with
+    pub fn line_time(&self, line: &str) -> Option<NaiveDateTime> {
+        let mut parsed = Parsed::new();
+        chrono::format::parse_and_remainder(&mut parsed, line, self.format.iter()).ok()?;
+        match parsed.offset() {
+            Some(_) => parsed.to_datetime().ok().map(|time| time.naive_utc()),
+            None => parsed.to_naive_datetime_with_offset(0).ok(),
+        }
+    }
+
+    /// Whether a file last modified at `modified` can hold lines in the window
+    ///
+    /// Lines are written before the file is modified, so a file modified
+    /// before `since` holds none.
+    pub fn modified_in_range(&self, modified: SystemTime) -> bool {
+        let modified = DateTime::<Utc>::from(modified).naive_utc();
+        self.since.is_none_or(|since| modified >= since)
+    }
+
+    /// Whether any time of `day` is in the window
+    pub fn overlaps_day(&self, day: NaiveDate) -> bool {
+        let start = day.and_time(NaiveTime::MIN);
+        self.since
+            .is_none_or(|since| since < start + TimeDelta::days(1))
+            && self.until.is_none_or(|until| start < until)
+    }
+}
+
+/// First `YYYY-MM-DD` date in a file name, like the one of `app-2024-03-15.log.gz`
+pub fn filename_date(path: &Path) -> Option<NaiveDate> {
+    let name = path.file_name()?.to_str()?;
+    name.char_indices()
+        .filter_map(|(start, _)| name.get(start..start + 10))
+        .filter(|candidate| {
+            candidate.bytes().enumerate().all(|(i, byte)| match i {
+                4 | 7 => byte == b'-',
+                _ => byte.is_ascii_digit(),
+            })
+        })
+        .find_map(|candidate| NaiveDate::parse_from_str(candidate, "%Y-%m-%d").ok())
+}
+
+/// Parse a `--since` or `--until` time: a date like `2024-03-15`, or a date
+/// and time like `2024-03-15T12:00`, `2024-03-15 12:00:30`, taken as UTC
+pub fn parse_time_bound(text: &str) -> Result<NaiveDateTime, String> {
+    let text = text.trim();
+    if let Ok(date) = NaiveDate::parse_from_str(text, "%Y-%m-%d") {
+        return Ok(date.and_time(NaiveTime::MIN));
+    }
+    [
+        "%Y-%m-%dT%H:%M:%S%.f",
+        "%Y-%m-%d %H:%M:%S%.f",
+        "%Y-%m-%dT%H:%M",
+        "%Y-%m-%d %H:%M",
+    ]
+    .iter()
+    .find_map(|format| NaiveDateTime::parse_from_str(text, format).ok())
+    .ok_or_else(|| {
+        format!(
+            "invalid time '{}', expected a date like 2024-03-15 or 2024-03-15T12:00:00",
+            text
+        )
+    })
+}
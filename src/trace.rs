@@ -0,0 +1,176 @@
+//! Sampled timing of atom evaluations, for finding the expensive atoms of a run
+
+use std::collections::hash_map::RandomState;
+use std::hash::BuildHasher;
+use std::time::Duration;
+
+/// Linear sub-buckets per power of two; 4 keeps each bucket within 25%
+const SUB_BUCKET_BITS: u32 = 2;
+const SUB_BUCKETS: u64 = 1 << SUB_BUCKET_BITS;
+const BUCKETS: usize = (SUB_BUCKETS + (64 - SUB_BUCKET_BITS as u64) * SUB_BUCKETS) as usize;
+
+/// Histogram of durations in nanoseconds, with HDR-style log-linear buckets
+///
+/// Each power of two is split into 4 linear buckets, so any value is known
+/// to within 25% in constant memory. Histograms merge by adding counts.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LatencyHistogram {
+    counts: Vec<u64>,
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        Self {
+            counts: vec![0; BUCKETS],
+        }
+    }
+}
+
+impl LatencyHistogram {
+    /// Count one duration of `nanos` nanoseconds
+    pub fn record(&mut self, nanos: u64) {
+        self.counts[bucket(nanos)] += 1;
+    }
+
+    /// Add the counts of `other`
+    pub fn merge(&mut self, other: &LatencyHistogram) {
+        for (count, other) in self.counts.iter_mut().zip(&other.counts) {
+            *count += other;
+        }
+    }
+
+    /// Number of durations recorded
+    pub fn count(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    /// Upper bound of the bucket holding the `quantile` (0.0 to 1.0) duration
+    ///
+    /// Returns 0 when nothing was recorded.
+    pub fn quantile(&self, quantile: f64) -> u64 {
+        let count = self.count();
+        if count == 0 {
+            return 0;
+        }
+        let rank = ((quantile.clamp(0.0, 1.0) * count as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (index, bucket_count) in self.counts.iter().enumerate() {
+            seen += bucket_count;
+            if seen >= rank {
+                return bucket_upper_bound(index);
+            }
+        }
+        u64::MAX
+    }
+}
+
+fn bucket(nanos: u64) -> usize {
+    if nanos < SUB_BUCKETS {
+        return nanos as usize;
+    }
+    let exponent = 63 - nanos.leading_zeros();
+    let sub_bucket = (nanos >> (exponent - SUB_BUCKET_BITS)) & (SUB_BUCKETS - 1);
+    (SUB_BUCKETS + (exponent - SUB_BUCKET_BITS) as u64 * SUB_BUCKETS + sub_bucket) as usize
+}
+
+fn bucket_upper_bound(index: usize) -> u64 {
+    let index = index as u64;
+    if index < SUB_BUCKETS {
+        return index;
+    }
+    let exponent = (index - SUB_BUCKETS) / SUB_BUCKETS + SUB_BUCKET_BITS as u64;
+    let sub_bucket = (index - SUB_BUCKETS) % SUB_BUCKETS;
+    let width = 1u64 << (exponent - SUB_BUCKET_BITS as u64);
+    (1u64 << exponent) - 1 + (sub_bucket + 1) * width
+}
+
+/// Sampled evaluation times of one atom of a search set
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AtomTiming {
+    /// Id of the atom within the search set
+    pub id: usize,
+    /// The atom as written in an expression
+    pub atom: String,
+    /// Sum of the sampled evaluation times
+    pub total: Duration,
+    /// Distribution of the sampled evaluation times
+    pub histogram: LatencyHistogram,
+}
+
+impl AtomTiming {
+    pub fn new(id: usize, atom: String) -> Self {
+        Self {
+            id,
+            atom,
+            total: Duration::ZERO,
+            histogram: LatencyHistogram::default(),
+        }
+    }
+
+    /// Number of sampled evaluations
+    pub fn samples(&self) -> u64 {
+        self.histogram.count()
+    }
+
+    pub(crate) fn record(&mut self, elapsed: Duration) {
+        self.total += elapsed;
+        self.histogram
+            .record(u64::try_from(elapsed.as_nanos()).unwrap_or(u64::MAX));
+    }
+
+    /// Add the samples of `other`, the same atom timed elsewhere
+    pub fn merge(&mut self, other: &AtomTiming) {
+        self.total += other.total;
+        self.histogram.merge(&other.histogram);
+    }
+}
+
+/// Decides which lines are traced, picking each with probability `fraction`
+///
+/// The gap to the next sampled line is drawn up front, so the lines in
+/// between only cost a decrement.
+pub(crate) struct Sampler {
+    fraction: f64,
+    left: u64,
+    state: u64,
+}
+
+impl Sampler {
+    /// A sampler for `fraction`, or `None` when it samples nothing
+    pub(crate) fn new(fraction: Option<f64>) -> Option<Self> {
+        let fraction = fraction.filter(|fraction| *fraction > 0.0)?.min(1.0);
+        // Each reader gets its own random sequence
+        let state = RandomState::new().hash_one(fraction.to_bits()) | 1;
+        let mut sampler = Self {
+            fraction,
+            left: 0,
+            state,
+        };
+        sampler.left = sampler.gap();
+        Some(sampler)
+    }
+
+    /// Whether the next line is traced
+    #[inline]
+    pub(crate) fn sample(&mut self) -> bool {
+        if self.left > 1 {
+            self.left -= 1;
+            return false;
+        }
+        self.left = self.gap();
+        true
+    }
+
+    /// Lines until the next sampled one, geometrically distributed
+    fn gap(&mut self) -> u64 {
+        if self.fraction >= 1.0 {
+            return 1;
+        }
+        // xorshift64
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        let uniform = ((self.state >> 11) as f64 + 1.0) / (1u64 << 53) as f64;
+        (uniform.ln() / (1.0 - self.fraction).ln()) as u64 + 1
+    }
+}
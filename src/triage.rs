@@ -0,0 +1,181 @@
+use crate::sink::{BlockLine, MatchRecord, MatchSink};
+use crate::{BooleanExpression, SearchTerm};
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Matches kept for a `ParserConfig::triage_output` given no `triage_top`
+pub const DEFAULT_TRIAGE_TOP: usize = 100;
+
+/// Bonus points added to the score of matches satisfying a condition
+#[derive(Clone, Debug)]
+pub enum ScoreRule {
+    /// Lines longer than `length` bytes, such as ones carrying a stack trace
+    LineLongerThan { length: usize, points: i32 },
+    /// Lines matching an expression, such as `level=error`
+    Matches {
+        expression: BooleanExpression,
+        points: i32,
+    },
+}
+
+impl ScoreRule {
+    /// Points this rule adds to a matched line
+    pub fn points(&self, line: &str, lowercase_line: &str) -> i32 {
+        match self {
+            ScoreRule::LineLongerThan { length, points } if line.len() > *length => *points,
+            ScoreRule::Matches { expression, points } if expression.matches(lowercase_line) => {
+                *points
+            }
+            _ => 0,
+        }
+    }
+}
+
+/// Score of a line matched by `term`: the term's own score plus every rule bonus
+pub fn score_match(
+    term: &SearchTerm,
+    rules: &[ScoreRule],
+    line: &str,
+    lowercase_line: &str,
+) -> i32 {
+    rules.iter().fold(term.score, |score, rule| {
+        score.saturating_add(rule.points(line, lowercase_line))
+    })
+}
+
+/// A matched line kept for triage
+///
+/// Ordered by score; ties rank earlier files and lines higher.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ScoredMatch {
+    pub score: i32,
+    pub path: PathBuf,
+    pub line_number: usize,
+    pub line: String,
+}
+
+impl Ord for ScoredMatch {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.score
+            .cmp(&other.score)
+            .then_with(|| other.path.cmp(&self.path))
+            .then_with(|| other.line_number.cmp(&self.line_number))
+    }
+}
+
+impl PartialOrd for ScoredMatch {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Keeps the `capacity` highest scoring matches, forwarding every match to
+/// the wrapped output
+pub struct TriageSink {
+    inner: Option<Arc<dyn MatchSink>>,
+    capacity: usize,
+    /// Min-heap, so the worst kept match is evicted first
+    top: Mutex<BinaryHeap<Reverse<ScoredMatch>>>,
+}
+
+impl TriageSink {
+    pub fn new(inner: Option<Arc<dyn MatchSink>>, capacity: usize) -> Self {
+        Self {
+            inner,
+            capacity,
+            top: Mutex::new(BinaryHeap::with_capacity(capacity + 1)),
+        }
+    }
+
+    /// The kept matches, best first
+    pub fn top_matches(&self) -> Vec<ScoredMatch> {
+        let top = self.top.lock().unwrap().clone();
+        top.into_sorted_vec()
+            .into_iter()
+            .map(|Reverse(scored)| scored)
+            .collect()
+    }
+
+    fn keep(&self, record: &MatchRecord<'_>) -> io::Result<()> {
+        let mut top = self
+            .top
+            .lock()
+            .map_err(|_| io::Error::other("triage heap poisoned"))?;
+
+        // Skip building the match when it could not displace the worst kept one
+        if top.len() >= self.capacity
+            && top.peek().is_some_and(|Reverse(worst)| {
+                worst.score.cmp(&record.score).then_with(|| {
+                    (record.source, record.line_number)
+                        .cmp(&(worst.path.as_path(), worst.line_number))
+                }) != Ordering::Less
+            })
+        {
+            return Ok(());
+        }
+
+        top.push(Reverse(ScoredMatch {
+            score: record.score,
+            path: record.source.to_path_buf(),
+            line_number: record.line_number,
+            line: record.line.to_string(),
+        }));
+        if top.len() > self.capacity {
+            top.pop();
+        }
+        Ok(())
+    }
+}
+
+impl MatchSink for TriageSink {
+    fn write_match(&self, record: &MatchRecord<'_>) -> io::Result<()> {
+        if self.capacity > 0 {
+            self.keep(record)?;
+        }
+        match &self.inner {
+            Some(inner) => inner.write_match(record),
+            None => Ok(()),
+        }
+    }
+
+    fn write_block(&self, source: &Path, lines: &[BlockLine<'_>]) -> io::Result<()> {
+        if self.capacity > 0 {
+            for line in lines {
+                if let BlockLine::Match(record) = line {
+                    self.keep(record)?;
+                }
+            }
+        }
+        match &self.inner {
+            Some(inner) => inner.write_block(source, lines),
+            None => Ok(()),
+        }
+    }
+
+    fn finish(&self) -> io::Result<()> {
+        match &self.inner {
+            Some(inner) => inner.finish(),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Write scored matches as `score<TAB>path:line<TAB>text` lines
+pub fn write_triage(path: &Path, matches: &[ScoredMatch]) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    for scored in matches {
+        writeln!(
+            writer,
+            "{}\t{}:{}\t{}",
+            scored.score,
+            scored.path.display(),
+            scored.line_number,
+            scored.line
+        )?;
+    }
+    writer.flush()
+}
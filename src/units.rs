@@ -0,0 +1,242 @@
+//! Human-friendly sizes and durations, like `250MB`, `1.5GiB` or `2h30m`
+//!
+//! Every flag and configuration value taking a size or a duration goes
+//! through `parse_size` and `parse_duration`, and describes the accepted
+//! units with `size_help` and `duration_help`, so they all agree.
+
+use serde::{Deserialize, Deserializer};
+use std::error::Error;
+use std::fmt;
+use std::time::Duration;
+
+/// Size units and their value in bytes; SI units are powers of 1000, IEC units of 1024
+pub const SIZE_UNITS: &[(&str, u64)] = &[
+    ("B", 1),
+    ("KB", 1000),
+    ("MB", 1000 * 1000),
+    ("GB", 1000 * 1000 * 1000),
+    ("TB", 1000 * 1000 * 1000 * 1000),
+    ("KiB", 1 << 10),
+    ("MiB", 1 << 20),
+    ("GiB", 1 << 30),
+    ("TiB", 1 << 40),
+];
+
+/// Duration units and their value in nanoseconds
+pub const DURATION_UNITS: &[(&str, u64)] = &[
+    ("ns", 1),
+    ("us", 1_000),
+    ("ms", 1_000_000),
+    ("s", 1_000_000_000),
+    ("m", 60 * 1_000_000_000),
+    ("h", 60 * 60 * 1_000_000_000),
+    ("d", 24 * 60 * 60 * 1_000_000_000),
+];
+
+/// Why a size or duration could not be parsed
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum UnitError {
+    /// Nothing but whitespace was given
+    Empty,
+    /// The value starts with `-`
+    Negative(String),
+    /// A number is missing or malformed, like the `1..5` of `1..5s`
+    InvalidNumber(String),
+    /// A duration component has no unit, like the `30` of `2h30`
+    MissingUnit(String),
+    /// The unit is not one of the accepted ones
+    UnknownUnit { unit: String, accepted: String },
+    /// The value does not fit in 64 bits of bytes or nanoseconds
+    Overflow(String),
+}
+
+impl fmt::Display for UnitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UnitError::Empty => write!(f, "empty value"),
+            UnitError::Negative(value) => write!(f, "'{}' is negative", value),
+            UnitError::InvalidNumber(value) => write!(f, "'{}' is not a number", value),
+            UnitError::MissingUnit(value) => write!(
+                f,
+                "'{}' needs a unit (accepted: {})",
+                value,
+                unit_list(DURATION_UNITS)
+            ),
+            UnitError::UnknownUnit { unit, accepted } => {
+                write!(f, "unknown unit '{}' (accepted: {})", unit, accepted)
+            }
+            UnitError::Overflow(value) => write!(f, "'{}' is too large", value),
+        }
+    }
+}
+
+impl Error for UnitError {}
+
+fn unit_list(units: &[(&str, u64)]) -> String {
+    units
+        .iter()
+        .map(|(name, _)| *name)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Help text for a size flag: `description`, followed by the accepted units
+pub fn size_help(description: &str) -> String {
+    format!(
+        "{} (a number of bytes, optionally with a unit: {}; like 250MB or 1.5GiB)",
+        description,
+        unit_list(SIZE_UNITS)
+    )
+}
+
+/// Help text for a duration flag: `description`, followed by the accepted units
+pub fn duration_help(description: &str) -> String {
+    format!(
+        "{} (one or more numbers with a unit: {}; like 500ms or 2h30m)",
+        description,
+        unit_list(DURATION_UNITS)
+    )
+}
+
+/// Parse a size like `4096`, `250MB` or `1.5GiB` into bytes
+///
+/// Units are case-insensitive, except that `KB`, `MB`, ... are powers of
+/// 1000 and `KiB`, `MiB`, ... powers of 1024. Fractions are rounded down to
+/// whole bytes.
+pub fn parse_size(text: &str) -> Result<u64, UnitError> {
+    let value = text.trim();
+    if value.is_empty() {
+        return Err(UnitError::Empty);
+    }
+    if value.starts_with('-') {
+        return Err(UnitError::Negative(value.to_string()));
+    }
+
+    let split = value
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let multiplier = match unit.trim() {
+        "" => 1,
+        unit => lookup(SIZE_UNITS, unit)?,
+    };
+
+    scale(value, number, multiplier)
+}
+
+/// Parse a duration like `500ms`, `90s` or `2h30m`
+///
+/// A duration is one or more numbers, each followed by a unit, optionally
+/// separated by spaces. Units are case-insensitive. `0` is accepted without a unit.
+pub fn parse_duration(text: &str) -> Result<Duration, UnitError> {
+    let value = text.trim();
+    if value.is_empty() {
+        return Err(UnitError::Empty);
+    }
+    if value.starts_with('-') {
+        return Err(UnitError::Negative(value.to_string()));
+    }
+    if value == "0" {
+        return Ok(Duration::ZERO);
+    }
+
+    let mut total: u64 = 0;
+    let mut rest = value;
+    while !rest.is_empty() {
+        let number_end = rest
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(rest.len());
+        let (number, after) = rest.split_at(number_end);
+        let after = after.trim_start();
+        let unit_end = after
+            .find(|c: char| !c.is_ascii_alphabetic())
+            .unwrap_or(after.len());
+        let (unit, after) = after.split_at(unit_end);
+
+        if number.is_empty() {
+            return Err(UnitError::InvalidNumber(value.to_string()));
+        }
+        if unit.is_empty() {
+            return Err(UnitError::MissingUnit(value.to_string()));
+        }
+
+        let nanos = scale(value, number, lookup(DURATION_UNITS, unit)?)?;
+        total = total
+            .checked_add(nanos)
+            .ok_or_else(|| UnitError::Overflow(value.to_string()))?;
+        rest = after.trim_start();
+    }
+
+    Ok(Duration::from_nanos(total))
+}
+
+/// Find `unit` in `units`, ignoring case but not the `i` of IEC units
+fn lookup(units: &[(&str, u64)], unit: &str) -> Result<u64, UnitError> {
+    units
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(unit))
+        .map(|(_, value)| *value)
+        .ok_or_else(|| UnitError::UnknownUnit {
+            unit: unit.to_string(),
+            accepted: unit_list(units),
+        })
+}
+
+/// `number * multiplier`, rounded down; errors mention the whole `value`
+fn scale(value: &str, number: &str, multiplier: u64) -> Result<u64, UnitError> {
+    let invalid = || UnitError::InvalidNumber(value.to_string());
+    let overflow = || UnitError::Overflow(value.to_string());
+
+    let (whole, fraction) = number.split_once('.').unwrap_or((number, ""));
+    if (whole.is_empty() && fraction.is_empty())
+        || !whole
+            .bytes()
+            .chain(fraction.bytes())
+            .all(|b| b.is_ascii_digit())
+    {
+        return Err(invalid());
+    }
+
+    let whole: u128 = match whole {
+        "" => 0,
+        whole => whole.parse().map_err(|_| overflow())?,
+    };
+    let mut total = whole.checked_mul(multiplier as u128).ok_or_else(overflow)?;
+    // Digits past what a u64 multiplier can resolve do not change the result
+    let fraction = &fraction[..fraction.len().min(20)];
+    if !fraction.is_empty() {
+        let numerator: u128 = fraction.parse().map_err(|_| invalid())?;
+        let denominator = 10u128.pow(fraction.len() as u32);
+        total = total
+            .checked_add(numerator * multiplier as u128 / denominator)
+            .ok_or_else(overflow)?;
+    }
+
+    u64::try_from(total).map_err(|_| overflow())
+}
+
+/// A size or duration in a configuration file: a string with units or a plain number
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawValue {
+    Number(u64),
+    Text(String),
+}
+
+/// `deserialize_with` helper reading a size, either `"250MB"` or a number of bytes
+pub fn deserialize_size<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+    match RawValue::deserialize(deserializer)? {
+        RawValue::Number(bytes) => Ok(bytes),
+        RawValue::Text(text) => parse_size(&text).map_err(serde::de::Error::custom),
+    }
+}
+
+/// `deserialize_with` helper reading a duration, either `"2h30m"` or a number of seconds
+pub fn deserialize_duration<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Duration, D::Error> {
+    match RawValue::deserialize(deserializer)? {
+        RawValue::Number(seconds) => Ok(Duration::from_secs(seconds)),
+        RawValue::Text(text) => parse_duration(&text).map_err(serde::de::Error::custom),
+    }
+}
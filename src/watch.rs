@@ -0,0 +1,167 @@
+//! Watch mode: a followed run woken by file system events, reporting its
+//! matches to a callback as they are found
+//!
+//! Only built with the `watch` feature, which pulls in `notify`.
+
+use crate::{CancelToken, MatchRecordBuf, ParserConfig, ParserResult, run_parser};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use tokio::runtime;
+use tokio::sync::{Notify, mpsc};
+
+/// What a watched run reports to its callback
+pub enum MatchEvent {
+    /// A line matched, in the files found at the start or in what was
+    /// written to the log folder since
+    Match(MatchRecordBuf),
+    /// The run ended, once stopped or because it failed; always the last
+    /// event
+    Finished(io::Result<ParserResult>),
+}
+
+/// Watched run started by `run_parser_watch`; stopped when dropped
+pub struct WatchHandle {
+    cancel: CancelToken,
+    wakeup: Arc<Notify>,
+    thread: Option<JoinHandle<()>>,
+    _watcher: RecommendedWatcher,
+}
+
+impl WatchHandle {
+    /// Stop watching and wait for the run to end, after which the callback
+    /// has received `MatchEvent::Finished`
+    ///
+    /// Blocks the calling thread, so it must not be called from the
+    /// callback itself.
+    pub fn stop(mut self) {
+        self.shutdown();
+    }
+
+    /// Whether the run ended, like on a failure, without being stopped
+    pub fn is_finished(&self) -> bool {
+        self.thread.as_ref().is_none_or(JoinHandle::is_finished)
+    }
+
+    fn shutdown(&mut self) {
+        self.cancel.cancel();
+        self.wakeup.notify_one();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for WatchHandle {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+/// Search the log folder, then keep searching the new files and the lines
+/// appended to the others until the returned handle is stopped
+///
+/// The files found at the start are scanned as by `run_parser`; the run then
+/// follows the log folder as with `ParserConfig::follow`, checking it as soon
+/// as a file of the folder changes, and every `follow_interval` in case an
+/// event was missed. Each followed file is read from where the previous read
+/// stopped, so only what was appended is scanned.
+///
+/// The run has a thread and runtime of its own, and `callback` is called on
+/// that thread. The matches also reach the output of `config`; set
+/// `output_target` to `OutputTarget::Discard` to only report them.
+///
+/// Only available with the `watch` feature, so that builds without watch
+/// mode do not depend on `notify`.
+///
+/// ```no_run
+/// # use elysiumparser::{MatchEvent, OutputTarget, ParserConfig, run_parser_watch};
+/// # fn example() -> std::io::Result<()> {
+/// let mut config = ParserConfig::builder()
+///     .log_folder("logs/application")
+///     .add_search("error", "")
+///     .build()
+///     .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+/// config.output_target = OutputTarget::Discard;
+///
+/// let watch = run_parser_watch(config, |event| match event {
+///     MatchEvent::Match(record) => println!("{}: {}", record.file.display(), record.line),
+///     MatchEvent::Finished(result) => println!("{:?}", result.map(|r| r.total_matches)),
+/// })?;
+/// std::thread::sleep(std::time::Duration::from_secs(60));
+/// watch.stop();
+/// # Ok(())
+/// # }
+/// ```
+pub fn run_parser_watch(
+    mut config: ParserConfig,
+    callback: impl Fn(MatchEvent) + Send + Sync + 'static,
+) -> io::Result<WatchHandle> {
+    if config.input.is_some() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "watch mode reads the log folder, not an input stream",
+        ));
+    }
+    let cancel = config.cancel.get_or_insert_with(CancelToken::new).clone();
+    let wakeup = Arc::new(Notify::new());
+    let (sender, mut receiver) = mpsc::unbounded_channel();
+    config.follow = true;
+    config.follow_wakeup = Some(Arc::clone(&wakeup));
+    config.match_sender = Some(sender);
+
+    // The folder must exist to be watched; `run_parser` would create it too
+    fs::create_dir_all(&config.log_folder)?;
+    let mode = match config.recursive {
+        true => RecursiveMode::Recursive,
+        false => RecursiveMode::NonRecursive,
+    };
+    let events = Arc::clone(&wakeup);
+    // Any event, even a failed one, wakes the run; its next check of the
+    // folder finds out what changed
+    let mut watcher = notify::recommended_watcher(move |_: notify::Result<notify::Event>| {
+        events.notify_one();
+    })
+    .map_err(io::Error::other)?;
+    watcher
+        .watch(Path::new(&config.log_folder), mode)
+        .map_err(io::Error::other)?;
+    // The other folders are not created: the run reports those missing, and
+    // the ones that appear later are only checked every `follow_interval`
+    for log_folder in &config.log_folders {
+        let _ = watcher.watch(Path::new(log_folder), mode);
+    }
+
+    let thread = thread::Builder::new()
+        .name("elysiumparser-watch".to_string())
+        .spawn(move || {
+            let result = runtime::Builder::new_multi_thread()
+                .enable_all()
+                .build()
+                .and_then(|runtime| {
+                    runtime.block_on(async {
+                        let report = async {
+                            while let Some(record) = receiver.recv().await {
+                                callback(MatchEvent::Match(record));
+                            }
+                        };
+                        // The channel closes once the run ends and drops
+                        // its sender, so every match is reported before
+                        // the result
+                        let (result, ()) = tokio::join!(run_parser(config, None), report);
+                        result
+                    })
+                });
+            callback(MatchEvent::Finished(result));
+        })?;
+
+    Ok(WatchHandle {
+        cancel,
+        wakeup,
+        thread: Some(thread),
+        _watcher: watcher,
+    })
+}
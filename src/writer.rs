@@ -0,0 +1,275 @@
+//! Output log written by a task of its own: the readers hand it their lines
+//! over a channel instead of taking turns on a lock around the file
+//!
+//! The task is a blocking loop, so the channel is a bounded `std` one: the
+//! readers wait on it while it is full without blocking on a runtime future,
+//! and the task can wait for lines with a timeout.
+
+use crate::sink::jsonl_line;
+use crate::{BlockLine, MatchRecord, MatchSink};
+use std::cell::RefCell;
+use std::fmt::Write as _;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, SyncSender};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use tokio::task;
+
+/// Lines waiting for the writer task of a run before the readers wait too
+pub const DEFAULT_WRITER_CHANNEL_CAPACITY: usize = 1024;
+
+/// Time the writer task waits for more lines before flushing those it holds
+const FLUSH_DELAY: Duration = Duration::from_millis(20);
+
+/// Bytes of lines a batch holds before sending them on
+const BATCH_BYTES: usize = 64 * 1024;
+
+thread_local! {
+    /// Batch open on this thread by `WriterSink::batch`, with the address of
+    /// its sink
+    static BATCH: RefCell<Option<(usize, String)>> = const { RefCell::new(None) };
+}
+
+enum Message {
+    /// Lines to write as they are, newlines included
+    Lines(String),
+    /// Write out everything sent before and report the first failure
+    Flush(SyncSender<io::Result<()>>),
+    /// Flush and close the writer, ending the task
+    Close(SyncSender<io::Result<()>>),
+}
+
+/// First write failure of a writer task, handed back to every later caller
+#[derive(Default)]
+struct Failure(OnceLock<(io::ErrorKind, String)>);
+
+impl Failure {
+    fn set(&self, error: &io::Error) {
+        let _ = self.0.set((error.kind(), error.to_string()));
+    }
+
+    fn check(&self) -> io::Result<()> {
+        match self.0.get() {
+            Some((kind, message)) => Err(io::Error::new(*kind, message.clone())),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Sink writing plain or JSONL lines from a dedicated writer task
+///
+/// Readers format their lines and send them over a bounded channel, one by
+/// one or a file at a time with `batch`; the task owns the writer and
+/// buffers it, flushing once no line came for a few milliseconds, so lines
+/// reach the output promptly once matches slow down. Blocks are sent whole,
+/// so they are never interleaved with other files.
+///
+/// Writes are reported as failed from the first one after the task met an
+/// error, and by `finish` and `close`.
+pub struct WriterSink {
+    sender: SyncSender<Message>,
+    failure: Arc<Failure>,
+    jsonl: bool,
+}
+
+impl WriterSink {
+    /// Start the writer task of `writer`, letting `capacity` lines wait for it
+    ///
+    /// The task runs on the blocking threads of the current Tokio runtime,
+    /// so this must be called within one.
+    pub fn spawn(writer: impl Write + Send + 'static, capacity: usize) -> Self {
+        let (sender, receiver) = mpsc::sync_channel(capacity.max(1));
+        let failure = Arc::new(Failure::default());
+        let task_failure = Arc::clone(&failure);
+        task::spawn_blocking(move || write_lines(writer, receiver, &task_failure));
+        Self {
+            sender,
+            failure,
+            jsonl: false,
+        }
+    }
+
+    /// Write each match as a JSON object, like `JsonlSink`
+    pub fn jsonl(mut self) -> Self {
+        self.jsonl = true;
+        self
+    }
+
+    /// Write a line that is not a match, like a header
+    pub fn write_line(&self, line: &str) -> io::Result<()> {
+        self.send_lines(format!("{}\n", line))
+    }
+
+    /// Run `scan`, holding the lines it writes to this sink from this thread
+    /// and sending them together, every `BATCH_BYTES` and once it returns
+    ///
+    /// Meant for the scan of a whole file, so its lines take one trip over
+    /// the channel instead of one each. The lines held are sent even if
+    /// `scan` panics. Batches do not nest: within one, `scan` runs as is.
+    pub fn batch<T>(&self, scan: impl FnOnce() -> T) -> T {
+        let opened = BATCH.with_borrow_mut(|batch| match batch {
+            Some(_) => false,
+            None => {
+                *batch = Some((self.address(), String::new()));
+                true
+            }
+        });
+        if !opened {
+            return scan();
+        }
+
+        /// Sends the rest of the batch when the scan ends, however it ends
+        struct Guard<'a>(&'a WriterSink);
+
+        impl Drop for Guard<'_> {
+            fn drop(&mut self) {
+                if let Some((_, lines)) = BATCH.take()
+                    && !lines.is_empty()
+                {
+                    // A failure is reported by the next write or `finish`
+                    let _ = self.0.send(Message::Lines(lines));
+                }
+            }
+        }
+
+        let _guard = Guard(self);
+        scan()
+    }
+
+    /// Flush what was written and close the writer, so the file can be moved
+    ///
+    /// Later writes fail.
+    pub fn close(&self) -> io::Result<()> {
+        let (reply, replied) = mpsc::sync_channel(1);
+        self.send(Message::Close(reply))?;
+        replied.recv().unwrap_or_else(|_| Err(stopped()))
+    }
+
+    fn address(&self) -> usize {
+        self as *const Self as usize
+    }
+
+    /// Send `lines`, or add them to the batch this thread holds for them
+    fn send_lines(&self, lines: String) -> io::Result<()> {
+        self.failure.check()?;
+        let full = BATCH.with_borrow_mut(|batch| match batch {
+            Some((sink, batch)) if *sink == self.address() => {
+                batch.push_str(&lines);
+                Ok((batch.len() >= BATCH_BYTES).then(|| std::mem::take(batch)))
+            }
+            _ => Err(lines),
+        });
+        match full {
+            Ok(Some(lines)) | Err(lines) => self.send(Message::Lines(lines)),
+            Ok(None) => Ok(()),
+        }
+    }
+
+    fn send(&self, message: Message) -> io::Result<()> {
+        self.failure.check()?;
+        self.sender.send(message).map_err(|_| stopped())
+    }
+
+    fn format_match(&self, lines: &mut String, record: &MatchRecord<'_>) -> io::Result<()> {
+        match self.jsonl {
+            true => lines.push_str(&jsonl_line(record)?),
+            false => lines.push_str(record.line),
+        }
+        lines.push('\n');
+        Ok(())
+    }
+}
+
+impl MatchSink for WriterSink {
+    fn write_match(&self, record: &MatchRecord<'_>) -> io::Result<()> {
+        let mut lines = String::with_capacity(record.line.len() + 1);
+        self.format_match(&mut lines, record)?;
+        self.send_lines(lines)
+    }
+
+    /// Plain blocks are written like by a `Mutex` writer; JSONL blocks only
+    /// keep their matches
+    fn write_block(&self, _source: &Path, block: &[BlockLine<'_>]) -> io::Result<()> {
+        let mut lines = String::new();
+        for line in block {
+            match line {
+                BlockLine::Match(record) if self.jsonl => self.format_match(&mut lines, record)?,
+                _ if self.jsonl => {}
+                BlockLine::Separator => lines.push_str("--\n"),
+                BlockLine::Context { line, .. } => {
+                    let _ = writeln!(lines, "- {}", line);
+                }
+                BlockLine::Match(record) => {
+                    let _ = writeln!(lines, "> {}", record.line);
+                }
+            }
+        }
+        if lines.is_empty() {
+            return Ok(());
+        }
+        self.send_lines(lines)
+    }
+
+    fn finish(&self) -> io::Result<()> {
+        let (reply, replied) = mpsc::sync_channel(1);
+        self.send(Message::Flush(reply))?;
+        replied.recv().unwrap_or_else(|_| Err(stopped()))
+    }
+}
+
+/// Body of the writer task: write the lines received until closed
+fn write_lines(writer: impl Write, receiver: Receiver<Message>, failure: &Failure) {
+    let mut writer = BufWriter::new(writer);
+    loop {
+        // Buffered lines are flushed once no more come for a while, unless
+        // the writer already failed
+        let pending = !writer.buffer().is_empty() && failure.check().is_ok();
+        let message = match pending {
+            true => receiver.recv_timeout(FLUSH_DELAY),
+            false => receiver.recv().map_err(|_| RecvTimeoutError::Disconnected),
+        };
+        let message = match message {
+            Ok(message) => message,
+            Err(RecvTimeoutError::Timeout) => {
+                if let Err(e) = writer.flush() {
+                    failure.set(&e);
+                }
+                continue;
+            }
+            Err(RecvTimeoutError::Disconnected) => {
+                let _ = writer.flush();
+                return;
+            }
+        };
+        match message {
+            Message::Lines(lines) => {
+                // Once a write failed, the lines are dropped until the
+                // readers notice
+                if failure.check().is_ok()
+                    && let Err(e) = writer.write_all(lines.as_bytes())
+                {
+                    failure.set(&e);
+                }
+            }
+            Message::Flush(reply) => {
+                let flushed = failure.check().and_then(|()| writer.flush());
+                if let Err(e) = &flushed {
+                    failure.set(e);
+                }
+                let _ = reply.send(flushed);
+            }
+            Message::Close(reply) => {
+                let flushed = failure.check().and_then(|()| writer.flush());
+                drop(writer);
+                drop(receiver);
+                let _ = reply.send(flushed);
+                return;
+            }
+        }
+    }
+}
+
+fn stopped() -> io::Error {
+    io::Error::other("output writer stopped")
+}
@@ -0,0 +1,105 @@
+use elysiumparser::bench::{Sample, bench_strategy, counts_agree};
+use elysiumparser::{MatchStrategy, SearchSet, SearchTerm, add_search_with_expression};
+
+fn terms() -> Vec<SearchTerm> {
+    let mut terms = Vec::new();
+    add_search_with_expression(&mut terms, "error", "disk | timeout").unwrap();
+    add_search_with_expression(&mut terms, "warn", "fuzzy:retrying:1 & db").unwrap();
+    add_search_with_expression(&mut terms, "", "panic & !expected").unwrap();
+    terms
+}
+
+fn sample() -> Sample {
+    let lines = [
+        "error: disk full on /var",
+        "error: connection timeout",
+        "error: permission denied",
+        "warn: db retryng query",
+        "warn: cache retrying query",
+        "thread main: panic at lib.rs",
+        "test: expected panic",
+        "info: all good",
+        "",
+    ];
+    Sample {
+        lines: lines.iter().map(|line| line.to_string()).collect(),
+        bytes: 0,
+    }
+}
+
+#[test]
+fn strategies_find_the_same_terms() {
+    let terms = terms();
+    let sets: Vec<_> = MatchStrategy::ALL
+        .iter()
+        .map(|strategy| SearchSet::new(&terms, "", *strategy))
+        .collect();
+
+    for line in &sample().lines {
+        let expected = sets[0].matching_term(line);
+        for set in &sets[1..] {
+            assert_eq!(
+                set.matching_term(line),
+                expected,
+                "{} on {:?}",
+                set.strategy(),
+                line
+            );
+        }
+    }
+    assert_eq!(sets[0].matching_term("warn: db retryng query"), Some(1));
+    assert_eq!(sets[0].matching_term("error: permission denied"), None);
+}
+
+#[test]
+fn strategies_respect_the_line_filter() {
+    let terms = terms();
+    for strategy in MatchStrategy::ALL {
+        let set = SearchSet::new(&terms, "/var", strategy);
+        assert_eq!(set.matching_term("error: disk full on /var"), Some(0));
+        assert_eq!(set.matching_term("error: connection timeout"), None);
+    }
+}
+
+#[test]
+fn bench_reports_identical_counts() {
+    let timings: Vec<_> = MatchStrategy::ALL
+        .iter()
+        .map(|strategy| bench_strategy(&terms(), "", *strategy, &sample(), 3))
+        .collect();
+
+    assert!(counts_agree(&timings));
+    assert_eq!(timings[0].matches, 4);
+    assert_eq!(timings[0].lines, 27);
+}
+
+#[test]
+fn strategy_names_round_trip() {
+    for strategy in MatchStrategy::ALL {
+        assert_eq!(strategy.to_string().parse::<MatchStrategy>(), Ok(strategy));
+    }
+    assert!("fast".parse::<MatchStrategy>().is_err());
+}
+
+#[test]
+fn identical_atoms_are_interned() {
+    let mut terms = Vec::new();
+    for keyword in ["get", "put", "post", "delete"] {
+        add_search_with_expression(
+            &mut terms,
+            keyword,
+            "re:req_id=[0-9a-f]{32} & (timeout | re:req_id=[0-9a-f]{32}) & timeout",
+        )
+        .unwrap();
+    }
+
+    for strategy in MatchStrategy::ALL {
+        let set = SearchSet::new(&terms, "req", strategy);
+        // req, four keywords, the regex and timeout
+        assert_eq!(set.unique_atoms(), 7);
+
+        let line = format!("post req_id={} timeout", "ab".repeat(16));
+        assert_eq!(set.matching_term(&line), Some(2), "{}", strategy);
+        assert_eq!(set.matching_term("post req_id=12 timeout"), None);
+    }
+}
@@ -0,0 +1,282 @@
+use elysiumparser::{
+    ConfigError, ParseErrorKind, ParserConfig, ParserConfigBuilder, RunMode, add_search_with_case,
+    run_parser,
+};
+use std::fs;
+use std::path::Path;
+
+/// Builder reading from and writing to `dir`
+fn builder_for(dir: &Path) -> ParserConfigBuilder {
+    ParserConfig::builder()
+        .log_folder(dir.to_string_lossy())
+        .output_log(dir.join("output.log").to_string_lossy())
+}
+
+#[tokio::test]
+async fn built_configs_run_like_literal_ones() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(
+        dir.path().join("app.log"),
+        "ERROR database connection lost\nerror timeout\nerror disk full\nwarning low memory\n",
+    )
+    .unwrap();
+
+    let config = builder_for(dir.path())
+        .add_search_expression("error", "(database & connection) | timeout")
+        .add_search("warning", "memory")
+        .workers(2)
+        .build()
+        .unwrap();
+    assert_eq!(config.search_terms.len(), 2);
+    assert_eq!(config.workers, Some(2));
+
+    let result = run_parser(config, None).await.unwrap();
+    assert_eq!(result.total_matches, 3);
+}
+
+#[test]
+fn terms_are_kept_as_written_and_patterns_follow_the_final_case_sensitivity() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let config = builder_for(dir.path())
+        .line_filter("Payment")
+        .add_search("Timeout", "Gateway")
+        .add_search("/Time(out)?/", "")
+        .build()
+        .unwrap();
+    // Lowercased as they are compiled, if at all
+    assert_eq!(config.line_filter, "Payment");
+    assert_eq!(config.search_terms[0].to_string(), "Timeout + Gateway");
+    let pattern = config.search_terms[1].keyword_pattern.as_ref().unwrap();
+    assert!(pattern.is_case_insensitive());
+
+    // Set after the terms were added, it still applies to them
+    let config = builder_for(dir.path())
+        .add_search("/Time(out)?/", "")
+        .case_sensitive(true)
+        .build()
+        .unwrap();
+    let pattern = config.search_terms[0].keyword_pattern.as_ref().unwrap();
+    assert!(!pattern.is_case_insensitive());
+}
+
+#[test]
+fn configs_without_terms_or_line_filter_are_rejected() {
+    let dir = tempfile::tempdir().unwrap();
+
+    assert_eq!(
+        builder_for(dir.path()).build().err(),
+        Some(ConfigError::NoSearchTerms)
+    );
+    assert!(builder_for(dir.path()).line_filter("error").build().is_ok());
+}
+
+#[test]
+fn zero_workers_are_rejected() {
+    let dir = tempfile::tempdir().unwrap();
+    let error = builder_for(dir.path())
+        .add_search("error", "")
+        .workers(0)
+        .build()
+        .err()
+        .unwrap();
+
+    assert_eq!(error, ConfigError::ZeroWorkers);
+    assert_eq!(error.to_string(), "workers must be at least 1");
+}
+
+#[test]
+fn invalid_expressions_name_their_keyword() {
+    let dir = tempfile::tempdir().unwrap();
+    let error = builder_for(dir.path())
+        .add_search_expression("error", "database &")
+        .build()
+        .err()
+        .unwrap();
+
+    let ConfigError::InvalidExpression { keyword, error } = &error else {
+        panic!("unexpected error: {:?}", error);
+    };
+    assert_eq!(keyword, "error");
+    assert_eq!(error.kind, ParseErrorKind::DanglingOperator('&'));
+}
+
+#[test]
+fn malformed_filename_globs_are_rejected() {
+    let dir = tempfile::tempdir().unwrap();
+    let error = builder_for(dir.path())
+        .add_search("error", "")
+        .filename_filter("app-[.log")
+        .build()
+        .err()
+        .unwrap();
+
+    assert!(matches!(error, ConfigError::InvalidFilenameFilter { .. }));
+    assert!(
+        error
+            .to_string()
+            .starts_with("invalid filename filter 'app-[.log'")
+    );
+}
+
+#[test]
+fn malformed_include_and_exclude_patterns_are_rejected() {
+    let dir = tempfile::tempdir().unwrap();
+    let error = builder_for(dir.path())
+        .add_search("error", "")
+        .include("app-*.log")
+        .exclude("2024/[")
+        .build()
+        .err()
+        .unwrap();
+
+    assert!(
+        error
+            .to_string()
+            .starts_with("invalid filename filter '2024/['")
+    );
+}
+
+#[test]
+fn output_folders_must_exist_unless_run_parser_creates_them() {
+    let dir = tempfile::tempdir().unwrap();
+    let missing = dir.path().join("missing");
+
+    let error = builder_for(dir.path())
+        .add_search("error", "")
+        .output_log(missing.join("output.log").to_string_lossy())
+        .build()
+        .err()
+        .unwrap();
+    assert_eq!(error, ConfigError::MissingOutputFolder(missing.clone()));
+
+    // The log folder and its parents are created by the run
+    let log_folder = missing.join("logs");
+    for output_log in [log_folder.join("output.log"), missing.join("output.log")] {
+        let built = ParserConfig::builder()
+            .log_folder(log_folder.to_string_lossy())
+            .output_log(output_log.to_string_lossy())
+            .add_search("error", "")
+            .build();
+        assert!(built.is_ok(), "{}", output_log.display());
+    }
+
+    // Nothing is written when only counting
+    let built = builder_for(dir.path())
+        .add_search("error", "")
+        .output_log(missing.join("output.log").to_string_lossy())
+        .mode(RunMode::CountOnly)
+        .build();
+    assert!(built.is_ok());
+}
+
+#[test]
+fn empty_log_folders_are_rejected() {
+    let error = ParserConfig::builder()
+        .log_folder("")
+        .add_search("error", "")
+        .build()
+        .err()
+        .unwrap();
+
+    assert_eq!(error, ConfigError::EmptyLogFolder);
+}
+
+#[test]
+fn output_logs_searched_as_input_are_rejected() {
+    let dir = tempfile::tempdir().unwrap();
+    let log_folder = dir.path().join("logs");
+    fs::create_dir(&log_folder).unwrap();
+    // Written through another path than the log folder, so not recognized
+    let output_log = dir.path().join("logs/../logs/output.log");
+
+    let error = ParserConfig::builder()
+        .log_folder(log_folder.to_string_lossy())
+        .output_log(output_log.to_string_lossy())
+        .add_search("error", "")
+        .build()
+        .err()
+        .unwrap();
+    assert_eq!(error, ConfigError::OutputInsideLogFolder(output_log));
+
+    // Output logs under the log folder as written are skipped by the run
+    let built = builder_for(&log_folder).add_search("error", "").build();
+    assert!(built.is_ok());
+
+    // Subfolders are only searched recursively
+    let nested = dir.path().join("logs/archive/../archive/output.log");
+    fs::create_dir(log_folder.join("archive")).unwrap();
+    let builder = || {
+        ParserConfig::builder()
+            .log_folder(log_folder.to_string_lossy())
+            .output_log(nested.to_string_lossy())
+            .add_search("error", "")
+    };
+    assert!(builder().build().is_ok());
+    assert!(matches!(
+        builder().recursive(true).build(),
+        Err(ConfigError::OutputInsideLogFolder(_))
+    ));
+}
+
+#[test]
+fn output_logs_searched_in_another_log_folder_are_rejected() {
+    let dir = tempfile::tempdir().unwrap();
+    let app = dir.path().join("app");
+    let nginx = dir.path().join("nginx");
+    fs::create_dir(&app).unwrap();
+    fs::create_dir(&nginx).unwrap();
+    let output_log = dir.path().join("nginx/../nginx/output.log");
+
+    let error = ParserConfig::builder()
+        .log_folder(app.to_string_lossy())
+        .add_log_folder(nginx.to_string_lossy())
+        .output_log(output_log.to_string_lossy())
+        .add_search("error", "")
+        .build()
+        .err()
+        .unwrap();
+
+    assert_eq!(error, ConfigError::OutputInsideLogFolder(output_log));
+}
+
+#[test]
+fn output_logs_among_the_input_files_are_rejected() {
+    let dir = tempfile::tempdir().unwrap();
+    let output_log = dir.path().join("output.log");
+    fs::write(&output_log, "").unwrap();
+    let builder = || {
+        builder_for(dir.path())
+            .add_input_file(dir.path().join("app.txt"))
+            .add_search("error", "")
+    };
+
+    assert!(builder().build().is_ok());
+    let error = builder()
+        .add_input_file(
+            dir.path()
+                .join("../")
+                .join(dir.path().file_name().unwrap())
+                .join("output.log"),
+        )
+        .build()
+        .err()
+        .unwrap();
+    assert_eq!(error, ConfigError::OutputInsideLogFolder(output_log));
+}
+
+#[test]
+fn compiled_terms_keep_their_case() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut terms = Vec::new();
+    add_search_with_case(&mut terms, "Timeout", "", true).unwrap();
+
+    let config = builder_for(dir.path())
+        .search_term(terms.remove(0))
+        .add_search("Error", "")
+        .build()
+        .unwrap();
+
+    assert_eq!(config.search_terms[0].keyword, "Timeout");
+    assert_eq!(config.search_terms[1].keyword, "Error");
+}
@@ -0,0 +1,32 @@
+use elysiumparser::{InputFormat, ScanOptions, add_search, process_reader};
+use std::io::Cursor;
+use std::path::Path;
+use std::sync::Mutex;
+
+/// Scan `input`, read from `source` in `input_format`, for `keyword`,
+/// returning the match count, lines and output
+pub fn scan(
+    input: &str,
+    source: &str,
+    keyword: &str,
+    input_format: InputFormat,
+) -> (usize, usize, String) {
+    let mut search_terms = Vec::new();
+    add_search(&mut search_terms, keyword, "");
+    let options = ScanOptions {
+        input_format,
+        ..Default::default()
+    };
+    let output = Mutex::new(Vec::new());
+
+    let scan = process_reader(
+        Cursor::new(input),
+        Path::new(source),
+        &search_terms,
+        &options,
+        Some(&output),
+    );
+
+    let output = String::from_utf8(output.into_inner().unwrap()).unwrap();
+    (scan.matches, scan.lines_scanned, output)
+}
@@ -0,0 +1,172 @@
+use elysiumparser::{
+    CompressionKind, ParserConfig, add_search, is_compressed_file, open_log_reader, run_parser,
+};
+use std::fs;
+use std::io::{Read, Write};
+use std::path::Path;
+
+const LOG: &[u8] = b"info: started\nerror: disk full\nerror: retrying\n";
+
+/// Scan `dir` for "error" and return the processed file and match counts
+async fn scan(dir: &Path) -> (usize, usize) {
+    let mut config = ParserConfig {
+        log_folder: dir.to_string_lossy().into_owned(),
+        output_log: dir.join("output.log").to_string_lossy().into_owned(),
+        ..Default::default()
+    };
+    add_search(&mut config.search_terms, "error", "");
+
+    let result = run_parser(config, None).await.unwrap();
+    (result.processed_files, result.total_matches)
+}
+
+#[test]
+fn kinds_are_detected_from_the_extension() {
+    let dir = tempfile::tempdir().unwrap();
+    let gz = dir.path().join("app.log.gz");
+    fs::write(&gz, b"").unwrap();
+
+    assert_eq!(is_compressed_file(&gz), Some(CompressionKind::Gzip));
+    assert_eq!(is_compressed_file(&dir.path().join("missing.gz")), None);
+    assert_eq!(
+        CompressionKind::from_path(Path::new("debug.log.gz")),
+        Some(CompressionKind::Gzip)
+    );
+    assert_eq!(CompressionKind::from_path(Path::new("app.log")), None);
+}
+
+#[cfg(not(feature = "bzip2"))]
+#[tokio::test]
+async fn disabled_formats_are_skipped() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("app.log.bz2"), LOG).unwrap();
+
+    assert_eq!(scan(dir.path()).await, (0, 0));
+}
+
+#[cfg(feature = "bzip2")]
+#[tokio::test]
+async fn bzip2_archives_are_scanned() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = fs::File::create(dir.path().join("app.log.bz2")).unwrap();
+    let mut encoder = bzip2::write::BzEncoder::new(file, bzip2::Compression::default());
+    encoder.write_all(LOG).unwrap();
+    encoder.finish().unwrap();
+
+    assert_eq!(scan(dir.path()).await, (1, 2));
+}
+
+#[cfg(feature = "zstd")]
+#[tokio::test]
+async fn zstd_archives_are_scanned() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(
+        dir.path().join("app.log.zst"),
+        zstd::encode_all(LOG, 0).unwrap(),
+    )
+    .unwrap();
+
+    assert_eq!(scan(dir.path()).await, (1, 2));
+}
+
+#[cfg(feature = "xz")]
+#[tokio::test]
+async fn xz_archives_are_scanned() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = fs::File::create(dir.path().join("app.log.xz")).unwrap();
+    let mut encoder = xz2::write::XzEncoder::new(file, 6);
+    encoder.write_all(LOG).unwrap();
+    encoder.finish().unwrap();
+
+    assert_eq!(scan(dir.path()).await, (1, 2));
+}
+
+#[tokio::test]
+async fn gzip_archives_are_scanned() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = fs::File::create(dir.path().join("app.log.gz")).unwrap();
+    let mut encoder = flate2::write::GzEncoder::new(file, flate2::Compression::default());
+    encoder.write_all(LOG).unwrap();
+    encoder.finish().unwrap();
+
+    assert_eq!(scan(dir.path()).await, (1, 2));
+}
+
+fn gzip(data: &[u8]) -> Vec<u8> {
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(data).unwrap();
+    encoder.finish().unwrap()
+}
+
+#[test]
+fn kinds_are_detected_from_the_first_bytes() {
+    assert_eq!(
+        CompressionKind::from_magic(&gzip(LOG)),
+        Some(CompressionKind::Gzip)
+    );
+    assert_eq!(CompressionKind::from_magic(LOG), None);
+    assert_eq!(CompressionKind::from_magic(b""), None);
+    // Text that happens to start like a bzip2 header
+    assert_eq!(
+        CompressionKind::from_magic(b"BZh9 is not an archive\n"),
+        None
+    );
+}
+
+#[tokio::test]
+async fn misnamed_archives_are_decompressed() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("app.log"), gzip(LOG)).unwrap();
+
+    assert_eq!(scan(dir.path()).await, (1, 2));
+
+    let mut text = String::new();
+    open_log_reader(&dir.path().join("app.log"))
+        .unwrap()
+        .read_to_string(&mut text)
+        .unwrap();
+    assert_eq!(text.as_bytes(), LOG);
+}
+
+#[tokio::test]
+async fn corrupt_archives_are_reported_without_stopping_the_run() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut corrupt = gzip(LOG);
+    corrupt.truncate(12);
+    fs::write(dir.path().join("a.log.gz"), corrupt).unwrap();
+    fs::write(dir.path().join("b.log"), LOG).unwrap();
+
+    let mut config = ParserConfig {
+        log_folder: dir.path().to_string_lossy().into_owned(),
+        output_log: dir.path().join("output.log").to_string_lossy().into_owned(),
+        ..Default::default()
+    };
+    add_search(&mut config.search_terms, "error", "");
+    let result = run_parser(config, None).await.unwrap();
+
+    assert_eq!(result.total_matches, 2);
+    assert_eq!(result.errors.len(), 1);
+    assert!(result.errors[0].0.ends_with("a.log.gz"));
+}
+
+#[cfg(feature = "zstd")]
+#[test]
+fn zstd_is_detected_from_the_first_bytes() {
+    assert_eq!(
+        CompressionKind::from_magic(&zstd::encode_all(LOG, 0).unwrap()),
+        Some(CompressionKind::Zstd)
+    );
+}
+
+#[cfg(feature = "bzip2")]
+#[test]
+fn bzip2_is_detected_from_the_first_bytes() {
+    for data in [LOG, b""] {
+        let mut encoder = bzip2::write::BzEncoder::new(Vec::new(), bzip2::Compression::default());
+        encoder.write_all(data).unwrap();
+        assert_eq!(
+            CompressionKind::from_magic(&encoder.finish().unwrap()),
+            Some(CompressionKind::Bzip2)
+        );
+    }
+}
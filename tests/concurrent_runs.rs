@@ -0,0 +1,96 @@
+use elysiumparser::{ParserConfig, add_search, run_parser};
+use std::fs;
+use std::path::Path;
+
+/// Build a config over the shared `dir`, writing `output` inside it
+fn config_for(dir: &Path, output: &str, keyword: &str) -> ParserConfig {
+    let mut config = ParserConfig {
+        log_folder: dir.to_string_lossy().into_owned(),
+        output_log: dir.join(output).to_string_lossy().into_owned(),
+        workers: Some(2),
+        ..Default::default()
+    };
+    add_search(&mut config.search_terms, keyword, "");
+    config
+}
+
+fn file_names(dir: &Path) -> Vec<String> {
+    let mut names: Vec<String> = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .collect();
+    names.sort();
+    names
+}
+
+#[tokio::test]
+async fn runs_over_the_same_folder_do_not_interfere() {
+    let dir = tempfile::tempdir().unwrap();
+    for index in 0..8 {
+        let lines: String = (0..200)
+            .map(|line| match line % 4 {
+                0 => format!("error {} in service {}\n", line, index),
+                1 => format!("warning {} in service {}\n", line, index),
+                _ => format!("info {} in service {}\n", line, index),
+            })
+            .collect();
+        fs::write(dir.path().join(format!("service{}.log", index)), lines).unwrap();
+    }
+
+    let errors = config_for(dir.path(), "errors.log", "error");
+    let warnings = config_for(dir.path(), "warnings.log", "warning");
+
+    let (errors, warnings) = tokio::join!(run_parser(errors, None), run_parser(warnings, None));
+    let (errors, warnings) = (errors.unwrap(), warnings.unwrap());
+
+    // Neither run scanned the other's output
+    assert_eq!(errors.processed_files, 8);
+    assert_eq!(warnings.processed_files, 8);
+    assert_eq!(errors.total_matches, 400);
+    assert_eq!(warnings.total_matches, 400);
+
+    let error_output = fs::read_to_string(dir.path().join("errors.log")).unwrap();
+    let warning_output = fs::read_to_string(dir.path().join("warnings.log")).unwrap();
+    assert_eq!(error_output.lines().count(), 400);
+    assert!(error_output.lines().all(|line| line.starts_with("error ")));
+    assert_eq!(warning_output.lines().count(), 400);
+    assert!(
+        warning_output
+            .lines()
+            .all(|line| line.starts_with("warning "))
+    );
+
+    // The inputs are untouched and no temporary file is left behind
+    let mut expected: Vec<String> = (0..8)
+        .map(|index| format!("service{}.log", index))
+        .collect();
+    expected.push("errors.log".to_string());
+    expected.push("warnings.log".to_string());
+    expected.sort();
+    assert_eq!(file_names(dir.path()), expected);
+}
+
+#[tokio::test]
+async fn previous_output_is_replaced_only_when_the_run_completes() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("app.log"), "error: disk full\n").unwrap();
+    let output_log = dir.path().join("output.txt");
+    fs::write(&output_log, "previous run\n").unwrap();
+
+    // Listing a file instead of a folder fails after the output is created
+    let mut config = config_for(dir.path(), "output.txt", "error");
+    config.log_folder = dir.path().join("app.log").to_string_lossy().into_owned();
+    assert!(run_parser(config, None).await.is_err());
+    assert_eq!(fs::read_to_string(&output_log).unwrap(), "previous run\n");
+    assert_eq!(file_names(dir.path()), ["app.log", "output.txt"]);
+
+    let config = config_for(dir.path(), "output.txt", "error");
+    let result = run_parser(config, None).await.unwrap();
+
+    assert_eq!(result.total_matches, 1);
+    assert_eq!(
+        fs::read_to_string(&output_log).unwrap(),
+        "error: disk full\n"
+    );
+    assert_eq!(file_names(dir.path()), ["app.log", "output.txt"]);
+}
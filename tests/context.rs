@@ -0,0 +1,128 @@
+use elysiumparser::{ParserConfig, ScanOptions, add_search, process_reader, run_parser};
+use std::fs;
+use std::io::Cursor;
+use std::path::Path;
+use std::sync::Mutex;
+
+const LOG: &str = "\
+1 boot
+2 starting
+3 error: disk full
+4 retrying
+5 recovered
+6 idle
+7 idle
+8 idle
+9 error: timeout
+10 shutdown
+";
+
+/// Scan `input` for `error` with the given context, returning the match count and output
+fn run(input: &str, before_context: usize, after_context: usize) -> (usize, String) {
+    let mut search_terms = Vec::new();
+    add_search(&mut search_terms, "error", "");
+    let options = ScanOptions {
+        before_context,
+        after_context,
+        ..Default::default()
+    };
+    let output = Mutex::new(Vec::new());
+
+    let scan = process_reader(
+        Cursor::new(input),
+        Path::new("app.log"),
+        &search_terms,
+        &options,
+        Some(&output),
+    );
+
+    (
+        scan.matches,
+        String::from_utf8(output.into_inner().unwrap()).unwrap(),
+    )
+}
+
+#[test]
+fn separate_windows_are_split_by_a_separator() {
+    let (matches, output) = run(LOG, 1, 1);
+
+    assert_eq!(matches, 2);
+    assert_eq!(
+        output,
+        "- 2 starting\n> 3 error: disk full\n- 4 retrying\n--\n- 8 idle\n> 9 error: timeout\n- 10 shutdown\n"
+    );
+}
+
+#[test]
+fn overlapping_windows_are_merged() {
+    let (matches, output) = run(LOG, 3, 2);
+
+    assert_eq!(matches, 2);
+    assert_eq!(
+        output,
+        "- 1 boot\n- 2 starting\n> 3 error: disk full\n- 4 retrying\n- 5 recovered\n\
+         - 6 idle\n- 7 idle\n- 8 idle\n> 9 error: timeout\n- 10 shutdown\n"
+    );
+}
+
+#[test]
+fn touching_windows_are_merged() {
+    // Line 5 ends the first window and line 6 starts the second
+    let (_, touching) = run(LOG, 3, 2);
+    let (_, apart) = run(LOG, 3, 1);
+
+    assert!(!touching.contains("--"));
+    assert_eq!(
+        apart,
+        "- 1 boot\n- 2 starting\n> 3 error: disk full\n- 4 retrying\n--\n\
+         - 6 idle\n- 7 idle\n- 8 idle\n> 9 error: timeout\n- 10 shutdown\n"
+    );
+}
+
+#[test]
+fn matches_inside_a_window_extend_it() {
+    let input = "a\nerror 1\nb\nerror 2\nerror 3\nc\nd\ne\n";
+
+    let (matches, output) = run(input, 1, 2);
+
+    assert_eq!(matches, 3);
+    assert_eq!(
+        output,
+        "- a\n> error 1\n- b\n> error 2\n> error 3\n- c\n- d\n"
+    );
+}
+
+#[test]
+fn context_is_clipped_at_the_edges() {
+    let (_, before) = run(LOG, 5, 0);
+    let (_, after) = run(LOG, 0, 3);
+
+    assert!(before.starts_with("- 1 boot\n- 2 starting\n> 3 error: disk full\n"));
+    assert!(after.ends_with("--\n> 9 error: timeout\n- 10 shutdown\n"));
+}
+
+#[tokio::test]
+async fn context_lines_are_not_counted_and_get_grep_locations() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("app.log"), LOG).unwrap();
+
+    let mut config = ParserConfig {
+        log_folder: dir.path().to_string_lossy().into_owned(),
+        output_log: dir.path().join("output.log").to_string_lossy().into_owned(),
+        before_context: 1,
+        show_location: true,
+        ..Default::default()
+    };
+    add_search(&mut config.search_terms, "error", "");
+    let output_log = config.output_log.clone();
+
+    let result = run_parser(config, None).await.unwrap();
+
+    assert_eq!(result.total_matches, 2);
+    assert_eq!(result.file_results[0].matches, 2);
+    assert_eq!(
+        fs::read_to_string(output_log).unwrap(),
+        "- app.log-2-2 starting\n> app.log:3:3 error: disk full\n--\n\
+         - app.log-8-8 idle\n> app.log:9:9 error: timeout\n"
+    );
+}
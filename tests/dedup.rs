@@ -0,0 +1,80 @@
+use elysiumparser::{ParserConfig, add_search, run_parser};
+use std::fs;
+use std::path::Path;
+
+fn config_for(dir: &Path) -> ParserConfig {
+    let mut config = ParserConfig {
+        log_folder: dir.to_string_lossy().into_owned(),
+        output_log: dir.join("output.txt").to_string_lossy().into_owned(),
+        deduplicate: true,
+        ordered_output: true,
+        ..Default::default()
+    };
+    add_search(&mut config.search_terms, "error", "");
+    config
+}
+
+#[tokio::test]
+async fn identical_lines_of_several_files_are_written_once() {
+    let dir = tempfile::tempdir().unwrap();
+    let banner = "error: config server unreachable, using defaults\n";
+    for name in ["a.log", "b.log", "c.log"] {
+        fs::write(
+            dir.path().join(name),
+            format!("{banner}error: {name} only\n"),
+        )
+        .unwrap();
+    }
+    let mut config = config_for(dir.path());
+    config.show_location = true;
+    let output_log = config.output_log.clone();
+
+    let result = run_parser(config, None).await.unwrap();
+
+    // Matches still count, and lines are compared without their location
+    assert_eq!(result.total_matches, 6);
+    assert_eq!(result.skipped_duplicates, 2);
+    assert_eq!(
+        fs::read_to_string(output_log).unwrap(),
+        "a.log:1:error: config server unreachable, using defaults\n\
+         a.log:2:error: a.log only\n\
+         b.log:2:error: b.log only\n\
+         c.log:2:error: c.log only\n"
+    );
+}
+
+#[tokio::test]
+async fn the_capacity_bounds_the_lines_remembered() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("a.log"), "error 1\nerror 2\nerror 1\n").unwrap();
+    let mut config = config_for(dir.path());
+    config.dedup_max_capacity = Some(1);
+    let output_log = config.output_log.clone();
+
+    let result = run_parser(config, None).await.unwrap();
+
+    // "error 1" was forgotten when "error 2" came
+    assert_eq!(result.skipped_duplicates, 0);
+    assert_eq!(result.dedup_resets, 2);
+    assert_eq!(
+        fs::read_to_string(output_log).unwrap(),
+        "error 1\nerror 2\nerror 1\n"
+    );
+}
+
+#[tokio::test]
+async fn duplicates_are_kept_unless_asked() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("a.log"), "error: same\nerror: same\n").unwrap();
+    let mut config = config_for(dir.path());
+    config.deduplicate = false;
+    let output_log = config.output_log.clone();
+
+    let result = run_parser(config, None).await.unwrap();
+
+    assert_eq!(result.skipped_duplicates, 0);
+    assert_eq!(
+        fs::read_to_string(output_log).unwrap(),
+        "error: same\nerror: same\n"
+    );
+}
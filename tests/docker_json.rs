@@ -0,0 +1,112 @@
+mod common;
+
+use elysiumparser::{InputFormat, ScanOptions, add_search, process_reader};
+use std::io::Cursor;
+use std::path::Path;
+use std::sync::Mutex;
+
+/// Docker splits lines longer than this into several records
+const DOCKER_CHUNK: usize = 16 * 1024;
+
+fn record(log: &str) -> String {
+    format!(
+        "{{\"log\":\"{}\",\"stream\":\"stdout\",\"time\":\"2024-06-07T12:00:00.000000000Z\"}}\n",
+        log
+    )
+}
+
+/// Records of a container log with one line long enough to be chunked
+fn chunked_fixture() -> (String, String) {
+    let long_line = format!(
+        "error payload {} end-of-payload",
+        "x".repeat(DOCKER_CHUNK * 2)
+    );
+    let mut input = record("info: starting\\n");
+    for chunk in long_line.as_bytes().chunks(DOCKER_CHUNK) {
+        input.push_str(&record(std::str::from_utf8(chunk).unwrap()));
+    }
+    input.push_str(&record("\\n"));
+    input.push_str(&record("error: \\\"disk\\\" full\\n"));
+    (input, long_line)
+}
+
+/// Scan `input`, as read from a container log, for `keyword`
+fn scan(input: &str, keyword: &str, input_format: InputFormat) -> (usize, usize, String) {
+    common::scan(input, "container-json.log", keyword, input_format)
+}
+
+#[test]
+fn docker_records_are_unwrapped_and_chunks_joined() {
+    let (input, long_line) = chunked_fixture();
+
+    let (matches, lines, output) = scan(&input, "error", InputFormat::DockerJson);
+
+    assert_eq!(matches, 2);
+    assert_eq!(lines, 3);
+    assert_eq!(output, format!("{}\nerror: \"disk\" full\n", long_line));
+}
+
+#[test]
+fn plain_format_matches_the_wrapper() {
+    let (input, _) = chunked_fixture();
+
+    let (matches, _, _) = scan(&input, "stdout", InputFormat::Plain);
+    let (unwrapped, _, _) = scan(&input, "stdout", InputFormat::DockerJson);
+
+    assert_eq!(matches, 6);
+    assert_eq!(unwrapped, 0);
+}
+
+#[test]
+fn auto_detects_docker_records_from_the_first_line() {
+    let (input, _) = chunked_fixture();
+
+    assert_eq!(scan(&input, "stdout", InputFormat::Auto).0, 0);
+    assert_eq!(
+        scan("{\"level\":\"error\"}\n", "error", InputFormat::Auto).0,
+        1
+    );
+}
+
+#[test]
+fn lines_that_are_not_records_pass_through() {
+    let input = format!(
+        "{}garbage error line\n{}",
+        record("error split "),
+        record("error whole\\n")
+    );
+
+    let (matches, lines, output) = scan(&input, "error", InputFormat::DockerJson);
+
+    assert_eq!(matches, 3);
+    assert_eq!(lines, 3);
+    assert_eq!(output, "error split \ngarbage error line\nerror whole\n");
+}
+
+#[test]
+fn joined_lines_are_cut_at_the_maximum_length() {
+    let (input, long_line) = chunked_fixture();
+    let mut search_terms = Vec::new();
+    add_search(&mut search_terms, "error", "");
+    let options = ScanOptions {
+        input_format: InputFormat::DockerJson,
+        max_line_length: Some(100),
+        ..Default::default()
+    };
+    let output = Mutex::new(Vec::new());
+
+    let scan = process_reader(
+        Cursor::new(input),
+        Path::new("container-json.log"),
+        &search_terms,
+        &options,
+        Some(&output),
+    );
+
+    assert_eq!((scan.matches, scan.long_lines), (2, 1));
+    let output = String::from_utf8(output.into_inner().unwrap()).unwrap();
+    assert_eq!(
+        output,
+        format!("{}\nerror: \"disk\" full\n", &long_line[..100])
+    );
+}
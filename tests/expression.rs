@@ -0,0 +1,472 @@
+use elysiumparser::{
+    BooleanExpression, EXPRESSION_COMPLEXITY_WARNING, FuzzyPattern, ParseError, ParseErrorKind,
+    Term, add_search_with_case, add_search_with_expression, set_expression_complexity_warning,
+};
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
+
+fn parse(expr: &str) -> BooleanExpression {
+    BooleanExpression::parse(expr).unwrap()
+}
+
+#[test]
+fn fuzzy_atom_matches_within_distance_one() {
+    let expr = parse("fuzzy:connection refused:1");
+
+    assert!(expr.matches("error: connec tion refused by peer"));
+    assert!(expr.matches("error: connection refuse"));
+    assert!(expr.matches("error: connection refused"));
+}
+
+#[test]
+fn fuzzy_atom_rejects_distance_two_when_capped_at_one() {
+    let expr = parse("fuzzy:connection refused:1");
+
+    assert!(!expr.matches("error: conection refuse"));
+    assert!(!expr.matches("error: connexion refuse"));
+    assert!(parse("fuzzy:connection refused:2").matches("error: conection refuse"));
+}
+
+#[test]
+fn fuzzy_distance_is_capped() {
+    let Term::Fuzzy(pattern) = Term::parse("fuzzy:timeout:5").unwrap() else {
+        panic!("expected a fuzzy term");
+    };
+
+    assert_eq!(pattern.max_distance(), 2);
+    assert!(!pattern.matches("the tim"));
+}
+
+#[test]
+fn fuzzy_atoms_compose_with_and_and_or() {
+    let expr = parse("(fuzzy:timeout:1 & db) | (disk)");
+
+    assert!(expr.matches("db read timout"));
+    assert!(!expr.matches("cache read timout"));
+    assert!(expr.matches("disk full"));
+}
+
+#[test]
+fn fuzzy_atoms_are_evaluated_last() {
+    let BooleanExpression::And(terms) = parse("fuzzy:timeout:1 & db & cache") else {
+        panic!("expected an AND expression");
+    };
+
+    assert_eq!(
+        terms.last(),
+        Some(&BooleanExpression::Term(
+            Term::parse("fuzzy:timeout:1").unwrap()
+        ))
+    );
+}
+
+#[test]
+fn long_fuzzy_patterns_use_the_fallback() {
+    let needle = "a".repeat(70);
+    let mut haystack = "x".repeat(10);
+    haystack.push_str(&"a".repeat(69));
+    haystack.push('b');
+
+    assert!(FuzzyPattern::new(&needle, 1).matches(&haystack));
+    assert!(!FuzzyPattern::new(&needle, 1).matches(&"a".repeat(68)));
+}
+
+#[test]
+fn not_inverts_a_term() {
+    let expr = parse("!expected");
+
+    assert!(expr.matches("error: disk full"));
+    assert!(!expr.matches("error: expected failure"));
+}
+
+#[test]
+fn double_negation_cancels_out() {
+    let expr = parse("!!timeout");
+
+    assert!(expr.matches("read timeout"));
+    assert!(!expr.matches("read ok"));
+    assert!(parse("!(!timeout)").matches("read timeout"));
+}
+
+#[test]
+fn not_combines_with_and_and_or() {
+    let expr = parse("(database & connection) | !expected");
+    assert!(expr.matches("database connection lost, expected"));
+    assert!(expr.matches("disk full"));
+    assert!(!expr.matches("expected shutdown"));
+
+    let expr = parse("error & !expected");
+    assert!(expr.matches("error: disk full"));
+    assert!(!expr.matches("error: expected"));
+    assert!(!expr.matches("info: disk full"));
+
+    let expr = parse("!(database & connection)");
+    assert!(expr.matches("database ok"));
+    assert!(!expr.matches("database connection lost"));
+
+    let expr = parse("timeout | !(database & connection)");
+    assert!(expr.matches("database connection timeout"));
+    assert!(!expr.matches("database connection lost"));
+}
+
+#[test]
+fn not_without_operand_is_rejected() {
+    let dangling = Err(ParseError {
+        kind: ParseErrorKind::DanglingOperator('!'),
+        position: 0,
+    });
+    assert_eq!(BooleanExpression::parse("!"), dangling);
+    assert_eq!(BooleanExpression::parse("! "), dangling);
+
+    let mut terms = Vec::new();
+    assert!(add_search_with_expression(&mut terms, "error", "!").is_err());
+    assert!(terms.is_empty());
+}
+
+#[test]
+fn nesting_three_levels_deep() {
+    let expr = parse("(a & (b | (c & !d))) | e");
+
+    assert!(expr.matches("a b"));
+    assert!(expr.matches("a c"));
+    assert!(!expr.matches("a c d"));
+    assert!(!expr.matches("b c"));
+    assert!(expr.matches("e"));
+}
+
+#[test]
+fn and_binds_tighter_than_or() {
+    let expr = parse("a & b | c & d");
+
+    assert!(expr.matches("a b"));
+    assert!(expr.matches("c d"));
+    assert!(!expr.matches("a d"));
+    assert!(!expr.matches("b c"));
+    assert_eq!(expr, parse("(a & b) | (c & d)"));
+}
+
+#[test]
+fn grouped_alternatives_must_each_match() {
+    let expr = parse("(a | b) & (c | d)");
+
+    for line in ["a c", "a d", "b c", "b d"] {
+        assert!(expr.matches(line), "{}", line);
+    }
+    for line in ["a b", "c d", "a", "d"] {
+        assert!(!expr.matches(line), "{}", line);
+    }
+}
+
+#[test]
+fn inner_groups_are_not_flattened() {
+    let expr = parse("(a & (b | c)) & d");
+
+    assert!(expr.matches("a b d"));
+    assert!(expr.matches("a c d"));
+    assert!(!expr.matches("a d"));
+    assert!(!expr.matches("a b c"));
+    assert!(!expr.matches("b c d"));
+
+    let expr = parse("((a | (b & (c | (d & !e)))))");
+    assert!(expr.matches("a"));
+    assert!(expr.matches("b c"));
+    assert!(expr.matches("b d"));
+    assert!(!expr.matches("b d e"));
+    assert!(!expr.matches("c d"));
+}
+
+#[test]
+fn simple_forms_keep_working() {
+    let expr = parse("(database & connection) | (timeout)");
+
+    assert!(expr.matches("database connection reset"));
+    assert!(expr.matches("read timeout"));
+    assert!(!expr.matches("database ok"));
+    assert_eq!(
+        parse("  Memory "),
+        BooleanExpression::Term(Term::parse("memory").unwrap())
+    );
+    assert!(parse("connection refused").matches("error: connection refused"));
+}
+
+#[test]
+fn malformed_expressions_report_where() {
+    let error = |expr: &str| BooleanExpression::parse(expr).unwrap_err();
+
+    assert_eq!(error("").kind, ParseErrorKind::Empty);
+    assert_eq!(error("()").kind, ParseErrorKind::Empty);
+    assert_eq!(
+        error("(a & b"),
+        ParseError {
+            kind: ParseErrorKind::UnclosedParen,
+            position: 0
+        }
+    );
+    assert_eq!(
+        error("a & b)"),
+        ParseError {
+            kind: ParseErrorKind::UnmatchedParen,
+            position: 5
+        }
+    );
+    assert_eq!(
+        error("a &"),
+        ParseError {
+            kind: ParseErrorKind::DanglingOperator('&'),
+            position: 2
+        }
+    );
+    assert_eq!(
+        error("| a"),
+        ParseError {
+            kind: ParseErrorKind::DanglingOperator('|'),
+            position: 0
+        }
+    );
+    assert_eq!(
+        error("a & | b"),
+        ParseError {
+            kind: ParseErrorKind::DanglingOperator('&'),
+            position: 2
+        }
+    );
+    assert_eq!(error("(a) b").kind, ParseErrorKind::MissingOperator);
+    assert_eq!(
+        error("(a & b").to_string(),
+        "'(' at position 0 is never closed"
+    );
+}
+
+#[test]
+fn regex_atoms_keep_their_case() {
+    let expr = parse(r"error & re:code=\D\d{3}");
+
+    assert!(expr.matches("error code=e503"));
+    assert!(!expr.matches("error code=5034"));
+    assert!(matches!(
+        BooleanExpression::parse("re:[a-").unwrap_err().kind,
+        ParseErrorKind::InvalidRegex(_)
+    ));
+}
+
+#[test]
+fn quoted_atoms_may_contain_operators() {
+    let expr = parse(r#""a | b" & re:"^(get|put) ""#);
+
+    assert!(expr.matches("get a | b"));
+    assert!(!expr.matches("post a | b"));
+    assert!(parse(r#""say \"hi\"""#).matches(r#"they say "hi" twice"#));
+    assert_eq!(
+        BooleanExpression::parse(r#"a & "b"#).unwrap_err(),
+        ParseError {
+            kind: ParseErrorKind::UnclosedQuote,
+            position: 4
+        }
+    );
+}
+
+#[test]
+fn negated_groups_exclude_lines() {
+    let expr = parse("error & !healthcheck");
+    assert!(expr.matches("error: db down"));
+    assert!(!expr.matches("error: healthcheck failed"));
+
+    let expr = parse("!(retry | backoff)");
+    assert!(expr.matches("giving up"));
+    assert!(!expr.matches("retry in 5s"));
+    assert!(!expr.matches("backoff 2x"));
+}
+
+#[test]
+fn display_renders_negations_and_round_trips() {
+    assert_eq!(
+        parse("error & !healthcheck").to_string(),
+        "(error & !healthcheck)"
+    );
+    assert_eq!(
+        parse("!(retry | backoff)").to_string(),
+        "!(retry | backoff)"
+    );
+    assert_eq!(parse("!(a & b)").to_string(), "!(a & b)");
+
+    for source in [
+        "(database & connection) | (timeout)",
+        "a & (b | !(c & d)) & !e",
+        r#""a | b" & re:"^(get|put)" & fuzzy:"x&y":1"#,
+        r#"!"!bang" | " padded ""#,
+    ] {
+        let expr = parse(source);
+        assert_eq!(parse(&expr.to_string()), expr, "{}", expr);
+    }
+}
+
+#[test]
+fn fuzzed_expressions_round_trip() {
+    assert_eq!(parse("(a | b) | c").to_string(), "(a | b) | c");
+    assert_eq!(parse(r#""a&"\"#).to_string(), r#""a&"\"#);
+
+    // Minimized from the `expression` fuzz target
+    for source in [
+        "(a | b) | c",
+        "(aß|ß|éİ)|B",
+        "\\\t|([|xx2)",
+        "\\a|(re:|x*&\\)",
+        "fuzzy:\t\\",
+        "fuzzy:!\\",
+        "(2|fuzzy:\t\\)",
+        "\"(ß*(\"\\",
+        "\" \"\\",
+        "\\\"a:B|\"x\\",
+    ] {
+        for case_sensitive in [false, true] {
+            let parse = match case_sensitive {
+                false => BooleanExpression::parse,
+                true => BooleanExpression::parse_case_sensitive,
+            };
+            let expr = parse(source).unwrap();
+            assert_eq!(parse(&expr.to_string()), Ok(expr), "{:?}", source);
+        }
+    }
+}
+
+fn term(text: &str) -> BooleanExpression {
+    BooleanExpression::Term(Term::parse(text).unwrap())
+}
+
+#[test]
+fn normalize_unwraps_single_operands_and_merges_nested_nodes() {
+    let wrapped = BooleanExpression::And(vec![BooleanExpression::Or(vec![Box::new(term("a"))])]);
+    assert_eq!(wrapped.normalize(), term("a"));
+
+    assert_eq!(parse("(a | b) | c").normalize(), parse("a | b | c"));
+    assert_eq!(parse("a & (b & c)").normalize(), parse("a & b & c"));
+    // Operands of the other kind stay grouped
+    assert_eq!(parse("a & (b | c)").normalize(), parse("a & (b | c)"));
+}
+
+#[test]
+fn normalize_drops_repeated_atoms_of_an_and() {
+    let expr = parse("a & b & a & !c & !c");
+    assert_eq!(expr.complexity(), 5);
+
+    let normalized = expr.normalize();
+    assert_eq!(normalized, parse("a & b & !c"));
+    assert_eq!(normalized.complexity(), 3);
+}
+
+#[test]
+fn normalize_drops_repeated_alternatives_of_an_or() {
+    let expr = parse("(a & b) | c | (a & b) | (c)");
+    assert_eq!(expr.normalize(), parse("(a & b) | c"));
+    // Equal only once merged into the outer node
+    assert_eq!(parse("a | (b | a)").normalize(), parse("a | b"));
+}
+
+#[test]
+fn normalize_leaves_empty_nodes_only_at_the_top() {
+    let always = || BooleanExpression::And(Vec::new());
+    let never = || BooleanExpression::Or(Vec::new());
+
+    let expr = BooleanExpression::And(vec![always(), term("a"), always()]);
+    assert_eq!(expr.normalize(), term("a"));
+    let expr = BooleanExpression::Or(vec![Box::new(never()), Box::new(term("a"))]);
+    assert_eq!(expr.normalize(), term("a"));
+
+    // Absorbing operands take the whole node over
+    let expr = BooleanExpression::And(vec![term("a"), never()]);
+    assert_eq!(expr.normalize(), never());
+    let expr = BooleanExpression::Or(vec![Box::new(term("a")), Box::new(always())]);
+    assert_eq!(expr.normalize(), always());
+    let expr = BooleanExpression::Not(Box::new(BooleanExpression::And(vec![never()])));
+    assert_eq!(expr.normalize(), always());
+
+    assert!(always().normalize().matches("anything"));
+    assert!(!never().normalize().matches("anything"));
+}
+
+#[test]
+fn deeply_nested_repeats_collapse_to_one_atom() {
+    let expr = parse("((((a & a) | (a)) & (a | (a & (a | a)))) | a)");
+    assert_eq!(expr.complexity(), 8);
+
+    let normalized = expr.normalize();
+    assert_eq!(normalized, term("a"));
+    assert_eq!(normalized.complexity(), 1);
+}
+
+#[test]
+fn normalized_expressions_match_the_same_lines() {
+    let lines = ["a", "b", "c", "a b", "a c", "b c", "a b c", ""];
+    for source in [
+        "(a | b) | (c | a)",
+        "a & (b & a) & !(c | c)",
+        "!(a & a) | (b & (b | b))",
+        "((a | b) & (b | a)) & !c",
+    ] {
+        let expr = parse(source);
+        let normalized = expr.clone().normalize();
+        for line in lines {
+            assert_eq!(
+                normalized.matches(line),
+                expr.matches(line),
+                "{} on {:?}",
+                source,
+                line
+            );
+        }
+    }
+}
+
+#[test]
+fn search_terms_keep_their_expression_normalized() {
+    let mut terms = Vec::new();
+    add_search_with_expression(&mut terms, "error", "(disk & disk) | (disk)").unwrap();
+
+    let expression = terms[0].additional_expression.as_ref().unwrap();
+    assert_eq!(expression, &term("disk"));
+    assert_eq!(expression.complexity(), 1);
+}
+
+/// Writer collecting what a `tracing` subscriber logs
+#[derive(Clone, Default)]
+struct Logged(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Logged {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(bytes);
+        Ok(bytes.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn complex_expressions_are_reported_once_normalized() {
+    let logged = Logged::default();
+    let subscriber = tracing_subscriber::fmt()
+        .with_writer({
+            let logged = logged.clone();
+            move || logged.clone()
+        })
+        .without_time()
+        .finish();
+    set_expression_complexity_warning(2);
+
+    tracing::subscriber::with_default(subscriber, || {
+        let mut terms = Vec::new();
+        add_search_with_expression(&mut terms, "error", "disk | disk | disk").unwrap();
+        add_search_with_case(&mut terms, "error", "disk | full | quota", true).unwrap();
+    });
+    set_expression_complexity_warning(EXPRESSION_COMPLEXITY_WARNING);
+
+    let logged = String::from_utf8(logged.0.lock().unwrap().clone()).unwrap();
+    assert_eq!(logged.lines().count(), 1, "{}", logged);
+    assert!(logged.contains("WARN"), "{}", logged);
+    assert!(
+        logged.contains("'disk | full | quota' has 3 atoms, more than 2"),
+        "{}",
+        logged
+    );
+}
@@ -0,0 +1,340 @@
+use elysiumparser::{
+    DEFAULT_EXCLUDED_PREFIX, DEFAULT_LOG_EXTENSION, FilenameFilter, ParserConfig,
+    collect_log_files, is_gz_file, is_valid_log_file,
+};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+
+const FILES: &[&str] = &[
+    "app.log",
+    "app-debug.log",
+    "app-2024-01-01.log",
+    "app-2024-01-02.log.gz",
+    "app-2023-12-31.log",
+    "server1.log",
+    "server2.log",
+    "server10.log",
+    "alpha.log",
+    "beta.log",
+    "gamma.log",
+];
+
+/// Names of the files of a folder holding `FILES` that `filter` selects
+fn selected(filter: &str) -> Vec<String> {
+    let dir = tempfile::tempdir().unwrap();
+    for name in FILES {
+        fs::write(dir.path().join(name), "").unwrap();
+    }
+    let config = ParserConfig {
+        log_folder: dir.path().to_string_lossy().into_owned(),
+        output_log: dir.path().join("output.log").to_string_lossy().into_owned(),
+        filename_filter: filter.to_string(),
+        ..Default::default()
+    };
+
+    collect_log_files(&config)
+        .unwrap()
+        .iter()
+        .map(|path| path.file_name().unwrap().to_string_lossy().into_owned())
+        .collect()
+}
+
+#[test]
+fn star_matches_any_run_of_characters() {
+    assert_eq!(
+        selected("app-2024-*"),
+        ["app-2024-01-01.log", "app-2024-01-02.log.gz"]
+    );
+    assert_eq!(
+        selected("APP-*.log"),
+        ["app-2023-12-31.log", "app-2024-01-01.log", "app-debug.log"]
+    );
+}
+
+#[test]
+fn question_mark_matches_one_character() {
+    assert_eq!(selected("server?.log"), ["server1.log", "server2.log"]);
+}
+
+#[test]
+fn brackets_match_a_character_class() {
+    assert_eq!(
+        selected("[ab]*.log"),
+        [
+            "alpha.log",
+            "app-2023-12-31.log",
+            "app-2024-01-01.log",
+            "app-debug.log",
+            "app.log",
+            "beta.log"
+        ]
+    );
+}
+
+#[test]
+fn braces_match_any_of_their_alternatives() {
+    assert_eq!(selected("{alpha,beta}.log"), ["alpha.log", "beta.log"]);
+    assert_eq!(
+        selected("server{1,10}.log"),
+        ["server1.log", "server10.log"]
+    );
+    assert_eq!(
+        selected("{gamma,app-{debug,2024-*}}.log*"),
+        [
+            "app-2024-01-01.log",
+            "app-2024-01-02.log.gz",
+            "app-debug.log",
+            "gamma.log"
+        ]
+    );
+    // An unclosed brace is literal
+    assert!(selected("{alpha.log").is_empty());
+}
+
+#[test]
+fn archives_are_matched_by_their_whole_name() {
+    assert_eq!(selected("*.log").len(), FILES.len() - 1);
+    assert_eq!(selected("*.log.gz"), ["app-2024-01-02.log.gz"]);
+    assert_eq!(selected("*.log*").len(), FILES.len());
+}
+
+#[test]
+fn plain_filters_are_still_substrings() {
+    assert_eq!(selected("").len(), FILES.len());
+    assert_eq!(
+        selected("SERVER"),
+        ["server1.log", "server10.log", "server2.log"]
+    );
+
+    let filter = FilenameFilter::new("debug").unwrap();
+    assert!(matches!(filter, FilenameFilter::Substring(_)));
+    assert!(filter.matches(Path::new("logs/app-debug.log")));
+    assert!(!filter.matches(Path::new("debug/app.log")));
+}
+
+#[test]
+fn malformed_globs_are_rejected() {
+    assert!(FilenameFilter::new("app[").is_err());
+
+    let config = ParserConfig {
+        filename_filter: "app[".to_string(),
+        ..Default::default()
+    };
+    let error = collect_log_files(&config).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidInput);
+    assert!(error.to_string().contains("app["));
+}
+
+/// Names of the files `collect_log_files` finds with `excluded_prefixes`, or
+/// with the default ones when `None`
+fn kept_with_prefixes(excluded_prefixes: Option<&[&str]>) -> Vec<String> {
+    let dir = tempfile::tempdir().unwrap();
+    for name in ["app.log", "debug-app.log", "Debug.log.gz", "trace-app.log"] {
+        fs::write(dir.path().join(name), "").unwrap();
+    }
+    let mut config = ParserConfig {
+        log_folder: dir.path().to_string_lossy().into_owned(),
+        output_log: dir.path().join("output.log").to_string_lossy().into_owned(),
+        ..Default::default()
+    };
+    if let Some(excluded_prefixes) = excluded_prefixes {
+        config.excluded_prefixes = excluded_prefixes.iter().map(ToString::to_string).collect();
+    }
+
+    collect_log_files(&config)
+        .unwrap()
+        .iter()
+        .map(|path| path.file_name().unwrap().to_string_lossy().into_owned())
+        .collect()
+}
+
+#[test]
+fn debug_files_are_excluded_by_default() {
+    assert_eq!(kept_with_prefixes(None), ["app.log", "trace-app.log"]);
+}
+
+#[test]
+fn excluded_prefixes_can_be_cleared_or_replaced() {
+    assert_eq!(
+        kept_with_prefixes(Some(&[])),
+        ["Debug.log.gz", "app.log", "debug-app.log", "trace-app.log"]
+    );
+    assert_eq!(
+        kept_with_prefixes(Some(&[""])),
+        ["Debug.log.gz", "app.log", "debug-app.log", "trace-app.log"]
+    );
+    assert_eq!(
+        kept_with_prefixes(Some(&["TRACE", "debug-"])),
+        ["Debug.log.gz", "app.log"]
+    );
+}
+
+#[test]
+fn predicates_take_the_excluded_prefixes() {
+    let dir = tempfile::tempdir().unwrap();
+    let log = dir.path().join("debug-app.log");
+    let archive = dir.path().join("debug-app.log.gz");
+    fs::write(&log, "").unwrap();
+    fs::write(&archive, "").unwrap();
+    let output_log = dir.path().join("output.log");
+    let output_log = output_log.to_str().unwrap();
+    let default = [DEFAULT_EXCLUDED_PREFIX.to_string()];
+
+    let log_extension = [DEFAULT_LOG_EXTENSION.to_string()];
+
+    assert!(!is_valid_log_file(
+        &log,
+        "",
+        output_log,
+        &default,
+        &log_extension
+    ));
+    assert!(is_valid_log_file(&log, "", output_log, &[], &log_extension));
+    assert!(!is_gz_file(&archive, &default, &log_extension));
+    assert!(is_gz_file(&archive, &[], &log_extension));
+}
+
+/// Paths, relative to the log folder, of the files `collect_log_files`
+/// finds recursively with `include` and `exclude` patterns
+fn kept_with_patterns(include: &[&str], exclude: &[&str]) -> Vec<String> {
+    let dir = tempfile::tempdir().unwrap();
+    for name in [
+        "app-prod-1.log",
+        "app-staging-1.log",
+        "App-Prod-2.log.gz",
+        "db.log",
+        "2023/app-prod-0.log",
+        "2024/01/app-prod-3.log",
+        "2024/db.log",
+    ] {
+        let path = dir.path().join(name);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, "").unwrap();
+    }
+    let config = ParserConfig {
+        log_folder: dir.path().to_string_lossy().into_owned(),
+        output_log: dir.path().join("output.log").to_string_lossy().into_owned(),
+        recursive: true,
+        include_patterns: include.iter().map(ToString::to_string).collect(),
+        exclude_patterns: exclude.iter().map(ToString::to_string).collect(),
+        ..Default::default()
+    };
+
+    let mut kept: Vec<String> = collect_log_files(&config)
+        .unwrap()
+        .iter()
+        .map(|path| {
+            let relative = path.strip_prefix(dir.path()).unwrap();
+            relative.to_string_lossy().replace('\\', "/")
+        })
+        .collect();
+    kept.sort();
+    kept
+}
+
+#[test]
+fn include_and_exclude_patterns_match_file_names() {
+    assert_eq!(kept_with_patterns(&[], &[]).len(), 7);
+    assert_eq!(
+        kept_with_patterns(&["app-*"], &["app-staging-*"]),
+        [
+            "2023/app-prod-0.log",
+            "2024/01/app-prod-3.log",
+            "App-Prod-2.log.gz",
+            "app-prod-1.log",
+        ]
+    );
+    assert_eq!(
+        kept_with_patterns(&[], &["*.gz", "db.log"]),
+        [
+            "2023/app-prod-0.log",
+            "2024/01/app-prod-3.log",
+            "app-prod-1.log",
+            "app-staging-1.log",
+        ]
+    );
+}
+
+#[test]
+fn patterns_with_a_separator_match_the_relative_path() {
+    assert_eq!(
+        kept_with_patterns(&["2024/**/*.log"], &[]),
+        ["2024/01/app-prod-3.log", "2024/db.log"]
+    );
+    // `*` does not cross directories
+    assert_eq!(kept_with_patterns(&["2024/*.log"], &[]), ["2024/db.log"]);
+    assert_eq!(
+        kept_with_patterns(&["*.log"], &["2024/**"]),
+        [
+            "2023/app-prod-0.log",
+            "app-prod-1.log",
+            "app-staging-1.log",
+            "db.log"
+        ]
+    );
+}
+
+#[test]
+fn malformed_patterns_are_reported() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = ParserConfig {
+        log_folder: dir.path().to_string_lossy().into_owned(),
+        exclude_patterns: vec!["app-[".to_string()],
+        ..Default::default()
+    };
+
+    let error = collect_log_files(&config).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidInput);
+    assert!(error.to_string().contains("app-["));
+}
+
+/// Names of the files `collect_log_files` finds with `allowed_extensions`,
+/// or with the default ones when `None`
+fn kept_with_extensions(allowed_extensions: Option<&[&str]>) -> Vec<String> {
+    let dir = tempfile::tempdir().unwrap();
+    for name in [
+        "app.log",
+        "app.TXT",
+        "app.txt.gz",
+        "app.log.gz",
+        "service.out",
+        "syslog",
+    ] {
+        fs::write(dir.path().join(name), "").unwrap();
+    }
+    let mut config = ParserConfig {
+        log_folder: dir.path().to_string_lossy().into_owned(),
+        output_log: dir.path().join("output.log").to_string_lossy().into_owned(),
+        ..Default::default()
+    };
+    if let Some(allowed_extensions) = allowed_extensions {
+        config.allowed_extensions = allowed_extensions.iter().map(ToString::to_string).collect();
+    }
+
+    collect_log_files(&config)
+        .unwrap()
+        .iter()
+        .map(|path| path.file_name().unwrap().to_string_lossy().into_owned())
+        .collect()
+}
+
+#[test]
+fn only_log_files_are_read_by_default() {
+    assert_eq!(kept_with_extensions(None), ["app.log", "app.log.gz"]);
+}
+
+#[test]
+fn allowed_extensions_select_plain_files_and_archives() {
+    assert_eq!(
+        kept_with_extensions(Some(&["txt", ".out"])),
+        ["app.TXT", "app.txt.gz", "service.out"]
+    );
+    assert_eq!(kept_with_extensions(Some(&[""])), ["syslog"]);
+}
+
+#[test]
+fn empty_or_star_allowlists_take_any_file() {
+    assert_eq!(kept_with_extensions(Some(&[])).len(), 6);
+    assert_eq!(kept_with_extensions(Some(&["*"])).len(), 6);
+}
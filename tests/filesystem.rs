@@ -0,0 +1,98 @@
+use elysiumparser::{FileMetadata, FileSystem, ParserConfig, add_search, run_parser};
+use std::collections::BTreeMap;
+use std::io::{self, Cursor, Read};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+const ROOT: &str = "/virtual/logs";
+
+/// In-memory file system whose listing contains one unreadable entry and
+/// one file that cannot be opened
+struct MockFileSystem {
+    files: BTreeMap<PathBuf, &'static str>,
+    denied: PathBuf,
+}
+
+impl MockFileSystem {
+    fn new() -> Self {
+        let mut files = BTreeMap::new();
+        files.insert(Path::new(ROOT).join("a.log"), "error one\ninfo\n");
+        files.insert(Path::new(ROOT).join("b.log"), "error two\nerror three\n");
+        files.insert(Path::new(ROOT).join("secret.log"), "error hidden\n");
+        Self {
+            files,
+            denied: Path::new(ROOT).join("secret.log"),
+        }
+    }
+}
+
+impl FileSystem for MockFileSystem {
+    fn list_dir(&self, _path: &Path) -> io::Result<Vec<io::Result<PathBuf>>> {
+        let mut entries: Vec<_> = self.files.keys().cloned().map(Ok).collect();
+        entries.push(Err(io::Error::other("transient read error")));
+        Ok(entries)
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<FileMetadata> {
+        if path == Path::new(ROOT) {
+            return Ok(FileMetadata {
+                is_dir: true,
+                ..Default::default()
+            });
+        }
+        match self.files.get(path) {
+            Some(contents) => Ok(FileMetadata {
+                is_file: true,
+                len: contents.len() as u64,
+                ..Default::default()
+            }),
+            None => Err(io::ErrorKind::NotFound.into()),
+        }
+    }
+
+    fn open(&self, path: &Path) -> io::Result<Box<dyn Read + Send>> {
+        if path == self.denied {
+            return Err(io::ErrorKind::PermissionDenied.into());
+        }
+        match self.files.get(path) {
+            Some(contents) => Ok(Box::new(Cursor::new(contents.as_bytes()))),
+            None => Err(io::ErrorKind::NotFound.into()),
+        }
+    }
+}
+
+#[tokio::test]
+async fn failing_entries_do_not_stop_the_run() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut config = ParserConfig {
+        log_folder: ROOT.to_string(),
+        output_log: dir.path().join("output.log").to_string_lossy().into_owned(),
+        file_system: Arc::new(MockFileSystem::new()),
+        ..Default::default()
+    };
+    add_search(&mut config.search_terms, "error", "");
+
+    let result = run_parser(config, None).await.unwrap();
+
+    assert_eq!(result.processed_files, 3);
+    assert_eq!(result.total_matches, 3);
+    assert!(!Path::new(ROOT).exists());
+}
+
+#[tokio::test]
+async fn missing_folders_are_created_through_the_file_system() {
+    let dir = tempfile::tempdir().unwrap();
+    let missing = dir.path().join("missing");
+    let mut config = ParserConfig {
+        log_folder: missing.to_string_lossy().into_owned(),
+        output_log: dir.path().join("output.log").to_string_lossy().into_owned(),
+        file_system: Arc::new(MockFileSystem::new()),
+        ..Default::default()
+    };
+    add_search(&mut config.search_terms, "error", "");
+
+    run_parser(config, None).await.unwrap();
+
+    // The mock creates nothing, so neither does the run on the real disk
+    assert!(!missing.exists());
+}
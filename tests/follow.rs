@@ -0,0 +1,141 @@
+use elysiumparser::{CancelToken, ParserConfig, add_search, run_parser};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::time::Duration;
+
+const INTERVAL: Duration = Duration::from_millis(20);
+
+fn config_for(dir: &Path, cancel: &CancelToken) -> ParserConfig {
+    let mut config = ParserConfig {
+        log_folder: dir.to_string_lossy().into_owned(),
+        output_log: dir.join("output.txt").to_string_lossy().into_owned(),
+        follow: true,
+        follow_interval: INTERVAL,
+        cancel: Some(cancel.clone()),
+        collect_matches: true,
+        ..Default::default()
+    };
+    add_search(&mut config.search_terms, "error", "");
+    config
+}
+
+fn append(path: &Path, text: &str) {
+    let mut file = OpenOptions::new()
+        .append(true)
+        .create(true)
+        .open(path)
+        .unwrap();
+    file.write_all(text.as_bytes()).unwrap();
+}
+
+/// Wait until the output log holds `count` lines, failing after a while
+async fn wait_for_lines(output: &Path, count: usize) -> String {
+    for _ in 0..250 {
+        let contents = fs::read_to_string(output).unwrap_or_default();
+        if contents.lines().count() >= count {
+            return contents;
+        }
+        tokio::time::sleep(INTERVAL).await;
+    }
+    panic!(
+        "expected {} lines, got {:?}",
+        count,
+        fs::read_to_string(output)
+    );
+}
+
+#[tokio::test]
+async fn appended_lines_and_new_files_are_searched_until_cancelled() {
+    let dir = tempfile::tempdir().unwrap();
+    let app = dir.path().join("app.log");
+    fs::write(&app, "error: first\n").unwrap();
+    let output = dir.path().join("output.txt");
+
+    let cancel = CancelToken::new();
+    let run = tokio::spawn(run_parser(config_for(dir.path(), &cancel), None));
+
+    // The output is written in place, each match as it is found
+    wait_for_lines(&output, 1).await;
+    append(&app, "info: fine\nerror: second\nerror: still being wr");
+    wait_for_lines(&output, 2).await;
+    append(&app, "itten\n");
+    fs::write(dir.path().join("new.log"), "error: new file\n").unwrap();
+    let contents = wait_for_lines(&output, 4).await;
+
+    cancel.cancel();
+    let result = run.await.unwrap().unwrap();
+
+    assert!(result.cancelled);
+    assert_eq!(result.total_matches, 4);
+    assert_eq!(result.processed_files, 2);
+    let mut lines: Vec<_> = contents.lines().collect();
+    lines.sort();
+    assert_eq!(
+        lines,
+        [
+            "error: first",
+            "error: new file",
+            "error: second",
+            "error: still being written"
+        ]
+    );
+
+    // Appended lines are numbered after the ones already read
+    let appended: Vec<_> = result
+        .matches
+        .iter()
+        .filter(|record| record.file == app)
+        .map(|record| record.line_number)
+        .collect();
+    assert_eq!(appended, [1, 3, 4]);
+    let app_result = result
+        .file_results
+        .iter()
+        .find(|file| file.path == app)
+        .unwrap();
+    assert_eq!(app_result.lines_scanned, 4);
+}
+
+#[tokio::test]
+async fn truncated_and_replaced_files_are_read_from_the_start() {
+    let dir = tempfile::tempdir().unwrap();
+    let app = dir.path().join("app.log");
+    fs::write(&app, "error: before rotation, a long line\n").unwrap();
+    let output = dir.path().join("output.txt");
+
+    let cancel = CancelToken::new();
+    let run = tokio::spawn(run_parser(config_for(dir.path(), &cancel), None));
+    wait_for_lines(&output, 1).await;
+
+    // Truncated in place
+    fs::write(&app, "error: short\n").unwrap();
+    wait_for_lines(&output, 2).await;
+
+    // Replaced by a longer file, like a rotation between two polls
+    let rotated = dir.path().join("app.log.tmp");
+    fs::write(
+        &rotated,
+        "error: short\nerror: after rotation, a longer file\n",
+    )
+    .unwrap();
+    fs::rename(&rotated, &app).unwrap();
+    let contents = wait_for_lines(&output, 4).await;
+
+    cancel.cancel();
+    run.await.unwrap().unwrap();
+
+    assert!(contents.contains("error: after rotation"), "{}", contents);
+}
+
+#[tokio::test]
+async fn following_without_a_cancel_token_is_rejected() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut config = config_for(dir.path(), &CancelToken::new());
+    config.cancel = None;
+
+    let error = run_parser(config, None).await.err().unwrap();
+
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+    assert!(!dir.path().join("output.txt").exists());
+}
@@ -0,0 +1,67 @@
+use elysiumparser::{BooleanExpression, InterpolationError, InterpolationMode, interpolate_with};
+
+fn lookup(name: &str) -> Option<String> {
+    match name {
+        "LOG_ROOT" => Some("/var/log/app".to_string()),
+        "LEVEL" => Some("error".to_string()),
+        "EMPTY" => Some(String::new()),
+        _ => None,
+    }
+}
+
+fn expand(value: &str, mode: InterpolationMode) -> Result<String, InterpolationError> {
+    interpolate_with(value, mode, lookup)
+}
+
+#[test]
+fn variables_and_defaults_are_expanded() {
+    let lenient = InterpolationMode::Lenient;
+
+    assert_eq!(
+        expand("${LOG_ROOT}/parser", lenient).unwrap(),
+        "/var/log/app/parser"
+    );
+    assert_eq!(
+        expand("${REGION:-eu}-${LEVEL:-warn}", lenient).unwrap(),
+        "eu-error"
+    );
+    assert_eq!(expand("${EMPTY:-fallback}", lenient).unwrap(), "fallback");
+    assert_eq!(expand("${MISSING}x", lenient).unwrap(), "x");
+    assert_eq!(
+        expand("cost $5 and $LEVEL", lenient).unwrap(),
+        "cost $5 and $LEVEL"
+    );
+}
+
+#[test]
+fn strict_mode_rejects_undefined_variables() {
+    let strict = InterpolationMode::Strict;
+
+    assert_eq!(
+        expand("${LOG_ROOT}/${MISSING}", strict),
+        Err(InterpolationError::UndefinedVariable("MISSING".to_string()))
+    );
+    assert_eq!(expand("${MISSING:-ok}", strict).unwrap(), "ok");
+    assert_eq!(
+        expand("abc ${LEVEL", strict),
+        Err(InterpolationError::Unterminated(4))
+    );
+    assert_eq!(
+        expand("${not valid}", strict),
+        Err(InterpolationError::InvalidName("not valid".to_string()))
+    );
+}
+
+#[test]
+fn escaped_dollars_survive_inside_expressions() {
+    let expanded = expand(
+        "(${LEVEL} & $${literal}) | price=$${LEVEL}",
+        InterpolationMode::Strict,
+    )
+    .unwrap();
+
+    assert_eq!(expanded, "(error & ${literal}) | price=${LEVEL}");
+    let expr = BooleanExpression::parse(&expanded).unwrap();
+    assert!(expr.matches("error: ${literal} seen"));
+    assert!(expr.matches("price=${level}"));
+}
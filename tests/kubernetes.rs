@@ -0,0 +1,167 @@
+mod common;
+
+use elysiumparser::{
+    CriLine, InputFormat, ParserConfig, ScanOptions, add_search, collect_log_files,
+    kubernetes_label_rule, parse_cri_line, path_labels, process_reader, run_parser,
+};
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use std::fs;
+use std::io::{Cursor, Write};
+use std::path::Path;
+use std::sync::Mutex;
+
+const POD_DIR: &str = "payments_api-7d9f8b6c5-x2x4q_0b6e4a52-1c1d-4f55-9d7e-2f0c8e3a9b11";
+
+/// A container log as written by containerd, with one line split in three
+const CRI_LOG: &str = "\
+2024-06-07T12:00:00.000000001Z stdout F listening on :8080
+2024-06-07T12:00:01.250000000Z stdout P error: request body {\"id\":
+2024-06-07T12:00:01.250000000Z stdout P 42,\"items\":[1,2,3]
+2024-06-07T12:00:01.250000000Z stdout F } rejected
+2024-06-07T12:00:02.000000000Z stderr F error: upstream timeout
+";
+
+/// Scan `input`, as read from a pod log, for `keyword`
+fn scan(input: &str, keyword: &str, input_format: InputFormat) -> (usize, usize, String) {
+    common::scan(input, "0.log", keyword, input_format)
+}
+
+#[test]
+fn cri_prefix_is_parsed() {
+    let line = parse_cri_line(b"2024-06-07T12:00:00.000Z stderr P partial text\n").unwrap();
+
+    assert_eq!(
+        line,
+        CriLine {
+            timestamp: "2024-06-07T12:00:00.000Z",
+            stream: "stderr",
+            partial: true,
+            message: b"partial text",
+        }
+    );
+    assert_eq!(
+        parse_cri_line(b"2024-06-07T12:00:00Z stdout F:x\n")
+            .unwrap()
+            .message,
+        b""
+    );
+    assert!(parse_cri_line(b"2024-06-07T12:00:00Z stdin F text").is_none());
+    assert!(parse_cri_line(b"2024-06-07T12:00:00Z stdout X text").is_none());
+    assert!(parse_cri_line(b"plain error line").is_none());
+}
+
+#[test]
+fn partial_lines_are_reassembled() {
+    let (matches, lines, output) = scan(CRI_LOG, "error", InputFormat::Cri);
+
+    assert_eq!(matches, 2);
+    assert_eq!(lines, 3);
+    assert_eq!(
+        output,
+        "error: request body {\"id\":42,\"items\":[1,2,3]} rejected\nerror: upstream timeout\n"
+    );
+}
+
+#[test]
+fn prefix_is_not_matched() {
+    assert_eq!(scan(CRI_LOG, "stdout", InputFormat::Cri).0, 0);
+    assert_eq!(scan(CRI_LOG, "stdout", InputFormat::Plain).0, 4);
+}
+
+#[test]
+fn auto_detects_cri_lines() {
+    assert_eq!(scan(CRI_LOG, "stderr", InputFormat::Auto).0, 0);
+    assert_eq!(scan("stderr F plain\n", "stderr", InputFormat::Auto).0, 1);
+}
+
+#[test]
+fn labels_come_from_the_pod_directory() {
+    let rules = [kubernetes_label_rule()];
+
+    let labels = path_labels(&rules, &Path::new(POD_DIR).join("api").join("0.log"));
+
+    assert_eq!(labels["namespace"], "payments");
+    assert_eq!(labels["pod"], "api-7d9f8b6c5-x2x4q");
+    assert_eq!(labels["uid"], "0b6e4a52-1c1d-4f55-9d7e-2f0c8e3a9b11");
+    assert_eq!(labels["container"], "api");
+    assert!(path_labels(&rules, Path::new("api/0.log")).is_empty());
+}
+
+#[tokio::test]
+async fn pod_log_tree_is_scanned_in_rotation_order() {
+    let dir = tempfile::tempdir().unwrap();
+    let pods = dir.path().join("pods");
+    let container = pods.join(POD_DIR).join("api");
+    fs::create_dir_all(&container).unwrap();
+    fs::write(container.join("0.log"), CRI_LOG).unwrap();
+    fs::write(
+        container.join("0.log.20240607-120000"),
+        "2024-06-07T11:59:00Z stdout F error: before rotation\n",
+    )
+    .unwrap();
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder
+        .write_all(b"2024-06-07T11:00:00Z stdout F error: oldest\n")
+        .unwrap();
+    fs::write(
+        container.join("0.log.20240607-110000.gz"),
+        encoder.finish().unwrap(),
+    )
+    .unwrap();
+
+    let mut config = ParserConfig {
+        log_folder: pods.to_string_lossy().into_owned(),
+        output_log: dir.path().join("output.log").to_string_lossy().into_owned(),
+        workers: Some(1),
+        ..ParserConfig::kubernetes()
+    };
+    add_search(&mut config.search_terms, "error", "");
+
+    let names: Vec<_> = collect_log_files(&config)
+        .unwrap()
+        .iter()
+        .map(|path| path.file_name().unwrap().to_string_lossy().into_owned())
+        .collect();
+    assert_eq!(
+        names,
+        ["0.log.20240607-110000.gz", "0.log.20240607-120000", "0.log"]
+    );
+
+    let output_log = config.output_log.clone();
+    let result = run_parser(config, None).await.unwrap();
+
+    assert_eq!(result.total_matches, 4);
+    assert!(
+        result
+            .file_results
+            .iter()
+            .all(|file| file.labels["pod"] == "api-7d9f8b6c5-x2x4q")
+    );
+    let output = fs::read_to_string(output_log).unwrap();
+    assert!(output.starts_with("error: oldest\nerror: before rotation\n"));
+}
+
+#[test]
+fn reassembled_lines_are_cut_at_the_maximum_length() {
+    let mut search_terms = Vec::new();
+    add_search(&mut search_terms, "error", "");
+    let options = ScanOptions {
+        input_format: InputFormat::Cri,
+        max_line_length: Some(20),
+        ..Default::default()
+    };
+    let output = Mutex::new(Vec::new());
+
+    let scan = process_reader(
+        Cursor::new(CRI_LOG),
+        Path::new("0.log"),
+        &search_terms,
+        &options,
+        Some(&output),
+    );
+
+    assert_eq!((scan.matches, scan.long_lines), (2, 2));
+    let output = String::from_utf8(output.into_inner().unwrap()).unwrap();
+    assert_eq!(output, "error: request body \nerror: upstream time\n");
+}
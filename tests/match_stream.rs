@@ -0,0 +1,89 @@
+use elysiumparser::{
+    MATCH_STREAM_CAPACITY, OutputTarget, ParserConfig, ReadProgress, add_search, run_parser_stream,
+};
+use futures::StreamExt;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+fn config(folder: &Path) -> ParserConfig {
+    let mut config = ParserConfig {
+        log_folder: folder.to_string_lossy().into_owned(),
+        output_target: OutputTarget::Discard,
+        ..Default::default()
+    };
+    add_search(&mut config.search_terms, "error", "");
+    add_search(&mut config.search_terms, "warning", "");
+    config
+}
+
+#[tokio::test]
+async fn stream_yields_every_match_with_its_term() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(
+        dir.path().join("app.log"),
+        "info: started\nerror: disk full\nwarning: slow query\n",
+    )
+    .unwrap();
+
+    let mut matches = run_parser_stream(config(dir.path())).unwrap();
+    let mut records = Vec::new();
+    while let Some(record) = matches.next().await {
+        records.push(record);
+    }
+    let result = matches.result().await.unwrap();
+
+    assert_eq!(result.total_matches, 2);
+    records.sort_by_key(|record| record.line_number);
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0].file, dir.path().join("app.log"));
+    assert_eq!(records[0].line_number, 2);
+    assert_eq!(records[0].term_index, 0);
+    assert_eq!(records[0].line, "error: disk full");
+    assert_eq!(records[1].line_number, 3);
+    assert_eq!(records[1].term_index, 1);
+}
+
+#[tokio::test]
+async fn slow_consumers_hold_the_readers_back() {
+    let dir = tempfile::tempdir().unwrap();
+    let lines = MATCH_STREAM_CAPACITY * 4;
+    fs::write(
+        dir.path().join("app.log"),
+        "error: disk full\n".repeat(lines),
+    )
+    .unwrap();
+
+    let read_progress = Arc::new(ReadProgress::default());
+    let mut config = config(dir.path());
+    config.read_progress = Some(Arc::clone(&read_progress));
+    let mut matches = run_parser_stream(config).unwrap();
+
+    assert!(matches.next().await.is_some());
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    // The reader waits for room in the channel instead of finishing the file
+    assert_eq!(read_progress.in_flight().len(), 1);
+
+    let received = 1 + matches.by_ref().count().await;
+    let result = matches.result().await.unwrap();
+    assert_eq!(received, lines);
+    assert_eq!(result.total_matches, lines);
+    assert!(read_progress.in_flight().is_empty());
+}
+
+#[tokio::test]
+async fn result_does_not_wait_for_unread_matches() {
+    let dir = tempfile::tempdir().unwrap();
+    let lines = MATCH_STREAM_CAPACITY * 4;
+    fs::write(
+        dir.path().join("app.log"),
+        "error: disk full\n".repeat(lines),
+    )
+    .unwrap();
+
+    let matches = run_parser_stream(config(dir.path())).unwrap();
+    let result = matches.result().await.unwrap();
+
+    assert_eq!(result.total_matches, lines);
+}
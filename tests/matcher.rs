@@ -0,0 +1,130 @@
+use elysiumparser::{
+    MatchMode, Matcher, ScanOptions, add_search, add_search_with_case, add_search_with_expression,
+    search_reader,
+};
+use std::io::Cursor;
+
+fn matched(matcher: &Matcher, line: &str) -> Option<usize> {
+    matcher.matches_line(line).map(|info| info.term_index)
+}
+
+#[test]
+fn keyword_alone_matches_any_line_containing_it() {
+    let mut terms = Vec::new();
+    add_search(&mut terms, "error", "");
+    let matcher = Matcher::new(&terms, "");
+
+    assert_eq!(matched(&matcher, "ERROR: disk full"), Some(0));
+    assert_eq!(matched(&matcher, "no errors here"), Some(0));
+    assert_eq!(matched(&matcher, "warning: disk full"), None);
+}
+
+#[test]
+fn line_filter_must_be_present_whatever_the_term() {
+    let mut terms = Vec::new();
+    add_search(&mut terms, "error", "");
+    add_search(&mut terms, "timeout", "");
+    let matcher = Matcher::new(&terms, "Payment");
+
+    assert_eq!(matched(&matcher, "payment: error 500"), Some(0));
+    assert_eq!(matched(&matcher, "PAYMENT: timeout"), Some(1));
+    assert_eq!(matched(&matcher, "checkout: error 500"), None);
+    assert_eq!(matched(&matcher, "payment: ok"), None);
+}
+
+#[test]
+fn additional_expression_narrows_its_keyword() {
+    let mut terms = Vec::new();
+    add_search_with_expression(&mut terms, "error", "(disk | network) & !retry").unwrap();
+    let matcher = Matcher::new(&terms, "");
+
+    assert_eq!(matched(&matcher, "error: disk full"), Some(0));
+    assert_eq!(matched(&matcher, "Error: Network unreachable"), Some(0));
+    assert_eq!(matched(&matcher, "error: network unreachable, retry"), None);
+    assert_eq!(matched(&matcher, "error: out of memory"), None);
+    // The expression alone is not enough without the keyword
+    assert_eq!(matched(&matcher, "warning: disk full"), None);
+}
+
+#[test]
+fn keyword_filter_and_expression_all_have_to_match() {
+    let mut terms = Vec::new();
+    add_search_with_expression(&mut terms, "error", "disk").unwrap();
+    add_search(&mut terms, "fatal", "");
+    let matcher = Matcher::new(&terms, "db");
+
+    assert_eq!(matched(&matcher, "db error: disk full"), Some(0));
+    assert_eq!(matched(&matcher, "db error: timeout"), None);
+    assert_eq!(matched(&matcher, "cache error: disk full"), None);
+    // A later term matches when the expression of an earlier one fails
+    assert_eq!(matched(&matcher, "db fatal error: timeout"), Some(1));
+    assert_eq!(matched(&matcher, "db fatal error: disk"), Some(0));
+}
+
+#[test]
+fn match_info_names_the_term_and_its_score() {
+    let mut terms = Vec::new();
+    add_search(&mut terms, "warn", "");
+    add_search(&mut terms, "panic", "");
+    terms[1].score = 50;
+    let matcher = Matcher::new(&terms, "");
+
+    let info = matcher.matches_line("thread main PANICKED").unwrap();
+    assert_eq!(info.term_index, 1);
+    assert_eq!(info.term.keyword, "panic");
+    assert_eq!(info.score, 50);
+}
+
+#[test]
+fn case_sensitive_matchers_take_lines_as_read() {
+    let mut terms = Vec::new();
+    add_search_with_case(&mut terms, "ERROR", "Disk", true).unwrap();
+    let options = ScanOptions {
+        line_filter: "db".to_string(),
+        case_sensitive: true,
+        ..Default::default()
+    };
+    let matcher = Matcher::with_options(&terms, &options).unwrap();
+
+    assert!(matcher.case_sensitive());
+    assert_eq!(matched(&matcher, "db ERROR: Disk full"), Some(0));
+    assert_eq!(matched(&matcher, "db error: Disk full"), None);
+    assert_eq!(matched(&matcher, "db ERROR: disk full"), None);
+    assert_eq!(matched(&matcher, "DB ERROR: Disk full"), None);
+}
+
+#[test]
+fn invalid_regex_modes_fail_to_build() {
+    let mut terms = Vec::new();
+    add_search(&mut terms, "(unclosed", "");
+
+    assert!(Matcher::with_mode(&terms, "", MatchMode::Regex).is_err());
+    assert!(Matcher::with_mode(&terms, "", MatchMode::Substring).is_ok());
+}
+
+#[test]
+fn readers_match_the_lines_the_matcher_does() {
+    let lines = [
+        "db error: disk full",
+        "db error: timeout",
+        "cache error: disk full",
+        "DB ERROR: NETWORK down",
+        "db warning: network slow",
+    ];
+    let mut terms = Vec::new();
+    add_search_with_expression(&mut terms, "error", "disk | network").unwrap();
+    let options = ScanOptions {
+        line_filter: "db".to_string(),
+        ..Default::default()
+    };
+    let matcher = Matcher::with_options(&terms, &options).unwrap();
+
+    let expected: Vec<_> = lines
+        .iter()
+        .filter(|line| matcher.matches_line(line).is_some())
+        .map(|line| line.to_string())
+        .collect();
+    let found = search_reader(Cursor::new(lines.join("\n")), &terms, &options).unwrap();
+    assert_eq!(found, expected);
+    assert_eq!(found.len(), 2);
+}
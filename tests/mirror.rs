@@ -0,0 +1,125 @@
+use elysiumparser::{
+    MatchSink, MirroredTreeSink, OutputMode, ParserConfig, ScanOptions, add_search, process_reader,
+    run_parser,
+};
+use std::fs;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+
+fn mirrored_config(log_folder: &Path, base_output_dir: &Path) -> ParserConfig {
+    let mut config = ParserConfig {
+        log_folder: log_folder.to_string_lossy().into_owned(),
+        output_log: log_folder.join("output.log").to_string_lossy().into_owned(),
+        output_mode: OutputMode::MirroredTree {
+            base_output_dir: base_output_dir.to_path_buf(),
+        },
+        recursive: true,
+        workers: Some(2),
+        ..Default::default()
+    };
+    add_search(&mut config.search_terms, "error", "");
+    config
+}
+
+#[tokio::test]
+async fn matches_of_each_log_go_to_the_same_path_below_the_output_folder() {
+    let logs = tempfile::tempdir().unwrap();
+    let output = tempfile::tempdir().unwrap();
+    let tree = output.path().join("tree");
+    fs::create_dir_all(logs.path().join("a/b")).unwrap();
+    fs::write(logs.path().join("a/b/c.log"), "error: deep\ninfo: ok\n").unwrap();
+    fs::write(logs.path().join("a/quiet.log"), "info: nothing to see\n").unwrap();
+    fs::write(logs.path().join("top.log"), "error: one\nerror: two\n").unwrap();
+
+    let config = mirrored_config(logs.path(), &tree);
+    let output_log = config.output_log.clone();
+    let result = run_parser(config, None).await.unwrap();
+
+    assert_eq!(result.total_matches, 3);
+    assert_eq!(
+        fs::read_to_string(tree.join("a/b/c.log")).unwrap(),
+        "error: deep\n"
+    );
+    assert_eq!(
+        fs::read_to_string(tree.join("top.log")).unwrap(),
+        "error: one\nerror: two\n"
+    );
+    // Logs without matches leave no file, and the output log is not written
+    assert!(!tree.join("a/quiet.log").exists());
+    assert!(!Path::new(&output_log).exists());
+
+    let outputs: Vec<_> = result
+        .file_results
+        .iter()
+        .map(|file| {
+            (
+                file.path.strip_prefix(logs.path()).unwrap(),
+                file.output_path.clone(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        outputs,
+        [
+            (Path::new("a/b/c.log"), Some(tree.join("a/b/c.log"))),
+            (Path::new("a/quiet.log"), None),
+            (Path::new("top.log"), Some(tree.join("top.log"))),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn output_folder_inside_the_log_folder_is_not_searched() {
+    let logs = tempfile::tempdir().unwrap();
+    let tree = logs.path().join("matches");
+    fs::write(logs.path().join("app.log"), "error: disk full\n").unwrap();
+
+    for _ in 0..2 {
+        let result = run_parser(mirrored_config(logs.path(), &tree), None)
+            .await
+            .unwrap();
+        assert_eq!(result.total_matches, 1);
+        assert_eq!(result.file_results.len(), 1);
+    }
+    assert_eq!(
+        fs::read_to_string(tree.join("app.log")).unwrap(),
+        "error: disk full\n"
+    );
+}
+
+#[test]
+fn files_closed_to_open_others_are_appended_to() {
+    let logs = tempfile::tempdir().unwrap();
+    let tree = logs.path().join("tree");
+    let sink = MirroredTreeSink::new(tree.clone(), vec![logs.path().to_path_buf()]);
+    let mut terms = Vec::new();
+    add_search(&mut terms, "error", "");
+
+    // More files than are kept open, written to in turns
+    let sources: Vec<PathBuf> = (0..100)
+        .map(|file| logs.path().join(format!("{}.log", file)))
+        .collect();
+    for round in 0..2 {
+        for source in &sources {
+            let input = format!("error: round {}\n", round);
+            let scan = process_reader(
+                Cursor::new(input),
+                source,
+                &terms,
+                &ScanOptions::default(),
+                Some(&sink),
+            );
+            assert!(scan.error.is_none());
+        }
+    }
+    sink.finish().unwrap();
+
+    for source in &sources {
+        let output = sink.written(source).unwrap();
+        assert_eq!(output, sink.output_path(source));
+        assert_eq!(
+            fs::read_to_string(output).unwrap(),
+            "error: round 0\nerror: round 1\n"
+        );
+    }
+}
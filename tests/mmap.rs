@@ -0,0 +1,107 @@
+#![cfg(feature = "mmap")]
+
+use elysiumparser::{
+    FileMetadata, FileSystem, ParserConfig, StdFileSystem, add_file_assertion, add_search,
+    run_parser,
+};
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// `StdFileSystem` without memory mapping, forcing the buffered fallback
+struct UnmappedFileSystem;
+
+impl FileSystem for UnmappedFileSystem {
+    fn list_dir(&self, path: &Path) -> io::Result<Vec<io::Result<PathBuf>>> {
+        StdFileSystem.list_dir(path)
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<FileMetadata> {
+        StdFileSystem.metadata(path)
+    }
+
+    fn open(&self, path: &Path) -> io::Result<Box<dyn Read + Send>> {
+        StdFileSystem.open(path)
+    }
+}
+
+/// Copy the fixture corpus plus edge cases into a fresh log folder
+fn corpus() -> tempfile::TempDir {
+    let dir = tempfile::tempdir().unwrap();
+    let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+    for entry in fs::read_dir(fixtures).unwrap() {
+        let path = entry.unwrap().path();
+        fs::copy(&path, dir.path().join(path.file_name().unwrap())).unwrap();
+    }
+    fs::write(dir.path().join("empty.log"), "").unwrap();
+    fs::write(
+        dir.path().join("unterminated.log"),
+        "error repeated\nerror repeated\r\ninfo\nerror last line",
+    )
+    .unwrap();
+    dir
+}
+
+/// Scan the corpus and return the output and per-file statistics
+async fn scan(dir: &Path, mmap: bool, file_system: Arc<dyn FileSystem>) -> (String, Vec<String>) {
+    let output_log = dir
+        .join("output")
+        .with_extension(if mmap { "mmap" } else { "read" });
+    let mut config = ParserConfig {
+        log_folder: dir.to_string_lossy().into_owned(),
+        output_log: output_log.to_string_lossy().into_owned(),
+        workers: Some(1),
+        collapse_consecutive: true,
+        file_system,
+        mmap,
+        ..Default::default()
+    };
+    add_search(&mut config.search_terms, "error", "");
+    add_search(&mut config.search_terms, "warn", "");
+    add_file_assertion(&mut config.assertions, "error", "info").unwrap();
+
+    let result = run_parser(config, None).await.unwrap();
+
+    let mut output: Vec<_> = fs::read_to_string(output_log)
+        .unwrap()
+        .lines()
+        .map(str::to_string)
+        .collect();
+    output.sort();
+    let files = result
+        .file_results
+        .iter()
+        .map(|file| {
+            format!(
+                "{} {} {} {}",
+                file.path.display(),
+                file.matches,
+                file.lines_scanned,
+                file.bytes_read
+            )
+        })
+        .collect();
+    (output.join("\n"), files)
+}
+
+#[tokio::test]
+async fn mapped_scans_match_buffered_scans() {
+    let dir = corpus();
+
+    let buffered = scan(dir.path(), false, Arc::new(StdFileSystem)).await;
+    let mapped = scan(dir.path(), true, Arc::new(StdFileSystem)).await;
+
+    assert!(buffered.0.contains("error last line"));
+    assert_eq!(mapped, buffered);
+}
+
+#[tokio::test]
+async fn unmappable_files_fall_back_to_buffered_reads() {
+    let dir = corpus();
+
+    let buffered = scan(dir.path(), false, Arc::new(StdFileSystem)).await;
+    let fallback = scan(dir.path(), true, Arc::new(UnmappedFileSystem)).await;
+
+    assert_eq!(fallback, buffered);
+}
@@ -0,0 +1,117 @@
+use elysiumparser::{
+    FileMetadata, FileSystem, ParserConfig, StdFileSystem, add_search, collect_log_files,
+    run_parser,
+};
+use filetime::FileTime;
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use std::fs::{self, File};
+use std::io::{self, ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+/// Set the modification time of `path` to `age` ago
+fn age(path: &Path, age: Duration) {
+    let modified = FileTime::from_system_time(SystemTime::now() - age);
+    filetime::set_file_mtime(path, modified).unwrap();
+}
+
+fn config_for(dir: &Path) -> ParserConfig {
+    ParserConfig {
+        log_folder: dir.to_string_lossy().into_owned(),
+        output_log: dir.join("output.txt").to_string_lossy().into_owned(),
+        ..Default::default()
+    }
+}
+
+fn names(paths: &[PathBuf]) -> Vec<String> {
+    paths
+        .iter()
+        .map(|path| path.file_name().unwrap().to_string_lossy().into_owned())
+        .collect()
+}
+
+#[tokio::test]
+async fn old_logs_and_archives_are_skipped() {
+    let dir = tempfile::tempdir().unwrap();
+    let hour = Duration::from_secs(3600);
+    for (name, hours) in [("fresh.log", 0), ("recent.log", 2), ("stale.log", 30)] {
+        let path = dir.path().join(name);
+        fs::write(&path, "error\n").unwrap();
+        age(&path, hour * hours);
+    }
+    for (name, hours) in [("recent.log.gz", 1), ("stale.log.gz", 48)] {
+        let path = dir.path().join(name);
+        let mut encoder = GzEncoder::new(File::create(&path).unwrap(), Compression::default());
+        encoder.write_all(b"error\n").unwrap();
+        encoder.finish().unwrap();
+        age(&path, hour * hours);
+    }
+
+    let mut config = config_for(dir.path());
+    assert_eq!(collect_log_files(&config).unwrap().len(), 5);
+
+    config.modified_within_secs = Some(3 * 3600);
+    assert_eq!(
+        names(&collect_log_files(&config).unwrap()),
+        ["fresh.log", "recent.log", "recent.log.gz"]
+    );
+
+    add_search(&mut config.search_terms, "error", "");
+    let result = run_parser(config, None).await.unwrap();
+    assert_eq!(result.processed_files, 3);
+    assert_eq!(result.total_matches, 3);
+}
+
+#[test]
+fn files_modified_in_the_future_are_kept() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("clock-skew.log");
+    fs::write(&path, "").unwrap();
+    let modified = FileTime::from_system_time(SystemTime::now() + Duration::from_secs(600));
+    filetime::set_file_mtime(&path, modified).unwrap();
+
+    let mut config = config_for(dir.path());
+    config.modified_within_secs = Some(0);
+
+    assert_eq!(
+        names(&collect_log_files(&config).unwrap()),
+        ["clock-skew.log"]
+    );
+}
+
+/// Local disk that does not report modification times
+struct NoModifiedTimes;
+
+impl FileSystem for NoModifiedTimes {
+    fn list_dir(&self, path: &Path) -> io::Result<Vec<io::Result<PathBuf>>> {
+        StdFileSystem.list_dir(path)
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<FileMetadata> {
+        Ok(FileMetadata {
+            modified: None,
+            ..StdFileSystem.metadata(path)?
+        })
+    }
+
+    fn open(&self, path: &Path) -> io::Result<Box<dyn Read + Send>> {
+        StdFileSystem.open(path)
+    }
+}
+
+#[test]
+fn missing_modification_times_are_an_error() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("app.log"), "").unwrap();
+
+    let mut config = config_for(dir.path());
+    config.file_system = Arc::new(NoModifiedTimes);
+    assert_eq!(collect_log_files(&config).unwrap().len(), 1);
+
+    config.modified_within_secs = Some(60);
+    let error = collect_log_files(&config).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::Unsupported);
+    assert!(error.to_string().contains("modification time"));
+}
@@ -0,0 +1,93 @@
+#![cfg(feature = "arrow")]
+
+use arrow_array::{Array, MapArray, StringArray, UInt64Array};
+use elysiumparser::{
+    OutputTarget, ParserConfig, PathLabelRule, add_search, parquet_schema, run_parser,
+};
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use regex::Regex;
+use std::fs::{self, File};
+
+#[tokio::test]
+async fn parquet_output_round_trips() {
+    let dir = tempfile::tempdir().unwrap();
+    let logs = dir.path().join("logs");
+    fs::create_dir(&logs).unwrap();
+    let lines: String = (1..=25)
+        .map(|n| format!("error {}\ninfo {}\n", n, n))
+        .collect();
+    fs::write(logs.join("app.log"), lines).unwrap();
+
+    let parquet_path = dir.path().join("matches.parquet");
+    let mut config = ParserConfig {
+        log_folder: logs.to_string_lossy().into_owned(),
+        output_log: dir.path().join("output.log").to_string_lossy().into_owned(),
+        output_target: OutputTarget::Parquet(parquet_path.clone()),
+        parquet_batch_rows: 10,
+        path_labels: vec![PathLabelRule::Regex(
+            Regex::new(r"^(?P<name>[a-z]+)\.log$").unwrap(),
+        )],
+        ..Default::default()
+    };
+    add_search(&mut config.search_terms, "error", "");
+
+    let result = run_parser(config, None).await.unwrap();
+    assert_eq!(result.total_matches, 25);
+    assert!(!dir.path().join("output.log").exists());
+
+    let reader = ParquetRecordBatchReaderBuilder::try_new(File::open(parquet_path).unwrap())
+        .unwrap()
+        .build()
+        .unwrap();
+    let batches: Vec<_> = reader.map(Result::unwrap).collect();
+
+    assert_eq!(batches[0].schema(), parquet_schema());
+    assert_eq!(
+        batches.iter().map(|batch| batch.num_rows()).sum::<usize>(),
+        25
+    );
+
+    let batch = &batches[0];
+    let line_numbers = batch
+        .column_by_name("line_number")
+        .unwrap()
+        .as_any()
+        .downcast_ref::<UInt64Array>()
+        .unwrap();
+    let texts = batch
+        .column_by_name("line")
+        .unwrap()
+        .as_any()
+        .downcast_ref::<StringArray>()
+        .unwrap();
+    let labels = batch
+        .column_by_name("label")
+        .unwrap()
+        .as_any()
+        .downcast_ref::<StringArray>()
+        .unwrap();
+
+    assert_eq!(line_numbers.value(1), 3);
+    assert_eq!(texts.value(1), "error 2");
+    assert_eq!(labels.value(1), "error");
+    assert!(batch.column_by_name("timestamp").unwrap().is_null(0));
+
+    let path_labels = batch
+        .column_by_name("path_labels")
+        .unwrap()
+        .as_any()
+        .downcast_ref::<MapArray>()
+        .unwrap();
+    let entries = path_labels.value(1);
+    let keys = entries
+        .column(0)
+        .as_any()
+        .downcast_ref::<StringArray>()
+        .unwrap();
+    let values = entries
+        .column(1)
+        .as_any()
+        .downcast_ref::<StringArray>()
+        .unwrap();
+    assert_eq!((keys.value(0), values.value(0)), ("name", "app"));
+}
@@ -0,0 +1,127 @@
+use elysiumparser::{
+    OutputFormat, ParserConfig, PathLabelRule, add_search, path_labels, run_parser,
+};
+use regex::Regex;
+use std::fs;
+use std::path::Path;
+
+fn component_rules() -> Vec<PathLabelRule> {
+    vec![
+        PathLabelRule::Component {
+            index: 0,
+            key: "customer".to_string(),
+        },
+        PathLabelRule::Component {
+            index: 1,
+            key: "service".to_string(),
+        },
+    ]
+}
+
+#[test]
+fn component_rules_label_directories() {
+    let labels = path_labels(&component_rules(), Path::new("acme/api/app.log.gz"));
+
+    assert_eq!(labels["customer"], "acme");
+    assert_eq!(labels["service"], "api");
+}
+
+#[test]
+fn windows_separators_are_normalized() {
+    let labels = path_labels(&component_rules(), Path::new(r"acme\api\app.log.gz"));
+
+    assert_eq!(labels["customer"], "acme");
+    assert_eq!(labels["service"], "api");
+}
+
+#[test]
+fn files_in_the_root_yield_no_labels() {
+    let regex = Regex::new(r"^(?P<customer>[^/]+)/(?P<service>[^/]+)/").unwrap();
+    let mut rules = component_rules();
+    rules.push(PathLabelRule::Regex(regex));
+
+    assert!(path_labels(&rules, Path::new("app.log")).is_empty());
+
+    let labels = path_labels(&rules, Path::new("acme/app.log"));
+    assert_eq!(labels.len(), 1);
+    assert_eq!(labels["customer"], "acme");
+}
+
+#[test]
+fn regex_rules_use_named_captures() {
+    let regex = Regex::new(r"^(?P<customer>[^/]+)/(?P<service>[^/]+)/").unwrap();
+    let rules = [PathLabelRule::Regex(regex)];
+
+    let labels = path_labels(&rules, Path::new(r"globex\billing\2024.log"));
+
+    assert_eq!(labels["customer"], "globex");
+    assert_eq!(labels["service"], "billing");
+}
+
+#[tokio::test]
+async fn file_results_carry_path_labels() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("app.log"), "error\n").unwrap();
+
+    let mut config = ParserConfig {
+        log_folder: dir.path().to_string_lossy().into_owned(),
+        output_log: dir.path().join("output.log").to_string_lossy().into_owned(),
+        path_labels: vec![PathLabelRule::Regex(
+            Regex::new(r"^(?P<name>[a-z]+)\.log$").unwrap(),
+        )],
+        ..Default::default()
+    };
+    add_search(&mut config.search_terms, "error", "");
+
+    let result = run_parser(config, None).await.unwrap();
+
+    assert_eq!(result.file_results[0].labels["name"], "app");
+}
+
+#[tokio::test]
+async fn matches_carry_the_labels_of_their_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let logs = dir.path().join("logs");
+    fs::create_dir_all(logs.join("acme/api")).unwrap();
+    fs::write(logs.join("acme/api/app.log"), "error one\n").unwrap();
+    fs::write(logs.join("root.log"), "error two\n").unwrap();
+
+    let output_log = dir.path().join("output.jsonl");
+    let mut config = ParserConfig {
+        log_folder: logs.to_string_lossy().into_owned(),
+        output_log: output_log.to_string_lossy().into_owned(),
+        recursive: true,
+        output_format: OutputFormat::Jsonl,
+        collect_matches: true,
+        path_labels: component_rules(),
+        ..Default::default()
+    };
+    add_search(&mut config.search_terms, "error", "");
+
+    let result = run_parser(config, None).await.unwrap();
+
+    let labelled: Vec<_> = result
+        .matches
+        .iter()
+        .map(|record| (record.line.as_str(), record.labels.len()))
+        .collect();
+    assert_eq!(labelled, [("error one", 2), ("error two", 0)]);
+    assert_eq!(result.matches[0].labels["service"], "api");
+
+    let output = fs::read_to_string(output_log).unwrap();
+    let records: Vec<serde_json::Value> = output
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .filter(|record: &serde_json::Value| record.get("line").is_some())
+        .collect();
+    let one = records
+        .iter()
+        .find(|record| record["line"] == "error one")
+        .unwrap();
+    assert_eq!(one["labels"]["customer"], "acme");
+    let two = records
+        .iter()
+        .find(|record| record["line"] == "error two")
+        .unwrap();
+    assert!(two.get("labels").is_none());
+}
@@ -0,0 +1,436 @@
+use elysiumparser::{
+    BooleanExpression, CancelToken, FileScan, FuzzyPattern, MatchMode, MatchRecord, MatchSink,
+    MatchStrategy, ParserError, ScanOptions, SearchTerm, Term, add_search, add_search_regex,
+    add_search_with_case, add_search_with_expression, process_file_silent, process_reader,
+    search_reader,
+};
+use std::fs::{self, File};
+use std::io::{self, Cursor};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+/// Run `process_reader` over `input` and return the match count and written output
+fn run(input: &str, keyword: &str, collapse_consecutive: bool) -> (usize, String) {
+    let dir = tempfile::tempdir().unwrap();
+    let output_path = dir.path().join("output.log");
+    let output_file = Arc::new(Mutex::new(File::create(&output_path).unwrap()));
+
+    let mut search_terms = Vec::new();
+    add_search(&mut search_terms, keyword, "");
+
+    let options = ScanOptions {
+        collapse_consecutive,
+        ..Default::default()
+    };
+    let scan = process_reader(
+        Cursor::new(input),
+        Path::new("input.log"),
+        &search_terms,
+        &options,
+        Some(&*output_file),
+    );
+    drop(output_file);
+
+    (scan.matches, fs::read_to_string(&output_path).unwrap())
+}
+
+#[test]
+fn collapse_consecutive_merges_identical_matches() {
+    let input = "error: disk full\nerror: disk full\nerror: disk full\ninfo: ok\n";
+
+    let (count, output) = run(input, "error", true);
+
+    assert_eq!(count, 3);
+    assert_eq!(output, "error: disk full (x3)\n");
+}
+
+#[test]
+fn collapse_consecutive_keeps_distinct_runs_in_order() {
+    let input = "error a\nerror a\nerror b\nerror a\n";
+
+    let (count, output) = run(input, "error", true);
+
+    assert_eq!(count, 4);
+    assert_eq!(output, "error a (x2)\nerror b\nerror a\n");
+}
+
+#[test]
+fn without_collapse_every_match_is_written() {
+    let input = "error: disk full\nerror: disk full\n";
+
+    let (count, output) = run(input, "error", false);
+
+    assert_eq!(count, 2);
+    assert_eq!(output, "error: disk full\nerror: disk full\n");
+}
+
+/// Run `process_reader` over `input` with a single regex term
+fn run_regex(input: &str, pattern: &str, case_insensitive: bool) -> String {
+    let mut search_terms = Vec::new();
+    add_search_regex(&mut search_terms, pattern, case_insensitive).unwrap();
+    let output = Mutex::new(Vec::new());
+
+    process_reader(
+        Cursor::new(input),
+        Path::new("input.log"),
+        &search_terms,
+        &ScanOptions::default(),
+        Some(&output),
+    );
+
+    String::from_utf8(output.into_inner().unwrap()).unwrap()
+}
+
+#[test]
+fn regex_terms_match_the_original_line() {
+    let input = "GET / status=503\nGET / status=200\nWARN Connection reset by peer\n10.0.0.7: connection reset by peer\n";
+
+    assert_eq!(
+        run_regex(input, r"status=(5\d\d)", true),
+        "GET / status=503\n"
+    );
+    assert_eq!(
+        run_regex(input, r"\d+\.\d+\.\d+\.\d+: connection reset by peer", true),
+        "10.0.0.7: connection reset by peer\n"
+    );
+    assert_eq!(
+        run_regex(input, "Connection reset", false),
+        "WARN Connection reset by peer\n"
+    );
+    assert_eq!(
+        run_regex(input, "CONNECTION RESET", true),
+        "WARN Connection reset by peer\n10.0.0.7: connection reset by peer\n"
+    );
+}
+
+#[test]
+fn invalid_regex_terms_are_rejected() {
+    let mut search_terms = Vec::new();
+
+    assert!(add_search_regex(&mut search_terms, "status=(5", true).is_err());
+    assert!(search_terms.is_empty());
+}
+
+/// Run `process_reader` over `input` with a case-sensitive term
+fn run_case_sensitive(
+    input: &str,
+    keyword: &str,
+    additional: &str,
+    options: ScanOptions,
+) -> String {
+    let mut search_terms = Vec::new();
+    add_search_with_case(&mut search_terms, keyword, additional, true).unwrap();
+    let options = ScanOptions {
+        case_sensitive: true,
+        ..options
+    };
+    let output = Mutex::new(Vec::new());
+
+    process_reader(
+        Cursor::new(input),
+        Path::new("input.log"),
+        &search_terms,
+        &options,
+        Some(&output),
+    );
+
+    String::from_utf8(output.into_inner().unwrap()).unwrap()
+}
+
+#[test]
+fn case_sensitive_terms_tell_error_from_error() {
+    let input = "ERROR disk full\nerror: retrying\nError handled\n";
+
+    assert_eq!(
+        run_case_sensitive(input, "ERROR", "", ScanOptions::default()),
+        "ERROR disk full\n"
+    );
+    assert_eq!(
+        run_case_sensitive(input, "error", "", ScanOptions::default()),
+        "error: retrying\n"
+    );
+    assert_eq!(
+        run_case_sensitive(input, "", "Error | ERROR", ScanOptions::default()),
+        "ERROR disk full\nError handled\n"
+    );
+    assert_eq!(run(input, "ERROR", false).0, 3);
+}
+
+#[test]
+fn insensitive_runs_lowercase_ascii_and_unicode_lines_alike() {
+    let input = "ÉCHEC: Disk full\nINFO: ok\nWARN: Disk nearly full\néchec: disk retry\n";
+
+    let (matches, output) = run(input, "disk", false);
+    assert_eq!(matches, 3);
+    assert_eq!(
+        output,
+        "ÉCHEC: Disk full\nWARN: Disk nearly full\néchec: disk retry\n"
+    );
+    assert_eq!(run(input, "ÉCHEC", false).0, 2);
+}
+
+#[test]
+fn case_sensitive_runs_keep_the_case_of_filters_and_regexes() {
+    let input = "userId=AB12 Login\nuserid=ab12 login\n";
+
+    let filtered = ScanOptions {
+        line_filter: "AB12".to_string(),
+        ..Default::default()
+    };
+    assert_eq!(
+        run_case_sensitive(input, "", "re:[Ll]ogin", filtered),
+        "userId=AB12 Login\n"
+    );
+
+    let regex = ScanOptions {
+        match_mode: MatchMode::Regex,
+        ..Default::default()
+    };
+    assert_eq!(
+        run_case_sensitive(input, "[a-z]+=[a-z]+", "", regex),
+        "userid=ab12 login\n"
+    );
+}
+
+#[test]
+fn regex_mode_keeps_the_escapes_of_patterns_as_written() {
+    let input = "build 1234\nDeploy failed\n";
+    let search_terms = vec![SearchTerm {
+        keyword: r"^\D+$".to_string(),
+        keyword_pattern: None,
+        additional_expression: None,
+        score: 0,
+        output: None,
+    }];
+
+    for line_filter in ["", r"\D{6}"] {
+        let options = ScanOptions {
+            match_mode: MatchMode::Regex,
+            line_filter: line_filter.to_string(),
+            ..Default::default()
+        };
+        let lines = search_reader(input.as_bytes(), &search_terms, &options).unwrap();
+        assert_eq!(lines, ["Deploy failed"], "{}", line_filter);
+    }
+}
+
+#[test]
+fn terms_match_alike_however_they_are_built() {
+    let input = "ERROR: Disk full\nerror: disk ok\nError: network down\ninfo: disk full\n";
+    let expression = BooleanExpression::parse("Disk & !OK").unwrap();
+
+    let mut built = vec![vec![SearchTerm {
+        keyword: "Error".to_string(),
+        keyword_pattern: None,
+        additional_expression: Some(BooleanExpression::And(vec![
+            BooleanExpression::Term(Term::Literal("Disk".to_string())),
+            BooleanExpression::Not(Box::new(BooleanExpression::Term(Term::Fuzzy(
+                FuzzyPattern::new("OK", 0),
+            )))),
+        ])),
+        score: 0,
+        output: None,
+    }]];
+    let mut helper = Vec::new();
+    add_search_with_expression(&mut helper, "Error", "Disk & !ok").unwrap();
+    built.push(helper);
+    let mut helper = Vec::new();
+    add_search_with_case(&mut helper, "ERROR", "disk & !OK", false).unwrap();
+    built.push(helper);
+    built.push(vec![SearchTerm {
+        keyword: "eRRor".to_string(),
+        keyword_pattern: None,
+        additional_expression: Some(expression),
+        score: 0,
+        output: None,
+    }]);
+
+    for strategy in [
+        MatchStrategy::Naive,
+        MatchStrategy::AhoCorasick,
+        MatchStrategy::Prefilter,
+    ] {
+        let options = ScanOptions {
+            strategy,
+            ..Default::default()
+        };
+        for search_terms in &built {
+            let output = Mutex::new(Vec::new());
+            let scan = process_reader(
+                Cursor::new(input),
+                Path::new("input.log"),
+                search_terms,
+                &options,
+                Some(&output),
+            );
+
+            assert_eq!(scan.matches, 1, "{} with {}", search_terms[0], strategy);
+            assert_eq!(output.into_inner().unwrap(), b"ERROR: Disk full\n");
+        }
+    }
+}
+
+#[test]
+fn cancelled_readers_stop_between_lines() {
+    let input: String = (0..100_000)
+        .map(|line| format!("error {}\n", line))
+        .collect();
+    let mut search_terms = Vec::new();
+    add_search(&mut search_terms, "error", "");
+    let cancel = CancelToken::new();
+    cancel.cancel();
+    let options = ScanOptions {
+        cancel: Some(cancel),
+        ..Default::default()
+    };
+
+    let scan = process_reader(
+        Cursor::new(input),
+        Path::new("input.log"),
+        &search_terms,
+        &options,
+        None,
+    );
+
+    assert!(scan.lines_scanned > 0);
+    assert!(scan.lines_scanned < 1000, "{}", scan.lines_scanned);
+    assert_eq!(scan.matches, scan.lines_scanned);
+}
+
+#[test]
+fn missing_files_report_an_error_instead_of_printing_it() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut search_terms = Vec::new();
+    add_search(&mut search_terms, "error", "");
+
+    let scan = process_file_silent(
+        &dir.path().join("missing.log"),
+        &search_terms,
+        &ScanOptions::default(),
+        None,
+    );
+
+    assert_eq!(scan.matches, 0);
+    let error = scan.error.unwrap();
+    assert!(matches!(error, ParserError::Open(_)), "{}", error);
+    assert!(error.to_string().starts_with("error opening file"));
+}
+
+#[test]
+fn search_reader_returns_the_matching_lines() {
+    let mut search_terms = Vec::new();
+    add_search_with_expression(&mut search_terms, "error", "disk | timeout").unwrap();
+    let input = "error: disk full\ninfo: ok\nerror: timeout\nerror: denied\n";
+    let options = ScanOptions {
+        line_filter: "error".to_string(),
+        ..Default::default()
+    };
+
+    let lines = search_reader(input.as_bytes(), &search_terms, &options).unwrap();
+
+    assert_eq!(lines, ["error: disk full", "error: timeout"]);
+}
+
+#[test]
+fn search_reader_reports_invalid_patterns() {
+    let mut search_terms = Vec::new();
+    add_search(&mut search_terms, "status=(5", "");
+    let options = ScanOptions {
+        match_mode: MatchMode::Regex,
+        ..Default::default()
+    };
+
+    let error = search_reader("status=500\n".as_bytes(), &search_terms, &options).unwrap_err();
+
+    assert!(error.to_string().starts_with("invalid search pattern"));
+}
+
+/// Sink whose every write fails, like a full disk
+struct FullDisk;
+
+impl MatchSink for FullDisk {
+    fn write_match(&self, _: &MatchRecord<'_>) -> io::Result<()> {
+        Err(io::Error::new(io::ErrorKind::StorageFull, "no space left"))
+    }
+}
+
+#[test]
+fn failed_writes_are_reported_as_write_errors() {
+    let mut search_terms = Vec::new();
+    add_search(&mut search_terms, "error", "");
+
+    let scan = process_reader(
+        Cursor::new("error: one\nerror: two\n"),
+        Path::new("input.log"),
+        &search_terms,
+        &ScanOptions::default(),
+        Some(&FullDisk),
+    );
+
+    // Scanning stops at the first failure, which is kept
+    assert_eq!(scan.matches, 1);
+    let Some(ParserError::Write(e)) = scan.error else {
+        panic!("unexpected error: {:?}", scan.error);
+    };
+    assert_eq!(e.kind(), io::ErrorKind::StorageFull);
+}
+
+/// Run `process_reader` over raw bytes with `options`, keeping the output in memory
+fn run_bytes(input: &[u8], options: &ScanOptions) -> (FileScan, String) {
+    let mut search_terms = Vec::new();
+    add_search(&mut search_terms, "error", "");
+    let output = Mutex::new(Vec::new());
+
+    let scan = process_reader(
+        Cursor::new(input),
+        Path::new("input.log"),
+        &search_terms,
+        options,
+        Some(&output),
+    );
+
+    (
+        scan,
+        String::from_utf8(output.into_inner().unwrap()).unwrap(),
+    )
+}
+
+#[test]
+fn invalid_utf8_lines_are_matched_with_replacement_characters() {
+    let (scan, output) = run_bytes(b"error \xff\xfe disk\ninfo \xc3\n", &ScanOptions::default());
+
+    assert_eq!(scan.matches, 1);
+    assert_eq!(scan.lines_scanned, 2);
+    assert_eq!(output, "error \u{fffd}\u{fffd} disk\n");
+}
+
+#[test]
+fn lines_longer_than_the_limit_are_cut_and_counted() {
+    let input = format!("error {}\nerror short\n", "x".repeat(100));
+    let options = ScanOptions {
+        max_line_length: Some(16),
+        ..Default::default()
+    };
+
+    let (scan, output) = run_bytes(input.as_bytes(), &options);
+
+    assert_eq!(scan.matches, 2);
+    assert_eq!(scan.long_lines, 1);
+    assert_eq!(output, "error xxxxxxxxxx\nerror short\n");
+}
+
+#[test]
+fn long_lines_can_be_skipped() {
+    let input = format!("error {}\nerror short\n", "x".repeat(100));
+    let options = ScanOptions {
+        max_line_length: Some(16),
+        skip_long_lines: true,
+        ..Default::default()
+    };
+
+    let (scan, output) = run_bytes(input.as_bytes(), &options);
+
+    assert_eq!(scan.matches, 1);
+    assert_eq!(scan.long_lines, 1);
+    assert_eq!(output, "error short\n");
+}
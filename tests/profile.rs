@@ -0,0 +1,214 @@
+use elysiumparser::{InterpolationMode, MatchMode, OutputFormat, ParserConfig, Profile, ScoreRule};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+
+fn write_profile(dir: &Path, name: &str, contents: &str) -> std::path::PathBuf {
+    let path = dir.join(name);
+    fs::write(&path, contents).unwrap();
+    path
+}
+
+#[test]
+fn sample_profile_loads() {
+    let config = ParserConfig::from_file(Path::new("examples/profile.json")).unwrap();
+
+    assert_eq!(config.log_folder, "logs/application");
+    assert_eq!(config.search_terms.len(), 2);
+    assert!(config.search_terms[0].additional_expression.is_some());
+    assert!(config.search_terms[1].additional_expression.is_none());
+    assert_eq!(config.match_mode, MatchMode::WholeWord);
+    assert_eq!(config.output_format, OutputFormat::Jsonl);
+    assert_eq!(config.modified_within_secs, Some(2 * 24 * 60 * 60));
+    assert!(config.recursive);
+    assert_eq!(config.after_context, 2);
+}
+
+#[test]
+fn unset_keys_keep_their_value() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = write_profile(dir.path(), "profile.json", r#"{ "workers": 3 }"#);
+    let mut config = ParserConfig {
+        log_folder: "custom".to_string(),
+        invert_match: true,
+        ..Default::default()
+    };
+
+    Profile::load(&path).unwrap().apply(&mut config).unwrap();
+
+    assert_eq!(config.workers, Some(3));
+    assert_eq!(config.log_folder, "custom");
+    assert!(config.invert_match);
+}
+
+#[test]
+fn terms_follow_the_case_sensitivity_of_the_profile() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = write_profile(
+        dir.path(),
+        "profile.json",
+        r#"{ "case_sensitive": true, "search": [{ "keyword": "Error" }] }"#,
+    );
+
+    let config = ParserConfig::from_file(&path).unwrap();
+
+    assert_eq!(config.search_terms[0].keyword, "Error");
+}
+
+#[test]
+fn unknown_keys_are_named() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = write_profile(dir.path(), "profile.json", r#"{ "log_foldr": "logs" }"#);
+
+    let error = ParserConfig::from_file(&path).err().unwrap();
+
+    assert_eq!(error.kind(), ErrorKind::InvalidData);
+    assert!(error.to_string().contains("log_foldr"), "{}", error);
+    assert!(error.to_string().contains("profile.json"), "{}", error);
+}
+
+#[test]
+fn malformed_values_are_rejected() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = write_profile(dir.path(), "profile.json", r#"{ "match_mode": "fuzzy" }"#);
+
+    let error = ParserConfig::from_file(&path).err().unwrap();
+
+    assert_eq!(error.kind(), ErrorKind::InvalidData);
+    assert!(error.to_string().contains("fuzzy"), "{}", error);
+}
+
+#[test]
+fn invalid_expressions_are_rejected() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = write_profile(
+        dir.path(),
+        "profile.json",
+        r#"{ "search": [{ "keyword": "error", "expression": "database &" }] }"#,
+    );
+
+    let error = ParserConfig::from_file(&path).err().unwrap();
+
+    assert_eq!(error.kind(), ErrorKind::InvalidInput);
+}
+
+#[test]
+fn environment_variables_are_expanded() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = write_profile(
+        dir.path(),
+        "profile.json",
+        r#"{
+            "log_folder": "${CARGO_MANIFEST_DIR}/logs",
+            "output_log": "${ELYSIUM_UNSET_OUTPUT:-out}/matches.log",
+            "search": [{ "keyword": "price", "expression": "$${total} | ${ELYSIUM_UNSET}amount" }]
+        }"#,
+    );
+
+    let config = ParserConfig::from_file(&path).unwrap();
+
+    assert_eq!(
+        config.log_folder,
+        format!("{}/logs", env!("CARGO_MANIFEST_DIR"))
+    );
+    assert_eq!(config.output_log, "out/matches.log");
+    let term = &config.search_terms[0];
+    assert!(term.matches("price: ${total}"));
+    assert!(term.matches("price amount"));
+    assert!(!term.matches("price total"));
+}
+
+#[test]
+fn strict_profiles_reject_unset_variables() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = write_profile(
+        dir.path(),
+        "profile.json",
+        r#"{ "line_filter": "${ELYSIUM_UNSET}" }"#,
+    );
+
+    let error = Profile::load_with(&path, InterpolationMode::Strict)
+        .err()
+        .unwrap();
+
+    assert_eq!(error.kind(), ErrorKind::InvalidData);
+    assert!(error.to_string().contains("ELYSIUM_UNSET"), "{}", error);
+    assert!(Profile::load(&path).is_ok());
+}
+
+#[test]
+fn toml_profiles_set_terms_scores_and_triage() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = write_profile(
+        dir.path(),
+        "profile.toml",
+        r#"
+log_folder = "logs/application"
+workers = 3
+match_mode = "whole-word"
+triage_output = "triage.tsv"
+
+[[search]]
+keyword = "error"
+expression = "database | timeout"
+score = 5
+
+[[search]]
+keyword = "warning"
+
+[[score_rules]]
+expression = "level=error"
+points = 3
+
+[[score_rules]]
+longer_than = 200
+points = 1
+"#,
+    );
+
+    let config = ParserConfig::from_file(&path).unwrap();
+
+    assert_eq!(config.log_folder, "logs/application");
+    assert_eq!(config.workers, Some(3));
+    assert_eq!(config.match_mode, MatchMode::WholeWord);
+    let scores: Vec<_> = config.search_terms.iter().map(|term| term.score).collect();
+    assert_eq!(scores, [5, 0]);
+    assert!(matches!(
+        config.score_rules.as_slice(),
+        [
+            ScoreRule::Matches { points: 3, .. },
+            ScoreRule::LineLongerThan {
+                length: 200,
+                points: 1
+            }
+        ]
+    ));
+    assert_eq!(
+        config.triage_output.as_deref(),
+        Some(Path::new("triage.tsv"))
+    );
+}
+
+#[test]
+fn score_rules_need_a_single_condition() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = write_profile(
+        dir.path(),
+        "profile.json",
+        r#"{ "score_rules": [{ "expression": "x", "longer_than": 3, "points": 1 }] }"#,
+    );
+
+    let error = ParserConfig::from_file(&path).err().unwrap();
+
+    assert_eq!(error.kind(), ErrorKind::InvalidInput);
+}
+
+#[test]
+fn other_formats_are_unsupported() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = write_profile(dir.path(), "profile.yaml", "workers: 3\n");
+
+    let error = ParserConfig::from_file(&path).err().unwrap();
+
+    assert_eq!(error.kind(), ErrorKind::Unsupported);
+}
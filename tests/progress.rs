@@ -0,0 +1,108 @@
+use elysiumparser::{
+    FileProgress, ParserConfig, ReadProgress, ScanOptions, add_search,
+    process_reader_with_progress, run_parser,
+};
+use std::fs;
+use std::io::{BufReader, Read};
+use std::path::Path;
+use std::sync::Arc;
+
+/// A reader yielding `line` over and over until `len` bytes were read
+struct RepeatingReader {
+    line: &'static [u8],
+    offset: usize,
+    left: u64,
+}
+
+impl RepeatingReader {
+    fn new(line: &'static str, len: u64) -> Self {
+        Self {
+            line: line.as_bytes(),
+            offset: 0,
+            left: len,
+        }
+    }
+}
+
+impl Read for RepeatingReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let mut written = 0;
+        while written < buf.len() && self.left > 0 {
+            let chunk = &self.line[self.offset..];
+            let n = chunk.len().min(buf.len() - written).min(self.left as usize);
+            buf[written..written + n].copy_from_slice(&chunk[..n]);
+            written += n;
+            self.left -= n as u64;
+            self.offset = (self.offset + n) % self.line.len();
+        }
+        Ok(written)
+    }
+}
+
+fn scan(len: u64, interval: u64) -> (u64, FileProgress) {
+    let mut search_terms = Vec::new();
+    add_search(&mut search_terms, "error", "");
+    let options = ScanOptions {
+        progress_interval_bytes: interval,
+        ..Default::default()
+    };
+    let progress = FileProgress::new(Path::new("large.log"), Some(len));
+    let scan = process_reader_with_progress(
+        BufReader::new(RepeatingReader::new("info: request served in 12ms\n", len)),
+        Path::new("large.log"),
+        &search_terms,
+        &options,
+        None,
+        &progress,
+    );
+    (scan.bytes_read, progress)
+}
+
+#[test]
+fn large_readers_tick_every_interval() {
+    let (bytes_read, progress) = scan(32 << 20, 1 << 20);
+
+    assert_eq!(bytes_read, 32 << 20);
+    // One tick per MiB, plus the final one
+    assert!(progress.ticks() >= 32, "only {} ticks", progress.ticks());
+    assert!(progress.ticks() <= 33, "{} ticks", progress.ticks());
+    assert_eq!(progress.position(), bytes_read);
+    assert_eq!(progress.fraction(), Some(1.0));
+}
+
+#[test]
+fn small_readers_tick_once_at_the_end() {
+    let (bytes_read, progress) = scan(4096, 1 << 20);
+
+    assert_eq!(progress.ticks(), 1);
+    assert_eq!(progress.position(), bytes_read);
+}
+
+#[test]
+fn zero_interval_disables_ticks() {
+    let (_, progress) = scan(1 << 20, 0);
+
+    assert_eq!(progress.ticks(), 0);
+    assert_eq!(progress.position(), 0);
+}
+
+#[tokio::test]
+async fn files_leave_the_in_flight_list_once_scanned() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("app.log"), "error: disk full\n").unwrap();
+    fs::write(dir.path().join("db.log"), "info: ok\n").unwrap();
+
+    let read_progress = Arc::new(ReadProgress::default());
+    let mut config = ParserConfig {
+        log_folder: dir.path().to_string_lossy().into_owned(),
+        output_log: dir.path().join("output.log").to_string_lossy().into_owned(),
+        read_progress: Some(Arc::clone(&read_progress)),
+        ..Default::default()
+    };
+    add_search(&mut config.search_terms, "error", "");
+
+    let result = run_parser(config, None).await.unwrap();
+
+    assert_eq!(result.total_matches, 1);
+    assert!(read_progress.in_flight().is_empty());
+}
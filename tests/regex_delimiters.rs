@@ -0,0 +1,78 @@
+#![cfg(feature = "regex")]
+
+use elysiumparser::{
+    BooleanExpression, MatchStrategy, ParseErrorKind, ParserConfig, SearchSet, Term,
+    add_search_with_expression, run_parser,
+};
+use std::fs;
+
+#[tokio::test]
+async fn delimited_keyword_and_atoms_match_error_codes() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(
+        dir.path().join("app.log"),
+        "2024-05-01 ERR-1042 disk quota exceeded on 10.0.0.7\n\
+         2024-05-01 ERR-7 truncated code on 10.0.0.8\n\
+         2024-05-01 ERR-2001 disk quota exceeded on db-primary\n\
+         2024-05-01 INFO-1042 disk quota nominal on 10.0.0.9\n",
+    )
+    .unwrap();
+
+    let mut config = ParserConfig {
+        log_folder: dir.path().to_string_lossy().into_owned(),
+        output_log: dir.path().join("output.log").to_string_lossy().into_owned(),
+        ..Default::default()
+    };
+    add_search_with_expression(
+        &mut config.search_terms,
+        r"/ERR-\d{4}\b/",
+        r"disk & /\d+\.\d+\.\d+\.\d+/",
+    )
+    .unwrap();
+    let output_log = config.output_log.clone();
+
+    let result = run_parser(config, None).await.unwrap();
+
+    assert_eq!(result.total_matches, 1);
+    let output = fs::read_to_string(output_log).unwrap();
+    assert!(output.contains("ERR-1042 disk quota exceeded on 10.0.0.7"));
+}
+
+#[test]
+fn delimited_atoms_parse_as_regex() {
+    let expression = BooleanExpression::parse(r#"/ERR-\D/ | "/a|b/""#).unwrap();
+    let BooleanExpression::Or(branches) = &expression else {
+        panic!("expected an Or, got {:?}", expression);
+    };
+    assert!(
+        branches
+            .iter()
+            .all(|branch| matches!(**branch, BooleanExpression::Term(Term::Regex(_))))
+    );
+    assert!(expression.matches("err-x"));
+    assert!(expression.matches("b"));
+    assert!(!expression.matches("err-1"));
+}
+
+#[test]
+fn every_strategy_agrees_on_delimited_keywords() {
+    let mut terms = Vec::new();
+    add_search_with_expression(&mut terms, r"/code=5\d\d/", "").unwrap();
+    add_search_with_expression(&mut terms, "timeout", "").unwrap();
+    let lines = ["code=503 upstream", "code=404 missing", "read timeout"];
+
+    for strategy in MatchStrategy::ALL {
+        let set = SearchSet::new(&terms, "", strategy);
+        let matched: Vec<_> = lines.iter().map(|line| set.matching_term(line)).collect();
+        assert_eq!(matched, [Some(0), None, Some(1)], "{}", strategy);
+    }
+}
+
+#[test]
+fn invalid_delimited_keyword_is_reported() {
+    let mut terms = Vec::new();
+    let error = add_search_with_expression(&mut terms, "/err(/", "").unwrap_err();
+
+    assert!(matches!(error.kind, ParseErrorKind::InvalidRegex(_)));
+    assert!(terms.is_empty());
+}
@@ -0,0 +1,1402 @@
+use elysiumparser::{
+    CancelToken, CompressionKind, INVERTED_MATCH, MatchMode, MatchRecord, MatchSink, OutputFormat,
+    OutputTarget, ParserConfig, ParserError, ProgressCallback, ProgressUpdate, RunMode,
+    STDIN_SOURCE, add_file_assertion, add_search, add_search_with_case, add_search_with_expression,
+    collect_log_files, format_utc_minute, run_parser,
+};
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use std::collections::HashSet;
+use std::fs;
+use std::io::{self, Cursor, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, UNIX_EPOCH};
+
+/// Build a config reading from `dir` and writing next to it
+fn config_for(dir: &Path) -> ParserConfig {
+    ParserConfig {
+        log_folder: dir.to_string_lossy().into_owned(),
+        output_log: dir.join("output.log").to_string_lossy().into_owned(),
+        workers: Some(2),
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn file_assertions_flag_files_missing_the_absent_clause() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(
+        dir.path().join("healthy.log"),
+        "startup complete\nlicense validated\n",
+    )
+    .unwrap();
+    fs::write(dir.path().join("broken.log"), "startup complete\nserving\n").unwrap();
+    fs::write(dir.path().join("idle.log"), "nothing happened\n").unwrap();
+
+    let mut config = config_for(dir.path());
+    add_search(&mut config.search_terms, "startup", "");
+    add_file_assertion(
+        &mut config.assertions,
+        "startup complete",
+        "license validated",
+    )
+    .unwrap();
+    config.write_assertion_failures = true;
+    let output_log = config.output_log.clone();
+
+    let result = run_parser(config, None).await.unwrap();
+
+    assert_eq!(result.total_matches, 2);
+    assert_eq!(result.assertion_failures.len(), 1);
+    assert_eq!(result.assertion_failures[0].assertion, 0);
+    assert!(result.assertion_failures[0].path.ends_with("broken.log"));
+    assert_eq!(
+        result.assertion_failures[0].clause,
+        "(startup complete & !license validated)"
+    );
+
+    let output = fs::read_to_string(output_log).unwrap();
+    assert!(output.lines().any(|line| {
+        line.starts_with("ASSERTION FAILED [0] (startup complete & !license validated): ")
+            && line.ends_with("broken.log")
+    }));
+}
+
+#[tokio::test]
+async fn count_only_collects_statistics_without_output() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("a.log"), "error one\ninfo\nerror two\n").unwrap();
+    fs::write(dir.path().join("b.log"), "info\r\nerror three\r\n").unwrap();
+
+    let mut config = config_for(dir.path());
+    add_search(&mut config.search_terms, "error", "");
+    config.mode = RunMode::CountOnly;
+    let output_log = config.output_log.clone();
+
+    let result = run_parser(config, None).await.unwrap();
+
+    assert!(!Path::new(&output_log).exists());
+    assert_eq!(result.total_matches, 3);
+    assert_eq!(result.processed_files, 2);
+    assert_eq!(result.lines_scanned, 5);
+    assert_eq!(result.bytes_read, 25 + 19);
+
+    let per_file: Vec<_> = result
+        .file_results
+        .iter()
+        .map(|file| (file.path.file_name().unwrap().to_owned(), file.matches))
+        .collect();
+    assert_eq!(per_file, [("a.log".into(), 2), ("b.log".into(), 1)]);
+}
+
+#[tokio::test]
+async fn dry_run_lists_files_without_touching_the_output() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("a.log"), "error one\n").unwrap();
+    fs::write(dir.path().join("b.log.gz"), "not really gzip").unwrap();
+
+    let mut config = config_for(dir.path());
+    add_search(&mut config.search_terms, "error", "");
+    config.mode = RunMode::DryRun;
+    fs::write(&config.output_log, "previous run\n").unwrap();
+    let output_log = config.output_log.clone();
+
+    let result = run_parser(config, None).await.unwrap();
+
+    assert_eq!(fs::read_to_string(output_log).unwrap(), "previous run\n");
+    assert_eq!(result.total_matches, 0);
+    assert_eq!(result.processed_files, 0);
+    assert!(result.errors.is_empty());
+    let listed: Vec<_> = result
+        .file_results
+        .iter()
+        .map(|file| (file.path.file_name().unwrap().to_owned(), file.compression))
+        .collect();
+    assert_eq!(
+        listed,
+        [
+            ("a.log".into(), None),
+            ("b.log.gz".into(), Some(CompressionKind::Gzip))
+        ]
+    );
+}
+
+#[tokio::test]
+async fn dry_run_creates_no_missing_folder() {
+    let dir = tempfile::tempdir().unwrap();
+    let missing = dir.path().join("missing");
+
+    let mut config = config_for(&missing);
+    config.mode = RunMode::DryRun;
+
+    // Nothing to list, and unlike a run the folder is not created
+    assert!(run_parser(config, None).await.is_err());
+    assert!(!missing.exists());
+}
+
+#[tokio::test]
+async fn load_threshold_above_current_load_does_not_pause() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("a.log"), "error one\n").unwrap();
+
+    let mut config = config_for(dir.path());
+    add_search(&mut config.search_terms, "error", "");
+    config.pause_when_load_above = Some(f32::MAX);
+
+    let result = run_parser(config, None).await.unwrap();
+
+    assert_eq!(result.total_matches, 1);
+    assert!(!result.background_applied);
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn background_mode_is_reported() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("a.log"), "error one\n").unwrap();
+
+    let mut config = config_for(dir.path());
+    add_search(&mut config.search_terms, "error", "");
+    config.background = true;
+
+    let result = run_parser(config, None).await.unwrap();
+
+    assert_eq!(result.total_matches, 1);
+    assert!(result.background_applied);
+}
+
+#[tokio::test]
+async fn regex_mode_anchors_terms_and_line_filter() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(
+        dir.path().join("app.log"),
+        "ERROR disk full on node-1\ninfo: last ERROR cleared\nerror timeout on node-22\nERROR disk full\n",
+    )
+    .unwrap();
+
+    let mut config = config_for(dir.path());
+    add_search(&mut config.search_terms, "^ERROR", r"node-\d+$");
+    config.line_filter = "disk|timeout".to_string();
+    config.match_mode = MatchMode::Regex;
+    let output_log = config.output_log.clone();
+
+    let result = run_parser(config, None).await.unwrap();
+
+    assert_eq!(result.total_matches, 2);
+    let output = fs::read_to_string(output_log).unwrap();
+    assert!(output.contains("ERROR disk full on node-1"));
+    assert!(output.contains("error timeout on node-22"));
+    assert!(!output.contains("last ERROR cleared"));
+}
+
+#[tokio::test]
+async fn substring_mode_matches_regex_syntax_literally() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(
+        dir.path().join("app.log"),
+        "ERROR at start\nsaw ^error here\n",
+    )
+    .unwrap();
+
+    let mut config = config_for(dir.path());
+    add_search(&mut config.search_terms, "^ERROR", "");
+
+    let result = run_parser(config, None).await.unwrap();
+
+    assert_eq!(result.total_matches, 1);
+}
+
+#[tokio::test]
+async fn invalid_regex_fails_the_run() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("app.log"), "error\n").unwrap();
+
+    let mut config = config_for(dir.path());
+    add_search(&mut config.search_terms, "error(", "");
+    config.match_mode = MatchMode::Regex;
+
+    let result = run_parser(config, None).await;
+
+    assert!(matches!(result, Err(e) if e.kind() == std::io::ErrorKind::InvalidInput));
+}
+
+#[tokio::test]
+async fn output_header_precedes_matches_without_being_counted() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("app.log"), "error one\n# error two\ninfo\n").unwrap();
+
+    let mut config = config_for(dir.path());
+    add_search(&mut config.search_terms, "error", "");
+    config.output_header = true;
+    config.collapse_consecutive = true;
+    let output_log = config.output_log.clone();
+    let folder = config.log_folder.clone();
+
+    let result = run_parser(config, None).await.unwrap();
+
+    assert_eq!(result.total_matches, 2);
+    let output = fs::read_to_string(output_log).unwrap();
+    let lines: Vec<_> = output.lines().collect();
+    assert_eq!(lines.len(), 3);
+    assert!(lines[0].starts_with("# elysiumparser v"));
+    assert!(lines[0].ends_with(&format!("— terms: error — folder: {}", folder)));
+    assert_eq!(&lines[1..], ["error one", "# error two"]);
+}
+
+#[test]
+fn header_timestamps_are_utc_minutes() {
+    let time = UNIX_EPOCH + Duration::from_secs(1_717_761_600);
+    assert_eq!(format_utc_minute(time), "2024-06-07T12:00Z");
+
+    let leap_day = UNIX_EPOCH + Duration::from_secs(951_827_459);
+    assert_eq!(format_utc_minute(leap_day), "2000-02-29T12:30Z");
+}
+
+#[tokio::test]
+async fn recursive_search_skips_a_nested_output_log() {
+    let dir = tempfile::tempdir().unwrap();
+    let nested = dir.path().join("2024").join("03");
+    fs::create_dir_all(&nested).unwrap();
+    fs::write(dir.path().join("top.log"), "error top\n").unwrap();
+    fs::write(nested.join("app.log"), "error nested\n").unwrap();
+    fs::write(nested.join("output.log"), "error stale output\n").unwrap();
+
+    let mut config = config_for(dir.path());
+    config.output_log = nested.join("output.log").to_string_lossy().into_owned();
+    config.recursive = true;
+    add_search(&mut config.search_terms, "error", "");
+
+    let result = run_parser(config, None).await.unwrap();
+
+    assert_eq!(result.processed_files, 2);
+    assert_eq!(result.total_matches, 2);
+}
+
+#[tokio::test]
+async fn max_depth_limits_the_recursive_search() {
+    let dir = tempfile::tempdir().unwrap();
+    let nested = dir.path().join("a").join("b");
+    fs::create_dir_all(&nested).unwrap();
+    fs::write(dir.path().join("top.log"), "error\n").unwrap();
+    fs::write(dir.path().join("a").join("middle.log"), "error\n").unwrap();
+    fs::write(nested.join("deep.log"), "error\n").unwrap();
+
+    let mut config = config_for(dir.path());
+    config.recursive = true;
+    config.max_depth = Some(2);
+
+    let files = collect_log_files(&config).unwrap();
+
+    let mut names: Vec<_> = files.iter().map(|path| path.file_name().unwrap()).collect();
+    names.sort();
+    assert_eq!(names, ["middle.log", "top.log"]);
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn symlink_loops_do_not_recurse_forever() {
+    let dir = tempfile::tempdir().unwrap();
+    let nested = dir.path().join("nested");
+    fs::create_dir(&nested).unwrap();
+    fs::write(nested.join("app.log"), "error\n").unwrap();
+    std::os::unix::fs::symlink(dir.path(), nested.join("loop")).unwrap();
+
+    let mut config = config_for(dir.path());
+    config.recursive = true;
+    add_search(&mut config.search_terms, "error", "");
+
+    let result = run_parser(config, None).await.unwrap();
+
+    assert_eq!(result.processed_files, 1);
+    assert_eq!(result.total_matches, 1);
+}
+
+#[tokio::test]
+async fn recursive_flag_controls_nested_discovery() {
+    let dir = tempfile::tempdir().unwrap();
+    let day = dir.path().join("2024").join("03").join("15");
+    fs::create_dir_all(&day).unwrap();
+    fs::write(dir.path().join("root.log"), "error root\n").unwrap();
+    fs::write(dir.path().join("2024").join("year.log"), "error year\n").unwrap();
+    fs::write(
+        dir.path().join("2024").join("03").join("month.log"),
+        "error month\n",
+    )
+    .unwrap();
+    let mut gz = GzEncoder::new(
+        fs::File::create(day.join("app.log.gz")).unwrap(),
+        Compression::default(),
+    );
+    gz.write_all(b"error day\n").unwrap();
+    gz.finish().unwrap();
+    fs::write(day.join("debug.log"), "error debug\n").unwrap();
+
+    for (recursive, expected) in [(false, 1), (true, 4)] {
+        let mut config = config_for(dir.path());
+        config.recursive = recursive;
+        add_search(&mut config.search_terms, "error", "");
+
+        let result = run_parser(config, None).await.unwrap();
+
+        assert_eq!(result.processed_files, expected, "recursive: {}", recursive);
+        assert_eq!(result.total_matches, expected, "recursive: {}", recursive);
+    }
+}
+
+#[tokio::test]
+async fn show_location_prefixes_file_name_and_line_number() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut contents = "info: ok\n".repeat(41);
+    contents.push_str("error: disk full\n\ninfo: ok\nerror: retry\n");
+    fs::write(dir.path().join("name.log"), contents).unwrap();
+
+    let mut config = config_for(dir.path());
+    add_search(&mut config.search_terms, "error", "");
+    config.show_location = true;
+    let output_log = config.output_log.clone();
+
+    run_parser(config, None).await.unwrap();
+
+    let output = fs::read_to_string(output_log).unwrap();
+    assert_eq!(
+        output,
+        "name.log:42:error: disk full\nname.log:45:error: retry\n"
+    );
+}
+
+#[tokio::test]
+async fn tsv_output_separates_path_line_number_and_line() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("app.log"), "info: ok\nerror: disk\tfull\n").unwrap();
+    let mut gz = GzEncoder::new(
+        fs::File::create(dir.path().join("old.log.gz")).unwrap(),
+        Compression::default(),
+    );
+    gz.write_all(b"info: ok\ninfo: ok\nerror: retry\n").unwrap();
+    gz.finish().unwrap();
+
+    let mut config = config_for(dir.path());
+    add_search(&mut config.search_terms, "error", "");
+    config.output_format = OutputFormat::Tsv;
+    config.ordered_output = true;
+    let output_log = config.output_log.clone();
+
+    run_parser(config, None).await.unwrap();
+
+    let output = fs::read_to_string(output_log).unwrap();
+    let fields: Vec<(&str, usize, &str)> = output
+        .lines()
+        .map(|line| {
+            let mut fields = line.splitn(3, '\t');
+            let path = fields.next().unwrap();
+            let line_number = fields.next().unwrap().parse().unwrap();
+            (path, line_number, fields.next().unwrap())
+        })
+        .collect();
+    assert_eq!(
+        fields,
+        [
+            ("app.log", 2, "error: disk\tfull"),
+            ("old.log.gz", 3, "error: retry")
+        ]
+    );
+}
+
+#[tokio::test]
+async fn file_results_rank_noisy_files_and_flag_archives() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("a.log"), "error\n").unwrap();
+    fs::write(dir.path().join("quiet.log"), "info\n").unwrap();
+    let mut gz = GzEncoder::new(
+        fs::File::create(dir.path().join("b.log.gz")).unwrap(),
+        Compression::default(),
+    );
+    gz.write_all(b"error\nerror\nerror\n").unwrap();
+    gz.finish().unwrap();
+
+    let mut config = config_for(dir.path());
+    add_search(&mut config.search_terms, "error", "");
+
+    let mut result = run_parser(config, None).await.unwrap();
+    result.sort_by_matches();
+
+    let ranked: Vec<_> = result
+        .file_results
+        .iter()
+        .map(|file| {
+            (
+                file.path.file_name().unwrap().to_str().unwrap(),
+                file.matches,
+                file.compression,
+            )
+        })
+        .collect();
+    assert_eq!(
+        ranked,
+        [
+            ("b.log.gz", 3, Some(CompressionKind::Gzip)),
+            ("a.log", 1, None),
+            ("quiet.log", 0, None),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn matches_are_collected_without_an_output_file() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("a.log"), "info\nwarn: slow\nerror: down\n").unwrap();
+    fs::write(dir.path().join("b.log"), "error: again\n").unwrap();
+
+    let mut config = config_for(dir.path());
+    add_search(&mut config.search_terms, "error", "");
+    add_search(&mut config.search_terms, "warn", "");
+    config.output_target = OutputTarget::Discard;
+    config.collect_matches = true;
+    let output_log = config.output_log.clone();
+
+    let result = run_parser(config, None).await.unwrap();
+
+    assert!(!Path::new(&output_log).exists());
+    let matches: Vec<_> = result
+        .matches
+        .iter()
+        .map(|found| {
+            (
+                found.file.file_name().unwrap().to_str().unwrap(),
+                found.line_number,
+                found.term_index,
+                found.line.as_str(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        matches,
+        [
+            ("a.log", 2, 1, "warn: slow"),
+            ("a.log", 3, 0, "error: down"),
+            ("b.log", 1, 0, "error: again"),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn custom_targets_take_the_place_of_the_output_log() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("a.log"), "error one\ninfo\nerror two\n").unwrap();
+
+    let buffer = Arc::new(Mutex::new(Vec::new()));
+    let mut config = config_for(dir.path());
+    add_search(&mut config.search_terms, "error", "");
+    config.output_target = OutputTarget::Custom(buffer.clone());
+    let output_log = config.output_log.clone();
+
+    let result = run_parser(config, None).await.unwrap();
+
+    assert_eq!(result.total_matches, 2);
+    assert!(!Path::new(&output_log).exists());
+    assert_eq!(
+        String::from_utf8(buffer.lock().unwrap().clone()).unwrap(),
+        "error one\nerror two\n"
+    );
+}
+
+#[test]
+fn custom_targets_are_equal_when_they_share_the_sink() {
+    let sink: Arc<Mutex<Vec<u8>>> = Arc::default();
+
+    assert_eq!(
+        OutputTarget::Custom(sink.clone()),
+        OutputTarget::Custom(sink)
+    );
+    assert_ne!(
+        OutputTarget::Custom(Arc::new(Mutex::new(Vec::<u8>::new()))),
+        OutputTarget::Custom(Arc::new(Mutex::new(Vec::<u8>::new())))
+    );
+    assert_ne!(OutputTarget::Stdout, OutputTarget::OutputLog);
+}
+
+#[tokio::test]
+async fn matches_are_streamed_to_a_channel() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("a.log"), "error one\ninfo\nerror two\n").unwrap();
+
+    let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+    let mut config = config_for(dir.path());
+    add_search(&mut config.search_terms, "error", "");
+    config.match_sender = Some(sender);
+    let output_log = config.output_log.clone();
+
+    let result = run_parser(config, None).await.unwrap();
+
+    let mut streamed = Vec::new();
+    while let Some(found) = receiver.recv().await {
+        streamed.push((found.line_number, found.line));
+    }
+    assert_eq!(
+        streamed,
+        [(1, "error one".to_string()), (3, "error two".to_string())]
+    );
+    assert!(result.matches.is_empty());
+    assert_eq!(
+        fs::read_to_string(output_log).unwrap(),
+        "error one\nerror two\n"
+    );
+}
+
+#[tokio::test]
+async fn jsonl_output_writes_one_object_per_match() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(
+        dir.path().join("app.log"),
+        "starting\nERROR: Disk \"sda\" full\n",
+    )
+    .unwrap();
+
+    let mut config = config_for(dir.path());
+    add_search(&mut config.search_terms, "Error", "");
+    config.output_format = OutputFormat::Jsonl;
+    config.output_header = true;
+    let output_log = config.output_log.clone();
+
+    run_parser(config, None).await.unwrap();
+
+    let output = fs::read_to_string(output_log).unwrap();
+    let lines: Vec<&str> = output.lines().collect();
+    assert_eq!(lines.len(), 2);
+    let header: serde_json::Value = serde_json::from_str(lines[0]).unwrap();
+    assert_eq!(header["header"]["terms"], serde_json::json!(["Error"]));
+    assert_eq!(header["header"]["version"], env!("CARGO_PKG_VERSION"));
+    let record: serde_json::Value = serde_json::from_str(lines[1]).unwrap();
+    assert!(record["source_file"].as_str().unwrap().ends_with("app.log"));
+    assert_eq!(record["line_number"], 2);
+    assert_eq!(record["matched_keyword"], "Error");
+    assert_eq!(record["content"], "ERROR: Disk \"sda\" full");
+    assert_eq!(record["line"], record["content"]);
+}
+
+#[tokio::test]
+async fn simple_terms_keep_their_case_in_case_sensitive_runs() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(
+        dir.path().join("app.log"),
+        "ERROR disk full\nerror disk full\nERROR Disk full\n",
+    )
+    .unwrap();
+
+    let mut config = config_for(dir.path());
+    config.case_sensitive = true;
+    add_search(&mut config.search_terms, "ERROR", "disk");
+    let output_log = config.output_log.clone();
+
+    let result = run_parser(config, None).await.unwrap();
+
+    assert_eq!(result.total_matches, 1);
+    assert_eq!(fs::read_to_string(output_log).unwrap(), "ERROR disk full\n");
+}
+
+#[tokio::test]
+async fn case_sensitive_runs_match_terms_and_filter_as_written() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(
+        dir.path().join("app.log"),
+        "ERROR [Auth] denied\nerror [auth] denied\nERROR [auth] denied\n",
+    )
+    .unwrap();
+
+    let mut config = config_for(dir.path());
+    config.case_sensitive = true;
+    config.line_filter = "[Auth]".to_string();
+    add_search_with_case(&mut config.search_terms, "ERROR", "", true).unwrap();
+    let output_log = config.output_log.clone();
+
+    let result = run_parser(config, None).await.unwrap();
+
+    assert_eq!(result.total_matches, 1);
+    assert_eq!(
+        fs::read_to_string(output_log).unwrap(),
+        "ERROR [Auth] denied\n"
+    );
+}
+
+#[tokio::test]
+async fn matches_are_counted_per_search_term() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(
+        dir.path().join("a.log"),
+        "error one\nwarning two\nerror three\n",
+    )
+    .unwrap();
+    fs::write(dir.path().join("b.log"), "error and warning\n").unwrap();
+
+    let mut config = config_for(dir.path());
+    add_search(&mut config.search_terms, "error", "");
+    add_search_with_expression(&mut config.search_terms, "warning", "two | and").unwrap();
+    add_search(&mut config.search_terms, "fatal", "");
+
+    let result = run_parser(config, None).await.unwrap();
+
+    // A line counts for the first term it matches
+    assert_eq!(result.per_term, [3, 1, 0]);
+    assert_eq!(result.per_term.iter().sum::<usize>(), result.total_matches);
+
+    let stats: Vec<_> = result
+        .term_stats
+        .iter()
+        .map(|stat| {
+            (
+                stat.keyword.as_str(),
+                stat.expression.as_str(),
+                stat.match_count,
+            )
+        })
+        .collect();
+    assert_eq!(
+        stats,
+        [
+            ("error", "", 3),
+            ("warning", "two | and", 1),
+            ("fatal", "", 0)
+        ]
+    );
+}
+
+#[tokio::test]
+async fn progress_callbacks_can_capture_state() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("a.log"), "error one\nerror two\n").unwrap();
+    fs::write(dir.path().join("b.log"), "error three\n").unwrap();
+    fs::write(dir.path().join("c.log"), "nothing\n").unwrap();
+
+    let mut config = config_for(dir.path());
+    add_search(&mut config.search_terms, "error", "");
+
+    let updates = Arc::new(Mutex::new(Vec::new()));
+    let recorded = Arc::clone(&updates);
+    let callback: ProgressCallback = Arc::new(move |update: ProgressUpdate| {
+        recorded.lock().unwrap().push((
+            update.processed,
+            update.total,
+            update.path.file_name().unwrap().to_owned(),
+            update.matches,
+        ));
+    });
+
+    let result = run_parser(config, Some(callback)).await.unwrap();
+
+    let updates = updates.lock().unwrap();
+    assert_eq!(updates.len(), 3);
+    for (index, (processed, total, _, _)) in updates.iter().enumerate() {
+        assert_eq!(*processed, index + 1);
+        assert_eq!(*total, 3);
+    }
+    assert!(updates.windows(2).all(|pair| pair[0].3 <= pair[1].3));
+    assert_eq!(updates[2].3, result.total_matches);
+
+    let mut names: Vec<_> = updates.iter().map(|update| update.2.clone()).collect();
+    names.sort();
+    assert_eq!(names, ["a.log", "b.log", "c.log"]);
+}
+
+#[tokio::test]
+async fn ordered_output_groups_matches_by_file_in_path_order() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut expected = String::new();
+    let mut expected_with_headers = String::new();
+    for index in 0..12 {
+        let name = format!("service{:02}.log", index);
+        // Larger files first, so later files tend to finish earlier
+        let lines: String = (0..(12 - index) * 300)
+            .map(|line| match line % 100 {
+                0 => format!("error {} in {}\n", line, name),
+                _ => format!("info {}\n", line),
+            })
+            .collect();
+        fs::write(dir.path().join(&name), &lines).unwrap();
+
+        let matches: String = lines
+            .lines()
+            .filter(|line| line.starts_with("error"))
+            .map(|line| format!("{}\n", line))
+            .collect();
+        expected.push_str(&matches);
+        expected_with_headers.push_str(&format!(
+            "==> {} <==\n{}",
+            dir.path().join(&name).display(),
+            matches
+        ));
+    }
+    fs::write(dir.path().join("quiet.log"), "nothing here\n").unwrap();
+
+    let run = |file_headers: bool| {
+        let mut config = config_for(dir.path());
+        config.workers = Some(4);
+        config.ordered_output = true;
+        config.file_headers = file_headers;
+        add_search(&mut config.search_terms, "error", "");
+        let output_log = config.output_log.clone();
+        async move {
+            let result = run_parser(config, None).await.unwrap();
+            (
+                result.total_matches,
+                fs::read_to_string(output_log).unwrap(),
+            )
+        }
+    };
+
+    let (total, output) = run(false).await;
+    assert_eq!(total, expected.lines().count());
+    assert_eq!(output, expected);
+    assert_eq!(run(false).await.1, output);
+
+    let (_, output) = run(true).await;
+    assert_eq!(output, expected_with_headers);
+}
+
+#[tokio::test]
+async fn ordered_output_keeps_context_blocks_and_locations() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("a.log"), "one\nerror a\ntwo\n").unwrap();
+    fs::write(dir.path().join("b.log"), "error b\nthree\n").unwrap();
+
+    let mut config = config_for(dir.path());
+    config.ordered_output = true;
+    config.show_location = true;
+    config.after_context = 1;
+    config.collect_matches = true;
+    add_search(&mut config.search_terms, "error", "");
+    let output_log = config.output_log.clone();
+
+    let result = run_parser(config, None).await.unwrap();
+
+    assert_eq!(result.matches.len(), 2);
+    assert_eq!(
+        fs::read_to_string(output_log).unwrap(),
+        "> a.log:2:error a\n- a.log-3-two\n> b.log:1:error b\n- b.log-2-three\n"
+    );
+}
+
+#[tokio::test]
+async fn a_limit_of_one_stops_reading_a_huge_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut log = String::from("error at the top\n");
+    for line in 0..500_000 {
+        log.push_str(&format!("info {} error\n", line));
+    }
+    fs::write(dir.path().join("huge.log"), &log).unwrap();
+
+    let mut config = config_for(dir.path());
+    config.max_matches = Some(1);
+    add_search(&mut config.search_terms, "error", "");
+    let output_log = config.output_log.clone();
+
+    let result = run_parser(config, None).await.unwrap();
+
+    assert_eq!(result.total_matches, 1);
+    assert!(result.limit_reached);
+    assert_eq!(result.lines_scanned, 1);
+    assert!(result.bytes_read < 100);
+    assert_eq!(
+        fs::read_to_string(output_log).unwrap(),
+        "error at the top\n"
+    );
+}
+
+#[tokio::test]
+async fn files_not_started_are_skipped_once_the_limit_is_reached() {
+    let dir = tempfile::tempdir().unwrap();
+    for index in 0..20 {
+        let log: String = (0..1000).map(|line| format!("error {}\n", line)).collect();
+        fs::write(dir.path().join(format!("app{:02}.log", index)), log).unwrap();
+    }
+
+    let mut config = config_for(dir.path());
+    config.workers = Some(1);
+    config.max_matches = Some(1500);
+    add_search(&mut config.search_terms, "error", "");
+
+    let result = run_parser(config, None).await.unwrap();
+
+    assert_eq!(result.total_matches, 1500);
+    assert!(result.limit_reached);
+    assert_eq!(result.processed_files, 2);
+    assert_eq!(result.file_results[1].matches, 500);
+}
+
+#[tokio::test]
+async fn assertions_still_check_every_file_once_the_limit_is_reached() {
+    let dir = tempfile::tempdir().unwrap();
+    for name in ["a.log", "b.log"] {
+        fs::write(
+            dir.path().join(name),
+            "startup complete\nstartup retried\nlicense validated\n",
+        )
+        .unwrap();
+    }
+    fs::write(dir.path().join("c.log"), "startup complete\nserving\n").unwrap();
+
+    let mut config = config_for(dir.path());
+    config.workers = Some(1);
+    config.max_matches = Some(1);
+    add_search(&mut config.search_terms, "startup", "");
+    add_file_assertion(
+        &mut config.assertions,
+        "startup complete",
+        "license validated",
+    )
+    .unwrap();
+
+    let result = run_parser(config, None).await.unwrap();
+
+    assert_eq!(result.total_matches, 1);
+    assert!(result.limit_reached);
+    assert_eq!(result.processed_files, 3);
+    assert_eq!(result.assertion_failures.len(), 1);
+    assert!(result.assertion_failures[0].path.ends_with("c.log"));
+}
+
+#[tokio::test]
+async fn concurrent_readers_notice_the_limit() {
+    let dir = tempfile::tempdir().unwrap();
+    for index in 0..4 {
+        let log: String = (0..100_000)
+            .map(|line| match line % 1000 {
+                0 => format!("error {}\n", line),
+                _ => format!("info {}\n", line),
+            })
+            .collect();
+        fs::write(dir.path().join(format!("app{}.log", index)), log).unwrap();
+    }
+
+    let mut config = config_for(dir.path());
+    config.workers = Some(4);
+    config.max_matches = Some(10);
+    add_search(&mut config.search_terms, "error", "");
+
+    let output_log = config.output_log.clone();
+
+    let result = run_parser(config, None).await.unwrap();
+
+    // Each reader stops within one check interval of the limit, dropping
+    // the matches it finds over it meanwhile
+    assert_eq!(result.total_matches, 10);
+    assert_eq!(fs::read_to_string(output_log).unwrap().lines().count(), 10);
+    assert!(result.limit_reached);
+    assert!(result.lines_scanned < 4 * 100_000);
+}
+
+#[tokio::test]
+async fn the_output_never_holds_more_than_the_limit() {
+    let dir = tempfile::tempdir().unwrap();
+    for index in 0..8 {
+        let log: String = (0..200).map(|line| format!("error {}\n", line)).collect();
+        fs::write(dir.path().join(format!("app{}.log", index)), log).unwrap();
+    }
+
+    let mut config = config_for(dir.path());
+    config.workers = Some(8);
+    config.max_matches = Some(5);
+    add_search(&mut config.search_terms, "error", "");
+    let output_log = config.output_log.clone();
+
+    let result = run_parser(config, None).await.unwrap();
+
+    assert_eq!(result.total_matches, 5);
+    assert!(result.limit_reached);
+    assert_eq!(fs::read_to_string(output_log).unwrap().lines().count(), 5);
+}
+
+#[tokio::test]
+async fn each_file_stops_at_its_own_limit() {
+    let dir = tempfile::tempdir().unwrap();
+    for index in 0..3 {
+        let log: String = (0..100)
+            .map(|line| {
+                format!(
+                    "{} {}\n",
+                    if line % 2 == 0 { "error" } else { "info" },
+                    line
+                )
+            })
+            .collect();
+        fs::write(dir.path().join(format!("app{}.log", index)), log).unwrap();
+    }
+
+    let mut config = config_for(dir.path());
+    config.max_matches_per_file = Some(3);
+    add_search(&mut config.search_terms, "error", "");
+    let output_log = config.output_log.clone();
+
+    let result = run_parser(config, None).await.unwrap();
+
+    assert_eq!(result.total_matches, 9);
+    assert!(!result.limit_reached);
+    for file in &result.file_results {
+        assert_eq!(file.matches, 3);
+        // Reading stopped at the third match, line 5
+        assert_eq!(file.lines_scanned, 5);
+    }
+    let output = fs::read_to_string(output_log).unwrap();
+    assert_eq!(output.lines().filter(|line| *line == "error 4").count(), 3);
+    assert_eq!(output.lines().count(), 9);
+}
+
+#[tokio::test]
+async fn files_in_assertions_are_read_past_their_own_limit() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(
+        dir.path().join("healthy.log"),
+        "startup complete\nstartup retried\nlicense validated\n",
+    )
+    .unwrap();
+    fs::write(dir.path().join("broken.log"), "startup complete\nserving\n").unwrap();
+
+    let mut config = config_for(dir.path());
+    config.max_matches_per_file = Some(1);
+    add_search(&mut config.search_terms, "startup", "");
+    add_file_assertion(
+        &mut config.assertions,
+        "startup complete",
+        "license validated",
+    )
+    .unwrap();
+    let output_log = config.output_log.clone();
+
+    let result = run_parser(config, None).await.unwrap();
+
+    assert_eq!(result.total_matches, 2);
+    assert_eq!(result.assertion_failures.len(), 1);
+    assert!(result.assertion_failures[0].path.ends_with("broken.log"));
+    let healthy = result
+        .file_results
+        .iter()
+        .find(|file| file.path.ends_with("healthy.log"))
+        .unwrap();
+    assert_eq!((healthy.matches, healthy.lines_scanned), (1, 3));
+    let output = fs::read_to_string(output_log).unwrap();
+    assert!(!output.contains("startup retried"));
+}
+
+#[tokio::test]
+async fn cancelled_runs_return_partial_results() {
+    let dir = tempfile::tempdir().unwrap();
+    for index in 0..20 {
+        let log: String = (0..1000).map(|line| format!("error {}\n", line)).collect();
+        fs::write(dir.path().join(format!("app{:02}.log", index)), log).unwrap();
+    }
+
+    let cancel = CancelToken::new();
+    let mut config = config_for(dir.path());
+    config.workers = Some(1);
+    config.cancel = Some(cancel.clone());
+    add_search(&mut config.search_terms, "error", "");
+    let output_log = config.output_log.clone();
+    let progress: ProgressCallback = Arc::new(move |_: ProgressUpdate| cancel.cancel());
+
+    let result = run_parser(config, Some(progress)).await.unwrap();
+
+    assert!(result.cancelled);
+    assert_eq!(result.processed_files, 1);
+    assert_eq!(result.total_matches, 1000);
+    assert_eq!(
+        fs::read_to_string(output_log).unwrap().lines().count(),
+        1000
+    );
+}
+
+#[tokio::test]
+async fn finished_runs_are_not_cancelled() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("app.log"), "error: disk full\n").unwrap();
+
+    let mut config = config_for(dir.path());
+    config.cancel = Some(CancelToken::new());
+    add_search(&mut config.search_terms, "error", "");
+
+    let result = run_parser(config, None).await.unwrap();
+
+    assert!(!result.cancelled);
+    assert_eq!(result.total_matches, 1);
+}
+
+#[tokio::test]
+async fn unreadable_files_are_reported_in_errors() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("app.log"), "error: disk full\n").unwrap();
+    fs::write(dir.path().join("corrupt.log.gz"), b"not gzip at all\n").unwrap();
+
+    let mut config = config_for(dir.path());
+    add_search(&mut config.search_terms, "error", "");
+
+    let result = run_parser(config, None).await.unwrap();
+
+    assert_eq!(result.total_matches, 1);
+    assert_eq!(result.processed_files, 2);
+    assert_eq!(result.errors.len(), 1);
+    let (path, error) = &result.errors[0];
+    assert!(path.ends_with("corrupt.log.gz"));
+    assert!(matches!(error, ParserError::Read(_)), "{}", error);
+    assert!(error.to_string().starts_with("error reading"), "{}", error);
+}
+
+#[tokio::test]
+async fn runs_without_failures_have_no_errors() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("app.log"), "error: disk full\n").unwrap();
+
+    let mut config = config_for(dir.path());
+    add_search(&mut config.search_terms, "error", "");
+
+    let result = run_parser(config, None).await.unwrap();
+
+    assert!(result.errors.is_empty());
+}
+
+#[tokio::test]
+async fn invert_match_keeps_lines_rejected_by_terms_or_line_filter() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(
+        dir.path().join("app.log"),
+        "error db down\nerror cache miss\ninfo db ok\ninfo started\n",
+    )
+    .unwrap();
+
+    let mut config = config_for(dir.path());
+    add_search(&mut config.search_terms, "error", "");
+    config.line_filter = "db".to_string();
+    config.invert_match = true;
+    config.collect_matches = true;
+    let output_log = config.output_log.clone();
+
+    let result = run_parser(config, None).await.unwrap();
+
+    // Only the line with both the term and the filter is dropped
+    assert_eq!(result.total_matches, 3);
+    assert_eq!(result.per_term, vec![0]);
+    assert_eq!(
+        fs::read_to_string(output_log).unwrap(),
+        "error cache miss\ninfo db ok\ninfo started\n"
+    );
+    assert!(
+        result
+            .matches
+            .iter()
+            .all(|record| record.term_index == INVERTED_MATCH)
+    );
+}
+
+#[tokio::test]
+async fn show_location_names_nested_files_by_their_relative_path() {
+    let dir = tempfile::tempdir().unwrap();
+    for service in ["api", "db"] {
+        fs::create_dir(dir.path().join(service)).unwrap();
+        fs::write(
+            dir.path().join(service).join("app.log"),
+            format!("info: ok\nerror: {} down\n", service),
+        )
+        .unwrap();
+    }
+
+    let mut config = config_for(dir.path());
+    add_search(&mut config.search_terms, "error", "");
+    config.recursive = true;
+    config.show_location = true;
+    config.ordered_output = true;
+    let output_log = config.output_log.clone();
+
+    run_parser(config, None).await.unwrap();
+
+    let separator = std::path::MAIN_SEPARATOR;
+    assert_eq!(
+        fs::read_to_string(output_log).unwrap(),
+        format!("api{separator}app.log:2:error: api down\ndb{separator}app.log:2:error: db down\n")
+    );
+}
+
+#[tokio::test]
+async fn input_files_are_read_instead_of_the_folder_whatever_their_name() {
+    let dir = tempfile::tempdir().unwrap();
+    let log_folder = dir.path().join("logs");
+    let elsewhere = dir.path().join("elsewhere");
+    fs::create_dir(&elsewhere).unwrap();
+    fs::write(
+        elsewhere.join("debug-notes.txt"),
+        "error: noted
+info
+",
+    )
+    .unwrap();
+    let mut gz = GzEncoder::new(Vec::new(), Compression::default());
+    gz.write_all(
+        b"error: archived
+",
+    )
+    .unwrap();
+    fs::write(elsewhere.join("old.txt.gz"), gz.finish().unwrap()).unwrap();
+    let missing = elsewhere.join("missing.log");
+
+    let mut config = config_for(&log_folder);
+    config.output_log = dir.path().join("output.log").to_string_lossy().into_owned();
+    config.input_files = vec![
+        elsewhere.join("debug-notes.txt"),
+        missing.clone(),
+        elsewhere.join("old.txt.gz"),
+    ];
+    add_search(&mut config.search_terms, "error", "");
+    let result = run_parser(config, None).await.unwrap();
+
+    assert_eq!(result.total_matches, 2);
+    assert_eq!(result.file_results.len(), 3);
+    let archive = result
+        .file_results
+        .iter()
+        .find(|file| file.path.ends_with("old.txt.gz"))
+        .unwrap();
+    assert_eq!(archive.compression, Some(CompressionKind::Gzip));
+    assert_eq!(result.errors.len(), 1);
+    let (path, error) = &result.errors[0];
+    assert_eq!(path, &missing);
+    assert!(matches!(error, ParserError::Open(_)));
+    // Nothing is searched there, so the log folder is not created either
+    assert!(!log_folder.exists());
+}
+
+#[tokio::test]
+async fn input_streams_are_scanned_instead_of_the_folder() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("ignored.log"), "error on disk\n").unwrap();
+
+    let mut config = config_for(dir.path());
+    add_search(&mut config.search_terms, "error", "");
+    config.input = Some(Box::new(Cursor::new("info\nerror one\nerror two\n")));
+    let output_log = config.output_log.clone();
+
+    let updates = Arc::new(Mutex::new(Vec::new()));
+    let recorded = Arc::clone(&updates);
+    let callback: ProgressCallback = Arc::new(move |update: ProgressUpdate| {
+        recorded
+            .lock()
+            .unwrap()
+            .push((update.processed, update.total));
+    });
+
+    let result = run_parser(config, Some(callback)).await.unwrap();
+
+    assert_eq!(result.total_matches, 2);
+    assert_eq!(result.processed_files, 1);
+    assert_eq!(result.file_results[0].path, Path::new(STDIN_SOURCE));
+    assert_eq!(*updates.lock().unwrap(), [(1, 0)]);
+    assert_eq!(
+        fs::read_to_string(output_log).unwrap(),
+        "error one\nerror two\n"
+    );
+}
+
+#[tokio::test]
+async fn compressed_input_streams_are_decompressed() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(b"info\nerror one\n").unwrap();
+
+    let mut config = config_for(dir.path());
+    add_search(&mut config.search_terms, "error", "");
+    config.input = Some(Box::new(Cursor::new(encoder.finish().unwrap())));
+
+    let result = run_parser(config, None).await.unwrap();
+
+    assert_eq!(result.total_matches, 1);
+    assert_eq!(result.lines_scanned, 2);
+}
+
+#[tokio::test]
+async fn appending_runs_keep_the_previous_matches() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("app.log"), "error one\nwarn two\n").unwrap();
+
+    let mut config = config_for(dir.path());
+    add_search(&mut config.search_terms, "error", "");
+    config.append = true;
+    let output_log = config.output_log.clone();
+    run_parser(config, None).await.unwrap();
+
+    let mut config = config_for(dir.path());
+    add_search(&mut config.search_terms, "warn", "");
+    config.append = true;
+    run_parser(config, None).await.unwrap();
+
+    let output = fs::read_to_string(&output_log).unwrap();
+    let lines: Vec<_> = output.lines().collect();
+    assert_eq!(lines.len(), 4);
+    assert!(lines[0].starts_with("# --- run at "));
+    assert_eq!(lines[1], "error one");
+    assert!(lines[2].starts_with("# --- run at "));
+    assert_eq!(lines[3], "warn two");
+
+    // Without append, the next run replaces them
+    let mut config = config_for(dir.path());
+    add_search(&mut config.search_terms, "error", "");
+    run_parser(config, None).await.unwrap();
+    assert_eq!(fs::read_to_string(&output_log).unwrap(), "error one\n");
+}
+
+#[tokio::test]
+async fn appended_jsonl_runs_are_separated_by_an_object() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("app.log"), "error one\nwarn two\n").unwrap();
+
+    let mut output_log = String::new();
+    for keyword in ["error", "warn"] {
+        let mut config = config_for(dir.path());
+        add_search(&mut config.search_terms, keyword, "");
+        config.append = true;
+        config.output_format = OutputFormat::Jsonl;
+        output_log = config.output_log.clone();
+        run_parser(config, None).await.unwrap();
+    }
+
+    let output = fs::read_to_string(&output_log).unwrap();
+    let records: Vec<serde_json::Value> = output
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(records.len(), 4);
+    for separator in [&records[0], &records[2]] {
+        let started = separator["run_separator"]["started"].as_str().unwrap();
+        assert!(started.ends_with('Z'), "{}", started);
+    }
+    assert_eq!(records[1]["line"], "error one");
+    assert_eq!(records[3]["line"], "warn two");
+}
+
+#[tokio::test]
+async fn runs_below_the_limit_are_complete() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("app.log"), "error one\nerror two\n").unwrap();
+
+    let mut config = config_for(dir.path());
+    config.max_matches = Some(3);
+    add_search(&mut config.search_terms, "error", "");
+
+    let result = run_parser(config, None).await.unwrap();
+
+    assert_eq!(result.total_matches, 2);
+    assert!(!result.limit_reached);
+}
+
+#[tokio::test]
+async fn any_extension_runs_skip_their_own_output() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("syslog"), "error: disk full\n").unwrap();
+    fs::write(dir.path().join("app.txt"), "error: timeout\n").unwrap();
+
+    let mut config = config_for(dir.path());
+    config.allowed_extensions = vec![];
+    add_search(&mut config.search_terms, "error", "");
+    let output_log = config.output_log.clone();
+
+    let result = run_parser(config, None).await.unwrap();
+
+    assert_eq!(result.processed_files, 2);
+    assert_eq!(result.total_matches, 2);
+    assert_eq!(fs::read_to_string(output_log).unwrap().lines().count(), 2);
+}
+
+#[tokio::test]
+async fn binary_files_are_skipped_on_request() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("app.log"), "error: disk full\n").unwrap();
+    fs::write(dir.path().join("core.log"), b"error\0\x01\x02 dump\n").unwrap();
+
+    let mut config = config_for(dir.path());
+    config.skip_binary_files = true;
+    add_search(&mut config.search_terms, "error", "");
+    let result = run_parser(config, None).await.unwrap();
+
+    assert_eq!(result.total_matches, 1);
+    assert_eq!(result.binary_files, 1);
+    let output = fs::read_to_string(dir.path().join("output.log")).unwrap();
+    assert_eq!(output, "error: disk full\n");
+}
+
+#[tokio::test]
+async fn several_log_folders_are_searched_in_one_run() {
+    let dir = tempfile::tempdir().unwrap();
+    let app = dir.path().join("app");
+    let nginx = dir.path().join("nginx");
+    let missing = dir.path().join("missing");
+    fs::create_dir(&app).unwrap();
+    fs::create_dir(&nginx).unwrap();
+    fs::write(app.join("app.log"), "error: app down\n").unwrap();
+    fs::write(nginx.join("access.log"), "info: ok\nerror: 502\n").unwrap();
+
+    // The output log of the first folder is left out like with a single one
+    let mut config = config_for(&app);
+    config.log_folders = vec![
+        nginx.to_string_lossy().into_owned(),
+        missing.to_string_lossy().into_owned(),
+        // Searched once however often it is given
+        app.to_string_lossy().into_owned(),
+    ];
+    add_search(&mut config.search_terms, "error", "");
+    config.show_location = true;
+    config.ordered_output = true;
+    let output_log = config.output_log.clone();
+
+    let result = run_parser(config, None).await.unwrap();
+
+    assert_eq!(result.total_matches, 2);
+    assert_eq!(result.processed_files, 2);
+    assert_eq!(
+        fs::read_to_string(output_log).unwrap(),
+        "app.log:1:error: app down\naccess.log:2:error: 502\n"
+    );
+    // A missing folder is reported instead of failing the run
+    assert_eq!(result.errors.len(), 1);
+    let (path, error) = &result.errors[0];
+    assert_eq!(path, &missing);
+    assert!(matches!(error, ParserError::ReadDirectory(_)), "{}", error);
+}
+
+/// Sink failing every write, like a full disk, noting the files it was sent
+#[derive(Default)]
+struct FullDisk {
+    writes: Mutex<Vec<PathBuf>>,
+}
+
+impl MatchSink for FullDisk {
+    fn write_match(&self, record: &MatchRecord<'_>) -> io::Result<()> {
+        self.writes
+            .lock()
+            .unwrap()
+            .push(record.source.to_path_buf());
+        Err(io::Error::new(io::ErrorKind::StorageFull, "no space left"))
+    }
+}
+
+#[tokio::test]
+async fn failed_writes_abort_the_run() {
+    let dir = tempfile::tempdir().unwrap();
+    for index in 0..50 {
+        fs::write(
+            dir.path().join(format!("app-{:02}.log", index)),
+            "error\n".repeat(100),
+        )
+        .unwrap();
+    }
+
+    let sink = Arc::new(FullDisk::default());
+    let mut config = config_for(dir.path());
+    add_search(&mut config.search_terms, "error", "");
+    config.output_target = OutputTarget::Custom(sink.clone());
+
+    let Err(error) = run_parser(config, None).await else {
+        panic!("the run succeeded");
+    };
+
+    assert_eq!(error.kind(), io::ErrorKind::StorageFull);
+    let writes = sink.writes.lock().unwrap();
+    let files: HashSet<_> = writes.iter().collect();
+    // Each reader stops at its first failed write, and no file starts after it
+    assert_eq!(writes.len(), files.len());
+    assert!(files.len() < 50, "{} files written to", files.len());
+}
@@ -0,0 +1,11 @@
+use elysiumparser::selftest::{SELF_TEST_CASES, run_self_test};
+
+#[tokio::test]
+async fn every_self_test_case_passes() {
+    let outcomes = run_self_test().await;
+
+    assert_eq!(outcomes.len(), SELF_TEST_CASES.len());
+    for outcome in outcomes {
+        assert!(outcome.passed(), "self-test case failed: {:?}", outcome);
+    }
+}
@@ -0,0 +1,168 @@
+use elysiumparser::{
+    FileMetadata, FileSystem, OutputTarget, ParserConfig, StdFileSystem, add_search, run_parser,
+};
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Files on disk, remembering the offsets they were opened at
+#[derive(Default)]
+struct OffsetsOpened(Mutex<Vec<u64>>);
+
+impl FileSystem for OffsetsOpened {
+    fn list_dir(&self, path: &Path) -> io::Result<Vec<io::Result<PathBuf>>> {
+        StdFileSystem.list_dir(path)
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<FileMetadata> {
+        StdFileSystem.metadata(path)
+    }
+
+    fn open(&self, path: &Path) -> io::Result<Box<dyn Read + Send>> {
+        StdFileSystem.open(path)
+    }
+
+    fn open_at(&self, path: &Path, offset: u64) -> io::Result<Box<dyn Read + Send>> {
+        self.0.lock().unwrap().push(offset);
+        StdFileSystem.open_at(path, offset)
+    }
+}
+
+/// Lines of uneven length, some matching, some CRLF, the last unterminated
+fn big_log() -> String {
+    let mut log: String = (0..5000)
+        .map(|line| match line % 7 {
+            0 => format!("error: request {} failed{}\n", line, "!".repeat(line % 13)),
+            3 => format!("warn: slow request {}\r\n", line),
+            _ => format!("info: {}\n", "x".repeat(line % 41)),
+        })
+        .collect();
+    log.push_str("error: no newline at the end");
+    log
+}
+
+/// Config searching `dir` with `workers`, writing each match with its line
+fn config_for(dir: &Path, split_threshold_bytes: Option<u64>, workers: usize) -> ParserConfig {
+    let mut config = ParserConfig {
+        log_folder: dir.to_string_lossy().into_owned(),
+        output_log: dir.join("output.txt").to_string_lossy().into_owned(),
+        show_location: true,
+        workers: Some(workers),
+        split_threshold_bytes,
+        ..Default::default()
+    };
+    add_search(&mut config.search_terms, "error", "");
+    add_search(&mut config.search_terms, "slow", "");
+    config
+}
+
+/// Totals of a run and its output
+async fn run(config: ParserConfig) -> (Vec<u64>, String) {
+    let output_log = config.output_log.clone();
+    let result = run_parser(config, None).await.unwrap();
+    assert!(result.errors.is_empty());
+    let totals = [result.total_matches, result.lines_scanned]
+        .into_iter()
+        .chain(result.per_term)
+        .map(|total| total as u64)
+        .chain([result.bytes_read])
+        .collect();
+    (totals, fs::read_to_string(output_log).unwrap())
+}
+
+#[tokio::test]
+async fn split_files_yield_the_results_of_a_whole_scan() {
+    let dir = tempfile::tempdir().unwrap();
+    let log = big_log();
+    fs::write(dir.path().join("big.log"), &log).unwrap();
+
+    let (whole_totals, whole_output) = run(config_for(dir.path(), None, 1)).await;
+    assert_eq!(whole_totals[1], 5001);
+    assert_eq!(whole_totals[4], log.len() as u64);
+
+    for ranges in 2..=7 {
+        let threshold = (log.len() as u64).div_ceil(ranges);
+        let config = config_for(dir.path(), Some(threshold), ranges as usize);
+        let (totals, output) = run(config).await;
+        assert_eq!(totals, whole_totals, "split into {} ranges", ranges);
+        assert_eq!(output, whole_output, "split into {} ranges", ranges);
+    }
+}
+
+#[cfg(feature = "mmap")]
+#[tokio::test]
+async fn split_mapped_files_yield_the_results_of_a_whole_scan() {
+    let dir = tempfile::tempdir().unwrap();
+    let log = big_log();
+    fs::write(dir.path().join("big.log"), &log).unwrap();
+
+    let (whole_totals, whole_output) = run(config_for(dir.path(), None, 1)).await;
+    for ranges in 2..=7 {
+        let threshold = (log.len() as u64).div_ceil(ranges);
+        let mut config = config_for(dir.path(), Some(threshold), ranges as usize);
+        config.mmap = true;
+        let (totals, output) = run(config).await;
+        assert_eq!(totals, whole_totals, "split into {} ranges", ranges);
+        assert_eq!(output, whole_output, "split into {} ranges", ranges);
+    }
+}
+
+#[tokio::test]
+async fn each_range_is_read_from_its_own_offset() {
+    let dir = tempfile::tempdir().unwrap();
+    let log = big_log();
+    fs::write(dir.path().join("big.log"), &log).unwrap();
+    let file_system = Arc::new(OffsetsOpened::default());
+
+    let mut config = ParserConfig {
+        log_folder: dir.path().to_string_lossy().into_owned(),
+        output_target: OutputTarget::Discard,
+        workers: Some(4),
+        split_threshold_bytes: Some(log.len() as u64 / 4),
+        file_system: Arc::clone(&file_system) as Arc<dyn FileSystem>,
+        ..Default::default()
+    };
+    add_search(&mut config.search_terms, "error", "");
+    let result = run_parser(config, None).await.unwrap();
+
+    assert_eq!(result.lines_scanned, 5001);
+    // Three lookups of a range end, then one read per range
+    let offsets = file_system.0.lock().unwrap().clone();
+    assert_eq!(offsets.len(), 3 + 4);
+    let mut starts: Vec<_> = offsets
+        .into_iter()
+        .filter(|offset| *offset == 0 || log.as_bytes()[*offset as usize - 1] == b'\n')
+        .collect();
+    starts.sort();
+    starts.dedup();
+    assert!(starts.len() >= 4);
+}
+
+#[tokio::test]
+async fn archives_and_small_files_are_scanned_whole() {
+    let dir = tempfile::tempdir().unwrap();
+    let log = big_log();
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(log.as_bytes()).unwrap();
+    // Found out as an archive by its first bytes, whatever its name
+    fs::write(dir.path().join("big.log"), encoder.finish().unwrap()).unwrap();
+    fs::write(dir.path().join("small.log"), "error: small\n").unwrap();
+    let file_system = Arc::new(OffsetsOpened::default());
+
+    let mut config = ParserConfig {
+        log_folder: dir.path().to_string_lossy().into_owned(),
+        output_target: OutputTarget::Discard,
+        workers: Some(4),
+        split_threshold_bytes: Some(64),
+        file_system: Arc::clone(&file_system) as Arc<dyn FileSystem>,
+        ..Default::default()
+    };
+    add_search(&mut config.search_terms, "error", "");
+    let result = run_parser(config, None).await.unwrap();
+
+    assert_eq!(result.total_matches, 715 + 1 + 1);
+    assert!(file_system.0.lock().unwrap().is_empty());
+}
@@ -0,0 +1,164 @@
+use elysiumparser::{
+    CollectSink, MatchSink, OutputFormat, ParserConfig, ScanOptions, TermRoutingSink, add_search,
+    process_reader, run_parser,
+};
+use std::fs;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+const LOG: &str = "\
+error: out of memory
+error: login failed
+kernel: oom killer invoked
+info: all good
+error: auth token expired
+";
+
+/// Config searching `dir` for OOMs, auth failures and errors, the first two
+/// written to files of their own
+fn config_for(dir: &Path) -> ParserConfig {
+    let mut config = ParserConfig {
+        log_folder: dir.join("logs").to_string_lossy().into_owned(),
+        output_log: dir.join("output.log").to_string_lossy().into_owned(),
+        workers: Some(2),
+        ..Default::default()
+    };
+    let terms = &mut config.search_terms;
+    add_search(terms, "oom", "");
+    add_search(terms, "out of memory", "");
+    add_search(terms, "auth", "");
+    add_search(terms, "error", "");
+    terms[0].output = Some(dir.join("out/oom.log"));
+    terms[1].output = Some(dir.join("out/oom.log"));
+    terms[2].output = Some(dir.join("out/auth.log"));
+    config
+}
+
+#[tokio::test]
+async fn matches_go_to_the_file_of_each_term_matching_them() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::create_dir(dir.path().join("logs")).unwrap();
+    fs::write(dir.path().join("logs/app.log"), LOG).unwrap();
+
+    let result = run_parser(config_for(dir.path()), None).await.unwrap();
+
+    assert_eq!(result.total_matches, 4);
+    assert_eq!(
+        fs::read_to_string(dir.path().join("out/oom.log")).unwrap(),
+        "error: out of memory\nkernel: oom killer invoked\n"
+    );
+    assert_eq!(
+        fs::read_to_string(dir.path().join("out/auth.log")).unwrap(),
+        "error: auth token expired\n"
+    );
+    // Errors have no file of their own, whichever term matched first
+    assert_eq!(
+        fs::read_to_string(dir.path().join("output.log")).unwrap(),
+        "error: out of memory\nerror: login failed\nerror: auth token expired\n"
+    );
+
+    let stats: Vec<_> = result
+        .term_stats
+        .iter()
+        .map(|stat| (stat.match_count, stat.lines_written, stat.output.clone()))
+        .collect();
+    assert_eq!(
+        stats,
+        [
+            (1, 1, Some(dir.path().join("out/oom.log"))),
+            (1, 1, Some(dir.path().join("out/oom.log"))),
+            (1, 1, Some(dir.path().join("out/auth.log"))),
+            (1, 0, None),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn term_outputs_in_the_log_folder_are_not_searched() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::create_dir(dir.path().join("logs")).unwrap();
+    fs::write(dir.path().join("logs/app.log"), LOG).unwrap();
+
+    for _ in 0..2 {
+        let mut config = config_for(dir.path());
+        config.search_terms[2].output = Some(dir.path().join("logs/auth.log"));
+        let result = run_parser(config, None).await.unwrap();
+        assert_eq!(result.file_results.len(), 1);
+        assert_eq!(result.total_matches, 4);
+    }
+    assert_eq!(
+        fs::read_to_string(dir.path().join("logs/auth.log")).unwrap(),
+        "error: auth token expired\n"
+    );
+}
+
+#[tokio::test]
+async fn term_outputs_are_moved_into_place_once_written() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::create_dir(dir.path().join("logs")).unwrap();
+    fs::write(dir.path().join("logs/app.log"), LOG).unwrap();
+    fs::create_dir(dir.path().join("out")).unwrap();
+    fs::write(dir.path().join("out/oom.log"), "previous run\n").unwrap();
+
+    let mut config = config_for(dir.path());
+    config.output_format = OutputFormat::Tsv;
+    run_parser(config, None).await.unwrap();
+
+    // Both OOM terms lead to one sink, so each line is written once
+    let oom = fs::read_to_string(dir.path().join("out/oom.log")).unwrap();
+    assert_eq!(oom.lines().count(), 2, "{}", oom);
+    assert!(oom.lines().all(|line| line.starts_with("app.log\t")));
+    let names: Vec<_> = fs::read_dir(dir.path().join("out"))
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name())
+        .collect();
+    assert_eq!(names.len(), 2, "{:?}", names);
+}
+
+#[test]
+fn lines_reach_each_output_once() {
+    let mut terms = Vec::new();
+    add_search(&mut terms, "disk", "");
+    add_search(&mut terms, "full", "");
+    add_search(&mut terms, "error", "");
+    let disk = Arc::new(CollectSink::default());
+    let fallback = Arc::new(CollectSink::default());
+    let routes: Vec<Option<Arc<dyn MatchSink>>> = vec![
+        Some(Arc::clone(&disk) as Arc<dyn MatchSink>),
+        Some(Arc::clone(&disk) as Arc<dyn MatchSink>),
+        None,
+    ];
+    let options = ScanOptions::default();
+    let sink = TermRoutingSink::new(
+        &terms,
+        &options,
+        routes,
+        Some(Arc::clone(&fallback) as Arc<dyn MatchSink>),
+    )
+    .unwrap();
+
+    let input = "DISK FULL\nerror: disk full\nerror: timeout\nwarn: full\n";
+    let scan = process_reader(
+        Cursor::new(input),
+        &PathBuf::from("app.log"),
+        &terms,
+        &options,
+        Some(&sink),
+    );
+    assert_eq!(scan.matches, 4);
+    sink.finish().unwrap();
+
+    let lines = |sink: &CollectSink| -> Vec<String> {
+        sink.take_matches()
+            .into_iter()
+            .map(|record| record.line)
+            .collect()
+    };
+    assert_eq!(
+        lines(&disk),
+        ["DISK FULL", "error: disk full", "warn: full"]
+    );
+    assert_eq!(lines(&fallback), ["error: disk full", "error: timeout"]);
+    assert_eq!(sink.written(), [2, 3, 0]);
+}
@@ -0,0 +1,311 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use elysiumparser::{
+    DEFAULT_TIMESTAMP_FORMAT, InputFormat, ParserConfig, ScanOptions, TimeWindow, add_search,
+    collect_log_files, filename_date, parse_time_bound, process_reader, run_parser,
+};
+use filetime::FileTime;
+use std::fs;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+
+fn at(text: &str) -> NaiveDateTime {
+    parse_time_bound(text).unwrap()
+}
+
+fn window(since: &str, until: &str) -> TimeWindow {
+    TimeWindow::new(
+        Some(at(since)),
+        Some(at(until)),
+        DEFAULT_TIMESTAMP_FORMAT,
+        false,
+    )
+    .unwrap()
+}
+
+fn config_for(dir: &Path) -> ParserConfig {
+    ParserConfig {
+        log_folder: dir.to_string_lossy().into_owned(),
+        output_log: dir.join("output.txt").to_string_lossy().into_owned(),
+        ..Default::default()
+    }
+}
+
+fn names(paths: &[PathBuf]) -> Vec<String> {
+    paths
+        .iter()
+        .map(|path| path.file_name().unwrap().to_string_lossy().into_owned())
+        .collect()
+}
+
+#[test]
+fn bounds_accept_dates_and_times() {
+    assert_eq!(at("2024-03-15"), at("2024-03-15T00:00:00"));
+    assert_eq!(at("2024-03-15 12:30"), at("2024-03-15T12:30:00"));
+    assert_eq!(
+        at(" 2024-03-15T12:30:05.250 "),
+        at("2024-03-15 12:30:05.25")
+    );
+    assert!(parse_time_bound("yesterday").is_err());
+    assert!(parse_time_bound("2024-02-30").is_err());
+}
+
+#[test]
+fn since_is_inclusive_and_until_exclusive() {
+    let window = window("2024-03-15", "2024-03-16");
+
+    assert!(window.contains_line("2024-03-15T00:00:00 start"));
+    assert!(window.contains_line("2024-03-15T23:59:59.999Z late"));
+    assert!(!window.contains_line("2024-03-16T00:00:00 next day"));
+    assert!(!window.contains_line("2024-03-14T23:59:59 day before"));
+}
+
+#[test]
+fn offsets_are_converted_to_utc() {
+    let window = TimeWindow::new(
+        Some(at("2024-03-15T10:00")),
+        None,
+        "%Y-%m-%dT%H:%M:%S%:z",
+        false,
+    )
+    .unwrap();
+
+    assert_eq!(
+        window.line_time("2024-03-15T11:30:00+02:00 error"),
+        Some(at("2024-03-15T09:30"))
+    );
+    assert!(!window.contains_line("2024-03-15T11:30:00+02:00 error"));
+    assert!(window.contains_line("2024-03-15T11:30:00+01:00 error"));
+}
+
+#[test]
+fn untimestamped_lines_follow_the_knob() {
+    let line = "    at com.example.Service.run(Service.java:42)";
+    assert!(!window("2024-03-15", "2024-03-16").contains_line(line));
+
+    let lenient =
+        TimeWindow::new(Some(at("2024-03-15")), None, DEFAULT_TIMESTAMP_FORMAT, true).unwrap();
+    assert!(lenient.contains_line(line));
+    assert!(!lenient.contains_line("2024-03-14T08:00:00 old"));
+}
+
+#[test]
+fn malformed_timestamps_count_as_untimestamped() {
+    let input = "2024-03-15T10:00:00 error valid\n\
+                 2024-13-45T10:00:00 error bad month\n\
+                 2024-03-15T25:61:00 error bad time\n\
+                 error no timestamp\n";
+    let mut search_terms = Vec::new();
+    add_search(&mut search_terms, "error", "");
+
+    for (include_untimestamped, expected) in [(false, 1), (true, 4)] {
+        let options = ScanOptions {
+            time_window: Some(
+                TimeWindow::new(
+                    Some(at("2024-03-15")),
+                    None,
+                    DEFAULT_TIMESTAMP_FORMAT,
+                    include_untimestamped,
+                )
+                .unwrap(),
+            ),
+            ..Default::default()
+        };
+        let scan = process_reader(
+            Cursor::new(input),
+            Path::new("app.log"),
+            &search_terms,
+            &options,
+            None,
+        );
+        assert_eq!(scan.matches, expected, "{}", include_untimestamped);
+        assert!(scan.error.is_none());
+    }
+}
+
+#[test]
+fn docker_and_cri_lines_are_dated_by_their_record() {
+    let records = [
+        ("2024-03-14T23:00:00Z", "error before\\n"),
+        ("2024-03-15T08:00:00Z", "error inside, "),
+        ("2024-03-16T00:00:00Z", "split\\n"),
+        (
+            "2024-03-16T01:00:00+02:00",
+            "2024-03-15T09:00:00 error after\\n",
+        ),
+    ];
+    let docker: String = records
+        .iter()
+        .map(|(time, log)| format!("{{\"log\":\"{}\",\"time\":\"{}\"}}\n", log, time))
+        .collect();
+    let cri = "2024-03-14T23:00:00Z stderr F error before\n\
+               2024-03-15T08:00:00Z stderr P error inside, \n\
+               2024-03-16T00:00:00Z stderr F split\n\
+               2024-03-16T01:00:00+02:00 stderr F 2024-03-15T09:00:00 error after\n";
+    let mut search_terms = Vec::new();
+    add_search(&mut search_terms, "error", "");
+
+    for (input_format, input) in [
+        (InputFormat::DockerJson, docker.as_str()),
+        (InputFormat::Cri, cri),
+    ] {
+        for (message_timestamps, expected) in [(false, 2), (true, 1)] {
+            let options = ScanOptions {
+                input_format,
+                time_window: Some(window("2024-03-15", "2024-03-16")),
+                message_timestamps,
+                ..Default::default()
+            };
+            let scan = process_reader(
+                Cursor::new(input),
+                Path::new("app.log"),
+                &search_terms,
+                &options,
+                None,
+            );
+            assert_eq!(
+                scan.matches, expected,
+                "{:?} {}",
+                input_format, message_timestamps
+            );
+        }
+    }
+}
+
+#[test]
+fn invalid_formats_are_rejected() {
+    assert!(TimeWindow::new(None, None, "%Y-%Q", true).is_err());
+}
+
+#[test]
+fn days_overlap_partially_covered_windows() {
+    let window = window("2024-03-15T18:00", "2024-03-17T06:00");
+    let day = |day| NaiveDate::from_ymd_opt(2024, 3, day).unwrap();
+
+    assert!(!window.overlaps_day(day(14)));
+    assert!(window.overlaps_day(day(15)));
+    assert!(window.overlaps_day(day(17)));
+    assert!(!window.overlaps_day(day(18)));
+}
+
+#[test]
+fn dates_are_found_anywhere_in_file_names() {
+    let date = |name| filename_date(Path::new(name));
+
+    assert_eq!(
+        date("app-2024-03-15.log.gz"),
+        NaiveDate::from_ymd_opt(2024, 3, 15)
+    );
+    assert_eq!(
+        date("2024-03-15_app.log"),
+        NaiveDate::from_ymd_opt(2024, 3, 15)
+    );
+    assert_eq!(date("app-2024-13-15.log"), None);
+    assert_eq!(date("app.log"), None);
+    assert_eq!(date("0.log.20240607-120000"), None);
+}
+
+#[tokio::test]
+async fn lines_outside_the_window_do_not_match() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(
+        dir.path().join("app.log"),
+        "2024-03-14T23:00:00 error before\n\
+         2024-03-15T08:00:00 error inside\n\
+         \tcaused by: error in a continuation line\n\
+         2024-03-16T00:00:00 error after\n",
+    )
+    .unwrap();
+
+    let mut config = config_for(dir.path());
+    add_search(&mut config.search_terms, "error", "");
+    config.since = Some(at("2024-03-15"));
+    config.until = Some(at("2024-03-16"));
+    config.include_untimestamped = false;
+    let output_log = config.output_log.clone();
+
+    let result = run_parser(config, None).await.unwrap();
+
+    assert_eq!(result.total_matches, 1);
+    assert_eq!(result.lines_scanned, 4);
+    assert_eq!(
+        fs::read_to_string(output_log).unwrap(),
+        "2024-03-15T08:00:00 error inside\n"
+    );
+}
+
+#[tokio::test]
+async fn inverted_runs_keep_only_lines_inside_the_window() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(
+        dir.path().join("app.log"),
+        "2024-03-14T23:00:00 info before\n\
+         2024-03-15T08:00:00 info inside\n\
+         2024-03-15T09:00:00 error inside\n",
+    )
+    .unwrap();
+
+    let mut config = config_for(dir.path());
+    add_search(&mut config.search_terms, "error", "");
+    config.invert_match = true;
+    config.since = Some(at("2024-03-15"));
+    let output_log = config.output_log.clone();
+
+    let result = run_parser(config, None).await.unwrap();
+
+    assert_eq!(result.total_matches, 1);
+    assert_eq!(
+        fs::read_to_string(output_log).unwrap(),
+        "2024-03-15T08:00:00 info inside\n"
+    );
+}
+
+#[test]
+fn files_modified_before_since_are_skipped() {
+    let dir = tempfile::tempdir().unwrap();
+    for (name, modified) in [
+        ("old.log", "2024-03-10T12:00"),
+        ("new.log", "2024-03-15T12:00"),
+    ] {
+        let path = dir.path().join(name);
+        fs::write(&path, "error\n").unwrap();
+        let seconds = at(modified).and_utc().timestamp();
+        filetime::set_file_mtime(&path, FileTime::from_unix_time(seconds, 0)).unwrap();
+    }
+
+    let mut config = config_for(dir.path());
+    config.since = Some(at("2024-03-15"));
+
+    assert_eq!(names(&collect_log_files(&config).unwrap()), ["new.log"]);
+}
+
+#[test]
+fn filename_dates_skip_days_outside_the_range() {
+    let dir = tempfile::tempdir().unwrap();
+    for name in ["app-2024-03-14.log", "app-2024-03-15.log", "app.log"] {
+        fs::write(dir.path().join(name), "error\n").unwrap();
+    }
+
+    let mut config = config_for(dir.path());
+    config.until = Some(at("2024-03-15T06:00"));
+    assert_eq!(names(&collect_log_files(&config).unwrap()).len(), 3);
+
+    config.since = Some(at("2024-03-15"));
+    config.filename_dates = true;
+    assert_eq!(
+        names(&collect_log_files(&config).unwrap()),
+        ["app-2024-03-15.log", "app.log"]
+    );
+}
+
+#[tokio::test]
+async fn invalid_timestamp_formats_fail_before_the_output_is_touched() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut config = config_for(dir.path());
+    add_search(&mut config.search_terms, "error", "");
+    config.since = Some(at("2024-03-15"));
+    config.timestamp_format = "%Y-%Q".to_string();
+    let output_log = config.output_log.clone();
+
+    assert!(run_parser(config, None).await.is_err());
+    assert!(!Path::new(&output_log).exists());
+}
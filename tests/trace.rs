@@ -0,0 +1,104 @@
+use elysiumparser::{
+    LatencyHistogram, MatchStrategy, ParserConfig, SearchSet, add_search,
+    add_search_with_expression, run_parser,
+};
+use std::fs;
+
+#[test]
+fn histogram_buckets_stay_within_a_quarter() {
+    let mut histogram = LatencyHistogram::default();
+    assert_eq!(histogram.quantile(0.5), 0);
+
+    for nanos in [0, 3, 4, 7, 8, 9, 1000, 1_000_000, u64::MAX] {
+        let mut single = LatencyHistogram::default();
+        single.record(nanos);
+        let bound = single.quantile(1.0);
+        assert!(bound >= nanos, "{} above its bound {}", nanos, bound);
+        assert!(
+            bound - nanos <= nanos / 4,
+            "{} too far from {}",
+            nanos,
+            bound
+        );
+        histogram.merge(&single);
+    }
+
+    assert_eq!(histogram.count(), 9);
+    assert_eq!(histogram.quantile(0.0), 0);
+    assert_eq!(histogram.quantile(0.5), 9);
+    assert_eq!(histogram.quantile(1.0), u64::MAX);
+}
+
+#[test]
+fn traced_lines_time_each_evaluated_atom() {
+    let mut terms = Vec::new();
+    add_search_with_expression(&mut terms, "error", "disk | timeout").unwrap();
+    let set = SearchSet::new(&terms, "", MatchStrategy::Naive);
+    let mut timings = set.atom_timings();
+    assert_eq!(timings.len(), 3);
+
+    for line in ["error: disk full", "error: timeout", "info: all good"] {
+        assert_eq!(
+            set.matching_line_traced(line, line, &mut timings),
+            set.matching_line(line, line)
+        );
+    }
+
+    let samples: Vec<(&str, u64)> = timings
+        .iter()
+        .map(|timing| (timing.atom.as_str(), timing.samples()))
+        .collect();
+    // The expression is only evaluated after the keyword matched, and
+    // `timeout` only when `disk` did not match
+    assert_eq!(samples, [("error", 3), ("disk", 2), ("timeout", 1)]);
+}
+
+#[tokio::test]
+async fn run_parser_samples_a_fraction_of_lines() {
+    let dir = tempfile::tempdir().unwrap();
+    let lines: String = (0..10_000)
+        .map(|line| format!("line {} error timeout\n", line))
+        .collect();
+    fs::write(dir.path().join("app.log"), lines).unwrap();
+
+    let mut config = ParserConfig {
+        log_folder: dir.path().to_string_lossy().into_owned(),
+        output_log: dir.path().join("output.log").to_string_lossy().into_owned(),
+        match_strategy: MatchStrategy::Naive,
+        trace_sampling: Some(0.1),
+        ..Default::default()
+    };
+    add_search(&mut config.search_terms, "error", "timeout");
+
+    let result = run_parser(config, None).await.unwrap();
+
+    assert_eq!(result.total_matches, 10_000);
+    assert_eq!(result.atom_timings.len(), 2);
+    for timing in &result.atom_timings {
+        assert!(
+            (700..1300).contains(&timing.samples()),
+            "{} sampled {} times",
+            timing.atom,
+            timing.samples()
+        );
+    }
+    assert!(result.atom_timings[0].total >= result.atom_timings[1].total);
+}
+
+#[tokio::test]
+async fn timings_are_empty_without_sampling() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("app.log"), "error\n").unwrap();
+
+    let mut config = ParserConfig {
+        log_folder: dir.path().to_string_lossy().into_owned(),
+        output_log: dir.path().join("output.log").to_string_lossy().into_owned(),
+        ..Default::default()
+    };
+    add_search(&mut config.search_terms, "error", "");
+
+    let result = run_parser(config, None).await.unwrap();
+
+    assert_eq!(result.total_matches, 1);
+    assert!(result.atom_timings.is_empty());
+}
@@ -0,0 +1,118 @@
+use elysiumparser::{
+    BooleanExpression, ParserConfig, RunMode, ScoreRule, add_scored_search, run_parser,
+};
+use std::fs;
+
+#[tokio::test]
+async fn triage_output_lists_top_scores_first() {
+    let dir = tempfile::tempdir().unwrap();
+    let logs = dir.path().join("logs");
+    fs::create_dir(&logs).unwrap();
+    fs::write(
+        logs.join("a.log"),
+        "warn: slow query\nfatal: out of memory\nwarn: level=error retry\n",
+    )
+    .unwrap();
+    fs::write(
+        logs.join("b.log"),
+        "warn: cache miss\nfatal: panic at src/main.rs:12 in handler::serve\n",
+    )
+    .unwrap();
+
+    let triage_path = dir.path().join("triage.tsv");
+    let mut config = ParserConfig {
+        log_folder: logs.to_string_lossy().into_owned(),
+        output_log: dir.path().join("output.log").to_string_lossy().into_owned(),
+        workers: Some(2),
+        mode: RunMode::CountOnly,
+        score_rules: vec![
+            ScoreRule::LineLongerThan {
+                length: 30,
+                points: 5,
+            },
+            ScoreRule::Matches {
+                expression: BooleanExpression::parse("level=error").unwrap(),
+                points: 3,
+            },
+        ],
+        triage_output: Some(triage_path.clone()),
+        triage_top: 3,
+        ..Default::default()
+    };
+    add_scored_search(&mut config.search_terms, "fatal", "", 10).unwrap();
+    add_scored_search(&mut config.search_terms, "warn", "", 1).unwrap();
+
+    let result = run_parser(config, None).await.unwrap();
+
+    assert_eq!(result.total_matches, 5);
+    let scores: Vec<_> = result.top_matches.iter().map(|m| m.score).collect();
+    assert_eq!(scores, vec![15, 10, 4]);
+    assert_eq!(result.top_matches[1].line, "fatal: out of memory");
+    assert_eq!(result.top_matches[2].line_number, 3);
+
+    let triage = fs::read_to_string(triage_path).unwrap();
+    let lines: Vec<_> = triage.lines().collect();
+    assert_eq!(lines.len(), 3);
+    assert!(lines[0].starts_with("15\t"));
+    assert!(lines[0].ends_with("b.log:2\tfatal: panic at src/main.rs:12 in handler::serve"));
+}
+
+#[tokio::test]
+async fn equal_scores_keep_the_earliest_matches() {
+    let dir = tempfile::tempdir().unwrap();
+    let logs = dir.path().join("logs");
+    fs::create_dir(&logs).unwrap();
+    for name in ["a.log", "b.log", "c.log"] {
+        fs::write(logs.join(name), "error one\nerror two\n").unwrap();
+    }
+
+    let mut config = ParserConfig {
+        log_folder: logs.to_string_lossy().into_owned(),
+        output_log: dir.path().join("output.log").to_string_lossy().into_owned(),
+        workers: Some(3),
+        triage_top: 3,
+        ..Default::default()
+    };
+    add_scored_search(&mut config.search_terms, "error", "", 1).unwrap();
+
+    let result = run_parser(config, None).await.unwrap();
+
+    let kept: Vec<_> = result
+        .top_matches
+        .iter()
+        .map(|m| {
+            format!(
+                "{}:{}",
+                m.path.file_name().unwrap().to_string_lossy(),
+                m.line_number
+            )
+        })
+        .collect();
+    assert_eq!(kept, vec!["a.log:1", "a.log:2", "b.log:1"]);
+}
+
+#[tokio::test]
+async fn triage_only_runs_when_asked_for() {
+    let dir = tempfile::tempdir().unwrap();
+    let logs = dir.path().join("logs");
+    fs::create_dir(&logs).unwrap();
+    fs::write(logs.join("a.log"), "error one\nerror two\n").unwrap();
+
+    let triage_path = dir.path().join("triage.tsv");
+    for triage_output in [None, Some(triage_path.clone())] {
+        let mut config = ParserConfig {
+            log_folder: logs.to_string_lossy().into_owned(),
+            output_log: dir.path().join("output.log").to_string_lossy().into_owned(),
+            triage_output: triage_output.clone(),
+            ..Default::default()
+        };
+        add_scored_search(&mut config.search_terms, "error", "", 1).unwrap();
+
+        let result = run_parser(config, None).await.unwrap();
+
+        assert_eq!(result.total_matches, 2);
+        let expected = if triage_output.is_some() { 2 } else { 0 };
+        assert_eq!(result.top_matches.len(), expected);
+    }
+    assert_eq!(fs::read_to_string(triage_path).unwrap().lines().count(), 2);
+}
@@ -0,0 +1,180 @@
+use elysiumparser::units::{
+    DURATION_UNITS, SIZE_UNITS, UnitError, deserialize_duration, deserialize_size, duration_help,
+    parse_duration, parse_size, size_help,
+};
+use serde::Deserialize;
+use std::time::Duration;
+
+#[test]
+fn sizes_distinguish_si_and_iec_units() {
+    assert_eq!(parse_size("1GB"), Ok(1_000_000_000));
+    assert_eq!(parse_size("1GiB"), Ok(1 << 30));
+    assert_eq!(parse_size("250MB"), Ok(250_000_000));
+    assert_eq!(parse_size("250MiB"), Ok(250 << 20));
+    assert_eq!(parse_size("1kb"), Ok(1000));
+    assert_eq!(parse_size("1kib"), Ok(1024));
+    assert_eq!(parse_size("2TiB"), Ok(2 << 40));
+}
+
+#[test]
+fn sizes_accept_plain_bytes_fractions_and_spaces() {
+    assert_eq!(parse_size("0"), Ok(0));
+    assert_eq!(parse_size("4096"), Ok(4096));
+    assert_eq!(parse_size("4096B"), Ok(4096));
+    assert_eq!(parse_size(" 250 MB "), Ok(250_000_000));
+    assert_eq!(parse_size("1.5GiB"), Ok(3 << 29));
+    assert_eq!(parse_size(".5KiB"), Ok(512));
+    assert_eq!(parse_size("1.0005KB"), Ok(1000));
+}
+
+#[test]
+fn size_boundaries() {
+    assert_eq!(parse_size("18446744073709551615"), Ok(u64::MAX));
+    assert_eq!(
+        parse_size("18446744073709551616"),
+        Err(UnitError::Overflow("18446744073709551616".to_string()))
+    );
+    assert_eq!(parse_size("16777215TiB"), Ok(16_777_215 << 40));
+    assert!(matches!(
+        parse_size("16777216TiB"),
+        Err(UnitError::Overflow(_))
+    ));
+    assert!(matches!(
+        parse_size("99999999999999999999999999999999999999999B"),
+        Err(UnitError::Overflow(_))
+    ));
+}
+
+#[test]
+fn invalid_sizes_are_rejected() {
+    assert_eq!(parse_size(""), Err(UnitError::Empty));
+    assert_eq!(parse_size("  "), Err(UnitError::Empty));
+    assert_eq!(
+        parse_size("-1MB"),
+        Err(UnitError::Negative("-1MB".to_string()))
+    );
+    assert_eq!(
+        parse_size("MB"),
+        Err(UnitError::InvalidNumber("MB".to_string()))
+    );
+    assert_eq!(
+        parse_size("1..5MB"),
+        Err(UnitError::InvalidNumber("1..5MB".to_string()))
+    );
+    assert!(matches!(
+        parse_size("5XB"),
+        Err(UnitError::UnknownUnit { unit, .. }) if unit == "XB"
+    ));
+    assert!(matches!(
+        parse_size("5 ms"),
+        Err(UnitError::UnknownUnit { .. })
+    ));
+}
+
+#[test]
+fn durations_combine_components() {
+    assert_eq!(parse_duration("500ms"), Ok(Duration::from_millis(500)));
+    assert_eq!(parse_duration("90s"), Ok(Duration::from_secs(90)));
+    assert_eq!(parse_duration("2h30m"), Ok(Duration::from_secs(9000)));
+    assert_eq!(parse_duration("2h 30m"), Ok(Duration::from_secs(9000)));
+    assert_eq!(parse_duration("1d1s"), Ok(Duration::from_secs(86_401)));
+    assert_eq!(parse_duration("1.5h"), Ok(Duration::from_secs(5400)));
+    assert_eq!(parse_duration("10 S"), Ok(Duration::from_secs(10)));
+    assert_eq!(parse_duration("3us"), Ok(Duration::from_micros(3)));
+    assert_eq!(parse_duration("7ns"), Ok(Duration::from_nanos(7)));
+}
+
+#[test]
+fn duration_boundaries() {
+    assert_eq!(parse_duration("0"), Ok(Duration::ZERO));
+    assert_eq!(parse_duration("0s"), Ok(Duration::ZERO));
+    assert_eq!(
+        parse_duration("18446744073709551615ns"),
+        Ok(Duration::from_nanos(u64::MAX))
+    );
+    assert!(matches!(
+        parse_duration("18446744073709551616ns"),
+        Err(UnitError::Overflow(_))
+    ));
+    assert!(matches!(
+        parse_duration("18446744073709551615ns1ns"),
+        Err(UnitError::Overflow(_))
+    ));
+    assert!(matches!(
+        parse_duration("213504d"),
+        Err(UnitError::Overflow(_))
+    ));
+}
+
+#[test]
+fn invalid_durations_are_rejected() {
+    assert_eq!(parse_duration(""), Err(UnitError::Empty));
+    assert_eq!(
+        parse_duration("-5s"),
+        Err(UnitError::Negative("-5s".to_string()))
+    );
+    assert_eq!(
+        parse_duration("30"),
+        Err(UnitError::MissingUnit("30".to_string()))
+    );
+    assert_eq!(
+        parse_duration("2h30"),
+        Err(UnitError::MissingUnit("2h30".to_string()))
+    );
+    assert_eq!(
+        parse_duration("h"),
+        Err(UnitError::InvalidNumber("h".to_string()))
+    );
+    assert_eq!(
+        parse_duration("1h-5m"),
+        Err(UnitError::InvalidNumber("1h-5m".to_string()))
+    );
+    assert!(matches!(
+        parse_duration("5y"),
+        Err(UnitError::UnknownUnit { unit, .. }) if unit == "y"
+    ));
+}
+
+#[test]
+fn errors_and_help_list_the_unit_table() {
+    let error = parse_size("5XB").unwrap_err().to_string();
+    let help = size_help("Largest file read");
+    for (unit, _) in SIZE_UNITS {
+        assert!(error.contains(unit));
+        assert!(help.contains(unit));
+    }
+
+    let error = parse_duration("5y").unwrap_err().to_string();
+    let help = duration_help("Give up after");
+    for (unit, _) in DURATION_UNITS {
+        assert!(error.contains(unit));
+        assert!(help.contains(unit));
+    }
+    assert!(help.starts_with("Give up after ("));
+}
+
+#[derive(Deserialize)]
+struct Limits {
+    #[serde(deserialize_with = "deserialize_size")]
+    max_file_size: u64,
+    #[serde(deserialize_with = "deserialize_duration")]
+    deadline: Duration,
+}
+
+#[test]
+fn config_values_accept_units_or_plain_numbers() {
+    let limits: Limits =
+        serde_json::from_str(r#"{"max_file_size": "1.5GiB", "deadline": "2h30m"}"#).unwrap();
+    assert_eq!(limits.max_file_size, 3 << 29);
+    assert_eq!(limits.deadline, Duration::from_secs(9000));
+
+    let limits: Limits =
+        serde_json::from_str(r#"{"max_file_size": 4096, "deadline": 30}"#).unwrap();
+    assert_eq!(limits.max_file_size, 4096);
+    assert_eq!(limits.deadline, Duration::from_secs(30));
+
+    let error = serde_json::from_str::<Limits>(r#"{"max_file_size": "-1MB", "deadline": "1s"}"#)
+        .err()
+        .unwrap();
+    assert!(error.to_string().contains("'-1MB' is negative"));
+}
@@ -0,0 +1,90 @@
+#![cfg(feature = "watch")]
+
+use elysiumparser::{MatchEvent, OutputTarget, ParserConfig, add_search, run_parser_watch};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver};
+use std::time::Duration;
+
+/// Matched lines and final match count reported by a watched run
+enum Reported {
+    Line(String),
+    Finished(usize),
+}
+
+fn config_for(dir: &Path) -> ParserConfig {
+    let mut config = ParserConfig {
+        log_folder: dir.to_string_lossy().into_owned(),
+        output_target: OutputTarget::Discard,
+        // Long enough that only file events explain a prompt match
+        follow_interval: Duration::from_secs(60),
+        ..Default::default()
+    };
+    add_search(&mut config.search_terms, "error", "");
+    config
+}
+
+fn next(events: &Receiver<Reported>) -> Reported {
+    events
+        .recv_timeout(Duration::from_secs(10))
+        .expect("no event reported in time")
+}
+
+fn next_line(events: &Receiver<Reported>) -> String {
+    match next(events) {
+        Reported::Line(line) => line,
+        Reported::Finished(_) => panic!("the run ended early"),
+    }
+}
+
+#[test]
+fn file_events_report_new_files_and_appended_lines() {
+    let dir = tempfile::tempdir().unwrap();
+    let app = dir.path().join("app.log");
+    fs::write(&app, "error: first\ninfo: fine\n").unwrap();
+
+    let (sender, events) = mpsc::channel();
+    let watch = run_parser_watch(config_for(dir.path()), move |event| {
+        let reported = match event {
+            MatchEvent::Match(record) => Reported::Line(record.line),
+            MatchEvent::Finished(result) => Reported::Finished(result.unwrap().total_matches),
+        };
+        let _ = sender.send(reported);
+    })
+    .unwrap();
+
+    // Existing files are scanned like by run_parser
+    assert_eq!(next_line(&events), "error: first");
+
+    // Only what is appended is scanned again
+    let mut file = OpenOptions::new().append(true).open(&app).unwrap();
+    file.write_all(b"error: appended\n").unwrap();
+    assert_eq!(next_line(&events), "error: appended");
+
+    fs::write(dir.path().join("new.log"), "error: new file\n").unwrap();
+    assert_eq!(next_line(&events), "error: new file");
+
+    watch.stop();
+    match next(&events) {
+        Reported::Finished(total_matches) => assert_eq!(total_matches, 3),
+        Reported::Line(line) => panic!("unexpected match {:?}", line),
+    }
+}
+
+#[test]
+fn dropping_the_handle_stops_the_run() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("app.log"), "info: fine\n").unwrap();
+
+    let (sender, events) = mpsc::channel();
+    let watch = run_parser_watch(config_for(dir.path()), move |event| {
+        let _ = sender.send(matches!(event, MatchEvent::Finished(Ok(_))));
+    })
+    .unwrap();
+    assert!(!watch.is_finished());
+
+    drop(watch);
+
+    assert_eq!(events.try_recv(), Ok(true));
+}
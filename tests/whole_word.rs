@@ -0,0 +1,89 @@
+use elysiumparser::{
+    MatchMode, MatchStrategy, ParserConfig, SearchSet, SearchTerm, add_search,
+    add_search_with_expression, run_parser,
+};
+use std::fs;
+
+/// Whether `line` matches `terms` in whole-word mode, checking every strategy agrees
+fn matches(terms: &[SearchTerm], line_filter: &str, line: &str) -> bool {
+    let results: Vec<_> = MatchStrategy::ALL
+        .iter()
+        .map(|strategy| {
+            SearchSet::with_mode(terms, line_filter, *strategy, MatchMode::WholeWord)
+                .unwrap()
+                .matching_term(line)
+                .is_some()
+        })
+        .collect();
+    assert!(
+        results.iter().all(|result| *result == results[0]),
+        "strategies disagree on {:?}: {:?}",
+        line,
+        results
+    );
+    results[0]
+}
+
+#[test]
+fn keywords_only_match_whole_words() {
+    let mut terms = Vec::new();
+    add_search(&mut terms, "err", "");
+
+    assert!(matches(&terms, "", "an err occurred"));
+    assert!(matches(&terms, "", "err"));
+    assert!(matches(&terms, "", "err: retry (err_code=5)"));
+    assert!(matches(&terms, "", "[err]"));
+    assert!(!matches(&terms, "", "terror"));
+    assert!(!matches(&terms, "", "errors"));
+    assert!(!matches(&terms, "", "2err"));
+    assert!(!matches(&terms, "", "éerr"));
+}
+
+#[test]
+fn later_occurrences_are_found_after_partial_ones() {
+    let mut terms = Vec::new();
+    add_search(&mut terms, "err", "");
+
+    assert!(matches(&terms, "", "terror, then err"));
+    assert!(matches(&terms, "", "errerr err"));
+    assert!(!matches(&terms, "", "errerr"));
+}
+
+#[test]
+fn expression_atoms_and_line_filter_match_whole_words() {
+    let mut terms = Vec::new();
+    add_search_with_expression(&mut terms, "", "disk & !full").unwrap();
+
+    assert!(matches(&terms, "", "disk almost fullish"));
+    assert!(!matches(&terms, "", "disk full"));
+    assert!(!matches(&terms, "", "diskette"));
+
+    let mut terms = Vec::new();
+    add_search(&mut terms, "timeout", "");
+    assert!(matches(&terms, "db", "db timeout"));
+    assert!(!matches(&terms, "db", "dbpool timeout"));
+}
+
+#[tokio::test]
+async fn runs_in_whole_word_mode_skip_partial_words() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(
+        dir.path().join("app.log"),
+        "an err occurred\nterror alert\nerrors: 0\n",
+    )
+    .unwrap();
+
+    let mut config = ParserConfig {
+        log_folder: dir.path().to_string_lossy().into_owned(),
+        output_log: dir.path().join("output.log").to_string_lossy().into_owned(),
+        match_mode: MatchMode::WholeWord,
+        ..Default::default()
+    };
+    add_search(&mut config.search_terms, "err", "");
+    let output_log = config.output_log.clone();
+
+    let result = run_parser(config, None).await.unwrap();
+
+    assert_eq!(result.total_matches, 1);
+    assert_eq!(fs::read_to_string(output_log).unwrap(), "an err occurred\n");
+}
@@ -0,0 +1,233 @@
+use elysiumparser::{
+    JsonlSink, MatchSink, ParserConfig, ParserError, ScanOptions, WriterSink, add_search,
+    collect_log_files, process_reader, run_parser,
+};
+use std::fs;
+use std::io::{self, Cursor, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// Writer whose bytes stay readable once it was moved into the writer task
+#[derive(Clone, Default)]
+struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl SharedBuffer {
+    fn contents(&self) -> String {
+        String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+    }
+}
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Writer failing like a closed pipe
+struct BrokenPipe;
+
+impl Write for BrokenPipe {
+    fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+        Err(io::ErrorKind::BrokenPipe.into())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn scan_into(input: &str, name: &str, sink: &dyn MatchSink) -> Option<ParserError> {
+    let mut terms = Vec::new();
+    add_search(&mut terms, "error", "");
+    let options = ScanOptions {
+        before_context: 1,
+        ..Default::default()
+    };
+    process_reader(
+        Cursor::new(input),
+        Path::new(name),
+        &terms,
+        &options,
+        Some(sink),
+    )
+    .error
+}
+
+#[tokio::test]
+async fn lines_of_concurrent_readers_all_reach_the_writer() {
+    let buffer = SharedBuffer::default();
+    // A tiny channel keeps the readers waiting on the writer task
+    let writer = Arc::new(WriterSink::spawn(buffer.clone(), 2));
+
+    let readers: Vec<_> = (0..8)
+        .map(|reader| {
+            let writer = Arc::clone(&writer);
+            thread::spawn(move || {
+                let input: String = (0..500)
+                    .map(|line| format!("info {}\nerror {} {}\n", line, reader, line))
+                    .collect();
+                scan_into(&input, &format!("{}.log", reader), writer.as_ref())
+            })
+        })
+        .collect();
+    for reader in readers {
+        assert!(reader.join().unwrap().is_none());
+    }
+    writer.finish().unwrap();
+    writer.close().unwrap();
+
+    let contents = buffer.contents();
+    let lines: Vec<_> = contents.lines().collect();
+    assert_eq!(lines.len(), 8 * 500 * 2);
+    // Blocks are written whole: each match right after its context line
+    for (index, line) in lines.iter().enumerate() {
+        if let Some(matched) = line.strip_prefix("> error ") {
+            let number = matched.split(' ').nth(1).unwrap();
+            assert_eq!(lines[index - 1], format!("- info {}", number));
+        }
+    }
+}
+
+#[tokio::test]
+async fn jsonl_lines_match_the_jsonl_sink() {
+    let input = "error: disk full\ninfo: ok\nerror: \"quoted\"\n";
+    let buffer = SharedBuffer::default();
+    let writer = WriterSink::spawn(buffer.clone(), 16).jsonl();
+    let expected = Arc::new(Mutex::new(Vec::new()));
+    let jsonl = JsonlSink::new(Arc::clone(&expected));
+
+    scan_into(input, "app.log", &writer);
+    scan_into(input, "app.log", &jsonl);
+    writer.close().unwrap();
+
+    let expected = String::from_utf8(expected.lock().unwrap().clone()).unwrap();
+    assert_eq!(buffer.contents(), expected);
+    assert_eq!(buffer.contents().lines().count(), 2);
+}
+
+#[tokio::test]
+async fn write_failures_reach_the_readers() {
+    let writer = WriterSink::spawn(BrokenPipe, 1);
+    // Matches apart from one another, so each block is sent on its own
+    let input = "error: disk full\ninfo\ninfo\n".repeat(1000);
+
+    let error = scan_into(&input, "app.log", &writer);
+
+    match error {
+        Some(ParserError::Write(e)) => assert_eq!(e.kind(), io::ErrorKind::BrokenPipe),
+        other => panic!("expected a write error, got {:?}", other),
+    }
+    let finished = writer.finish().unwrap_err();
+    assert_eq!(finished.kind(), io::ErrorKind::BrokenPipe);
+}
+
+#[tokio::test]
+async fn closed_writers_reject_lines() {
+    let buffer = SharedBuffer::default();
+    let writer = WriterSink::spawn(buffer.clone(), 16);
+    writer.write_line("# header").unwrap();
+    writer.close().unwrap();
+
+    assert!(writer.write_line("late").is_err());
+    assert_eq!(buffer.contents(), "# header\n");
+}
+
+#[tokio::test]
+async fn batches_are_sent_once_the_scan_ends() {
+    let buffer = SharedBuffer::default();
+    let writer = WriterSink::spawn(buffer.clone(), 16);
+
+    writer.batch(|| {
+        scan_into(
+            "error: disk full\ninfo\ninfo\nerror: timeout\n",
+            "app.log",
+            &writer,
+        );
+        writer.finish().unwrap();
+        // Held by the batch until the scan returns
+        assert_eq!(buffer.contents(), "");
+    });
+    writer.finish().unwrap();
+
+    assert_eq!(
+        buffer.contents(),
+        "> error: disk full\n--\n- info\n> error: timeout\n"
+    );
+}
+
+#[tokio::test]
+async fn batches_are_sent_when_the_scan_panics() {
+    let buffer = SharedBuffer::default();
+    let writer = WriterSink::spawn(buffer.clone(), 16);
+
+    let scanned = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        writer.batch(|| {
+            writer.write_line("error: before the panic").unwrap();
+            panic!("reader failed");
+        })
+    }));
+    writer.finish().unwrap();
+
+    assert!(scanned.is_err());
+    assert_eq!(buffer.contents(), "error: before the panic\n");
+}
+
+/// Output of `run_parser` over `dir`, and the same matches written one by one
+/// through a `Mutex` writer, file after file
+async fn batched_and_direct_output(dir: &Path, ordered_output: bool) -> (String, String) {
+    let output_log = dir.join("output.txt");
+    let mut config = ParserConfig {
+        log_folder: dir.to_string_lossy().into_owned(),
+        output_log: output_log.to_string_lossy().into_owned(),
+        before_context: 1,
+        ordered_output,
+        ..Default::default()
+    };
+    add_search(&mut config.search_terms, "error", "");
+    run_parser(config, None).await.unwrap();
+
+    let direct = Mutex::new(Vec::new());
+    let mut paths = collect_log_files(&ParserConfig {
+        log_folder: dir.to_string_lossy().into_owned(),
+        ..Default::default()
+    })
+    .unwrap();
+    paths.sort();
+    for path in paths {
+        let input = fs::read_to_string(&path).unwrap();
+        scan_into(&input, &path.to_string_lossy(), &direct);
+    }
+    let direct = String::from_utf8(direct.into_inner().unwrap()).unwrap();
+    (fs::read_to_string(output_log).unwrap(), direct)
+}
+
+#[tokio::test]
+async fn batched_runs_write_what_direct_writes_do() {
+    let dir = tempfile::tempdir().unwrap();
+    // Files of several batches each, with context blocks
+    for file in 0..4 {
+        let input: String = (0..5000)
+            .map(|line| match line % 3 {
+                0 => format!("error: request {} of file {} failed\n", line, file),
+                _ => format!("info: request {} served\n", line),
+            })
+            .collect();
+        fs::write(dir.path().join(format!("{}.log", file)), input).unwrap();
+    }
+
+    let (batched, direct) = batched_and_direct_output(dir.path(), true).await;
+    assert!(direct.len() > 4 * 64 * 1024);
+    assert_eq!(batched, direct);
+
+    let (batched, direct) = batched_and_direct_output(dir.path(), false).await;
+    let mut batched: Vec<_> = batched.lines().collect();
+    let mut direct: Vec<_> = direct.lines().collect();
+    batched.sort();
+    direct.sort();
+    assert_eq!(batched, direct);
+}